@@ -0,0 +1,179 @@
+"""Write the project as a DAWproject file for Bitwig Studio, Studio One and
+the other DAWs that open the format (https://github.com/bitwig/dawproject).
+
+Each track becomes an audio track with its volume, pan, mute and solo, its
+source placed as one clip at the start of the arrangement; the mix volume
+goes on the master, and the player's markers (state/markers.json) become
+arrangement markers. Times are in seconds, so nothing depends on the DAW
+reading the tempo the same way. Sources are copied into the file unless
+linked, which keeps it small but only opens on this machine.
+
+Effects chains are SoX settings with no DAW equivalent and are left out,
+as are MIDI tracks' notes (the track is there, empty).
+"""
+
+import json
+import struct
+import subprocess
+import zipfile
+from pathlib import Path
+from xml.etree import ElementTree as ET
+
+from cornwall import state
+
+MARKERS_FILE = state.STATE_DIR / "markers.json"
+
+
+class Ids:
+    """Sequential ids for the elements other elements refer to."""
+
+    def __init__(self):
+        self.n = 0
+
+    def __call__(self) -> str:
+        self.n += 1
+        return f"id{self.n}"
+
+
+def audio_info(path: Path) -> tuple[int, int, float]:
+    """Channels, sample rate and length in seconds: read from the RIFF
+    header for WAV, through soxi for anything else."""
+    if path.suffix.lower() == ".wav":
+        info = _wav_info(path)
+        if info:
+            return info
+    try:
+        out = subprocess.run(["soxi", "-c", "-r", "-D", str(path)], capture_output=True, text=True, check=True)
+        channels, rate, duration = out.stdout.split()
+        return int(channels), int(float(rate)), float(duration)
+    except (OSError, subprocess.CalledProcessError, ValueError):
+        raise SystemExit(f"Error: Cannot read {path} (not WAV, and soxi couldn't either)")
+
+
+def _wav_info(path: Path) -> tuple[int, int, float] | None:
+    with open(path, "rb") as f:
+        header = f.read(12)
+        if len(header) < 12 or header[:4] not in (b"RIFF", b"RF64") or header[8:12] not in (b"WAVE", b"BW64"):
+            return None
+        channels = rate = block = 0
+        while chunk := f.read(8):
+            if len(chunk) < 8:
+                return None
+            kind, size = chunk[:4], struct.unpack("<I", chunk[4:])[0]
+            if kind == b"fmt ":
+                fmt = f.read(size + size % 2)
+                channels, rate = struct.unpack("<HI", fmt[2:8])
+                block = struct.unpack("<H", fmt[12:14])[0]
+            elif kind == b"data":
+                # RF64 leaves the real size in ds64; fall back to the file's
+                if size == 0xFFFFFFFF:
+                    size = path.stat().st_size - f.tell()
+                if not (channels and rate and block):
+                    return None
+                return channels, rate, size // block / rate
+            else:
+                f.seek(size + size % 2, 1)
+    return None
+
+
+def _channel(parent: ET.Element, ids: Ids, track: dict | None, role: str, volume: float,
+             destination: str | None = None) -> ET.Element:
+    channel = ET.SubElement(parent, "Channel", {
+        "audioChannels": "2", "role": role, "id": ids(),
+        "solo": "true" if track and track.get("solo") else "false",
+    })
+    if destination:
+        channel.set("destination", destination)
+    ET.SubElement(channel, "Mute", {
+        "value": "true" if track and track.get("mute") else "false", "id": ids(), "name": "Mute",
+    })
+    pan = (track.get("pan", 0.0) if track else 0.0) + 1.0
+    ET.SubElement(channel, "Pan", {
+        "unit": "normalized", "min": "0.0", "max": "1.0", "value": f"{pan / 2:.4f}", "id": ids(), "name": "Pan",
+    })
+    ET.SubElement(channel, "Volume", {
+        "unit": "linear", "min": "0.0", "max": "2.0", "value": f"{volume:.4f}", "id": ids(), "name": "Volume",
+    })
+    return channel
+
+
+def export(output: Path, link: bool = False) -> dict:
+    """Write the project to `output`. Returns what went in: track, clip and
+    marker counts and the tracks left empty."""
+    project = state.get_project()
+    tracks = state.get_tracks()
+    mix = state.get_mix()
+    markers = json.loads(MARKERS_FILE.read_text()) if MARKERS_FILE.exists() else []
+    num, den = (int(x) for x in project.get("time_sig", "4/4").split("/"))
+
+    ids = Ids()
+    root = ET.Element("Project", {"version": "1.0"})
+    ET.SubElement(root, "Application", {"name": "Cornwall", "version": "1.0"})
+    transport = ET.SubElement(root, "Transport")
+    ET.SubElement(transport, "Tempo", {
+        "unit": "bpm", "min": "20", "max": "999", "value": str(project["bpm"]), "id": ids(), "name": "Tempo",
+    })
+    ET.SubElement(transport, "TimeSignature", {"numerator": str(num), "denominator": str(den), "id": ids()})
+
+    structure = ET.SubElement(root, "Structure")
+    arrangement = ET.SubElement(root, "Arrangement", {"id": ids()})
+    lanes = ET.SubElement(arrangement, "Lanes", {"timeUnit": "seconds", "id": ids()})
+
+    master = ET.Element("Track", {"contentType": "audio notes", "loaded": "true", "id": ids(), "name": "Master"})
+    master_channel = _channel(master, ids, None, "master", mix.get("master_volume", 1.0))
+
+    files = []
+    empty = []
+    for track in tracks:
+        midi = track.get("type") == "midi"
+        element = ET.SubElement(structure, "Track", {
+            "contentType": "notes" if midi else "audio", "loaded": "true", "id": ids(), "name": track["name"],
+        })
+        _channel(element, ids, track, "regular", track.get("volume", 1.0), master_channel.get("id"))
+        source = Path(track["source"]) if track.get("source") else None
+        if midi or not source or not source.exists():
+            empty.append(track["name"])
+            continue
+        channels, rate, duration = audio_info(source)
+        if link:
+            path = str(source.resolve())
+        else:
+            path = f"audio/{track['id']:02d}-{source.name}"
+            files.append((source, path))
+        track_lanes = ET.SubElement(lanes, "Lanes", {"track": element.get("id"), "id": ids()})
+        clips = ET.SubElement(track_lanes, "Clips", {"id": ids()})
+        clip = ET.SubElement(clips, "Clip", {
+            "time": "0.0", "duration": f"{duration:.6f}", "playStart": "0.0", "name": track["name"],
+        })
+        audio = ET.SubElement(clip, "Audio", {
+            "channels": str(channels), "sampleRate": str(rate), "duration": f"{duration:.6f}",
+            "algorithm": "raw", "id": ids(),
+        })
+        ET.SubElement(audio, "File", {"path": path, "external": "true" if link else "false"})
+    structure.append(master)
+
+    if markers:
+        marker_list = ET.SubElement(arrangement, "Markers", {"timeUnit": "seconds", "id": ids()})
+        for m in markers:
+            ET.SubElement(marker_list, "Marker", {"time": f"{m['position']:.6f}", "name": m["name"]})
+
+    metadata = ET.Element("MetaData")
+    ET.SubElement(metadata, "Title").text = project["name"]
+    ET.SubElement(metadata, "Comment").text = "Exported from Cornwall"
+
+    output.parent.mkdir(parents=True, exist_ok=True)
+    with zipfile.ZipFile(output, "w", zipfile.ZIP_DEFLATED) as z:
+        for name, element in (("project.xml", root), ("metadata.xml", metadata)):
+            ET.indent(element)
+            xml = ET.tostring(element, encoding="unicode", xml_declaration=False)
+            z.writestr(name, '<?xml version="1.0" encoding="UTF-8" standalone="yes"?>\n' + xml + "\n")
+        for source, path in files:
+            # Deflate gains little on audio, and stored files open faster
+            z.write(source, path, compress_type=zipfile.ZIP_STORED)
+
+    return {
+        "tracks": len(tracks),
+        "clips": len(tracks) - len(empty),
+        "markers": len(markers),
+        "empty": empty,
+    }
@@ -0,0 +1,32 @@
+"""Send commands to a running Cornwall player.
+
+The player listens on state/.player.sock for `:` commands, one per line,
+and answers each with a JSON line: {"ok": true, "status": {...}} or
+{"ok": false, "error": "...", "status": {...}}.
+"""
+
+import json
+import socket
+
+from cornwall import state
+
+SOCKET_FILE = state.STATE_DIR / ".player.sock"
+
+
+def send(line: str, timeout: float = 3.0) -> dict | None:
+    """Run a command in the running player, e.g. "reload" or "seek 17".
+
+    Returns the player's reply, or None if no player is listening.
+    """
+    try:
+        with socket.socket(socket.AF_UNIX, socket.SOCK_STREAM) as sock:
+            sock.settimeout(timeout)
+            sock.connect(str(SOCKET_FILE))
+            sock.sendall(line.encode() + b"\n")
+            reply = sock.makefile(encoding="utf-8").readline()
+    except OSError:
+        return None
+    try:
+        return json.loads(reply)
+    except ValueError:
+        return None
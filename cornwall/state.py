@@ -4,6 +4,7 @@ All project state lives in state/ as JSON files. This module provides
 read/write access with sensible defaults so scripts don't duplicate logic.
 """
 
+import hashlib
 import json
 import os
 from datetime import datetime, timezone
@@ -19,6 +20,7 @@ PROJECT_FILE = STATE_DIR / "project.json"
 TRACKS_FILE = STATE_DIR / "tracks.json"
 EFFECTS_FILE = STATE_DIR / "effects.json"
 MIX_FILE = STATE_DIR / "mix.json"
+HASHES_FILE = STATE_DIR / "hashes.json"
 PID_FILE = STATE_DIR / ".playback.pid"
 
 
@@ -94,6 +96,7 @@ def get_tracks() -> list[dict]:
 
 def save_tracks(tracks: list[dict]):
     _write_json(TRACKS_FILE, tracks)
+    _record_hashes(tracks)
 
 
 def next_track_id() -> int:
@@ -146,6 +149,11 @@ def update_track(track_id: int, **fields) -> dict:
     for t in tracks:
         if t["id"] == track_id:
             t.update(fields)
+            if "source" in fields:
+                # A new source, even at the same path, gets hashed afresh
+                hashes = get_hashes()
+                if hashes.pop(str(track_id), None) is not None:
+                    _write_json(HASHES_FILE, hashes)
             save_tracks(tracks)
             return t
     raise SystemExit(f"Error: Track {track_id} not found")
@@ -181,6 +189,71 @@ def get_active_tracks() -> list[dict]:
     return [t for t in tracks if not t["mute"] and t["source"]]
 
 
+# --- Source hashes ---
+
+def file_hash(path: Path) -> str:
+    digest = hashlib.sha256()
+    with open(path, "rb") as f:
+        for block in iter(lambda: f.read(1 << 20), b""):
+            digest.update(block)
+    return digest.hexdigest()
+
+
+def get_hashes() -> dict:
+    return _read_json(HASHES_FILE, {})
+
+
+def _record_hashes(tracks: list[dict]):
+    """Hash each track's source when it is first seen at that path.
+
+    Sources already recorded keep their hash, so a later change to the file
+    shows up in verify_sources() rather than being written over.
+    """
+    hashes = get_hashes()
+    recorded = {}
+    for t in tracks:
+        source = t.get("source")
+        if not source:
+            continue
+        entry = hashes.get(str(t["id"]))
+        if entry is None or entry["source"] != source:
+            if not Path(source).exists():
+                continue
+            entry = {"source": source, "sha256": file_hash(Path(source))}
+        recorded[str(t["id"])] = entry
+    if recorded != hashes:
+        _write_json(HASHES_FILE, recorded)
+
+
+def update_hashes():
+    """Re-hash every track's source, accepting the files as they are now."""
+    _write_json(HASHES_FILE, {})
+    _record_hashes(get_tracks())
+
+
+def verify_sources() -> list[tuple[dict, str]]:
+    """Tracks whose source is missing or changed since its hash was recorded.
+
+    Returns (track, problem) pairs, problem being "missing", "changed" or
+    "unrecorded" for a source that was never hashed.
+    """
+    hashes = get_hashes()
+    problems = []
+    for t in get_tracks():
+        source = t.get("source")
+        if not source:
+            continue
+        path = Path(source)
+        entry = hashes.get(str(t["id"]))
+        if not path.exists():
+            problems.append((t, "missing"))
+        elif entry is None or entry["source"] != source:
+            problems.append((t, "unrecorded"))
+        elif file_hash(path) != entry["sha256"]:
+            problems.append((t, "changed"))
+    return problems
+
+
 # --- Effects ---
 
 def get_effects() -> dict:
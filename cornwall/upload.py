@@ -0,0 +1,171 @@
+"""Upload rendered files for review and get a link to send.
+
+The destination lives in state/upload.json, either an S3-compatible bucket:
+
+    {"type": "s3", "endpoint": "https://s3.eu-central-1.amazonaws.com",
+     "region": "eu-central-1", "bucket": "mixes", "prefix": "cornwall/"}
+
+or a WebDAV folder (Nextcloud, ownCloud, a NAS):
+
+    {"type": "webdav", "url": "https://cloud.example.com/remote.php/dav/files/me/Mixes/",
+     "user": "me"}
+
+Secrets can sit in the file ("access_key"/"secret_key" for S3, "password"
+for WebDAV) or come from AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY and
+CORNWALL_WEBDAV_PASSWORD. S3 links are presigned GETs valid for
+"link_expires" seconds (7 days by default, the most S3 allows) unless
+"public_url" gives the bucket's public address. WebDAV links are the
+uploaded file's URL, or "share_url" plus the file name when the server
+shares the folder somewhere else.
+"""
+
+import base64
+import hashlib
+import hmac
+import json
+import os
+import shutil
+import subprocess
+import urllib.error
+import urllib.request
+from datetime import datetime, timezone
+from pathlib import Path
+from urllib.parse import quote, urlparse
+
+from cornwall import state
+
+UPLOAD_FILE = state.STATE_DIR / "upload.json"
+
+# S3 caps presigned links at a week
+MAX_LINK_EXPIRES = 7 * 24 * 3600
+
+
+def get_config() -> dict | None:
+    if not UPLOAD_FILE.exists():
+        return None
+    with open(UPLOAD_FILE) as f:
+        return json.load(f)
+
+
+def require_config() -> dict:
+    config = get_config()
+    if not config:
+        raise SystemExit(f"Error: No upload destination. Create {UPLOAD_FILE} (see cornwall/upload.py).")
+    if config.get("type") not in ("s3", "webdav"):
+        raise SystemExit(f"Error: {UPLOAD_FILE}: type must be 's3' or 'webdav'")
+    return config
+
+
+def upload(path: Path, name: str | None = None) -> str:
+    """Upload `path` to the configured destination as `name` (its own name
+    by default) and return a link to it."""
+    config = require_config()
+    name = name or path.name
+    if config["type"] == "s3":
+        return _upload_s3(config, path, name)
+    return _upload_webdav(config, path, name)
+
+
+def copy_to_clipboard(text: str) -> bool:
+    """Put `text` on the clipboard with whichever tool is installed."""
+    for cmd in (["pbcopy"], ["wl-copy"], ["xclip", "-selection", "clipboard"], ["clip.exe"]):
+        if shutil.which(cmd[0]):
+            try:
+                subprocess.run(cmd, input=text.encode(), check=True)
+                return True
+            except (OSError, subprocess.CalledProcessError):
+                continue
+    return False
+
+
+def _put(url: str, path: Path, headers: dict):
+    request = urllib.request.Request(url, data=path.read_bytes(), method="PUT", headers=headers)
+    try:
+        with urllib.request.urlopen(request, timeout=300):
+            pass
+    except urllib.error.HTTPError as e:
+        detail = e.read().decode(errors="replace").strip()
+        raise SystemExit(f"Error: Upload refused ({e.code} {e.reason}) {detail[:300]}")
+    except urllib.error.URLError as e:
+        raise SystemExit(f"Error: Cannot reach {urlparse(url).netloc}: {e.reason}")
+
+
+# --- S3 ---
+
+def _upload_s3(config: dict, path: Path, name: str) -> str:
+    access = config.get("access_key") or os.environ.get("AWS_ACCESS_KEY_ID")
+    secret = config.get("secret_key") or os.environ.get("AWS_SECRET_ACCESS_KEY")
+    if not access or not secret:
+        raise SystemExit("Error: S3 upload needs access_key/secret_key or AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY")
+    endpoint = config.get("endpoint", "https://s3.amazonaws.com").rstrip("/")
+    region = config.get("region", "us-east-1")
+    key = config.get("prefix", "") + name
+    # Path-style addressing works with AWS and the compatible stores alike
+    uri = "/" + quote(config["bucket"], safe="") + "/" + quote(key, safe="/-_.~")
+    host = urlparse(endpoint).netloc
+    now = datetime.now(timezone.utc)
+
+    payload = hashlib.sha256(path.read_bytes()).hexdigest()
+    headers = {"host": host, "x-amz-content-sha256": payload, "x-amz-date": now.strftime("%Y%m%dT%H%M%SZ")}
+    signature, scope, signed = _sign_s3("PUT", uri, "", headers, payload, secret, region, now)
+    headers["Authorization"] = (
+        f"AWS4-HMAC-SHA256 Credential={access}/{scope}, SignedHeaders={signed}, Signature={signature}"
+    )
+    del headers["host"]
+    _put(endpoint + uri, path, headers)
+
+    if config.get("public_url"):
+        return config["public_url"].rstrip("/") + "/" + quote(key, safe="/-_.~")
+    expires = min(int(config.get("link_expires", MAX_LINK_EXPIRES)), MAX_LINK_EXPIRES)
+    params = {
+        "X-Amz-Algorithm": "AWS4-HMAC-SHA256",
+        "X-Amz-Credential": f"{access}/{_scope(now, region)}",
+        "X-Amz-Date": now.strftime("%Y%m%dT%H%M%SZ"),
+        "X-Amz-Expires": str(expires),
+        "X-Amz-SignedHeaders": "host",
+    }
+    query = "&".join(f"{quote(k, safe='-_.~')}={quote(v, safe='-_.~')}" for k, v in sorted(params.items()))
+    signature, _, _ = _sign_s3("GET", uri, query, {"host": host}, "UNSIGNED-PAYLOAD", secret, region, now)
+    return f"{endpoint}{uri}?{query}&X-Amz-Signature={signature}"
+
+
+def _scope(now: datetime, region: str) -> str:
+    return f"{now:%Y%m%d}/{region}/s3/aws4_request"
+
+
+def _sign_s3(method: str, uri: str, query: str, headers: dict, payload: str,
+             secret: str, region: str, now: datetime) -> tuple[str, str, str]:
+    """AWS Signature Version 4: returns the signature, the credential scope
+    and the signed header names."""
+    scope = _scope(now, region)
+    names = sorted(headers)
+    canonical = "\n".join([
+        method, uri, query,
+        "".join(f"{n}:{str(headers[n]).strip()}\n" for n in names),
+        ";".join(names), payload,
+    ])
+    to_sign = "\n".join([
+        "AWS4-HMAC-SHA256", now.strftime("%Y%m%dT%H%M%SZ"), scope,
+        hashlib.sha256(canonical.encode()).hexdigest(),
+    ])
+    key = ("AWS4" + secret).encode()
+    for part in scope.split("/"):
+        key = hmac.new(key, part.encode(), hashlib.sha256).digest()
+    signature = hmac.new(key, to_sign.encode(), hashlib.sha256).hexdigest()
+    return signature, scope, ";".join(names)
+
+
+# --- WebDAV ---
+
+def _upload_webdav(config: dict, path: Path, name: str) -> str:
+    url = config["url"].rstrip("/") + "/" + quote(name)
+    headers = {"Content-Type": "audio/wav" if path.suffix.lower() == ".wav" else "application/octet-stream"}
+    user = config.get("user")
+    if user:
+        password = config.get("password") or os.environ.get("CORNWALL_WEBDAV_PASSWORD", "")
+        token = base64.b64encode(f"{user}:{password}".encode()).decode()
+        headers["Authorization"] = f"Basic {token}"
+    _put(url, path, headers)
+    if config.get("share_url"):
+        return config["share_url"].rstrip("/") + "/" + quote(name)
+    return url
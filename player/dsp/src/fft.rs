@@ -0,0 +1,79 @@
+//! Radix-2 FFT and the fast convolution built on it, for the long
+//! convolutions of room measurement.
+
+use alloc::{vec, vec::Vec};
+use core::f64::consts::PI;
+
+/// Transforms `re` and `im` in place. Both must be the same power of two
+/// long. The inverse is scaled by 1/n, so a round trip gives the input back.
+pub fn fft(re: &mut [f64], im: &mut [f64], inverse: bool) {
+    let n = re.len();
+    debug_assert!(n.is_power_of_two() && im.len() == n);
+    // Bit-reversed order, so the butterflies can work in place
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    // One table of twiddles for every stage; a recurrence would drift over
+    // a million points
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let twiddles: Vec<(f64, f64)> = (0..n / 2)
+        .map(|k| {
+            let angle = sign * 2.0 * PI * k as f64 / n as f64;
+            (libm::cos(angle), libm::sin(angle))
+        })
+        .collect();
+    let mut len = 2;
+    while len <= n {
+        let stride = n / len;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_re, w_im) = twiddles[k * stride];
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+    if inverse {
+        let scale = 1.0 / n as f64;
+        re.iter_mut().chain(im.iter_mut()).for_each(|x| *x *= scale);
+    }
+}
+
+/// Linear convolution of `a` and `b`, `a.len() + b.len() - 1` long.
+pub fn convolve(a: &[f64], b: &[f64]) -> Vec<f64> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let len = a.len() + b.len() - 1;
+    let n = len.next_power_of_two();
+    let (mut a_re, mut a_im) = (vec![0.0; n], vec![0.0; n]);
+    let (mut b_re, mut b_im) = (vec![0.0; n], vec![0.0; n]);
+    a_re[..a.len()].copy_from_slice(a);
+    b_re[..b.len()].copy_from_slice(b);
+    fft(&mut a_re, &mut a_im, false);
+    fft(&mut b_re, &mut b_im, false);
+    for i in 0..n {
+        let re = a_re[i] * b_re[i] - a_im[i] * b_im[i];
+        a_im[i] = a_re[i] * b_im[i] + a_im[i] * b_re[i];
+        a_re[i] = re;
+    }
+    fft(&mut a_re, &mut a_im, true);
+    a_re.truncate(len);
+    a_re
+}
@@ -0,0 +1,97 @@
+//! Level scans of interleaved samples (-1..1): per-chunk RMS for the
+//! meters and waveform, and sample peaks.
+
+use alloc::{vec, vec::Vec};
+
+/// RMS of the first two channels over `chunk_ms` chunks, the player's
+/// meter and waveform data. Mono files give the same levels on both sides.
+pub fn chunk_levels(
+    samples: &[f64],
+    channels: usize,
+    sample_rate: u32,
+    chunk_ms: u32,
+) -> (Vec<f64>, Vec<f64>) {
+    let channels = channels.max(1);
+    let chunk = (sample_rate as usize * chunk_ms as usize / 1000).max(1);
+    let (mut left, mut right) = (Vec::new(), Vec::new());
+    for frames in samples.chunks(chunk * channels) {
+        let mut sum_l = 0.0;
+        let mut sum_r = 0.0;
+        let mut count = 0;
+        for frame in frames.chunks_exact(channels) {
+            let l = frame[0];
+            let r = if channels > 1 { frame[1] } else { l };
+            sum_l += l * l;
+            sum_r += r * r;
+            count += 1;
+        }
+        if count > 0 {
+            left.push(libm::sqrt(sum_l / count as f64));
+            right.push(libm::sqrt(sum_r / count as f64));
+        }
+    }
+    (left, right)
+}
+
+/// Brightness of each `chunk_ms` chunk in Hz: the RMS frequency of the
+/// first two channels' sum, from the energy of its slope against its own.
+/// It follows the spectral centroid, low for bass-heavy chunks and high
+/// for cymbals and hiss, without a transform. Silent chunks give 0.
+pub fn chunk_brightness(
+    samples: &[f64],
+    channels: usize,
+    sample_rate: u32,
+    chunk_ms: u32,
+) -> Vec<f64> {
+    let channels = channels.max(1);
+    let chunk = (sample_rate as usize * chunk_ms as usize / 1000).max(1);
+    let mut prev = 0.0;
+    samples
+        .chunks(chunk * channels)
+        .map(|frames| {
+            let mut energy = 0.0;
+            let mut slope = 0.0;
+            for frame in frames.chunks_exact(channels) {
+                let mid = if channels > 1 {
+                    (frame[0] + frame[1]) / 2.0
+                } else {
+                    frame[0]
+                };
+                energy += mid * mid;
+                slope += (mid - prev) * (mid - prev);
+                prev = mid;
+            }
+            if energy < 1e-9 {
+                return 0.0;
+            }
+            // A sine of frequency f has slope energy 4·sin²(πf/rate) times its own
+            let ratio = (libm::sqrt(slope / energy) / 2.0).min(1.0);
+            sample_rate as f64 / core::f64::consts::PI * libm::asin(ratio)
+        })
+        .collect()
+}
+
+/// Largest absolute sample of each channel.
+pub fn sample_peaks(samples: &[f64], channels: usize) -> Vec<f64> {
+    let mut peaks = vec![0.0_f64; channels];
+    if channels == 0 {
+        return peaks;
+    }
+    for frame in samples.chunks_exact(channels) {
+        for (peak, s) in peaks.iter_mut().zip(frame) {
+            *peak = peak.max(libm::fabs(*s));
+        }
+    }
+    peaks
+}
+
+/// Amplitude in dBFS; negative infinity for silence.
+pub fn dbfs(amplitude: f64) -> f64 {
+    20.0 * libm::log10(amplitude)
+}
+
+/// How full to draw a meter or waveform for an RMS `level`, 0 to 1.
+/// Scaled up so speech and mixes fill most of the range.
+pub fn display_level(level: f64) -> f64 {
+    (level * 3.0).min(1.0)
+}
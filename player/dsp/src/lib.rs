@@ -0,0 +1,28 @@
+//! Metering, loudness, pitch and waveform maths shared by the player and
+//! the web review page, so both draw the same levels and waveforms from the
+//! same code. Without its default `std` feature it is `no_std` with
+//! `alloc` (floating point via `libm`), so it builds for
+//! `wasm32-unknown-unknown` without the standard library:
+//!
+//! ```sh
+//! wasm-pack build player/dsp --target web -- --no-default-features --features wasm
+//! ```
+//!
+//! With the `wasm` feature the functions are exported to JavaScript under
+//! camel-case names (`chunkLevels`, `samplePeaks`, `integratedLoudness`,
+//! `columnPeaks`, `columnHeights`, `displayLevel`), taking interleaved `Float32Array`s as
+//! `AudioBuffer` data gives them.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod fft;
+pub mod levels;
+pub mod loudness;
+pub mod pitch;
+pub mod room;
+pub mod waveform;
+
+#[cfg(feature = "wasm")]
+mod wasm;
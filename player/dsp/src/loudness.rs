@@ -0,0 +1,247 @@
+//! Integrated loudness per ITU-R BS.1770-4: K-weighting, 400 ms blocks
+//! with 75% overlap, absolute and relative gates. [`LoudnessMeter`] does
+//! the same on a stream, adding EBU R128's momentary (400 ms) and
+//! short-term (3 s) loudness.
+//!
+//! All channels are weighted 1.0, which is right for mono and stereo;
+//! surround weighting isn't applied.
+
+use alloc::{collections::VecDeque, vec::Vec};
+use core::f64::consts::PI;
+
+/// Block length and step of the measurement.
+const BLOCK_SECS: f64 = 0.4;
+const STEP_SECS: f64 = 0.1;
+/// Blocks quieter than this never count.
+const ABSOLUTE_GATE: f64 = -70.0;
+/// Blocks this far below the ungated loudness don't count either.
+const RELATIVE_GATE: f64 = -10.0;
+/// Steps in the momentary and short-term windows.
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+
+fn loudness(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * libm::log10(mean_square)
+}
+
+/// Integrated loudness of interleaved samples in LUFS; negative infinity
+/// for silence or less than one block of audio.
+pub fn integrated_loudness(samples: &[f64], channels: usize, sample_rate: u32) -> f64 {
+    if channels == 0 {
+        return f64::NEG_INFINITY;
+    }
+    let rate = sample_rate as f64;
+    // K-weighting: a high shelf for the head, then a high-pass
+    let mut weighted: Vec<Vec<f64>> = (0..channels)
+        .map(|c| samples.iter().skip(c).step_by(channels).copied().collect())
+        .collect();
+    for channel in &mut weighted {
+        Biquad::shelf(rate).run(channel);
+        Biquad::high_pass(rate).run(channel);
+    }
+
+    let frames = weighted.first().map_or(0, Vec::len);
+    let block = (BLOCK_SECS * rate) as usize;
+    let step = (STEP_SECS * rate) as usize;
+    if block == 0 || step == 0 || frames < block {
+        return f64::NEG_INFINITY;
+    }
+    let blocks: Vec<f64> = (0..=(frames - block) / step)
+        .map(|i| {
+            let start = i * step;
+            weighted
+                .iter()
+                .map(|c| c[start..start + block].iter().map(|s| s * s).sum::<f64>() / block as f64)
+                .sum()
+        })
+        .collect();
+    gated(&blocks)
+}
+
+/// Loudness of the block mean squares that pass both gates.
+fn gated(blocks: &[f64]) -> f64 {
+    let blocks: Vec<f64> = blocks
+        .iter()
+        .copied()
+        .filter(|&z| loudness(z) > ABSOLUTE_GATE)
+        .collect();
+    if blocks.is_empty() {
+        return f64::NEG_INFINITY;
+    }
+    let mean = |zs: &[f64]| zs.iter().sum::<f64>() / zs.len() as f64;
+    let gate = loudness(mean(&blocks)) + RELATIVE_GATE;
+    let gated: Vec<f64> = blocks.into_iter().filter(|&z| loudness(z) > gate).collect();
+    if gated.is_empty() {
+        return f64::NEG_INFINITY;
+    }
+    loudness(mean(&gated))
+}
+
+/// Loudness of a stream of interleaved frames, fed as it plays. Values
+/// are LUFS, negative infinity until a window has filled or in silence.
+pub struct LoudnessMeter {
+    channels: usize,
+    /// K-weighting stages per channel.
+    filters: Vec<[Filter; 2]>,
+    /// Frames per 100 ms step, and the squares summed over this one.
+    step: usize,
+    frames: usize,
+    sum: f64,
+    /// Mean squares of the last `SHORT_TERM_STEPS` steps.
+    steps: VecDeque<f64>,
+    /// Mean squares of every 400 ms block since the reset.
+    blocks: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        let rate = sample_rate as f64;
+        LoudnessMeter {
+            channels,
+            filters: (0..channels)
+                .map(|_| {
+                    [
+                        Filter::new(Biquad::shelf(rate)),
+                        Filter::new(Biquad::high_pass(rate)),
+                    ]
+                })
+                .collect(),
+            step: ((STEP_SECS * rate) as usize).max(1),
+            frames: 0,
+            sum: 0.0,
+            steps: VecDeque::with_capacity(SHORT_TERM_STEPS),
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Measure interleaved samples; a trailing part frame is dropped.
+    pub fn push(&mut self, samples: &[f64]) {
+        if self.channels == 0 {
+            return;
+        }
+        for frame in samples.chunks_exact(self.channels) {
+            for (x, [shelf, high_pass]) in frame.iter().zip(&mut self.filters) {
+                let y = high_pass.process(shelf.process(*x));
+                self.sum += y * y;
+            }
+            self.frames += 1;
+            if self.frames == self.step {
+                if self.steps.len() == SHORT_TERM_STEPS {
+                    self.steps.pop_front();
+                }
+                self.steps.push_back(self.sum / self.step as f64);
+                self.frames = 0;
+                self.sum = 0.0;
+                if let Some(block) = self.window(MOMENTARY_STEPS) {
+                    self.blocks.push(block);
+                }
+            }
+        }
+    }
+
+    /// Mean square of the last `steps` steps, once there are that many.
+    fn window(&self, steps: usize) -> Option<f64> {
+        let len = self.steps.len();
+        (len >= steps).then(|| self.steps.range(len - steps..).sum::<f64>() / steps as f64)
+    }
+
+    /// Loudness over the last 400 ms.
+    pub fn momentary(&self) -> f64 {
+        self.window(MOMENTARY_STEPS)
+            .map_or(f64::NEG_INFINITY, loudness)
+    }
+
+    /// Loudness over the last 3 s.
+    pub fn short_term(&self) -> f64 {
+        self.window(SHORT_TERM_STEPS)
+            .map_or(f64::NEG_INFINITY, loudness)
+    }
+
+    /// Gated loudness of everything since the reset.
+    pub fn integrated(&self) -> f64 {
+        gated(&self.blocks)
+    }
+
+    /// Start measuring afresh.
+    pub fn reset(&mut self) {
+        for stage in self.filters.iter_mut().flatten() {
+            *stage = Filter::new(stage.biquad);
+        }
+        self.frames = 0;
+        self.sum = 0.0;
+        self.steps.clear();
+        self.blocks.clear();
+    }
+}
+
+/// A biquad and its state, for filtering one sample at a time.
+struct Filter {
+    biquad: Biquad,
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Filter {
+    fn new(biquad: Biquad) -> Self {
+        Filter {
+            biquad,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let Biquad { b, a } = self.biquad;
+        let y =
+            b[0] * x + b[1] * self.x[0] + b[2] * self.x[1] - a[0] * self.y[0] - a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+/// Second-order IIR filter, direct form I. The K-weighting stages are
+/// designed for the file's sample rate the way libebur128 does it, which
+/// gives the BS.1770 reference coefficients at 48 kHz.
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+}
+
+impl Biquad {
+    /// Stage 1: +4 dB high shelf modelling the head.
+    fn shelf(rate: f64) -> Self {
+        let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+        let k = libm::tan(PI * f0 / rate);
+        let vh = libm::pow(10.0, gain_db / 20.0);
+        let vb = libm::pow(vh, 0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        Biquad {
+            b: [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        }
+    }
+
+    /// Stage 2: high-pass at 38 Hz.
+    fn high_pass(rate: f64) -> Self {
+        let (f0, q) = (38.13547087613982, 0.5003270373253953);
+        let k = libm::tan(PI * f0 / rate);
+        let a0 = 1.0 + k / q + k * k;
+        Biquad {
+            b: [1.0, -2.0, 1.0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        }
+    }
+
+    fn run(&self, signal: &mut [f64]) {
+        let mut filter = Filter::new(*self);
+        for s in signal {
+            *s = filter.process(*s);
+        }
+    }
+}
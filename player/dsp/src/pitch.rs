@@ -0,0 +1,77 @@
+//! Pitch detection for the tuner: the YIN method (de Cheveigné and
+//! Kawahara, 2002) on a block of mono samples, and the nearest
+//! equal-tempered note.
+
+use alloc::vec;
+
+/// Lowest and highest pitch reported, in Hz: a five-string bass's low B up
+/// to well past a violin's open E.
+pub const MIN_HZ: f64 = 30.0;
+pub const MAX_HZ: f64 = 2000.0;
+
+/// Dips of the normalised difference below this count as a period.
+const THRESHOLD: f64 = 0.15;
+
+/// Blocks quieter than this (RMS) have no pitch.
+const MIN_LEVEL: f64 = 0.003;
+
+/// Note names from C, with sharps.
+pub const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// Fundamental of `samples` in Hz, or `None` without a clear pitch
+/// (silence, noise, chords). Pitches down to `MIN_HZ` need two of its
+/// periods.
+pub fn detect(samples: &[f64], sample_rate: u32) -> Option<f64> {
+    let rate = sample_rate as f64;
+    let min_lag = ((rate / MAX_HZ) as usize).max(2);
+    let max_lag = ((rate / MIN_HZ) as usize).min(samples.len() / 2);
+    if max_lag <= min_lag + 1 {
+        return None;
+    }
+    let power = samples.iter().map(|s| s * s).sum::<f64>() / samples.len() as f64;
+    if libm::sqrt(power) < MIN_LEVEL {
+        return None;
+    }
+    let window = samples.len() - max_lag;
+
+    // Cumulative mean normalised difference: near 0 at multiples of the
+    // period, near 1 elsewhere
+    let mut normalised = vec![1.0; max_lag + 1];
+    let mut running = 0.0;
+    for lag in 1..=max_lag {
+        let difference: f64 = samples[..window]
+            .iter()
+            .zip(&samples[lag..lag + window])
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum();
+        running += difference;
+        if running > 0.0 {
+            normalised[lag] = difference * lag as f64 / running;
+        }
+    }
+
+    // The first dip taken to its bottom, so an octave below never wins
+    let mut lag = (min_lag..max_lag).find(|&lag| normalised[lag] < THRESHOLD)?;
+    while lag + 1 < max_lag && normalised[lag + 1] < normalised[lag] {
+        lag += 1;
+    }
+    // Between samples by a parabola through the dip
+    let (a, b, c) = (normalised[lag - 1], normalised[lag], normalised[lag + 1]);
+    let curve = a - 2.0 * b + c;
+    let shift = if curve.abs() > 1e-12 {
+        (a - c) / (2.0 * curve)
+    } else {
+        0.0
+    };
+    Some(rate / (lag as f64 + shift))
+}
+
+/// The nearest equal-tempered note to `hz` as a MIDI note number, with A4
+/// at `a4` Hz, and how far `hz` is from it in cents (-50 to +50).
+pub fn nearest_note(hz: f64, a4: f64) -> (i32, f64) {
+    let semitones = 69.0 + 12.0 * libm::log2(hz / a4);
+    let note = libm::round(semitones);
+    (note as i32, (semitones - note) * 100.0)
+}
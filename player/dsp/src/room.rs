@@ -0,0 +1,77 @@
+//! Room impulse responses by exponential sine sweep (Farina, 2000): play a
+//! sweep, record it through the monitors and a microphone, and convolve
+//! the recording with the sweep's inverse filter. Harmonic distortion from
+//! the speakers lands ahead of the linear response and is cut off.
+
+use crate::fft::convolve;
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+/// Range of the sweep in Hz.
+pub const SWEEP_FROM: f64 = 20.0;
+pub const SWEEP_TO: f64 = 20000.0;
+
+/// Fade at each end of the sweep, against clicks.
+const FADE_SECS: f64 = 0.05;
+
+/// Response kept ahead of the direct sound's peak.
+const PRE_SECS: f64 = 0.005;
+
+/// Full-scale sweep from `SWEEP_FROM` to `SWEEP_TO` over `secs`, spending
+/// the same time on every octave.
+pub fn sweep(sample_rate: u32, secs: f64) -> Vec<f64> {
+    let rate = sample_rate as f64;
+    let octaves = libm::log(SWEEP_TO / SWEEP_FROM);
+    let len = (secs * rate) as usize;
+    let fade = (FADE_SECS * rate) as usize;
+    (0..len)
+        .map(|i| {
+            let t = i as f64 / rate;
+            let phase =
+                2.0 * PI * SWEEP_FROM * secs / octaves * (libm::exp(t * octaves / secs) - 1.0);
+            let edge = i.min(len - 1 - i);
+            let gain = if edge < fade {
+                0.5 - 0.5 * libm::cos(PI * edge as f64 / fade as f64)
+            } else {
+                1.0
+            };
+            libm::sin(phase) * gain
+        })
+        .collect()
+}
+
+/// The impulse response in a `recording` of `sweep(sample_rate, secs)`:
+/// `ir_secs` of it from just before the direct sound, peaking at 1. Empty
+/// when the recording is silent.
+pub fn impulse_response(recording: &[f64], sample_rate: u32, secs: f64, ir_secs: f64) -> Vec<f64> {
+    let rate = sample_rate as f64;
+    let octaves = libm::log(SWEEP_TO / SWEEP_FROM);
+    // Time-reversed sweep, falling 6 dB an octave towards the bass so the
+    // pair has a flat response
+    let mut inverse = sweep(sample_rate, secs);
+    inverse.reverse();
+    for (i, s) in inverse.iter_mut().enumerate() {
+        *s *= libm::exp(-(i as f64 / rate) * octaves / secs);
+    }
+    let response = convolve(recording, &inverse);
+
+    // Everything after the sweep's length is the linear response; the
+    // direct sound is its loudest point
+    let linear = inverse.len().saturating_sub(1).min(response.len());
+    let Some((peak_at, peak)) = response[linear..]
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (linear + i, libm::fabs(*s)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+    else {
+        return Vec::new();
+    };
+    if peak <= 0.0 {
+        return Vec::new();
+    }
+    let start = peak_at
+        .saturating_sub((PRE_SECS * rate) as usize)
+        .max(linear);
+    let end = (start + (ir_secs * rate) as usize).min(response.len());
+    response[start..end].iter().map(|s| s / peak).collect()
+}
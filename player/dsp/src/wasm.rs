@@ -0,0 +1,61 @@
+//! JavaScript exports. Samples arrive as `Float32Array`s, interleaved like
+//! the player's WAV data; results come back as `Float64Array`s.
+
+use crate::{levels, loudness, waveform};
+use alloc::vec::Vec;
+use wasm_bindgen::prelude::*;
+
+fn widen(samples: &[f32]) -> Vec<f64> {
+    samples.iter().map(|&s| s as f64).collect()
+}
+
+/// Per-chunk RMS of the left and right channels.
+#[wasm_bindgen]
+pub struct Levels {
+    left: Vec<f64>,
+    right: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl Levels {
+    #[wasm_bindgen(getter)]
+    pub fn left(&self) -> Vec<f64> {
+        self.left.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn right(&self) -> Vec<f64> {
+        self.right.clone()
+    }
+}
+
+#[wasm_bindgen(js_name = chunkLevels)]
+pub fn chunk_levels(samples: &[f32], channels: usize, sample_rate: u32, chunk_ms: u32) -> Levels {
+    let (left, right) = levels::chunk_levels(&widen(samples), channels, sample_rate, chunk_ms);
+    Levels { left, right }
+}
+
+#[wasm_bindgen(js_name = samplePeaks)]
+pub fn sample_peaks(samples: &[f32], channels: usize) -> Vec<f64> {
+    levels::sample_peaks(&widen(samples), channels)
+}
+
+#[wasm_bindgen(js_name = integratedLoudness)]
+pub fn integrated_loudness(samples: &[f32], channels: usize, sample_rate: u32) -> f64 {
+    loudness::integrated_loudness(&widen(samples), channels, sample_rate)
+}
+
+#[wasm_bindgen(js_name = columnPeaks)]
+pub fn column_peaks(left: &[f64], right: &[f64], columns: usize) -> Vec<f64> {
+    waveform::column_peaks(left, right, columns)
+}
+
+#[wasm_bindgen(js_name = columnHeights)]
+pub fn column_heights(left: &[f64], right: &[f64], columns: usize) -> Vec<f64> {
+    waveform::column_heights(left, right, columns)
+}
+
+#[wasm_bindgen(js_name = displayLevel)]
+pub fn display_level(level: f64) -> f64 {
+    levels::display_level(level)
+}
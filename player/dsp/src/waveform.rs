@@ -0,0 +1,58 @@
+//! Waveform overview data: the loudest chunk level under each drawn
+//! column, scaled like the meters, and its brightness. The player turns it
+//! into braille or block glyphs; the web page into canvas bars.
+
+use crate::levels::display_level;
+use alloc::{vec, vec::Vec};
+
+/// Loudest chunk of `left`/`right` under each of `columns` columns.
+pub fn column_peaks(left: &[f64], right: &[f64], columns: usize) -> Vec<f64> {
+    let len = left.len().min(right.len());
+    if len == 0 {
+        return vec![0.0; columns];
+    }
+    (0..columns)
+        .map(|c| {
+            let start = c * len / columns;
+            let end = ((c + 1) * len / columns).max(start + 1).min(len);
+            (start..end)
+                .map(|i| left[i].max(right[i]))
+                .fold(0.0, f64::max)
+        })
+        .collect()
+}
+
+/// How far each column reaches from the centre line, 0 to 1.
+pub fn column_heights(left: &[f64], right: &[f64], columns: usize) -> Vec<f64> {
+    column_peaks(left, right, columns)
+        .into_iter()
+        .map(display_level)
+        .collect()
+}
+
+/// Brightness under each of `columns` columns from per-chunk `tones` in Hz,
+/// averaged on a log scale and weighted by level so a quiet tail doesn't
+/// tint a loud hit. Silent columns give 0.
+pub fn column_tones(left: &[f64], right: &[f64], tones: &[f64], columns: usize) -> Vec<f64> {
+    let len = left.len().min(right.len()).min(tones.len());
+    if len == 0 {
+        return vec![0.0; columns];
+    }
+    (0..columns)
+        .map(|c| {
+            let start = c * len / columns;
+            let end = ((c + 1) * len / columns).max(start + 1).min(len);
+            let (sum, weight) = (start..end)
+                .filter(|&i| tones[i] > 0.0)
+                .map(|i| (libm::log(tones[i]), left[i] * left[i] + right[i] * right[i]))
+                .fold((0.0, 0.0), |(sum, weight), (log, w)| {
+                    (sum + log * w, weight + w)
+                });
+            if weight > 0.0 {
+                libm::exp(sum / weight)
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
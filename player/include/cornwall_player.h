@@ -0,0 +1,55 @@
+/*
+ * C ABI for the Cornwall player engine (player/src/ffi.rs).
+ *
+ * Link against libcornwall_player from `cargo build --release`. Functions
+ * returning int give 0 on success and -1 on failure, with the reason in
+ * cornwall_player_last_error(). A player must only be used from the thread
+ * that opened it.
+ */
+#ifndef CORNWALL_PLAYER_H
+#define CORNWALL_PLAYER_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct CornwallPlayer CornwallPlayer;
+
+typedef struct CornwallStatus {
+    bool playing;
+    double position_secs;
+    double duration_secs;
+    uint32_t bar;
+    uint32_t beat;
+    double bpm;
+    bool paused;
+} CornwallStatus;
+
+/* Open the project whose state directory is state_dir; NULL on failure. */
+CornwallPlayer *cornwall_player_open(const char *state_dir);
+/* Stop playback and release the player and its audio device. */
+void cornwall_player_close(CornwallPlayer *player);
+
+/* Play from the playhead; resumes when paused. */
+int cornwall_player_play(CornwallPlayer *player);
+/* Hold playback where it is. */
+int cornwall_player_pause(CornwallPlayer *player);
+/* Carry on from where pause held playback; nothing unless paused. */
+int cornwall_player_resume(CornwallPlayer *player);
+/* Stop and rewind to the start. */
+int cornwall_player_stop(CornwallPlayer *player);
+/* Move the playhead, continuing playback there if playing. */
+int cornwall_player_seek(CornwallPlayer *player, double secs);
+int cornwall_player_status(CornwallPlayer *player, CornwallStatus *status);
+
+/* Why the last failing call on this thread failed. */
+const char *cornwall_player_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
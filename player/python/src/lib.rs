@@ -0,0 +1,121 @@
+//! Python bindings for the player engine, built with maturin
+//! (`cd player/python && maturin develop --release`):
+//!
+//! ```python
+//! import cornwall_player
+//!
+//! p = cornwall_player.Player("state")
+//! p.play()
+//! p.status()  # {"playing": True, "position_secs": 1.2, "bar": 1, ...}
+//!
+//! cornwall_player.render("state", "/tmp/check.wav")
+//! qa = cornwall_player.analyze("/tmp/check.wav")
+//! assert qa["peak_dbfs"] < -1.0 and -16 <= qa["integrated_lufs"] <= -12
+//! ```
+//!
+//! Errors raise `RuntimeError` with the engine's message.
+
+use player::{analysis, engine::Engine, render as mix};
+use pyo3::{exceptions::PyRuntimeError, prelude::*, types::PyDict};
+use std::path::PathBuf;
+
+fn error(e: String) -> PyErr {
+    PyRuntimeError::new_err(e)
+}
+
+/// One project's audio on the default output device. The audio device
+/// belongs to the thread that opened it, so a player can't move between
+/// Python threads.
+#[pyclass(unsendable)]
+struct Player {
+    engine: Engine,
+}
+
+#[pymethods]
+impl Player {
+    /// Open the project whose state directory is `state_dir` and get ready
+    /// to play its mix (or a track when nothing has been mixed).
+    #[new]
+    fn new(state_dir: PathBuf) -> PyResult<Self> {
+        let engine = Engine::open(&state_dir).map_err(error)?;
+        Ok(Player { engine })
+    }
+
+    #[getter]
+    fn audio_file(&self) -> PathBuf {
+        self.engine.audio_file().to_path_buf()
+    }
+
+    /// Play from the playhead; resumes when paused.
+    fn play(&mut self) -> PyResult<()> {
+        self.engine.play().map_err(error)
+    }
+
+    /// Hold playback where it is.
+    fn pause(&mut self) {
+        self.engine.pause();
+    }
+
+    /// Carry on from where `pause()` held playback; nothing unless paused.
+    fn resume(&mut self) -> PyResult<()> {
+        self.engine.resume().map_err(error)
+    }
+
+    /// Stop and rewind to the start.
+    fn stop(&mut self) {
+        self.engine.stop();
+    }
+
+    /// Move the playhead to `secs`, continuing playback there if playing.
+    fn seek(&mut self, secs: f64) -> PyResult<()> {
+        self.engine.seek(secs).map_err(error)
+    }
+
+    fn status<'py>(&mut self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let s = self.engine.status();
+        let status = PyDict::new_bound(py);
+        status.set_item("playing", s.playing)?;
+        status.set_item("paused", s.paused)?;
+        status.set_item("position_secs", s.position_secs)?;
+        status.set_item("duration_secs", s.duration_secs)?;
+        status.set_item("bar", s.bar)?;
+        status.set_item("beat", s.beat)?;
+        status.set_item("bpm", s.bpm)?;
+        Ok(status)
+    }
+}
+
+/// Sample peaks (dBFS) and integrated loudness (LUFS, BS.1770) of a WAV
+/// file.
+#[pyfunction]
+fn analyze(py: Python<'_>, path: PathBuf) -> PyResult<Bound<'_, PyDict>> {
+    let a = py
+        .allow_threads(|| analysis::analyze(&path))
+        .map_err(error)?;
+    let result = PyDict::new_bound(py);
+    result.set_item("duration_secs", a.duration_secs)?;
+    result.set_item("sample_rate", a.sample_rate)?;
+    result.set_item("channels", a.channels)?;
+    result.set_item("channel_peaks", a.channel_peaks)?;
+    result.set_item("peak_dbfs", a.peak_dbfs)?;
+    result.set_item("integrated_lufs", a.integrated_lufs)?;
+    Ok(result)
+}
+
+/// Mix the project's tracks to a 32-bit float WAV like `play.py mix`, but
+/// without effects. When the tracks would decode to more than `memory_mb`
+/// MB, they're streamed from disk instead.
+#[pyfunction]
+#[pyo3(signature = (state_dir, output, memory_mb = mix::DEFAULT_MEMORY_MB))]
+fn render(py: Python<'_>, state_dir: PathBuf, output: PathBuf, memory_mb: usize) -> PyResult<()> {
+    py.allow_threads(|| mix::render_mix(&state_dir, &output, memory_mb.saturating_mul(1 << 20)))
+        .map_err(error)
+}
+
+#[pymodule]
+fn cornwall_player(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Player>()?;
+    m.add_function(wrap_pyfunction!(analyze, m)?)?;
+    m.add_function(wrap_pyfunction!(render, m)?)?;
+    Ok(())
+}
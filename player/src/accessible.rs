@@ -0,0 +1,247 @@
+//! Screen-reader mode (`--accessible`): no alternate screen, no grid, no
+//! box-drawing or meter glyphs. State changes are announced as plain text
+//! lines, and `i` reads out the whole player state linearly.
+
+use crate::{handle_key, lock::Lock, App};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use std::{
+    io::{self, Write},
+    path::Path,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, PartialEq)]
+struct Snapshot {
+    playing: bool,
+    paused: bool,
+    bar: u32,
+    beat: u32,
+    /// Action feedback covers loop, mono, monitor and cue changes.
+    notice: Option<(String, Instant)>,
+    tone: Option<String>,
+    menu_open: bool,
+    menu_choice: String,
+    switcher_open: bool,
+    switcher_choice: Option<String>,
+}
+
+impl Snapshot {
+    fn of(app: &App) -> Self {
+        let menu = &app.utilities;
+        Snapshot {
+            playing: app.playing,
+            paused: app.paused.is_some(),
+            bar: app.current_bar(),
+            beat: app.current_beat(),
+            notice: app.notice.as_ref().map(|n| (n.text.clone(), n.shown)),
+            tone: app.generator.map(|s| {
+                format!(
+                    "{}, {:.0} dBFS, {}",
+                    s.label(),
+                    menu.level(),
+                    menu.routing.label()
+                )
+            }),
+            menu_open: menu.open,
+            menu_choice: format!(
+                "{}, {:.0} dBFS, output {}",
+                menu.signal().label(),
+                menu.level(),
+                menu.routing.label()
+            ),
+            switcher_open: app.switcher.open,
+            switcher_choice: app
+                .switcher
+                .chosen()
+                .map(|w| format!("{}, {}", w.name, w.state_dir.display())),
+        }
+    }
+}
+
+fn say(line: &str) {
+    let mut out = io::stdout();
+    let _ = write!(out, "{}\r\n", line);
+    let _ = out.flush();
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn level_db(level: f64) -> String {
+    if level <= 0.0 {
+        "silent".to_string()
+    } else {
+        format!("{:.0} dB", 20.0 * level.log10())
+    }
+}
+
+fn announce_changes(old: &Snapshot, new: &Snapshot) {
+    if new.playing != old.playing {
+        if new.playing {
+            say(&format!("Playing from bar {} beat {}", new.bar, new.beat));
+        } else if new.paused {
+            say(&format!("Paused at bar {} beat {}", old.bar, old.beat));
+        } else {
+            say(&format!("Stopped at bar {} beat {}", old.bar, old.beat));
+        }
+    }
+    if let Some((text, shown)) = &new.notice {
+        if old.notice.as_ref().map(|(_, s)| s) != Some(shown) {
+            say(text);
+        }
+    }
+    if new.tone != old.tone {
+        match &new.tone {
+            Some(tone) => say(&format!("Tone on: {}", tone)),
+            None => say("Tone off"),
+        }
+    }
+    if new.menu_open != old.menu_open {
+        if new.menu_open {
+            say("Utilities menu. Up and down choose the signal, left and right the level, c the output, enter starts or stops, u closes.");
+            say(&new.menu_choice);
+        } else {
+            say("Utilities menu closed");
+        }
+    } else if new.menu_open && new.menu_choice != old.menu_choice {
+        say(&new.menu_choice);
+    }
+    if new.switcher_open != old.switcher_open {
+        if new.switcher_open {
+            say("Project switcher. Up and down choose the project, enter opens it, escape closes.");
+            say(new
+                .switcher_choice
+                .as_deref()
+                .unwrap_or("No workspaces registered"));
+        } else {
+            say("Project switcher closed");
+        }
+    } else if new.switcher_open && new.switcher_choice != old.switcher_choice {
+        if let Some(choice) = &new.switcher_choice {
+            say(choice);
+        }
+    }
+}
+
+/// Read out the full player state, one fact per line.
+fn describe(app: &App) {
+    say(&format!(
+        "Project {}, {} BPM, {}",
+        app.project.name,
+        app.bpm(),
+        app.time_sig
+    ));
+    let file = app
+        .audio_file
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    say(&format!("File {}, {:.1} seconds", file, app.audio_duration));
+    say(&format!(
+        "Transport {}, bar {} beat {}, {:.1} seconds, loop {}",
+        match (app.playing, app.paused) {
+            (true, _) => "playing",
+            (false, Some(_)) => "paused",
+            (false, None) => "stopped",
+        },
+        app.current_bar(),
+        app.current_beat(),
+        app.position,
+        on_off(app.looping)
+    ));
+    say(&format!(
+        "Levels left {}, right {}",
+        level_db(app.meter.current_l),
+        level_db(app.meter.current_r)
+    ));
+    say(&format!(
+        "Monitor {:+.0} dB, mute {}, dim {}, mono {}",
+        app.monitor.volume_db,
+        on_off(app.monitor.muted),
+        on_off(app.monitor.dimmed),
+        on_off(app.mono.load(Ordering::Relaxed))
+    ));
+    if app.tracks.is_empty() {
+        say("No tracks");
+    }
+    for t in &app.tracks {
+        let source = t
+            .source
+            .as_deref()
+            .map(|s| {
+                Path::new(s)
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+            })
+            .unwrap_or_else(|| "no audio".into());
+        let mut line = format!(
+            "Track {}, {}, volume {:.1}, pan {:.1}, {}",
+            t.id, t.name, t.volume, t.pan, source
+        );
+        if t.mute {
+            line.push_str(", muted");
+        }
+        if t.solo {
+            line.push_str(", soloed");
+        }
+        say(&line);
+    }
+}
+
+fn help() {
+    say("Keys: space play or pause, enter stop, left and right seek 5 seconds, shift with them a bar, page up and page down 30 seconds, home the start, l loop, o mono, minus and plus monitor volume, m mute, d dim, brackets cue level, up and down select a track, x and s mute and solo it, p prelistens its file, comma and period its volume, n adds a marker, 1 to 9 go to markers, u undo, control r redo, control s save, t metronome, v VU meters, control l MIDI learn, braces speed, parentheses pitch, capital O oscilloscope, capital V vectorscope, capital G loudness gauge, capital F freeze the meters and save their readings, capital T tuner, capital E export the loop region's stems, capital N write a session note, control n show the notes, control o switch project, capital S skip silence, capital R play the loop region once, capital P MIDI panic, capital U utilities, i describe, question mark help, q quit.");
+}
+
+pub fn run(app: &mut App) -> io::Result<()> {
+    say("Cornwall player, accessible mode. Press i to describe, question mark for keys.");
+    describe(app);
+    if let Some(checkpoint) = &app.recovery {
+        say(&checkpoint.prompt());
+    }
+
+    let tick_rate = Duration::from_millis(33);
+    let mut last = Snapshot::of(app);
+    loop {
+        if event::poll(tick_rate)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    match key.code {
+                        KeyCode::Char('i') if !app.utilities.open && app.command.is_none() => {
+                            describe(app)
+                        }
+                        KeyCode::Char('?') if app.command.is_none() => help(),
+                        _ => {
+                            if !handle_key(app, key) {
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        app.tick();
+        // The caller opens the new project and comes back here
+        if app.switch_to.is_some() {
+            break;
+        }
+        if app.lock.as_mut().is_some_and(Lock::taken_over) {
+            say("Another player took over this project. Exiting.");
+            break;
+        }
+
+        let now = Snapshot::of(app);
+        if now != last {
+            announce_changes(&last, &now);
+            last = now;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,91 @@
+//! Loudness and peak analysis of WAV files for QA checks: sample peaks per
+//! channel and integrated loudness per ITU-R BS.1770-4, computed by
+//! `cornwall-dsp` like the web review page does. [`normalize`] uses it to
+//! write a copy of a file at a given peak or loudness.
+
+use crate::wav;
+use cornwall_dsp::{
+    levels::{dbfs, sample_peaks},
+    loudness::integrated_loudness,
+};
+use std::path::{Path, PathBuf};
+
+pub struct Analysis {
+    pub duration_secs: f64,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Sample peak of each channel, dBFS.
+    pub channel_peaks: Vec<f64>,
+    /// Loudest channel's peak, dBFS.
+    pub peak_dbfs: f64,
+    /// Integrated loudness, LUFS; negative infinity for silence.
+    pub integrated_lufs: f64,
+}
+
+pub fn analyze(path: &Path) -> Result<Analysis, String> {
+    let (spec, samples) = wav::read(path)?;
+    let channels = spec.channels as usize;
+    if channels == 0 || spec.sample_rate == 0 {
+        return Err(format!("{}: empty WAV header", path.display()));
+    }
+    let frames = samples.len() / channels;
+
+    let channel_peaks: Vec<f64> = sample_peaks(&samples, channels)
+        .into_iter()
+        .map(dbfs)
+        .collect();
+    let peak_dbfs = channel_peaks
+        .iter()
+        .copied()
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    Ok(Analysis {
+        duration_secs: frames as f64 / spec.sample_rate as f64,
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+        channel_peaks,
+        peak_dbfs,
+        integrated_lufs: integrated_loudness(&samples, channels, spec.sample_rate),
+    })
+}
+
+/// What [`normalize`] aims for.
+#[derive(Clone, Copy)]
+pub enum Target {
+    /// Sample peak, dBFS.
+    Peak(f64),
+    /// Integrated loudness, LUFS.
+    Lufs(f64),
+}
+
+pub struct Normalized {
+    pub path: PathBuf,
+    pub gain_db: f64,
+    /// Sample peak of the copy before any clipping, dBFS.
+    pub peak_dbfs: f64,
+}
+
+/// Write a copy of `path` brought to `target`, as `<name>-normalized.wav`
+/// beside it in the same format.
+pub fn normalize(path: &Path, target: Target) -> Result<Normalized, String> {
+    let analysis = analyze(path)?;
+    let gain_db = match target {
+        Target::Peak(dbfs) => dbfs - analysis.peak_dbfs,
+        Target::Lufs(lufs) => lufs - analysis.integrated_lufs,
+    };
+    if !gain_db.is_finite() {
+        return Err(format!("{}: silent, nothing to normalize", path.display()));
+    }
+
+    let (spec, samples) = wav::read(path)?;
+    let gain = 10f64.powf(gain_db / 20.0);
+    let samples: Vec<f64> = samples.iter().map(|s| s * gain).collect();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let output = path.with_file_name(format!("{}-normalized.wav", stem));
+    wav::write(&output, spec, &samples)?;
+    Ok(Normalized {
+        path: output,
+        gain_db,
+        peak_dbfs: analysis.peak_dbfs + gain_db,
+    })
+}
@@ -0,0 +1,323 @@
+//! A virtual MIDI output that marks every beat of the player's grid, so
+//! drum machines, lights and visuals can follow it:
+//!
+//! ```json
+//! "midi_output": {"port": "Cornwall", "channel": 16, "downbeat": "note 36", "beat": "note 37"}
+//! ```
+//!
+//! The first beat of each bar sends `downbeat`, the others `beat`: a note
+//! held until the next beat, a controller at 127 then back to 0, or a
+//! program change. Beats come from the tempo and time signature the
+//! transport shows. A thread of its own times them, so they don't wait for
+//! the UI tick.
+//!
+//! The metronome is the same grid sent to a hardware port, so a drum module
+//! plays the click; `t` turns it on and off:
+//!
+//! ```json
+//! "metronome": {"port": "TD-17", "channel": 10, "accent": "note 34", "beat": "note 33"}
+//! ```
+//!
+//! Downbeats and accents go out at velocity 127, other beats at 100.
+//!
+//! MIDI panic (`P`) sends All Notes Off and Reset All Controllers on every
+//! channel of both ports.
+
+use crate::midi_control::Trigger;
+use midir::{MidiOutput, MidiOutputConnection};
+use serde::Deserialize;
+use std::{
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A beat this far behind is still sent, late, when playback starts on it.
+const LATE: f64 = 0.02;
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct MidiOutputConfig {
+    /// Name of the virtual port other programs connect to.
+    pub port: String,
+    /// 1-based MIDI channel.
+    pub channel: u8,
+    /// Sent on the first beat of a bar.
+    pub downbeat: String,
+    /// Sent on the other beats.
+    pub beat: String,
+}
+
+impl Default for MidiOutputConfig {
+    fn default() -> Self {
+        MidiOutputConfig {
+            port: "Cornwall".to_string(),
+            channel: 16,
+            downbeat: "note 36".to_string(),
+            beat: "note 37".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct MetronomeConfig {
+    /// Output port name (substring match). Uses the first port when unset.
+    pub port: Option<String>,
+    /// 1-based MIDI channel; 10 is the General MIDI drum channel.
+    pub channel: u8,
+    /// Sent on the first beat of a bar; GM2 metronome bell by default.
+    pub accent: String,
+    /// Sent on the other beats; GM2 metronome click by default.
+    pub beat: String,
+    /// Click from startup rather than waiting for `toggle_metronome`.
+    pub enabled: bool,
+}
+
+impl Default for MetronomeConfig {
+    fn default() -> Self {
+        MetronomeConfig {
+            port: None,
+            channel: 10,
+            accent: "note 34".to_string(),
+            beat: "note 33".to_string(),
+            enabled: false,
+        }
+    }
+}
+
+/// What the output thread is told.
+enum Update {
+    /// The grid to follow, `None` while stopped.
+    Schedule(Option<Schedule>),
+    /// Silence everything on the port.
+    Panic,
+}
+
+/// Where the beats fall while playing.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Schedule {
+    /// When playback reached `offset`.
+    pub started: Instant,
+    /// Seconds into the file at `started`.
+    pub offset: f64,
+    pub beat_secs: f64,
+    pub beats_per_bar: u32,
+}
+
+impl Schedule {
+    /// Index of the first beat at or after `now`, counting one that has
+    /// only just gone.
+    fn next_beat(&self, now: Instant) -> u64 {
+        let position = self.offset + now.saturating_duration_since(self.started).as_secs_f64();
+        ((position - LATE) / self.beat_secs).ceil().max(0.0) as u64
+    }
+
+    fn at(&self, beat: u64) -> Instant {
+        let secs = beat as f64 * self.beat_secs - self.offset;
+        if secs >= 0.0 {
+            self.started + Duration::from_secs_f64(secs)
+        } else {
+            self.started
+        }
+    }
+}
+
+pub struct BeatOutput {
+    updates: Sender<Update>,
+    last: Option<Schedule>,
+}
+
+impl BeatOutput {
+    /// The beat output on a virtual port.
+    pub fn open(config: &MidiOutputConfig) -> Result<Self, String> {
+        let (downbeat, beat) = triggers(&config.downbeat, &config.beat, config.channel)?;
+        let connection = open_virtual(&config.port)?;
+        Ok(Self::start(connection, config.channel, downbeat, beat))
+    }
+
+    /// The metronome on a hardware port.
+    pub fn metronome(config: &MetronomeConfig) -> Result<Self, String> {
+        let (accent, beat) = triggers(&config.accent, &config.beat, config.channel)?;
+        let connection = open_port(config.port.as_deref())?;
+        Ok(Self::start(connection, config.channel, accent, beat))
+    }
+
+    fn start(
+        connection: MidiOutputConnection,
+        channel: u8,
+        downbeat: Trigger,
+        beat: Trigger,
+    ) -> Self {
+        let (updates, rx) = mpsc::channel();
+        let output = Output {
+            connection,
+            channel: channel - 1,
+            downbeat,
+            beat,
+            held: None,
+        };
+        thread::spawn(move || output.run(rx));
+        BeatOutput {
+            updates,
+            last: None,
+        }
+    }
+
+    /// The grid to follow, `None` while stopped. Unchanged schedules
+    /// aren't resent.
+    pub fn update(&mut self, schedule: Option<Schedule>) {
+        if schedule != self.last {
+            self.last = schedule;
+            let _ = self.updates.send(Update::Schedule(schedule));
+        }
+    }
+
+    /// All Notes Off and Reset All Controllers on every channel.
+    pub fn panic(&self) {
+        let _ = self.updates.send(Update::Panic);
+    }
+}
+
+fn triggers(downbeat: &str, beat: &str, channel: u8) -> Result<(Trigger, Trigger), String> {
+    let parse =
+        |s: &str| Trigger::parse(s).ok_or_else(|| format!("invalid MIDI output message \"{}\"", s));
+    if !(1..=16).contains(&channel) {
+        return Err(format!("MIDI channel {} is not 1-16", channel));
+    }
+    Ok((parse(downbeat)?, parse(beat)?))
+}
+
+fn open_port(wanted: Option<&str>) -> Result<MidiOutputConnection, String> {
+    let output = MidiOutput::new("cornwall-player").map_err(|e| e.to_string())?;
+    let ports: Vec<_> = output
+        .ports()
+        .into_iter()
+        .filter_map(|p| {
+            let name = output.port_name(&p).ok()?;
+            Some((p, name))
+        })
+        .collect();
+    let found = match wanted {
+        Some(wanted) => ports.iter().find(|(_, name)| name.contains(wanted)),
+        None => ports.first(),
+    };
+    let Some((port, name)) = found else {
+        let names: Vec<&str> = ports.iter().map(|(_, name)| name.as_str()).collect();
+        return Err(match wanted {
+            Some(wanted) => format!(
+                "no MIDI output matches \"{}\" ({})",
+                wanted,
+                names.join(", ")
+            ),
+            None => "no MIDI outputs".to_string(),
+        });
+    };
+    output
+        .connect(port, "cornwall-player-metronome")
+        .map_err(|e| format!("{}: {}", name, e))
+}
+
+#[cfg(unix)]
+fn open_virtual(port: &str) -> Result<MidiOutputConnection, String> {
+    use midir::os::unix::VirtualOutput;
+    MidiOutput::new("cornwall-player")
+        .map_err(|e| e.to_string())?
+        .create_virtual(port)
+        .map_err(|e| format!("{}: {}", port, e))
+}
+
+#[cfg(not(unix))]
+fn open_virtual(_port: &str) -> Result<MidiOutputConnection, String> {
+    Err("virtual MIDI ports need macOS or Linux".to_string())
+}
+
+/// The output thread's end.
+struct Output {
+    connection: MidiOutputConnection,
+    channel: u8,
+    downbeat: Trigger,
+    beat: Trigger,
+    /// Note or controller to release at the next beat or stop.
+    held: Option<Trigger>,
+}
+
+impl Output {
+    fn run(mut self, updates: Receiver<Update>) {
+        let mut schedule: Option<Schedule> = None;
+        let mut next = 0;
+        loop {
+            let received = match schedule {
+                Some(s) => {
+                    let wait = s.at(next).saturating_duration_since(Instant::now());
+                    updates.recv_timeout(wait)
+                }
+                None => updates.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(Update::Panic) => {
+                    self.held = None;
+                    for channel in 0..16 {
+                        let _ = self.connection.send(&[0xB0 | channel, 123, 0]);
+                        let _ = self.connection.send(&[0xB0 | channel, 121, 0]);
+                    }
+                }
+                Ok(Update::Schedule(update)) => {
+                    let now = Instant::now();
+                    next = match (schedule, update) {
+                        // Drift corrections and clock tempo changes keep
+                        // playing from the same place; don't repeat a beat
+                        (Some(old), Some(new)) if old.offset == new.offset => {
+                            next.max(new.next_beat(now))
+                        }
+                        (_, Some(new)) => new.next_beat(now),
+                        (_, None) => {
+                            self.release();
+                            0
+                        }
+                    };
+                    schedule = update;
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    let Some(s) = schedule else {
+                        continue;
+                    };
+                    let (trigger, velocity) = if next % s.beats_per_bar.max(1) as u64 == 0 {
+                        (self.downbeat, 127)
+                    } else {
+                        (self.beat, 100)
+                    };
+                    self.release();
+                    self.press(trigger, velocity);
+                    next += 1;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.release();
+                    return;
+                }
+            }
+        }
+    }
+
+    fn press(&mut self, trigger: Trigger, velocity: u8) {
+        let message = match trigger {
+            Trigger::Note(note) => [0x90 | self.channel, note, velocity],
+            Trigger::Cc(cc) => [0xB0 | self.channel, cc, 127],
+            Trigger::Program(program) => {
+                let _ = self.connection.send(&[0xC0 | self.channel, program]);
+                return;
+            }
+        };
+        let _ = self.connection.send(&message);
+        self.held = Some(trigger);
+    }
+
+    fn release(&mut self) {
+        let message = match self.held.take() {
+            Some(Trigger::Note(note)) => [0x80 | self.channel, note, 0],
+            Some(Trigger::Cc(cc)) => [0xB0 | self.channel, cc, 0],
+            _ => return,
+        };
+        let _ = self.connection.send(&message);
+    }
+}
@@ -0,0 +1,302 @@
+//! Command-line parsing.
+
+use crate::{
+    convert::{Conversion, BITS},
+    lan_sync::Role,
+};
+use cornwall_player::analysis::Target;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: cornwall-player [OPTIONS] [FILE]
+       cornwall-player --status [STATE_DIR]
+       cornwall-player schema
+       cornwall-player scan [STATE_DIR]
+       cornwall-player discover [--json]
+       cornwall-player workspace (add [STATE_DIR] [--name NAME] | list | remove NAME)
+       cornwall-player normalize FILE (--peak DBFS | --lufs LUFS)
+       cornwall-player convert IN OUT [--rate HZ] [--bits BITS] [--channels N]
+                       [--no-dither]
+
+Plays FILE, or the project's mix.wav, or the first track with audio.
+schema prints the JSON Schema of the --status JSON, which is also what
+state/.player.json, --status-fifo and GET /status give.
+scan prints the loudness, peak and length of every track source and the
+mix, to compare their gain staging.
+discover lists the players running on the LAN, found over mDNS, with
+their address and HTTP port.
+workspace registers the project in STATE_DIR (found from the current
+directory by default) under its name or NAME, lists the registered ones
+or removes one; Ctrl+o in the player switches between them.
+normalize writes a copy of the WAV file FILE brought to a sample peak
+(--peak -1dB) or integrated loudness (--lufs -16) beside it, as
+NAME-normalized.wav in the same format. convert rewrites IN (WAV, FLAC
+or Ogg Vorbis) as the WAV file OUT, keeping whatever isn't given; BITS
+is 8, 16, 24, 32 or 32f for float, and fewer integer bits are dithered
+unless --no-dither.
+
+Options:
+  --accessible   Screen-reader mode: plain text announcements, no grid layout
+  --clock        Start in big-clock mode (bar:beat and timecode only)
+  --ascii        Draw meters, bars and borders with ASCII characters only
+  --no-color     Disable colors (also honored via the NO_COLOR variable)
+  --no-braille   Draw the waveform and meters with block characters
+  --http PORT    Serve a remote-control page and JSON API on PORT, on
+                 localhost unless the config sets http.host
+  --status-fifo PATH
+                 Stream a status JSON line per tick to the named pipe PATH
+  --stream HOST:PORT
+                 Stream the main output to HOST:PORT as RTP (Opus), in a
+                 build with the rtp feature
+  --lead         Broadcast the transport for followers on the LAN
+  --follow       Follow a leading player's transport on the LAN
+  --take-over    Replace a player already running for this project
+  --record FILE  Write each key press and when it came to FILE
+  --replay FILE  Press the keys in a --record file again at the same times
+  --bench        Play a fixed 20 s script and print frame, event loop,
+                 audio callback and status-write timings on exit
+  --status       Print the running player's status JSON and exit
+  -h, --help     Show this help";
+
+pub enum Command {
+    Play,
+    Status(Option<PathBuf>),
+    Schema,
+    Scan(Option<PathBuf>),
+    Discover(bool),
+    Workspace(Workspace),
+    Normalize(PathBuf, Target),
+    Convert(PathBuf, PathBuf, Conversion),
+    Help,
+}
+
+pub enum Workspace {
+    Add(Option<PathBuf>, Option<String>),
+    List,
+    Remove(String),
+}
+
+pub struct Options {
+    pub command: Command,
+    pub file: Option<PathBuf>,
+    pub accessible: bool,
+    pub clock: bool,
+    pub ascii: bool,
+    pub no_color: bool,
+    pub no_braille: bool,
+    pub take_over: bool,
+    pub http: Option<u16>,
+    pub status_fifo: Option<PathBuf>,
+    pub sync: Option<Role>,
+    pub stream: Option<String>,
+    pub bench: bool,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+}
+
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        command: Command::Play,
+        file: None,
+        accessible: false,
+        clock: false,
+        ascii: false,
+        no_color: false,
+        no_braille: false,
+        take_over: false,
+        http: None,
+        status_fifo: None,
+        sync: None,
+        stream: None,
+        bench: false,
+        record: None,
+        replay: None,
+    };
+    let mut args = args.into_iter().skip(1).peekable();
+    if args.next_if(|a| a == "schema").is_some() {
+        options.command = Command::Schema;
+        return Ok(options);
+    }
+    if args.next_if(|a| a == "scan").is_some() {
+        options.command = Command::Scan(args.next().map(PathBuf::from));
+        return Ok(options);
+    }
+    if args.next_if(|a| a == "discover").is_some() {
+        options.command = match args.next().as_deref() {
+            None => Command::Discover(false),
+            Some("--json") => Command::Discover(true),
+            Some(arg) => return Err(format!("unknown discover option {}", arg)),
+        };
+        return Ok(options);
+    }
+    if args.next_if(|a| a == "workspace").is_some() {
+        options.command = Command::Workspace(parse_workspace(args)?);
+        return Ok(options);
+    }
+    if args.next_if(|a| a == "normalize").is_some() {
+        options.command = parse_normalize(args)?;
+        return Ok(options);
+    }
+    if args.next_if(|a| a == "convert").is_some() {
+        options.command = parse_convert(args)?;
+        return Ok(options);
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--status" => {
+                options.command = Command::Status(args.next().map(PathBuf::from));
+                break;
+            }
+            "-h" | "--help" => options.command = Command::Help,
+            "--accessible" => options.accessible = true,
+            "--clock" => options.clock = true,
+            "--ascii" => options.ascii = true,
+            "--no-color" => options.no_color = true,
+            "--no-braille" => options.no_braille = true,
+            "--take-over" => options.take_over = true,
+            "--bench" => options.bench = true,
+            "--lead" => options.sync = Some(Role::Lead),
+            "--follow" => options.sync = Some(Role::Follow),
+            "--http" => {
+                let port = args.next().ok_or("--http needs a port")?;
+                options.http = Some(port.parse().map_err(|_| format!("invalid port {}", port))?);
+            }
+            "--stream" => {
+                options.stream = Some(args.next().ok_or("--stream needs HOST:PORT")?);
+            }
+            "--record" => {
+                let path = args.next().ok_or("--record needs a file")?;
+                options.record = Some(PathBuf::from(path));
+            }
+            "--replay" => {
+                let path = args.next().ok_or("--replay needs a file")?;
+                options.replay = Some(PathBuf::from(path));
+            }
+            "--status-fifo" => {
+                let path = args.next().ok_or("--status-fifo needs a path")?;
+                options.status_fifo = Some(PathBuf::from(path));
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option {}", flag)),
+            file if options.file.is_none() => options.file = Some(PathBuf::from(file)),
+            extra => return Err(format!("unexpected argument {}", extra)),
+        }
+    }
+    if options.accessible && (options.record.is_some() || options.replay.is_some()) {
+        return Err("--record and --replay need the full-screen player, not --accessible".into());
+    }
+    Ok(options)
+}
+
+/// `workspace add state --name demo`, `workspace list` or `workspace remove
+/// demo`.
+fn parse_workspace(mut args: impl Iterator<Item = String>) -> Result<Workspace, String> {
+    let workspace = match args.next().as_deref() {
+        Some("add") => {
+            let mut dir = None;
+            let mut name = None;
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--name" => name = Some(args.next().ok_or("--name needs a name")?),
+                    flag if flag.starts_with("--") => {
+                        return Err(format!("unknown option {}", flag))
+                    }
+                    path if dir.is_none() => dir = Some(PathBuf::from(path)),
+                    extra => return Err(format!("unexpected argument {}", extra)),
+                }
+            }
+            return Ok(Workspace::Add(dir, name));
+        }
+        Some("list") => Workspace::List,
+        Some("remove") => Workspace::Remove(args.next().ok_or("workspace remove needs a name")?),
+        Some(other) => return Err(format!("unknown workspace command {}", other)),
+        None => return Err("workspace needs add, list or remove".into()),
+    };
+    match args.next() {
+        Some(extra) => Err(format!("unexpected argument {}", extra)),
+        None => Ok(workspace),
+    }
+}
+
+/// `normalize FILE --peak -1dB` or `normalize FILE --lufs -16`.
+fn parse_normalize(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut file = None;
+    let mut target = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--peak" | "--lufs" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("{} needs a level", arg))?;
+                let number = value
+                    .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+                    .parse::<f64>()
+                    .map_err(|_| format!("invalid level {}", value))?;
+                target = Some(if arg == "--peak" {
+                    Target::Peak(number)
+                } else {
+                    Target::Lufs(number)
+                });
+            }
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            path if file.is_none() => file = Some(PathBuf::from(path)),
+            extra => return Err(format!("unexpected argument {}", extra)),
+        }
+    }
+    let file = file.ok_or("normalize needs a file")?;
+    let target = target.ok_or("normalize needs --peak or --lufs")?;
+    Ok(Command::Normalize(file, target))
+}
+
+/// `convert IN OUT --rate 48000 --bits 24 --channels 2 --no-dither`.
+fn parse_convert(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut paths = Vec::new();
+    let mut conversion = Conversion {
+        dither: true,
+        ..Conversion::default()
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--rate" => {
+                let rate = value()?;
+                conversion.rate = Some(
+                    rate.parse()
+                        .ok()
+                        .filter(|&r| r > 0)
+                        .ok_or_else(|| format!("invalid sample rate {}", rate))?,
+                );
+            }
+            "--bits" => {
+                let bits = value()?;
+                if bits == "32f" {
+                    conversion.float = true;
+                } else {
+                    conversion.bits = Some(
+                        bits.parse()
+                            .ok()
+                            .filter(|b| BITS.contains(b))
+                            .ok_or_else(|| format!("invalid bit depth {}", bits))?,
+                    );
+                }
+            }
+            "--channels" => {
+                let channels = value()?;
+                conversion.channels = Some(
+                    channels
+                        .parse()
+                        .ok()
+                        .filter(|&c| c > 0)
+                        .ok_or_else(|| format!("invalid channel count {}", channels))?,
+                );
+            }
+            "--no-dither" => conversion.dither = false,
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            path if paths.len() < 2 => paths.push(PathBuf::from(path)),
+            extra => return Err(format!("unexpected argument {}", extra)),
+        }
+    }
+    let [input, output]: [PathBuf; 2] = paths
+        .try_into()
+        .map_err(|_| "convert needs an input and an output file")?;
+    Ok(Command::Convert(input, output, conversion))
+}
@@ -0,0 +1,164 @@
+//! Big-clock display: bar:beat in large block digits with the timecode
+//! under it, in smaller block digits when there's room, and the transport
+//! state above, readable from across the room on a small terminal.
+
+use crate::{format_position, i18n::tr, state_indicator, App};
+use ratatui::{
+    layout::Alignment,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+use std::sync::atomic::Ordering;
+
+/// 3x5 pixel digits; `#` is a lit pixel.
+const DIGITS: [[&str; 5]; 10] = [
+    ["###", "# #", "# #", "# #", "###"],
+    ["  #", "  #", "  #", "  #", "  #"],
+    ["###", "  #", "###", "#  ", "###"],
+    ["###", "  #", "###", "  #", "###"],
+    ["# #", "# #", "###", "  #", "  #"],
+    ["###", "#  ", "###", "  #", "###"],
+    ["###", "#  ", "###", "# #", "###"],
+    ["###", "  #", "  #", "  #", "  #"],
+    ["###", "# #", "###", "# #", "###"],
+    ["###", "# #", "###", "  #", "###"],
+];
+const COLON: [&str; 5] = [" ", "#", " ", "#", " "];
+const DOT: [&str; 5] = [" ", " ", " ", " ", "#"];
+const MAX_SCALE: usize = 4;
+
+fn glyph(c: char) -> [&'static str; 5] {
+    match (c, c.to_digit(10)) {
+        (_, Some(d)) => DIGITS[d as usize],
+        ('.', _) => DOT,
+        _ => COLON,
+    }
+}
+
+/// Width of `text` in font pixels, with one blank pixel between glyphs.
+fn pixel_width(text: &str) -> usize {
+    let glyphs: usize = text.chars().map(|c| glyph(c)[0].len()).sum();
+    glyphs + text.chars().count().saturating_sub(1)
+}
+
+/// Rasterize `text`. Each pixel is two cells wide (terminal cells are about
+/// twice as tall as they are wide), times `scale` in both directions.
+fn big_text(text: &str, scale: usize) -> Vec<String> {
+    let mut rows = Vec::new();
+    for row in 0..5 {
+        let mut line = String::new();
+        for (i, c) in text.chars().enumerate() {
+            if i > 0 {
+                line.push_str(&" ".repeat(2 * scale));
+            }
+            for px in glyph(c)[row].chars() {
+                let cell = if px == '#' { "█" } else { " " };
+                line.push_str(&cell.repeat(2 * scale));
+            }
+        }
+        for _ in 0..scale {
+            rows.push(line.clone());
+        }
+    }
+    rows
+}
+
+pub fn render(f: &mut Frame, app: &App) {
+    let area = f.area();
+    let readout = format!("{}:{}", app.current_bar(), app.current_beat());
+
+    let mut status = vec![state_indicator(app)];
+    if app.looping {
+        status.push(Span::styled(
+            format!("  {}", tr("badge.loop")),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+    if app.mono.load(Ordering::Relaxed) {
+        status.push(Span::styled(
+            format!("  {}", tr("badge.mono")),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if app.monitor.muted {
+        status.push(Span::styled(
+            format!("  {}", tr("badge.mute")),
+            Style::default().fg(Color::Red),
+        ));
+    }
+
+    let digits_style = Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD);
+    let timecode = format_position(app.position);
+    let fits = |text: &str, scale: usize| 2 * pixel_width(text) * scale <= area.width as usize;
+    // Both in block digits, the timecode at half the size, with the status
+    // and tempo lines and a blank line around each: the largest that fits
+    let both = (1..=MAX_SCALE).rev().find(|&scale| {
+        let small = scale.div_ceil(2);
+        fits(&readout, scale)
+            && fits(&timecode, small)
+            && 5 * (scale + small) + 5 <= area.height as usize
+    });
+    // Status and timecode lines plus a blank line around the digits
+    let spare_rows = area.height.saturating_sub(4) as usize;
+    let scale = both.unwrap_or(
+        (area.width as usize / (2 * pixel_width(&readout)))
+            .min(spare_rows / 5)
+            .min(MAX_SCALE),
+    );
+    let big: Vec<Line> = if scale == 0 {
+        // Too small for block digits; still show the readout
+        vec![Line::from(Span::styled(
+            format!(
+                "{} {}  {} {}",
+                tr("transport.bar"),
+                app.current_bar(),
+                tr("transport.beat"),
+                app.current_beat()
+            ),
+            digits_style,
+        ))]
+    } else {
+        big_text(&readout, scale)
+            .into_iter()
+            .map(|row| Line::from(Span::styled(row, digits_style)))
+            .collect()
+    };
+
+    let tempo = Span::styled(
+        format!("{} {}  {}", app.bpm(), tr("transport.bpm"), app.time_sig),
+        Style::default().fg(Color::DarkGray),
+    );
+    let timecode_style = Style::default()
+        .fg(Color::White)
+        .add_modifier(Modifier::BOLD);
+    let mut below: Vec<Line> = match both {
+        Some(scale) => {
+            let mut rows: Vec<Line> = big_text(&timecode, scale.div_ceil(2))
+                .into_iter()
+                .map(|row| Line::from(Span::styled(row, timecode_style)))
+                .collect();
+            rows.push(Line::from(""));
+            rows.push(Line::from(tempo));
+            rows
+        }
+        None => vec![Line::from(vec![
+            Span::styled(timecode, timecode_style),
+            Span::raw("   "),
+            tempo,
+        ])],
+    };
+
+    let height = big.len() + below.len() + 3;
+    let mut lines = vec![Line::from(""); (area.height as usize).saturating_sub(height) / 2];
+    lines.push(Line::from(status));
+    lines.push(Line::from(""));
+    lines.extend(big);
+    lines.push(Line::from(""));
+    lines.append(&mut below);
+
+    f.render_widget(Paragraph::new(lines).alignment(Alignment::Center), area);
+}
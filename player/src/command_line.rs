@@ -0,0 +1,318 @@
+//! The `:` command line (vim profile, or any key bound to `command_line`).
+//!
+//! Commands:
+//! - `:q`, `:quit` (asks first if there are unsaved edits), `:q!` quits
+//!   without saving
+//! - `:seek 33`, `:seek 33.2`, `:seek 33.2.480` (bar.beat.tick, 960 ticks
+//!   per beat) or `:seek 1:32.5` (minutes:seconds)
+//! - `:loop 33 41` loops bars 33 through 41, `:loop off` clears the region,
+//!   `:loop` alone toggles looping
+//! - `:w` saves track and marker edits, `:wq` saves and quits
+//! - `:mark [NAME]` adds a marker at the playhead, `:jump 2` seeks to marker
+//!   2, `:delmark 2` deletes it
+//! - `:reload` rereads the project, tracks, markers and audio after the
+//!   Cornwall CLI changed them
+//! - `:audition 3` plays track 3's source in place of the mix, `:audition
+//!   off` goes back
+//! - `:speed 80` plays at 80% without changing pitch, `:pitch -2` shifts
+//!   the pitch two semitones down without changing speed
+//! - `:practice 17 24` loops bars 17-24 from a reduced speed that ramps up
+//!   as it goes round (`:practice` alone uses the current loop),
+//!   `:practice off` ends it
+//! - `:scope 40` spans the oscilloscope over 40 ms, `:scope` alone shows
+//!   or hides it
+//! - `:loudness broadcast` colours the loudness gauge against -23 LUFS
+//!   (`streaming` is -14, or give a number), `:loudness` alone shows or
+//!   hides it
+//! - `:tone 440 -18` plays a 440 Hz sine at -18 dBFS peak in place of the
+//!   file (`pink`, `white` or `sweep` for noise at RMS or a 20 Hz-20 kHz log
+//!   sweep; the level defaults to the utilities menu's), `:tone off` stops
+//! - `:measure` plays a sweep through the monitors and saves the room's
+//!   impulse response from the microphone input to `state/measurements/`
+//! - `:note TEXT` saves a session note at the playhead (`N` starts one
+//!   where it was pressed)
+//! - `:import labels.txt` adds the labels from an Audacity label file as
+//!   markers
+//! - `:export reaper` or `:export ardour` writes the markers and loop
+//!   region as a Reaper region CSV or Ardour locations to the project folder
+//! - `:snapshot` saves the project and tracks to `state/snapshots/`,
+//!   `:diff` shows or hides what has changed since the latest one
+//! - `:mixer NAME` switches the MIDI mixer mapping profile
+//! - `:lua CODE` runs a line of Lua with the scripting API
+//! - any action name from the keymap, e.g. `:toggle_mono`
+
+use crate::{
+    edits::Edit,
+    gauge,
+    generator::Signal,
+    i18n::{tr, trf},
+    keymap::Action,
+    quantize::Queued,
+    App,
+};
+use crossterm::event::KeyCode;
+use std::path::Path;
+
+const TICKS_PER_BEAT: f64 = 960.0;
+
+/// Quietest level `:tone` takes.
+const MIN_TONE_DBFS: f32 = -60.0;
+
+/// Edit the pending command line. Returns false when the player should quit.
+pub fn handle_key(app: &mut App, code: KeyCode) -> bool {
+    let Some(line) = &mut app.command else {
+        return true;
+    };
+    match code {
+        KeyCode::Char(c) => line.push(c),
+        KeyCode::Backspace if line.is_empty() => {
+            app.command = None;
+            app.note_at = None;
+        }
+        KeyCode::Backspace => {
+            line.pop();
+        }
+        KeyCode::Esc => {
+            app.command = None;
+            app.note_at = None;
+        }
+        KeyCode::Enter => {
+            let line = app.command.take().unwrap_or_default();
+            match execute(app, line.trim()) {
+                Ok(keep_running) => return keep_running,
+                Err(e) => app.notify_error(e),
+            }
+        }
+        _ => {}
+    }
+    true
+}
+
+pub fn execute(app: &mut App, line: &str) -> Result<bool, String> {
+    if let Some(code) = line.strip_prefix("lua ") {
+        let result = app.script.eval(code, app.script_view());
+        return Ok(app.apply_script(result));
+    }
+    let mut words = line.split_whitespace();
+    let Some(name) = words.next() else {
+        return Ok(true);
+    };
+    let args: Vec<&str> = words.collect();
+    match (name, args.as_slice()) {
+        ("q" | "quit" | "qa", []) => return Ok(app.request_quit()),
+        ("q!" | "qa!", []) => return Ok(false),
+        ("w", []) => app.save_edits(),
+        ("wq" | "x", []) => {
+            app.save_edits();
+            return Ok(app.history.dirty);
+        }
+        ("note", text) if !text.is_empty() => {
+            let position = app.note_at.take().unwrap_or(app.position);
+            app.add_note(text.join(" "), position, None);
+        }
+        ("export", [daw]) => app.export_locations(daw)?,
+        ("import", path) if !path.is_empty() => app.import_labels(Path::new(&path.join(" ")))?,
+        ("mark", []) => app.add_marker(None),
+        ("mark", name) => app.add_marker(Some(name.join(" "))),
+        ("jump", [n]) => {
+            let marker = parse_marker(app, n)?;
+            app.queue(Queued::Seek(app.markers[marker].position));
+        }
+        ("delmark", [n]) => {
+            let marker = parse_marker(app, n)?;
+            app.history.record(Edit::Markers, &app.tracks, &app.markers);
+            let removed = app.markers.remove(marker);
+            app.notify(trf("notice.marker_deleted", &[("name", &removed.name)]));
+        }
+        ("seek", [pos]) => {
+            let secs = parse_position(app, pos)?;
+            app.seek(secs);
+            app.notify(app.position_label());
+        }
+        ("loop", []) => app.queue(Queued::ToggleLoop),
+        ("loop", ["off"]) => {
+            app.loop_region = None;
+            app.notify(tr("notice.loop_cleared"));
+        }
+        ("loop", [start, end]) => {
+            let start = parse_bar(start)?;
+            let end = parse_bar(end)?;
+            if end < start {
+                return Err(trf("error.loop_order", &[("start", &start), ("end", &end)]));
+            }
+            if app.bar_secs().is_none() {
+                return Err(tr("error.no_tempo").to_string());
+            }
+            app.loop_region = Some((start, end));
+            app.looping = true;
+            app.notify(app.loop_label());
+        }
+        ("reload", []) => {
+            app.reload()?;
+            app.notify(tr("notice.reloaded"));
+        }
+        ("audition", ["off"]) => app.audition(None)?,
+        ("audition", [id]) => {
+            let id = id
+                .parse()
+                .map_err(|_| trf("error.unknown_track", &[("id", id)]))?;
+            app.audition(Some(id))?;
+        }
+        ("mixer", name) if !name.is_empty() => {
+            let name = name.join(" ");
+            let midi_control = app
+                .midi_control
+                .as_mut()
+                .ok_or_else(|| tr("error.no_midi_input").to_string())?;
+            midi_control.select_mixer(&name)?;
+            app.notify(trf("notice.mixer", &[("name", &name)]));
+        }
+        ("speed", [percent]) => {
+            let percent: f64 = percent
+                .trim_end_matches('%')
+                .parse()
+                .map_err(|_| trf("error.usage", &[("usage", &usage(name))]))?;
+            app.set_speed(percent / 100.0);
+        }
+        ("practice", []) => app.start_practice(None),
+        ("practice", ["off"]) => app.stop_practice(),
+        ("practice", [start, end]) => {
+            let start = parse_bar(start)?;
+            let end = parse_bar(end)?;
+            if end < start {
+                return Err(trf("error.loop_order", &[("start", &start), ("end", &end)]));
+            }
+            if app.bar_secs().is_none() {
+                return Err(tr("error.no_tempo").to_string());
+            }
+            app.start_practice(Some((start, end)));
+        }
+        ("pitch", [semitones]) => {
+            let semitones: i32 = semitones
+                .trim_start_matches('+')
+                .parse()
+                .map_err(|_| trf("error.usage", &[("usage", &usage(name))]))?;
+            app.set_pitch(semitones);
+        }
+        ("scope", []) => return Ok(app.perform(Action::ToggleScope)),
+        ("scope", [ms]) => {
+            let ms: f64 = ms
+                .trim_end_matches("ms")
+                .parse()
+                .map_err(|_| trf("error.usage", &[("usage", &usage(name))]))?;
+            app.set_scope_window(ms);
+        }
+        ("loudness", []) => return Ok(app.perform(Action::ToggleLoudness)),
+        ("loudness", [target]) => {
+            let target = gauge::parse_target(target)
+                .ok_or_else(|| trf("error.usage", &[("usage", &usage(name))]))?;
+            app.set_loudness_target(target);
+        }
+        ("measure", []) => app.start_measurement(),
+        ("snapshot", []) => app.take_snapshot(),
+        ("diff", []) => app.toggle_diff(),
+        ("tone", ["off"]) => {
+            if app.generator.is_some() {
+                app.stop();
+            }
+        }
+        ("tone", [signal]) => {
+            let signal = Signal::parse(signal)
+                .ok_or_else(|| trf("error.usage", &[("usage", &usage(name))]))?;
+            app.start_generator(signal, app.utilities.level());
+        }
+        ("tone", [signal, level]) => {
+            let signal = Signal::parse(signal)
+                .ok_or_else(|| trf("error.usage", &[("usage", &usage(name))]))?;
+            let level: f32 = level
+                .trim_end_matches("dbfs")
+                .parse()
+                .ok()
+                .filter(|level| (MIN_TONE_DBFS..=0.0).contains(level))
+                .ok_or_else(|| trf("error.usage", &[("usage", &usage(name))]))?;
+            app.start_generator(signal, level);
+        }
+        (
+            "seek" | "loop" | "jump" | "delmark" | "audition" | "mixer" | "speed" | "pitch"
+            | "practice" | "scope" | "loudness" | "tone" | "measure" | "snapshot" | "diff" | "note"
+            | "export" | "import",
+            _,
+        ) => return Err(trf("error.usage", &[("usage", &usage(name))])),
+        _ => match Action::from_name(name) {
+            Some(action) if args.is_empty() => return Ok(app.perform(action)),
+            _ => return Err(trf("error.unknown_command", &[("command", &line)])),
+        },
+    }
+    Ok(true)
+}
+
+/// Whether `line` would end the player; remote callers can't run these.
+pub fn quits(line: &str) -> bool {
+    let name = line.split_whitespace().next().unwrap_or_default();
+    matches!(name, "q" | "q!" | "quit" | "qa" | "qa!" | "wq" | "x")
+}
+
+fn usage(name: &str) -> &'static str {
+    match name {
+        "seek" => ":seek BAR[.BEAT[.TICK]] | :seek M:SS",
+        "jump" | "delmark" => ":jump MARKER | :delmark MARKER",
+        "audition" => ":audition TRACK | :audition off",
+        "mixer" => ":mixer PROFILE",
+        "speed" => ":speed PERCENT",
+        "pitch" => ":pitch SEMITONES",
+        "practice" => ":practice [START_BAR END_BAR | off]",
+        "scope" => ":scope [MILLISECONDS]",
+        "loudness" => ":loudness [streaming | broadcast | LUFS]",
+        "measure" => ":measure",
+        "snapshot" => ":snapshot",
+        "diff" => ":diff",
+        "note" => ":note TEXT",
+        "export" => ":export reaper | ardour",
+        "import" => ":import LABELS.txt",
+        "tone" => ":tone HZ | pink | white | sweep [DBFS] | :tone off",
+        _ => ":loop [START_BAR END_BAR | off]",
+    }
+}
+
+/// Index into `app.markers` from a 1-based marker number.
+fn parse_marker(app: &App, s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(n) if (1..=app.markers.len()).contains(&n) => Ok(n - 1),
+        _ => Err(trf("error.no_marker", &[("value", &s)])),
+    }
+}
+
+fn parse_bar(s: &str) -> Result<u32, String> {
+    match s.parse() {
+        Ok(bar) if bar >= 1 => Ok(bar),
+        _ => Err(trf("error.invalid_bar", &[("value", &s)])),
+    }
+}
+
+/// Seconds from `bar.beat.tick` (1-based bar and beat) or `m:ss.s`.
+pub fn parse_position(app: &App, s: &str) -> Result<f64, String> {
+    let invalid = || trf("error.invalid_position", &[("value", &s)]);
+    if let Some((min, sec)) = s.split_once(':') {
+        let min: f64 = min.parse().map_err(|_| invalid())?;
+        let sec: f64 = sec.parse().map_err(|_| invalid())?;
+        return Ok(min * 60.0 + sec);
+    }
+    let bar_secs = app.bar_secs().ok_or(tr("error.no_tempo"))?;
+    let beat_secs = bar_secs / app.time_sig.beats_per_bar() as f64;
+    let mut parts = s.split('.');
+    let bar = parse_bar(parts.next().unwrap_or_default()).map_err(|_| invalid())?;
+    let beat: u32 = match parts.next() {
+        Some(b) => b.parse().ok().filter(|&b| b >= 1).ok_or_else(invalid)?,
+        None => 1,
+    };
+    let tick: f64 = match parts.next() {
+        Some(t) => t.parse().map_err(|_| invalid())?,
+        None => 0.0,
+    };
+    if parts.next().is_some() || beat > app.time_sig.beats_per_bar() {
+        return Err(invalid());
+    }
+    Ok((bar - 1) as f64 * bar_secs
+        + (beat - 1) as f64 * beat_secs
+        + tick / TICKS_PER_BEAT * beat_secs)
+}
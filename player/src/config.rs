@@ -0,0 +1,198 @@
+//! Player configuration, read from `state/player-config.json`.
+//!
+//! Every field is optional; a missing file or missing keys fall back to defaults.
+
+use crate::{
+    beat_out::{MetronomeConfig, MidiOutputConfig},
+    gauge::LoudnessConfig,
+    hooks::HooksConfig,
+    http::HttpConfig,
+    keymap::KeysConfig,
+    lan_sync::SyncConfig,
+    markers::HotCues,
+    mdns::DiscoveryConfig,
+    measure::MeasureConfig,
+    midi_control::MidiInputConfig,
+    practice::PracticeConfig,
+    program::MemoryConfig,
+    quantize::Quantize,
+    resample::Quality,
+    scope::ScopeConfig,
+    silence::SkipSilenceConfig,
+    stretch,
+    tuner::TunerConfig,
+    watchdog::WatchdogConfig,
+};
+use cornwall_player::json;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct PlayerConfig {
+    pub output: OutputConfig,
+    /// Secondary cue/headphone output. Disabled when absent.
+    pub cue: Option<CueConfig>,
+    pub keys: KeysConfig,
+    /// UI language (`"de"`). Taken from `LANG` when unset.
+    pub locale: Option<String>,
+    /// Shell commands run on transport events.
+    pub hooks: HooksConfig,
+    /// SF2 file that MIDI tracks are rendered through.
+    pub soundfont: Option<PathBuf>,
+    /// MIDI input that drives the transport. Disabled when absent.
+    pub midi_input: Option<MidiInputConfig>,
+    /// Virtual MIDI port sending beats. Disabled when absent.
+    pub midi_output: Option<MidiOutputConfig>,
+    /// Click played by a drum module over MIDI. Disabled when absent.
+    pub metronome: Option<MetronomeConfig>,
+    /// Grid that play, loop toggles and marker jumps snap to.
+    pub quantize: Quantize,
+    /// Speed ramp for `:practice`.
+    pub practice: PracticeConfig,
+    /// What keys 1-9 do with markers 1-9.
+    pub hot_cues: HotCues,
+    /// Jumping over silence during playback.
+    pub skip_silence: SkipSilenceConfig,
+    /// Where the playhead goes on stop.
+    pub stop: StopPosition,
+    /// Oscilloscope pane.
+    pub scope: ScopeConfig,
+    /// Loudness gauge pane.
+    pub loudness: LoudnessConfig,
+    /// How the level meters move.
+    pub meter: MeterConfig,
+    /// How the waveform overview is coloured.
+    pub waveform_color: WaveformColor,
+    /// Input and concert pitch for the tuner.
+    pub tuner: TunerConfig,
+    /// Microphone and sweep level for `:measure`.
+    pub measure: MeasureConfig,
+    /// Address and token for `--http`.
+    pub http: HttpConfig,
+    /// Port and address for `--lead` and `--follow`.
+    pub sync: SyncConfig,
+    /// mDNS announcement for `discover`.
+    pub discovery: DiscoveryConfig,
+    /// Decoded audio held in memory before files play from disk.
+    pub memory: MemoryConfig,
+    /// How long the output may stall before it's reopened.
+    pub watchdog: WatchdogConfig,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct OutputConfig {
+    /// Output device name (substring match). Uses the system default when unset.
+    pub device: Option<String>,
+    /// 1-based device channels that receive the main mix, as `[left, right]`.
+    pub main: [u16; 2],
+    /// Run the device at this rate instead of its default.
+    pub sample_rate: Option<u32>,
+    /// Resampler used when program material differs from the device rate.
+    pub resample: Quality,
+    /// Time-stretch used when playing at other than normal speed.
+    pub stretch: stretch::Quality,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        OutputConfig {
+            device: None,
+            main: [1, 2],
+            sample_rate: None,
+            resample: Quality::default(),
+            stretch: stretch::Quality::default(),
+        }
+    }
+}
+
+impl PlayerConfig {
+    pub fn load(state_dir: &Path) -> Result<Self, String> {
+        json::load(&state_dir.join("player-config.json"))
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct CueConfig {
+    /// Cue device name (substring match). Uses the system default when unset.
+    pub device: Option<String>,
+    /// 1-based device channels that receive the cue mix, as `[left, right]`.
+    pub channels: [u16; 2],
+    pub source: CueSource,
+    /// Cue level in dB, independent of the main output.
+    pub level_db: f32,
+}
+
+impl Default for CueConfig {
+    fn default() -> Self {
+        CueConfig {
+            device: None,
+            channels: [1, 2],
+            source: CueSource::Mix,
+            level_db: 0.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct MeterConfig {
+    pub mode: MeterMode,
+    /// Level that reads 0 VU, in dBFS.
+    pub reference_dbfs: f64,
+}
+
+impl Default for MeterConfig {
+    fn default() -> Self {
+        MeterConfig {
+            mode: MeterMode::Rms,
+            reference_dbfs: -18.0,
+        }
+    }
+}
+
+/// Level meter ballistics.
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MeterMode {
+    /// Bars of the RMS level over 50 ms.
+    Rms,
+    /// Needles averaging over 300 ms, like an analogue VU meter.
+    Vu,
+}
+
+/// Where the playhead goes when playback stops.
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StopPosition {
+    /// Back to where play was last pressed.
+    Origin,
+    /// Back to the start of the file.
+    #[default]
+    Zero,
+    /// Where playback got to.
+    Stay,
+}
+
+/// How the waveform overview is coloured.
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WaveformColor {
+    /// By brightness, dark red for bass to bright cyan for treble.
+    #[default]
+    Tone,
+    /// Cyan where played, grey ahead.
+    Plain,
+}
+
+/// What the cue output plays.
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CueSource {
+    /// Same program as the main output.
+    Mix,
+    /// The first soloed track's source, falling back to the mix.
+    Solo,
+}
@@ -0,0 +1,108 @@
+//! Command socket for the Cornwall CLI, the other direction from
+//! `.player.json`: `state/.player.sock` takes one `:` command per line
+//! (`reload`, `seek 17`, `audition 3`, `mark Verse` …) and answers each with
+//! a JSON line, `{"ok": true, "status": {...}}` or
+//! `{"ok": false, "error": "..."}`. `scripts/player.py send` wraps it.
+//!
+//! Like the HTTP API, lines are read on their own threads and run on the UI
+//! thread once per tick. Unix only.
+
+use crate::{command_line, App};
+use cornwall_player::status::PlayerStatus;
+use serde::Serialize;
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    time::Duration,
+};
+
+pub const SOCKET: &str = ".player.sock";
+
+/// Longest a connection waits for the UI thread to answer.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize)]
+struct Reply {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    status: PlayerStatus,
+}
+
+type Pending = (String, Sender<String>);
+
+pub struct Control {
+    requests: Receiver<Pending>,
+    pub path: PathBuf,
+}
+
+impl Control {
+    #[cfg(unix)]
+    pub fn start(state_dir: &Path) -> Result<Self, String> {
+        use std::{
+            io::{BufRead, BufReader, Write},
+            os::unix::net::UnixListener,
+            thread,
+        };
+        let path = state_dir.join(SOCKET);
+        // Left by a player that crashed; the lock says the directory is ours
+        let _ = std::fs::remove_file(&path);
+        let listener =
+            UnixListener::bind(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let (tx, requests) = mpsc::channel::<Pending>();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    let _ = stream.set_read_timeout(Some(REPLY_TIMEOUT));
+                    let mut writer = match stream.try_clone() {
+                        Ok(w) => w,
+                        Err(_) => return,
+                    };
+                    for line in BufReader::new(stream).lines() {
+                        let Ok(line) = line else {
+                            return;
+                        };
+                        let (reply, answer) = mpsc::channel();
+                        if tx.send((line, reply)).is_err() {
+                            return;
+                        }
+                        let Ok(answer) = answer.recv_timeout(REPLY_TIMEOUT) else {
+                            return;
+                        };
+                        if writeln!(writer, "{}", answer).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        Ok(Control { requests, path })
+    }
+
+    #[cfg(not(unix))]
+    pub fn start(_state_dir: &Path) -> Result<Self, String> {
+        Err("the command socket needs Unix".to_string())
+    }
+
+    /// Commands waiting for the UI thread.
+    pub fn pending(&self) -> Vec<Pending> {
+        self.requests.try_iter().collect()
+    }
+}
+
+/// Run one command line against the player and build the reply.
+pub fn respond(app: &mut App, line: &str) -> String {
+    let line = line.trim().trim_start_matches(':');
+    let result = if command_line::quits(line) {
+        Err("quit is not available over the command socket".to_string())
+    } else {
+        command_line::execute(app, line).map(|_| ())
+    };
+    let reply = Reply {
+        ok: result.is_ok(),
+        error: result.err(),
+        status: app.status(),
+    };
+    serde_json::to_string(&reply).unwrap_or_default()
+}
@@ -0,0 +1,137 @@
+//! `cornwall-player convert IN OUT`: rewrites an audio file as WAV at another
+//! sample rate, bit depth or channel count. WAV input is read at its full
+//! depth; anything else goes through rodio's decoders, which give 16 bits.
+//! Rate changes use the player's best-quality resampler, and going down to
+//! fewer integer bits adds TPDF dither unless told not to.
+
+use crate::resample::{Quality, Resample};
+use cornwall_player::wav;
+use hound::{SampleFormat, WavSpec};
+use rodio::{buffer::SamplesBuffer, Decoder, Source};
+use std::{fs::File, io::BufReader, path::Path};
+
+/// What to change; `None` keeps the input's.
+#[derive(Default)]
+pub struct Conversion {
+    pub rate: Option<u32>,
+    /// Bits per sample, `32` with `float` for 32-bit float.
+    pub bits: Option<u16>,
+    pub float: bool,
+    pub channels: Option<u16>,
+    pub dither: bool,
+}
+
+/// Integer bit depths a WAV file can be written at.
+pub const BITS: [u16; 4] = [8, 16, 24, 32];
+
+/// Convert `input` to `output`, returning the format written.
+pub fn convert(input: &Path, output: &Path, conversion: &Conversion) -> Result<WavSpec, String> {
+    let (source, samples) = decode(input)?;
+    let channels = conversion.channels.unwrap_or(source.channels);
+    let samples = remix(&samples, source.channels, channels);
+
+    let rate = conversion.rate.unwrap_or(source.sample_rate);
+    let samples: Vec<f64> = if rate == source.sample_rate {
+        samples.into_iter().map(|s| s as f64).collect()
+    } else {
+        let buffer = SamplesBuffer::new(channels, source.sample_rate, samples);
+        Resample::new(buffer, rate, Quality::Best)
+            .map(|s| s as f64)
+            .collect()
+    };
+
+    let spec = match (conversion.bits, conversion.float) {
+        (_, true) => WavSpec {
+            channels,
+            sample_rate: rate,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        },
+        (Some(bits), false) => WavSpec {
+            channels,
+            sample_rate: rate,
+            bits_per_sample: bits,
+            sample_format: SampleFormat::Int,
+        },
+        (None, false) => WavSpec {
+            channels,
+            sample_rate: rate,
+            ..source
+        },
+    };
+    let losing_bits = spec.sample_format == SampleFormat::Int
+        && (source.sample_format == SampleFormat::Float
+            || spec.bits_per_sample < source.bits_per_sample);
+    let samples = if conversion.dither && losing_bits {
+        dither(samples, spec.bits_per_sample)
+    } else {
+        samples
+    };
+    wav::write(output, spec, &samples)?;
+    Ok(spec)
+}
+
+/// Interleaved samples as f32 with the format they came in: WAV through
+/// hound at full depth, anything else through rodio.
+fn decode(path: &Path) -> Result<(WavSpec, Vec<f32>), String> {
+    let is_wav = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("wav"));
+    if is_wav {
+        let (spec, samples) = wav::read(path)?;
+        return Ok((spec, samples.into_iter().map(|s| s as f32).collect()));
+    }
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let decoder = Decoder::new(BufReader::new(file))
+        .map_err(|e| format!("{}: cannot decode ({})", path.display(), e))?;
+    let spec = WavSpec {
+        channels: decoder.channels(),
+        sample_rate: decoder.sample_rate(),
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    if spec.channels == 0 || spec.sample_rate == 0 {
+        return Err(format!("{}: no audio", path.display()));
+    }
+    Ok((spec, decoder.convert_samples::<f32>().collect()))
+}
+
+/// `from` channels to `to`: the average of all of them for mono, else
+/// channel `c` from input channel `c`, wrapping round, so mono fills every
+/// channel and extra channels are dropped.
+fn remix(samples: &[f32], from: u16, to: u16) -> Vec<f32> {
+    let (from, to) = (from as usize, to as usize);
+    if from == to {
+        return samples.to_vec();
+    }
+    samples
+        .chunks_exact(from)
+        .flat_map(|frame| {
+            (0..to).map(move |c| {
+                if to == 1 {
+                    frame.iter().sum::<f32>() / from as f32
+                } else {
+                    frame[c % from]
+                }
+            })
+        })
+        .collect()
+}
+
+/// Add triangular dither of one least significant bit at `bits`, so
+/// quantization error becomes steady noise instead of distortion.
+fn dither(samples: Vec<f64>, bits: u16) -> Vec<f64> {
+    let lsb = 1.0 / (1_i64 << (bits.clamp(8, 32) - 1)) as f64;
+    let mut rng = 0x9E37_79B9_u32;
+    let mut uniform = move || {
+        // xorshift32
+        rng ^= rng << 13;
+        rng ^= rng >> 17;
+        rng ^= rng << 5;
+        rng as f64 / u32::MAX as f64
+    };
+    samples
+        .into_iter()
+        .map(|s| s + (uniform() - uniform()) * lsb)
+        .collect()
+}
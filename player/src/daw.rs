@@ -0,0 +1,116 @@
+//! Markers and the loop region written out for a DAW (`:export reaper`,
+//! `:export ardour`), so what was noted while auditioning carries over to
+//! the session:
+//!
+//! - Reaper: a region/marker CSV for the Region/Marker Manager's import,
+//!   times as minutes:seconds (set the ruler to Minutes:Seconds first)
+//! - Ardour: a `<Locations>` list to paste into the session file, positions
+//!   in samples at the project rate, which Ardour 6 and later both read
+//!
+//! Both go to the project folder. Positions are seconds into the file, so
+//! they line up when the file sits at the start of the DAW session.
+
+use crate::markers::Marker;
+use std::{fs, path::Path};
+
+/// A marker or, with an end, a region.
+pub struct Location<'a> {
+    pub name: &'a str,
+    pub start: f64,
+    pub end: Option<f64>,
+}
+
+pub fn locations<'a>(markers: &'a [Marker], loop_span: Option<(f64, f64)>) -> Vec<Location<'a>> {
+    let mut locations: Vec<Location> = markers
+        .iter()
+        .map(|m| Location {
+            name: &m.name,
+            start: m.position,
+            end: None,
+        })
+        .collect();
+    if let Some((start, end)) = loop_span {
+        locations.push(Location {
+            name: "Loop",
+            start,
+            end: Some(end),
+        });
+    }
+    locations
+}
+
+/// Reaper's region/marker CSV: `M` rows for markers, `R` rows for regions.
+pub fn reaper(locations: &[Location]) -> String {
+    let mut csv = String::from("#,Name,Start,End,Length\n");
+    let (mut markers, mut regions) = (0, 0);
+    for location in locations {
+        let name = format!("\"{}\"", location.name.replace('"', "\"\""));
+        match location.end {
+            Some(end) => {
+                regions += 1;
+                csv += &format!(
+                    "R{},{},{},{},{}\n",
+                    regions,
+                    name,
+                    clock(location.start),
+                    clock(end),
+                    clock(end - location.start)
+                );
+            }
+            None => {
+                markers += 1;
+                csv += &format!("M{},{},{},,\n", markers, name, clock(location.start));
+            }
+        }
+    }
+    csv
+}
+
+/// `m:ss.sss`, as Reaper writes minutes:seconds.
+fn clock(secs: f64) -> String {
+    let millis = (secs.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Ardour's `<Locations>` at `sample_rate`.
+pub fn ardour(locations: &[Location], sample_rate: u32) -> String {
+    let samples = |secs: f64| (secs.max(0.0) * sample_rate as f64).round() as u64;
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Locations>\n");
+    for (i, location) in locations.iter().enumerate() {
+        let start = samples(location.start);
+        let (end, flags) = match location.end {
+            Some(end) => (samples(end), "IsRangeMarker"),
+            None => (start, "IsMark"),
+        };
+        xml += &format!(
+            "  <Location id=\"{}\" name=\"{}\" start=\"{}\" end=\"{}\" flags=\"{}\" locked=\"0\" timestamp=\"0\"/>\n",
+            // Clear of the ids Ardour gives its own objects in a new session
+            900_000 + i,
+            escape(location.name),
+            start,
+            end,
+            flags
+        );
+    }
+    xml + "</Locations>\n"
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Write `contents` to `path`, making the folder if needed.
+pub fn write(path: &Path, contents: &str) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    fs::write(path, contents).map_err(|e| format!("{}: {}", path.display(), e))
+}
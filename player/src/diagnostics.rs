@@ -0,0 +1,247 @@
+//! Timing diagnostics for checking changes to the realtime engine: the
+//! overlay toggled with `F12`, and `--bench`, which plays a fixed script
+//! (playback, seeks, the scope panes, time-stretch, the big clock) for
+//! 20 s and prints the same figures when the player exits.
+//!
+//! - frame: drawing the UI
+//! - event loop: each pass through the loop bar waiting for input
+//!   (drawing, keys, the tick), so the longest a key press can wait
+//! - audio callback: producing one device buffer, against the time the
+//!   buffer lasts (see [`crate::metrics`])
+//! - underruns: output stalls while playing (see [`crate::drift`])
+//! - status write: writing `state/.player.json`
+//!
+//! Times are the mean and worst over the last few seconds.
+
+use crate::{i18n::tr, keymap::Action, metrics::AudioStats, App};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+/// Passes through the loop kept, about 3 s at the 33 ms tick.
+const WINDOW: usize = 90;
+
+/// Recent timings of one kind of work.
+#[derive(Default)]
+pub struct Window {
+    means: VecDeque<Duration>,
+    maxes: VecDeque<Duration>,
+}
+
+impl Window {
+    pub fn push(&mut self, took: Duration) {
+        self.push_span(took, took);
+    }
+
+    /// Add a pass that covered several runs of the work.
+    fn push_span(&mut self, mean: Duration, max: Duration) {
+        if self.means.len() == WINDOW {
+            self.means.pop_front();
+            self.maxes.pop_front();
+        }
+        self.means.push_back(mean);
+        self.maxes.push_back(max);
+    }
+
+    fn mean(&self) -> Option<Duration> {
+        let n = self.means.len() as u32;
+        (n > 0).then(|| self.means.iter().sum::<Duration>() / n)
+    }
+
+    fn max(&self) -> Option<Duration> {
+        self.maxes.iter().max().copied()
+    }
+}
+
+#[derive(Default)]
+pub struct Diagnostics {
+    pub frame: Window,
+    pub event_loop: Window,
+    pub audio_callback: Window,
+    pub status_write: Window,
+}
+
+impl Diagnostics {
+    /// Take in the callbacks the audio thread has timed since the last tick.
+    pub fn update(&mut self, audio: &AudioStats) {
+        if let Some((mean, max)) = audio.take_callbacks() {
+            self.audio_callback.push_span(mean, max);
+        }
+    }
+}
+
+/// `850 µs`, `12.4 ms`.
+fn format_duration(d: Option<Duration>) -> String {
+    match d {
+        None => "-".to_string(),
+        Some(d) if d < Duration::from_millis(1) => format!("{} µs", d.as_micros()),
+        Some(d) => format!("{:.1} ms", d.as_secs_f64() * 1000.0),
+    }
+}
+
+/// Label, mean, worst and a note for each row.
+fn rows(app: &App) -> Vec<(&'static str, String, String, String)> {
+    let d = &app.diagnostics;
+    let timed = |key, window: &Window, note: String| {
+        (
+            key,
+            format_duration(window.mean()),
+            format_duration(window.max()),
+            note,
+        )
+    };
+    let period = app.audio_stats.period();
+    let budget = if period.is_zero() {
+        String::new()
+    } else {
+        format!("/ {}", format_duration(Some(period)))
+    };
+    vec![
+        timed("diagnostics.frame", &d.frame, String::new()),
+        timed("diagnostics.event_loop", &d.event_loop, String::new()),
+        timed("diagnostics.audio_callback", &d.audio_callback, budget),
+        (
+            "diagnostics.underruns",
+            String::new(),
+            app.drift.stalls.to_string(),
+            String::new(),
+        ),
+        timed("diagnostics.status_write", &d.status_write, String::new()),
+    ]
+}
+
+/// The overlay, in the top right corner over whatever is showing.
+pub fn render(f: &mut Frame, app: &App) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let value = Style::default()
+        .fg(Color::White)
+        .add_modifier(Modifier::BOLD);
+    let mut lines = vec![Line::from(vec![
+        Span::raw(format!(" {:<18}", "")),
+        Span::styled(format!("{:>9}", tr("diagnostics.mean")), dim),
+        Span::styled(format!("{:>9}", tr("diagnostics.max")), dim),
+    ])];
+    for (key, mean, max, note) in rows(app) {
+        lines.push(Line::from(vec![
+            Span::styled(format!(" {:<18}", tr(key)), dim),
+            Span::styled(format!("{:>9}", mean), value),
+            Span::styled(format!("{:>9}", max), value),
+            Span::styled(format!(" {}", note), dim),
+        ]));
+    }
+
+    let area = f.area();
+    let width = 52.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let rect = Rect {
+        x: area.right().saturating_sub(width),
+        y: area.y,
+        width,
+        height,
+    };
+    let overlay = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(dim)
+            .title(format!(" {} ", tr("diagnostics.title"))),
+    );
+    f.render_widget(Clear, rect);
+    f.render_widget(overlay, rect);
+}
+
+/// A step of the `--bench` script.
+enum Step {
+    Action(Action),
+    /// Seek to this fraction of the file.
+    Seek(f64),
+}
+
+/// What `--bench` does, at seconds into the run.
+const SCRIPT: &[(f64, Step)] = &[
+    (0.0, Step::Action(Action::TogglePlay)),
+    (3.0, Step::Action(Action::ToggleScope)),
+    (3.0, Step::Action(Action::ToggleVectorscope)),
+    (3.0, Step::Action(Action::ToggleLoudness)),
+    (6.0, Step::Seek(0.5)),
+    (9.0, Step::Action(Action::SpeedDown)),
+    (9.0, Step::Action(Action::PitchUp)),
+    (12.0, Step::Action(Action::SpeedUp)),
+    (12.0, Step::Action(Action::PitchDown)),
+    (12.0, Step::Action(Action::ToggleScope)),
+    (12.0, Step::Action(Action::ToggleVectorscope)),
+    (12.0, Step::Action(Action::ToggleLoudness)),
+    (13.0, Step::Action(Action::ToggleClock)),
+    (15.0, Step::Action(Action::ToggleClock)),
+    (15.0, Step::Seek(0.9)),
+    (17.0, Step::Action(Action::TogglePlay)),
+    (18.0, Step::Action(Action::TogglePlay)),
+];
+
+/// Seconds the `--bench` run lasts.
+const BENCH_SECS: f64 = 20.0;
+
+/// A `--bench` run under way.
+pub struct Bench {
+    started: Instant,
+    next: usize,
+    frames: u64,
+}
+
+impl Bench {
+    pub fn new() -> Self {
+        Bench {
+            started: Instant::now(),
+            next: 0,
+            frames: 0,
+        }
+    }
+
+    /// Take the steps that are due. False once the run is over.
+    pub fn step(&mut self, app: &mut App) -> bool {
+        self.frames += 1;
+        let elapsed = self.started.elapsed().as_secs_f64();
+        while let Some((_, step)) = SCRIPT.get(self.next).filter(|(at, _)| *at <= elapsed) {
+            match step {
+                Step::Action(action) => {
+                    app.perform(*action);
+                }
+                Step::Seek(fraction) => app.seek(app.audio_duration * fraction),
+            }
+            self.next += 1;
+        }
+        elapsed < BENCH_SECS
+    }
+
+    /// The figures, for printing once the terminal is back.
+    pub fn report(&self, app: &App) -> String {
+        let mut out = format!(
+            "Bench: {:.1} s, {} frames, {}\n\n{:<18}{:>10}{:>10}\n",
+            self.started.elapsed().as_secs_f64(),
+            self.frames,
+            app.audio_file.display(),
+            "",
+            "mean",
+            "max"
+        );
+        let labels = [
+            "frame",
+            "event loop",
+            "audio callback",
+            "underruns",
+            "status write",
+        ];
+        for (label, (_, mean, max, note)) in labels.iter().zip(rows(app)) {
+            let _ = writeln!(out, "{:<18}{:>10}{:>10} {}", label, mean, max, note);
+        }
+        out
+    }
+}
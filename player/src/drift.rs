@@ -0,0 +1,76 @@
+//! Keeps the playhead clock in step with the audio actually played.
+//!
+//! The position shown in the UI runs off `Instant` so it moves smoothly
+//! between ticks. The sink's consumed-sample count is what the listener
+//! hears, but it only advances a device buffer at a time. The two are
+//! compared every tick; when the smoothed difference grows past
+//! `TOLERANCE` (device clock skew, underruns, a stalled output) the clock
+//! is re-anchored on the sink. The worst drift and the number of
+//! corrections are reported in `state/.player.json`.
+//!
+//! A sink that stops advancing altogether for `STALL` counts as an output
+//! stall, the closest the player gets to seeing a device underrun.
+
+use std::time::{Duration, Instant};
+
+/// Largest difference allowed before the clock is corrected, in seconds.
+pub const TOLERANCE: f64 = 0.005;
+/// Weight of each new reading in the running average; sink readings move
+/// in buffer-sized steps, so single readings are noisy.
+const SMOOTHING: f64 = 0.1;
+/// Readings further off than this are not drift: just after a restart the
+/// sink can still report the previous source's position for a moment.
+const STALE: f64 = 0.5;
+/// A sink position frozen this long during playback is a stall.
+const STALL: Duration = Duration::from_millis(100);
+
+#[derive(Default)]
+pub struct Drift {
+    average: f64,
+    /// Largest smoothed drift since playback started, in seconds.
+    pub worst: f64,
+    pub corrections: u32,
+    /// Output stalls since the player started; survives `reset`.
+    pub stalls: u32,
+    last_played: f64,
+    advanced: Option<Instant>,
+    stalled: bool,
+}
+
+impl Drift {
+    /// Start over, when playback (re)starts from a new offset.
+    pub fn reset(&mut self) {
+        *self = Drift {
+            stalls: self.stalls,
+            ..Drift::default()
+        };
+    }
+
+    /// Note the sink's position, counting a stall when it stops moving.
+    pub fn watch(&mut self, played: f64) {
+        if played != self.last_played {
+            self.last_played = played;
+            self.advanced = Some(Instant::now());
+            self.stalled = false;
+        } else if !self.stalled && self.advanced.is_some_and(|t| t.elapsed() >= STALL) {
+            self.stalled = true;
+            self.stalls += 1;
+        }
+    }
+
+    /// Compare seconds elapsed on the clock with seconds consumed by the
+    /// sink. Returns true when the clock should be re-anchored on the sink.
+    pub fn check(&mut self, clock: f64, sink: f64) -> bool {
+        if (clock - sink).abs() > STALE {
+            return false;
+        }
+        self.average += (clock - sink - self.average) * SMOOTHING;
+        self.worst = self.worst.max(self.average.abs());
+        if self.average.abs() <= TOLERANCE {
+            return false;
+        }
+        self.average = 0.0;
+        self.corrections += 1;
+        true
+    }
+}
@@ -0,0 +1,122 @@
+//! Player-side edits to tracks (mute, solo, volume, pan) and markers, with
+//! undo/redo. Edits stay in memory until saved back to `state/`.
+
+use crate::{markers::Marker, TrackState};
+use cornwall_player::json;
+use serde_json::Value;
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+const MAX_UNDO: usize = 200;
+/// Repeated nudges of the same control within this window undo as one step.
+const COALESCE: Duration = Duration::from_millis(1500);
+
+/// What an edit touched; used to merge runs of fader nudges.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Edit {
+    Mute(u32),
+    Solo(u32),
+    Volume(u32),
+    Pan(u32),
+    /// Several fields of one track at once, from a script.
+    Track(u32),
+    Markers,
+    /// Unsaved edits put back after a crash.
+    Restore,
+}
+
+#[derive(Clone)]
+struct Snapshot {
+    tracks: Vec<TrackState>,
+    markers: Vec<Marker>,
+}
+
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    last: Option<(Edit, Instant)>,
+    /// Edits not yet written back to the state files.
+    pub dirty: bool,
+}
+
+impl History {
+    /// Remember the state before applying `edit`.
+    pub fn record(&mut self, edit: Edit, tracks: &[TrackState], markers: &[Marker]) {
+        let merge = matches!(edit, Edit::Volume(_) | Edit::Pan(_))
+            && self
+                .last
+                .is_some_and(|(last, at)| last == edit && at.elapsed() < COALESCE);
+        if !merge {
+            self.undo.push(Snapshot {
+                tracks: tracks.to_vec(),
+                markers: markers.to_vec(),
+            });
+            if self.undo.len() > MAX_UNDO {
+                self.undo.remove(0);
+            }
+        }
+        self.redo.clear();
+        self.last = Some((edit, Instant::now()));
+        self.dirty = true;
+    }
+
+    /// Restore the state before the last edit. Returns false if there was
+    /// nothing to undo.
+    pub fn undo(&mut self, tracks: &mut Vec<TrackState>, markers: &mut Vec<Marker>) -> bool {
+        Self::step(&mut self.undo, &mut self.redo, tracks, markers) && self.touched()
+    }
+
+    pub fn redo(&mut self, tracks: &mut Vec<TrackState>, markers: &mut Vec<Marker>) -> bool {
+        Self::step(&mut self.redo, &mut self.undo, tracks, markers) && self.touched()
+    }
+
+    fn step(
+        from: &mut Vec<Snapshot>,
+        to: &mut Vec<Snapshot>,
+        tracks: &mut Vec<TrackState>,
+        markers: &mut Vec<Marker>,
+    ) -> bool {
+        let Some(snapshot) = from.pop() else {
+            return false;
+        };
+        to.push(Snapshot {
+            tracks: std::mem::replace(tracks, snapshot.tracks),
+            markers: std::mem::replace(markers, snapshot.markers),
+        });
+        true
+    }
+
+    fn touched(&mut self) -> bool {
+        self.last = None;
+        self.dirty = true;
+        true
+    }
+}
+
+/// Write mute/solo/volume/pan back into `tracks.json`, leaving every other
+/// field as the Python side wrote it.
+pub fn write_tracks(state_dir: &Path, tracks: &[TrackState]) -> Result<(), String> {
+    let path = state_dir.join("tracks.json");
+    let mut saved: Value = fs::read_to_string(&path)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+        .and_then(|s| json::parse(&path, &s))?;
+    for entry in saved.as_array_mut().into_iter().flatten() {
+        let Some(t) = entry
+            .get("id")
+            .and_then(Value::as_u64)
+            .and_then(|id| tracks.iter().find(|t| t.id as u64 == id))
+        else {
+            continue;
+        };
+        entry["mute"] = Value::from(t.mute);
+        entry["solo"] = Value::from(t.solo);
+        entry["volume"] = Value::from(t.volume);
+        entry["pan"] = Value::from(t.pan);
+    }
+    let json = serde_json::to_string_pretty(&saved).map_err(|e| e.to_string())?;
+    fs::write(&path, json + "\n").map_err(|e| e.to_string())
+}
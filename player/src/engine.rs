@@ -0,0 +1,241 @@
+//! The playback engine without the TUI: one project's audio on the default
+//! output device, with play, pause, stop and seek. This is what the C ABI
+//! ([`crate::ffi`]) wraps. It is a transport of its own, much smaller than
+//! the TUI player's: it plays the audio file as it is, without device
+//! routing, cue output, resampling, loops or MIDI, and its playhead is
+//! where it last started plus what the output has played since: the
+//! sink's own count of the source's frames ([`Sink::get_pos`]), not the
+//! wall clock, so it holds when the device stalls. Bar and beat come from
+//! the same [`TimeSig::bar_beat`] as the player's.
+//!
+//! [`Engine::open_null`] plays into the null output ([`crate::null`])
+//! instead, rendered by the host with [`Engine::render`] and timed by it.
+
+use crate::{
+    null::NullOutput,
+    project::{self, ProjectState},
+    time_sig::TimeSig,
+    wav,
+};
+use rodio::{OutputStream, Sink};
+use std::path::{Path, PathBuf};
+
+/// Playback state, polled by the host.
+#[derive(Clone, Copy, Default)]
+pub struct Status {
+    pub playing: bool,
+    /// Held by [`Engine::pause`]; `playing` is false meanwhile.
+    pub paused: bool,
+    pub position_secs: f64,
+    pub duration_secs: f64,
+    pub bar: u32,
+    pub beat: u32,
+    pub bpm: f64,
+}
+
+enum Output {
+    Device {
+        // Dropping the stream closes the device
+        _stream: OutputStream,
+        sink: Sink,
+    },
+    Null(NullOutput),
+}
+
+pub struct Engine {
+    output: Output,
+    audio_file: PathBuf,
+    duration: f64,
+    project: ProjectState,
+    time_sig: TimeSig,
+    /// Position at the last start, seek or pause.
+    offset: f64,
+    /// Running on from `offset`.
+    playing: bool,
+    /// Paused at `offset`.
+    paused: bool,
+}
+
+impl Engine {
+    /// Open the project in `state_dir` and the audio it would play in the
+    /// player: its mix, or a track when there is no mix.
+    pub fn open(state_dir: &Path) -> Result<Self, String> {
+        let (stream, handle) =
+            OutputStream::try_default().map_err(|e| format!("audio output: {}", e))?;
+        let sink = Sink::try_new(&handle).map_err(|e| format!("audio output: {}", e))?;
+        sink.pause();
+        Self::load(state_dir, |_| Output::Device {
+            _stream: stream,
+            sink,
+        })
+    }
+
+    /// Open the project like [`Self::open`], but on the null output in the
+    /// audio file's rate and channels: nothing plays until [`Self::render`]
+    /// asks for it.
+    pub fn open_null(state_dir: &Path) -> Result<Self, String> {
+        Self::load(state_dir, |spec| {
+            Output::Null(NullOutput::new(spec.channels, spec.sample_rate))
+        })
+    }
+
+    fn load(
+        state_dir: &Path,
+        output: impl FnOnce(hound::WavSpec) -> Output,
+    ) -> Result<Self, String> {
+        let audio_file = project::audio_file(state_dir)?
+            .ok_or_else(|| format!("no audio to play in {}", state_dir.display()))?;
+        let project = ProjectState::load(state_dir)?;
+        let time_sig = TimeSig::parse(&project.time_sig).unwrap_or_default();
+
+        let reader = hound::WavReader::open(&audio_file)
+            .map_err(|e| format!("{}: {}", audio_file.display(), e))?;
+        let spec = reader.spec();
+        if spec.sample_rate == 0 || spec.channels == 0 {
+            return Err(format!("{}: empty WAV header", audio_file.display()));
+        }
+        let duration = reader.len() as f64 / (spec.sample_rate as f64 * spec.channels as f64);
+
+        Ok(Engine {
+            output: output(spec),
+            audio_file,
+            duration,
+            project,
+            time_sig,
+            offset: 0.0,
+            playing: false,
+            paused: false,
+        })
+    }
+
+    pub fn audio_file(&self) -> &Path {
+        &self.audio_file
+    }
+
+    /// Start playing from the playhead; when paused, this resumes.
+    pub fn play(&mut self) -> Result<(), String> {
+        if !self.playing {
+            self.start_at(self.offset)?;
+        }
+        Ok(())
+    }
+
+    /// Hold playback where it is. [`Self::resume`] carries on from the
+    /// same frame.
+    pub fn pause(&mut self) {
+        if !self.playing {
+            return;
+        }
+        self.offset = self.position();
+        self.playing = false;
+        self.paused = true;
+        self.silence();
+    }
+
+    /// Carry on from where [`Self::pause`] held playback. Does nothing
+    /// unless paused.
+    pub fn resume(&mut self) -> Result<(), String> {
+        if self.paused {
+            self.start_at(self.offset)?;
+        }
+        Ok(())
+    }
+
+    /// Stop and rewind to the start of the file, as the player's stop
+    /// does with its default `"stop": "zero"`.
+    pub fn stop(&mut self) {
+        self.playing = false;
+        self.paused = false;
+        self.offset = 0.0;
+        self.silence();
+    }
+
+    fn silence(&mut self) {
+        match &mut self.output {
+            Output::Device { sink, .. } => sink.clear(),
+            Output::Null(null) => null.clear(),
+        }
+    }
+
+    /// Move the playhead, restarting playback there if running. A paused
+    /// engine stays paused at the new place.
+    pub fn seek(&mut self, secs: f64) -> Result<(), String> {
+        let secs = secs.clamp(0.0, self.duration);
+        if self.playing {
+            self.start_at(secs)
+        } else {
+            self.offset = secs;
+            Ok(())
+        }
+    }
+
+    fn start_at(&mut self, offset: f64) -> Result<(), String> {
+        // Decoded from disk as it plays, so opening doesn't wait on the file
+        // and a seek far into a long one doesn't decode up to it, and read
+        // ahead off the audio thread
+        let mut stream = wav::Stream::open(&self.audio_file)?;
+        stream.seek(offset);
+        let source = wav::ReadAhead::new(stream);
+        match &mut self.output {
+            Output::Device { sink, .. } => {
+                sink.clear();
+                sink.append(source);
+                sink.play();
+            }
+            // Renders faster than the reader might keep up with
+            Output::Null(null) => null.play(source.waiting()),
+        }
+        self.offset = offset;
+        self.playing = true;
+        self.paused = false;
+        Ok(())
+    }
+
+    fn position(&self) -> f64 {
+        if !self.playing {
+            return self.offset;
+        }
+        match &self.output {
+            Output::Null(null) => self.offset + null.played_secs(),
+            // Frames taken by the mixer, a device buffer ahead of the ear
+            Output::Device { sink, .. } => self.offset + sink.get_pos().as_secs_f64(),
+        }
+    }
+
+    /// Current state. Playback that reached the end of the file stops here.
+    pub fn status(&mut self) -> Status {
+        let mut position = self.position();
+        if self.playing && position >= self.duration {
+            self.stop();
+            position = 0.0;
+        }
+        let (bar, beat) = self.time_sig.bar_beat(position, self.project.bpm);
+        Status {
+            playing: self.playing,
+            paused: self.paused,
+            position_secs: position,
+            duration_secs: self.duration,
+            bar,
+            beat,
+            bpm: self.project.bpm,
+        }
+    }
+
+    /// The next `frames` frames of the null output, interleaved in the
+    /// audio file's channels; the playhead moves on by as much.
+    pub fn render(&mut self, frames: usize) -> Result<Vec<f32>, String> {
+        match &mut self.output {
+            Output::Null(null) => Ok(null.render(frames)),
+            Output::Device { .. } => Err("only the null output renders".into()),
+        }
+    }
+
+    /// Left and right RMS, linear, of the last meter chunk the null
+    /// output rendered; 0 on a device.
+    pub fn levels(&self) -> (f64, f64) {
+        match &self.output {
+            Output::Null(null) => null.levels(),
+            Output::Device { .. } => (0.0, 0.0),
+        }
+    }
+}
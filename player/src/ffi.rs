@@ -0,0 +1,193 @@
+//! C ABI for embedding the engine in non-Rust front-ends, declared in
+//! `player/include/cornwall_player.h`. `cargo build --release` produces
+//! `libcornwall_player.{so,dylib,a}` next to the player binary.
+//!
+//! ```c
+//! CornwallPlayer *p = cornwall_player_open("/path/to/cornwall/state");
+//! if (!p) fprintf(stderr, "%s\n", cornwall_player_last_error());
+//! cornwall_player_play(p);
+//! CornwallStatus s;
+//! cornwall_player_status(p, &s);  // from the host's run loop or a timer
+//! cornwall_player_close(p);
+//! ```
+//!
+//! Functions returning `int` give 0 on success and -1 on failure, with the
+//! reason in `cornwall_player_last_error()`. A player must only be used from
+//! the thread that opened it.
+
+use crate::engine::Engine;
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_int, CStr, CString},
+    path::Path,
+    ptr,
+};
+
+pub type CornwallPlayer = Engine;
+
+#[repr(C)]
+pub struct CornwallStatus {
+    pub playing: bool,
+    pub position_secs: f64,
+    pub duration_secs: f64,
+    pub bar: u32,
+    pub beat: u32,
+    pub bpm: f64,
+    pub paused: bool,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn fail(message: &str) -> c_int {
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = message);
+    -1
+}
+
+fn result(r: Result<(), String>) -> c_int {
+    match r {
+        Ok(()) => 0,
+        Err(e) => fail(&e),
+    }
+}
+
+/// Open the Cornwall project whose `state` directory is `state_dir` and
+/// get ready to play its mix. Returns NULL on failure.
+///
+/// # Safety
+///
+/// `state_dir` must be NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cornwall_player_open(state_dir: *const c_char) -> *mut CornwallPlayer {
+    if state_dir.is_null() {
+        fail("state_dir is NULL");
+        return ptr::null_mut();
+    }
+    let state_dir = CStr::from_ptr(state_dir).to_string_lossy();
+    match Engine::open(Path::new(state_dir.as_ref())) {
+        Ok(engine) => Box::into_raw(Box::new(engine)),
+        Err(e) => {
+            fail(&e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Stop playback and release the player and its audio device.
+///
+/// # Safety
+///
+/// `player` must be NULL or come from `cornwall_player_open`, and is
+/// invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn cornwall_player_close(player: *mut CornwallPlayer) {
+    if !player.is_null() {
+        drop(Box::from_raw(player));
+    }
+}
+
+/// # Safety
+///
+/// `player` must be NULL or come from `cornwall_player_open`.
+#[no_mangle]
+pub unsafe extern "C" fn cornwall_player_play(player: *mut CornwallPlayer) -> c_int {
+    match player.as_mut() {
+        Some(engine) => result(engine.play()),
+        None => fail("player is NULL"),
+    }
+}
+
+/// Hold playback where it is.
+///
+/// # Safety
+///
+/// `player` must be NULL or come from `cornwall_player_open`.
+#[no_mangle]
+pub unsafe extern "C" fn cornwall_player_pause(player: *mut CornwallPlayer) -> c_int {
+    match player.as_mut() {
+        Some(engine) => {
+            engine.pause();
+            0
+        }
+        None => fail("player is NULL"),
+    }
+}
+
+/// Carry on from where `cornwall_player_pause` held playback; nothing
+/// unless paused.
+///
+/// # Safety
+///
+/// `player` must be NULL or come from `cornwall_player_open`.
+#[no_mangle]
+pub unsafe extern "C" fn cornwall_player_resume(player: *mut CornwallPlayer) -> c_int {
+    match player.as_mut() {
+        Some(engine) => result(engine.resume()),
+        None => fail("player is NULL"),
+    }
+}
+
+/// Stop and rewind to the start.
+///
+/// # Safety
+///
+/// `player` must be NULL or come from `cornwall_player_open`.
+#[no_mangle]
+pub unsafe extern "C" fn cornwall_player_stop(player: *mut CornwallPlayer) -> c_int {
+    match player.as_mut() {
+        Some(engine) => {
+            engine.stop();
+            0
+        }
+        None => fail("player is NULL"),
+    }
+}
+
+/// Move the playhead to `secs`, continuing playback there if playing.
+///
+/// # Safety
+///
+/// `player` must be NULL or come from `cornwall_player_open`.
+#[no_mangle]
+pub unsafe extern "C" fn cornwall_player_seek(player: *mut CornwallPlayer, secs: f64) -> c_int {
+    match player.as_mut() {
+        Some(engine) => result(engine.seek(secs)),
+        None => fail("player is NULL"),
+    }
+}
+
+/// Fill `status` with the current playback state.
+///
+/// # Safety
+///
+/// `player` must be NULL or come from `cornwall_player_open`; `status` must
+/// be NULL or point to a writable `CornwallStatus`.
+#[no_mangle]
+pub unsafe extern "C" fn cornwall_player_status(
+    player: *mut CornwallPlayer,
+    status: *mut CornwallStatus,
+) -> c_int {
+    let (Some(engine), Some(out)) = (player.as_mut(), status.as_mut()) else {
+        return fail("player or status is NULL");
+    };
+    let s = engine.status();
+    *out = CornwallStatus {
+        playing: s.playing,
+        position_secs: s.position_secs,
+        duration_secs: s.duration_secs,
+        bar: s.bar,
+        beat: s.beat,
+        bpm: s.bpm,
+        paused: s.paused,
+    };
+    0
+}
+
+/// The reason the last call on this thread failed. Valid until the next
+/// failing call.
+#[no_mangle]
+pub extern "C" fn cornwall_player_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ptr())
+}
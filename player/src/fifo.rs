@@ -0,0 +1,81 @@
+//! `--status-fifo PATH`: one status JSON line per tick on a named pipe, for
+//! statusbar widgets and shell scripts (`while read -r line < PATH`).
+//!
+//! The pipe is created if it doesn't exist. A writer thread waits for a
+//! reader and reopens the pipe when one goes away, so the UI thread never
+//! blocks on it; lines nobody is reading are dropped.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, SyncSender},
+    thread,
+};
+
+/// Lines queued while the reader catches up; further ones are dropped.
+const BACKLOG: usize = 4;
+
+pub struct StatusFifo {
+    lines: SyncSender<String>,
+}
+
+impl StatusFifo {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        prepare(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let (lines, rx) = mpsc::sync_channel(BACKLOG);
+        let path = path.to_path_buf();
+        thread::spawn(move || write_lines(path, rx));
+        Ok(StatusFifo { lines })
+    }
+
+    /// Queue `line` for the reader, if there is room.
+    pub fn send(&self, line: String) {
+        let _ = self.lines.try_send(line);
+    }
+}
+
+#[cfg(unix)]
+fn prepare(path: &Path) -> Result<(), String> {
+    use std::{os::unix::fs::FileTypeExt, process::Command};
+    match fs::metadata(path) {
+        Ok(meta) if meta.file_type().is_fifo() => Ok(()),
+        Ok(_) => Err("exists and is not a named pipe".to_string()),
+        Err(_) => {
+            let status = Command::new("mkfifo")
+                .arg(path)
+                .status()
+                .map_err(|e| format!("cannot run mkfifo: {}", e))?;
+            if status.success() {
+                Ok(())
+            } else {
+                Err("mkfifo failed".to_string())
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn prepare(_path: &Path) -> Result<(), String> {
+    Err("named pipes are only supported on Unix".to_string())
+}
+
+fn write_lines(path: PathBuf, rx: Receiver<String>) {
+    loop {
+        // Blocks until a reader opens the other end
+        let Ok(mut pipe) = OpenOptions::new().write(true).open(&path) else {
+            return;
+        };
+        // Lines queued while nobody was listening are out of date
+        rx.try_iter().for_each(drop);
+        loop {
+            let Ok(line) = rx.recv() else {
+                return;
+            };
+            if writeln!(pipe, "{}", line).is_err() {
+                // Reader closed its end; wait for the next one
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Loudness gauge (`G`): EBU R128 momentary, short-term and integrated
+//! loudness of the main output against a target, measured from the same
+//! frames as the oscilloscope:
+//!
+//! ```json
+//! "loudness": {"target": -14, "enabled": false}
+//! ```
+//!
+//! `:loudness streaming` (-14 LUFS) or `:loudness broadcast` (-23) switch
+//! the target, as does a number. Integrated loudness starts over each time
+//! play is pressed, so it covers the pass being listened to.
+
+use serde::Deserialize;
+
+/// Targets that can be picked by name.
+const PRESETS: [(&str, f64); 2] = [("streaming", -14.0), ("broadcast", -23.0)];
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct LoudnessConfig {
+    /// LUFS the gauge colours against.
+    pub target: f64,
+    /// Show the gauge from startup.
+    pub enabled: bool,
+}
+
+impl Default for LoudnessConfig {
+    fn default() -> Self {
+        LoudnessConfig {
+            target: -14.0,
+            enabled: false,
+        }
+    }
+}
+
+/// A target from a preset name or a LUFS value between -40 and 0.
+pub fn parse_target(s: &str) -> Option<f64> {
+    PRESETS
+        .iter()
+        .find(|(name, _)| *name == s)
+        .map(|(_, lufs)| *lufs)
+        .or_else(|| s.parse().ok().filter(|lufs| (-40.0..=0.0).contains(lufs)))
+}
+
+/// The preset name of `target`, if it has one.
+pub fn preset_name(target: f64) -> Option<&'static str> {
+    PRESETS
+        .iter()
+        .find(|(_, lufs)| *lufs == target)
+        .map(|(name, _)| *name)
+}
@@ -0,0 +1,216 @@
+//! Reference signal generator for calibrating monitor levels, checking
+//! channel routing and listening for room resonances: sines at any
+//! frequency, pink and white noise, and a repeating log sweep.
+
+use crate::{
+    i18n::{tr, trf},
+    output::db_to_gain,
+};
+use rodio::Source;
+use std::{f32::consts::TAU, time::Duration};
+
+/// RMS of the unscaled pink filter output for uniform white input.
+const PINK_RMS: f32 = 1.745;
+
+/// RMS of uniform white noise from -1 to 1.
+const WHITE_RMS: f32 = 0.577;
+
+/// Range of the sweep and of sines, in Hz.
+pub const MIN_FREQ: f32 = 20.0;
+pub const MAX_FREQ: f32 = 20000.0;
+
+/// Time the sweep takes from bottom to top before starting over.
+const SWEEP_SECS: f32 = 10.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Signal {
+    /// Sine at `freq` Hz; level is peak dBFS.
+    Sine { freq: f32 },
+    /// Pink noise; level is RMS dBFS.
+    Pink,
+    /// White noise; level is RMS dBFS.
+    White,
+    /// Sine rising from `MIN_FREQ` to `MAX_FREQ` at an even rate per
+    /// octave, then again; level is peak dBFS.
+    Sweep,
+}
+
+impl Signal {
+    /// `pink`, `white`, `sweep`, or a sine frequency such as `440`, `440hz`
+    /// or `1k`.
+    pub fn parse(word: &str) -> Option<Signal> {
+        match word.to_lowercase().as_str() {
+            "pink" => Some(Signal::Pink),
+            "white" => Some(Signal::White),
+            "sweep" => Some(Signal::Sweep),
+            word => {
+                let word = word.trim_end_matches("hz");
+                let (digits, scale) = match word.strip_suffix('k') {
+                    Some(digits) => (digits, 1000.0),
+                    None => (word, 1.0),
+                };
+                let freq = digits.parse::<f32>().ok()? * scale;
+                (MIN_FREQ..=MAX_FREQ)
+                    .contains(&freq)
+                    .then_some(Signal::Sine { freq })
+            }
+        }
+    }
+
+    /// Whether the level is RMS rather than peak.
+    pub fn rms(&self) -> bool {
+        matches!(self, Signal::Pink | Signal::White)
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Signal::Sine { freq } if *freq >= 1000.0 => {
+                trf("signal.sine_khz", &[("freq", &(freq / 1000.0))])
+            }
+            Signal::Sine { freq } => trf("signal.sine_hz", &[("freq", freq)]),
+            Signal::Pink => tr("signal.pink").to_string(),
+            Signal::White => tr("signal.white").to_string(),
+            Signal::Sweep => tr("signal.sweep").to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Routing {
+    Both,
+    Left,
+    Right,
+}
+
+impl Routing {
+    pub fn next(self) -> Self {
+        match self {
+            Routing::Both => Routing::Left,
+            Routing::Left => Routing::Right,
+            Routing::Right => Routing::Both,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Routing::Both => "L+R",
+            Routing::Left => "L",
+            Routing::Right => "R",
+        }
+    }
+}
+
+/// Endless stereo test signal.
+pub struct Generator {
+    signal: Signal,
+    sample_rate: u32,
+    gain: f32,
+    routing: Routing,
+    phase: f32,
+    rng: u32,
+    pink: [f32; 7],
+    /// Frames into the current sweep.
+    elapsed: u32,
+    right_next: bool,
+    current: f32,
+}
+
+impl Generator {
+    pub fn new(signal: Signal, level_dbfs: f32, routing: Routing, sample_rate: u32) -> Self {
+        let gain = match signal {
+            Signal::Sine { .. } | Signal::Sweep => db_to_gain(level_dbfs),
+            Signal::Pink => db_to_gain(level_dbfs) / PINK_RMS,
+            Signal::White => db_to_gain(level_dbfs) / WHITE_RMS,
+        };
+        Generator {
+            signal,
+            sample_rate,
+            gain,
+            routing,
+            phase: 0.0,
+            rng: 0x9E37_79B9,
+            pink: [0.0; 7],
+            elapsed: 0,
+            right_next: false,
+            current: 0.0,
+        }
+    }
+
+    fn white(&mut self) -> f32 {
+        // xorshift32
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        self.rng as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+
+    fn next_value(&mut self) -> f32 {
+        match self.signal {
+            Signal::Sine { freq } => {
+                let v = self.phase.sin();
+                self.phase = (self.phase + TAU * freq / self.sample_rate as f32) % TAU;
+                v
+            }
+            Signal::Pink => {
+                // Paul Kellet's refined pink filter
+                let w = self.white();
+                let b = &mut self.pink;
+                b[0] = 0.99886 * b[0] + w * 0.0555179;
+                b[1] = 0.99332 * b[1] + w * 0.0750759;
+                b[2] = 0.96900 * b[2] + w * 0.153852;
+                b[3] = 0.86650 * b[3] + w * 0.3104856;
+                b[4] = 0.55000 * b[4] + w * 0.5329522;
+                b[5] = -0.7616 * b[5] - w * 0.0168980;
+                let v = b.iter().sum::<f32>() + w * 0.5362;
+                b[6] = w * 0.115926;
+                v
+            }
+            Signal::White => self.white(),
+            Signal::Sweep => {
+                let rate = self.sample_rate as f32;
+                let v = self.phase.sin();
+                let octaves = self.elapsed as f32 / rate / SWEEP_SECS;
+                let freq = MIN_FREQ * (MAX_FREQ / MIN_FREQ).powf(octaves);
+                self.phase = (self.phase + TAU * freq / rate) % TAU;
+                self.elapsed = (self.elapsed + 1) % (SWEEP_SECS * rate) as u32;
+                v
+            }
+        }
+    }
+}
+
+impl Iterator for Generator {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let right = self.right_next;
+        self.right_next = !right;
+        if !right {
+            self.current = (self.next_value() * self.gain).clamp(-1.0, 1.0);
+        }
+        let on = match self.routing {
+            Routing::Both => true,
+            Routing::Left => !right,
+            Routing::Right => right,
+        };
+        Some(if on { self.current } else { 0.0 })
+    }
+}
+
+impl Source for Generator {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
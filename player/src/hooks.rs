@@ -0,0 +1,125 @@
+//! Shell commands run on transport events, for driving lighting, OBS and
+//! the like from playback:
+//!
+//! ```json
+//! "hooks": {"play": "obs-cli recording start", "marker": "./scene.sh"}
+//! ```
+//!
+//! Events are `play`, `stop`, `loop` (playback wrapped back to the loop
+//! start) and `marker` (the playhead passed a marker). Commands run through
+//! the shell without waiting for them, with `CORNWALL_EVENT`,
+//! `CORNWALL_POSITION` (seconds), `CORNWALL_BAR`, `CORNWALL_BEAT`,
+//! `CORNWALL_FILE`, `CORNWALL_PROJECT` and, for markers, `CORNWALL_MARKER`
+//! set.
+
+use crate::markers::Marker;
+use serde::Deserialize;
+use std::{
+    process::{Command, Stdio},
+    thread,
+};
+
+/// Playback steps longer than this are seeks, which don't pass markers.
+const MAX_STEP: f64 = 0.5;
+
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct HooksConfig {
+    pub play: Option<String>,
+    pub stop: Option<String>,
+    #[serde(rename = "loop")]
+    pub loop_: Option<String>,
+    pub marker: Option<String>,
+}
+
+pub enum Event {
+    Play,
+    Stop,
+    Loop,
+    Marker(String),
+}
+
+impl Event {
+    fn name(&self) -> &'static str {
+        match self {
+            Event::Play => "play",
+            Event::Stop => "stop",
+            Event::Loop => "loop",
+            Event::Marker(_) => "marker",
+        }
+    }
+}
+
+pub struct Hooks {
+    config: HooksConfig,
+    playing: bool,
+    position: f64,
+}
+
+impl Hooks {
+    pub fn new(config: HooksConfig) -> Self {
+        Hooks {
+            config,
+            playing: false,
+            position: 0.0,
+        }
+    }
+
+    /// Start/stop and markers passed since the last call.
+    pub fn events(&mut self, playing: bool, position: f64, markers: &[Marker]) -> Vec<Event> {
+        let mut events = Vec::new();
+        if playing != self.playing {
+            events.push(if playing { Event::Play } else { Event::Stop });
+        }
+        let step = position - self.position;
+        if playing && self.playing && step > 0.0 && step < MAX_STEP {
+            events.extend(
+                markers
+                    .iter()
+                    .filter(|m| m.position > self.position && m.position <= position)
+                    .map(|m| Event::Marker(m.name.clone())),
+            );
+        }
+        self.playing = playing;
+        self.position = position;
+        events
+    }
+
+    /// Start the command for `event`, if one is configured. `env` carries
+    /// the player state at the time of the event.
+    pub fn run(&self, event: &Event, env: &[(&str, String)]) {
+        let command = match event {
+            Event::Play => &self.config.play,
+            Event::Stop => &self.config.stop,
+            Event::Loop => &self.config.loop_,
+            Event::Marker(_) => &self.config.marker,
+        };
+        let Some(command) = command else {
+            return;
+        };
+        let mut shell = if cfg!(windows) {
+            let mut c = Command::new("cmd");
+            c.arg("/C");
+            c
+        } else {
+            let mut c = Command::new("sh");
+            c.arg("-c");
+            c
+        };
+        shell
+            .arg(command)
+            .envs(env.iter().map(|(k, v)| (*k, v)))
+            .env("CORNWALL_EVENT", event.name())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if let Event::Marker(name) = event {
+            shell.env("CORNWALL_MARKER", name);
+        }
+        // Output would scribble over the TUI; a failed hook just doesn't run
+        if let Ok(mut child) = shell.spawn() {
+            // Reap it when it exits
+            thread::spawn(move || child.wait());
+        }
+    }
+}
@@ -0,0 +1,671 @@
+//! Small HTTP API (`--http PORT`) for dashboards and phone remotes:
+//!
+//! - `GET /` a remote-control page mirroring the TUI: transport, meters,
+//!   the track list with mute and solo, and the markers to jump to
+//! - `GET /status`, `GET /tracks`, `GET /meters`, `GET /markers`,
+//!   `GET /notes` as JSON
+//! - `GET /metrics` in the Prometheus text format
+//! - `POST /play`, `POST /pause`, `POST /stop`, `POST /toggle` (play or
+//!   pause)
+//! - `POST /seek?to=33.2` (any `:seek` position)
+//! - `POST /tracks/ID/mute`, `POST /tracks/ID/solo` toggle a track, as an
+//!   undoable edit like the keys
+//! - `POST /action/NAME` runs a keymap action
+//! - `POST /command?line=loop%2033%2041` runs a `:` command
+//! - `POST /notes?name=Sam&text=Vocal%20too%20loud&at=33.2` adds a review
+//!   note from a listener to the project's notes, at any `:seek` position
+//!   or the playhead without `at`; it shows up in the TUI straight away
+//!
+//! POSTs answer with the status after the change. Connections are read on
+//! their own threads and handed to the UI thread, which owns the player,
+//! once per tick.
+//!
+//! The server listens on localhost unless `http.host` in the player config
+//! says otherwise, and every request carries the token, as `Authorization:
+//! Bearer TOKEN` or `?token=TOKEN` (the page is opened with the latter and
+//! sends the former). The token is `http.token`, or a new one each launch.
+//! No other origin is allowed to read the answers, and `/command` only runs
+//! the transport commands in [`REMOTE_COMMANDS`].
+
+use crate::{command_line, edits::Edit, keymap::Action, metrics, App};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{IpAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Longest a connection waits for the UI thread to answer.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+/// Connections served at once; more are turned away.
+const MAX_CONNECTIONS: usize = 16;
+/// Longest request line and headers taken together.
+const MAX_HEAD_BYTES: u64 = 8192;
+/// The `:` commands `/command` runs: the transport and markers, nothing
+/// that reads or writes files or runs code.
+pub const REMOTE_COMMANDS: &[&str] =
+    &["seek", "jump", "loop", "mark", "speed", "pitch", "practice"];
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// Address to listen on; `0.0.0.0` lets phones on the network connect.
+    pub host: String,
+    /// Token requests must carry. A new one each launch when unset.
+    pub token: Option<String>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            host: "127.0.0.1".to_string(),
+            token: None,
+        }
+    }
+}
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    query: String,
+    /// The `Authorization` header's bearer token.
+    bearer: Option<String>,
+}
+
+impl Request {
+    /// Percent-decoded value of query parameter `name`.
+    pub fn param(&self, name: &str) -> Option<String> {
+        self.query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .and_then(|(_, value)| decode(value))
+    }
+
+    /// Whether the request carries `token`, in the header or the query.
+    fn authorized(&self, token: &str) -> bool {
+        self.bearer
+            .clone()
+            .or_else(|| self.param("token"))
+            .is_some_and(|given| same(given.as_bytes(), token.as_bytes()))
+    }
+}
+
+/// Compare without stopping at the first difference, so the time taken
+/// gives nothing of the token away.
+fn same(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+pub struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json(status: u16, body: String) -> Self {
+        Response {
+            status,
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Response::json(status, to_json(&Error { error: message }))
+    }
+}
+
+#[derive(Serialize)]
+struct Error<'a> {
+    error: &'a str,
+}
+
+#[derive(Serialize)]
+struct Track<'a> {
+    id: u32,
+    name: &'a str,
+    mute: bool,
+    solo: bool,
+    volume: f64,
+    pan: f64,
+}
+
+#[derive(Serialize)]
+struct Marker<'a> {
+    /// 1-based, as `:jump` takes it.
+    number: usize,
+    name: &'a str,
+    position_secs: f64,
+}
+
+#[derive(Serialize)]
+struct Meters {
+    level_l: f64,
+    level_r: f64,
+    position_secs: f64,
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+type Pending = (Request, Sender<Response>);
+
+pub struct Server {
+    requests: Receiver<Pending>,
+    /// Where the remote-control page opens, token included.
+    pub url: String,
+    /// Whether other hosts can connect, so the port is worth announcing.
+    pub on_network: bool,
+}
+
+impl Server {
+    /// Listen on `port` at the configured host.
+    pub fn start(port: u16, config: &HttpConfig) -> Result<Self, String> {
+        let listener = TcpListener::bind((config.host.as_str(), port))
+            .map_err(|e| format!("{}:{}: {}", config.host, port, e))?;
+        let token = match &config.token {
+            Some(token) if !token.is_empty() => token.clone(),
+            Some(_) => return Err("http.token is empty".to_string()),
+            None => new_token()?,
+        };
+        let url = format!("http://{}:{}/?token={}", config.host, port, token);
+        let on_network = config.host != "localhost"
+            && !config
+                .host
+                .parse::<IpAddr>()
+                .is_ok_and(|ip| ip.is_loopback());
+        let (tx, requests) = mpsc::channel();
+        let open = Arc::new(AtomicUsize::new(0));
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                if open.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    open.fetch_sub(1, Ordering::SeqCst);
+                    write_response(&mut stream, &Response::error(503, "too many connections"));
+                    continue;
+                }
+                let (tx, token, open) = (tx.clone(), token.clone(), open.clone());
+                thread::spawn(move || {
+                    serve(stream, &token, tx);
+                    open.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        Ok(Server {
+            requests,
+            url,
+            on_network,
+        })
+    }
+
+    /// Requests waiting for the UI thread.
+    pub fn pending(&self) -> Vec<Pending> {
+        self.requests.try_iter().collect()
+    }
+}
+
+/// 128 random bits in hex.
+fn new_token() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| format!("cannot make a token: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn serve(mut stream: TcpStream, token: &str, tx: Sender<Pending>) {
+    let response = match read_request(&stream) {
+        Some(request) if !request.authorized(token) => Response::error(401, "missing token"),
+        Some(request) => {
+            let (reply, answer) = mpsc::channel();
+            let _ = tx.send((request, reply));
+            answer
+                .recv_timeout(REPLY_TIMEOUT)
+                .unwrap_or_else(|_| Response::error(503, "player busy"))
+        }
+        None => Response::error(400, "bad request"),
+    };
+    write_response(&mut stream, &response);
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Service Unavailable",
+    };
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len(),
+        response.body
+    );
+}
+
+/// Request line and headers, at most `MAX_HEAD_BYTES` of them; bodies are
+/// ignored, parameters go in the query.
+fn read_request(stream: &TcpStream) -> Option<Request> {
+    stream.set_read_timeout(Some(REPLY_TIMEOUT)).ok()?;
+    parse_request(BufReader::new(stream.take(MAX_HEAD_BYTES)))
+}
+
+fn parse_request(mut reader: impl BufRead) -> Option<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if !query.split('&').all(|pair| decode(pair).is_some()) {
+        return None;
+    }
+    // Read to the blank line so the client sees a clean close; running out
+    // first means the head was cut off or too long
+    let mut bearer = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).ok()? == 0 || !header.ends_with('\n') {
+            return None;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("authorization") {
+                bearer = value.trim().strip_prefix("Bearer ").map(str::to_string);
+            }
+        }
+    }
+    Some(Request {
+        method,
+        path: path.to_string(),
+        query: query.to_string(),
+        bearer,
+    })
+}
+
+/// Percent-decode a query value; `None` for a `%` without two hex digits
+/// after it or anything that isn't UTF-8.
+fn decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => {
+                let hex = bytes.get(i + 1..i + 3)?;
+                if !hex.iter().all(u8::is_ascii_hexdigit) {
+                    return None;
+                }
+                out.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+                i += 2;
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8(out).ok()
+}
+
+/// Answer one request against the player.
+pub fn respond(app: &mut App, request: &Request) -> Response {
+    let get = request.method == "GET";
+    let post = request.method == "POST";
+    let result = match request.path.as_str() {
+        "/" if get => {
+            return Response {
+                status: 200,
+                content_type: "text/html; charset=utf-8",
+                body: REMOTE_PAGE.to_string(),
+            }
+        }
+        "/status" if get => Ok(()),
+        "/metrics" if get => {
+            return Response {
+                status: 200,
+                content_type: "text/plain; version=0.0.4",
+                body: metrics::render(app),
+            }
+        }
+        "/tracks" if get => {
+            let tracks: Vec<Track> = app
+                .tracks
+                .iter()
+                .map(|t| Track {
+                    id: t.id,
+                    name: &t.name,
+                    mute: t.mute,
+                    solo: t.solo,
+                    volume: t.volume,
+                    pan: t.pan,
+                })
+                .collect();
+            return Response::json(200, to_json(&tracks));
+        }
+        "/meters" if get => {
+            let meters = Meters {
+                level_l: app.meter.current_l,
+                level_r: app.meter.current_r,
+                position_secs: app.position,
+            };
+            return Response::json(200, to_json(&meters));
+        }
+        "/markers" if get => {
+            let markers: Vec<Marker> = app
+                .markers
+                .iter()
+                .enumerate()
+                .map(|(i, m)| Marker {
+                    number: i + 1,
+                    name: &m.name,
+                    position_secs: m.position,
+                })
+                .collect();
+            return Response::json(200, to_json(&markers));
+        }
+        "/notes" if get => return Response::json(200, to_json(&app.notes)),
+        "/notes" if post => add_note(app, request),
+        "/play" if post => {
+            if app.paused.is_some() {
+                app.resume();
+            } else if !app.playing {
+                app.play();
+            }
+            Ok(())
+        }
+        "/pause" if post => {
+            if app.playing {
+                app.pause();
+            }
+            Ok(())
+        }
+        "/stop" if post => {
+            app.stop();
+            Ok(())
+        }
+        "/toggle" if post => run(app, Action::TogglePlay),
+        "/seek" if post => match request.param("to") {
+            Some(to) => command(app, &format!("seek {}", to)),
+            None => Err("missing ?to=".to_string()),
+        },
+        "/command" if post => match request.param("line") {
+            Some(line) => command(app, &line),
+            None => Err("missing ?line=".to_string()),
+        },
+        path if post && path.starts_with("/tracks/") => {
+            match toggle_track(app, &path["/tracks/".len()..]) {
+                Some(result) => result,
+                None => return Response::error(404, "not found"),
+            }
+        }
+        path if post && path.starts_with("/action/") => {
+            match Action::from_name(&path["/action/".len()..]) {
+                Some(action) => run(app, action),
+                None => return Response::error(404, "unknown action"),
+            }
+        }
+        "/" | "/status" | "/metrics" | "/tracks" | "/meters" | "/markers" | "/play" | "/pause"
+        | "/stop" | "/toggle" | "/seek" | "/command" | "/notes" => {
+            return Response::error(405, "wrong method")
+        }
+        _ => return Response::error(404, "not found"),
+    };
+    match result {
+        Ok(()) => Response::json(200, to_json(&app.status())),
+        Err(e) => Response::error(400, &e),
+    }
+}
+
+/// Quitting isn't offered remotely; the player stays up for the room.
+fn run(app: &mut App, action: Action) -> Result<(), String> {
+    if action == Action::Quit {
+        return Err("quit is not available over HTTP".to_string());
+    }
+    app.perform(action);
+    Ok(())
+}
+
+/// `ID/mute` or `ID/solo`; `None` for any other path.
+fn toggle_track(app: &mut App, path: &str) -> Option<Result<(), String>> {
+    let (id, what) = path.split_once('/')?;
+    let id: u32 = id.parse().ok()?;
+    let Some(index) = app.tracks.iter().position(|t| t.id == id) else {
+        return Some(Err(format!("no track {}", id)));
+    };
+    match what {
+        "mute" => app.edit_track_at(index, Edit::Mute, |t| t.mute = !t.mute),
+        "solo" => app.edit_track_at(index, Edit::Solo, |t| t.solo = !t.solo),
+        _ => return None,
+    }
+    Some(Ok(()))
+}
+
+/// A listener's note: `text`, and optionally `name` and `at`.
+fn add_note(app: &mut App, request: &Request) -> Result<(), String> {
+    let text = request
+        .param("text")
+        .filter(|t| !t.trim().is_empty())
+        .ok_or("missing ?text=")?;
+    let position = match request.param("at") {
+        Some(at) => command_line::parse_position(app, &at)?,
+        None => app.position,
+    };
+    let name = request.param("name").filter(|n| !n.trim().is_empty());
+    app.add_note(text.trim().to_string(), position, name);
+    Ok(())
+}
+
+fn command(app: &mut App, line: &str) -> Result<(), String> {
+    let name = line.split_whitespace().next().unwrap_or_default();
+    if !REMOTE_COMMANDS.contains(&name) {
+        return Err(format!(":{} is not available over HTTP", name));
+    }
+    command_line::execute(app, line).map(|_| ())
+}
+
+const REMOTE_PAGE: &str = r#"<!doctype html>
+<html><head><meta charset="utf-8"><meta name="viewport" content="width=device-width">
+<title>Cornwall player</title>
+<style>
+body { font-family: sans-serif; background: #111; color: #eee; text-align: center; margin: 0 auto; max-width: 32em; }
+#pos { font-size: 3em; margin: 0.4em; }
+button { font-size: 1.5em; margin: 0.3em; padding: 0.5em 1em; background: #333; color: #eee; border: 1px solid #555; }
+h2 { font-size: 1em; color: #888; text-align: left; margin: 1em 0.5em 0.3em; }
+.meter { display: flex; align-items: center; margin: 0.3em 0.5em; }
+.meter .bar { flex: 1; height: 1em; background: #222; margin: 0 0.5em; }
+.meter .fill { height: 100%; width: 0; background: #2a2; }
+.meter span { width: 4.5em; text-align: right; font-family: monospace; }
+table { width: 100%; border-collapse: collapse; }
+td { padding: 0.2em 0.5em; text-align: left; }
+td button { font-size: 1em; margin: 0.1em; padding: 0.3em 0.7em; }
+button.on { background: #a80; color: #000; }
+button.solo.on { background: #2a2; }
+#markers button { font-size: 1em; }
+#notes div { text-align: left; margin: 0.2em 0.5em; }
+#notes .at { color: #888; font-family: monospace; margin-right: 0.5em; }
+#notes .name { color: #6cc; margin-right: 0.5em; }
+.note { display: flex; margin: 0.3em 0.5em; }
+.note input { font-size: 1em; padding: 0.4em; background: #222; color: #eee; border: 1px solid #555; margin-right: 0.3em; }
+#note_name { width: 6em; }
+#note_text { flex: 1; }
+.note button { font-size: 1em; margin: 0; padding: 0.4em 0.8em; }
+</style></head>
+<body>
+<div id="pos">-</div>
+<button onclick="send('/action/bar_back')">&#9664;&#9664;</button>
+<button onclick="send('/toggle')">&#9654; / &#10073;&#10073;</button>
+<button onclick="send('/stop')">&#9632;</button>
+<button onclick="send('/action/bar_forward')">&#9654;&#9654;</button><br>
+<button onclick="send('/action/toggle_loop')">Loop</button>
+<button onclick="send('/action/add_marker')">Marker</button>
+<h2>Meters</h2>
+<div class="meter">L<div class="bar"><div class="fill" id="fill_l"></div></div><span id="db_l"></span></div>
+<div class="meter">R<div class="bar"><div class="fill" id="fill_r"></div></div><span id="db_r"></span></div>
+<h2>Tracks</h2>
+<table id="tracks"></table>
+<h2>Markers</h2>
+<div id="markers"></div>
+<h2>Notes</h2>
+<div id="notes"></div>
+<div class="note"><input id="note_name" placeholder="Name"><input id="note_text" placeholder="Comment"><button onclick="addNote()">Send</button></div>
+<script>
+// Meters on the TUI's scale, -48 to 0 dBFS
+function meter(side, level) {
+  const db = level > 0 ? 20 * Math.log10(level) : -Infinity;
+  const fraction = Math.min(Math.max((db + 48) / 48, 0), 1);
+  const fill = document.getElementById('fill_' + side);
+  fill.style.width = (fraction * 100) + '%';
+  fill.style.background = db > -3 ? '#c22' : db > -12 ? '#cc2' : '#2a2';
+  document.getElementById('db_' + side).textContent = db > -100 ? db.toFixed(1) : '-inf';
+}
+let last = null;
+let noteAt = null;
+function show(s) {
+  last = s;
+  document.getElementById('pos').textContent =
+    (s.playing ? '▶ ' : s.transport == 'paused' ? '❚❚ ' : '■ ') + s.bar + '.' + s.beat + '  ' + s.position_secs.toFixed(1) + 's';
+  meter('l', s.level_l);
+  meter('r', s.level_r);
+}
+function button(label, classes, path) {
+  const b = document.createElement('button');
+  b.textContent = label;
+  b.className = classes;
+  b.onclick = () => send(path).then(refresh);
+  return b;
+}
+function showTracks(tracks) {
+  const table = document.getElementById('tracks');
+  table.replaceChildren(...tracks.map(t => {
+    const row = document.createElement('tr');
+    const name = document.createElement('td');
+    name.textContent = t.id + ' ' + t.name;
+    const buttons = document.createElement('td');
+    buttons.append(
+      button('M', 'mute' + (t.mute ? ' on' : ''), '/tracks/' + t.id + '/mute'),
+      button('S', 'solo' + (t.solo ? ' on' : ''), '/tracks/' + t.id + '/solo'));
+    row.append(name, buttons);
+    return row;
+  }));
+}
+function showMarkers(markers) {
+  document.getElementById('markers').replaceChildren(...markers.map(m =>
+    button(m.number + ' ' + m.name, '', '/command?line=' + encodeURIComponent('jump ' + m.number))));
+}
+function showNotes(notes) {
+  document.getElementById('notes').replaceChildren(...notes.map(n => {
+    const row = document.createElement('div');
+    const at = document.createElement('span');
+    at.className = 'at';
+    at.textContent = n.bar || n.position.toFixed(1) + 's';
+    row.append(at);
+    if (n.author) {
+      const name = document.createElement('span');
+      name.className = 'name';
+      name.textContent = n.author;
+      row.append(name);
+    }
+    row.append(n.text);
+    return row;
+  }));
+}
+// The note goes where the listener started typing, like N in the TUI
+function mark() {
+  if (noteAt === null && last) {
+    const secs = last.position_secs;
+    noteAt = Math.floor(secs / 60) + ':' + (secs % 60).toFixed(1);
+  }
+}
+function addNote() {
+  const name = document.getElementById('note_name').value;
+  const text = document.getElementById('note_text');
+  if (!text.value.trim()) return;
+  mark();
+  localStorage.setItem('cornwall_name', name);
+  const query = 'text=' + encodeURIComponent(text.value) + '&name=' + encodeURIComponent(name) +
+    '&at=' + encodeURIComponent(noteAt);
+  send('/notes?' + query).then(refresh);
+  text.value = '';
+  noteAt = null;
+}
+document.getElementById('note_name').value = localStorage.getItem('cornwall_name') || '';
+document.getElementById('note_text').addEventListener('input', mark);
+document.getElementById('note_text').addEventListener('keydown', e => { if (e.key === 'Enter') addNote(); });
+// The page is opened with ?token=; requests carry it in a header
+const token = new URLSearchParams(location.search).get('token');
+function api(path, method) {
+  return fetch(path, {method, headers: {Authorization: 'Bearer ' + token}}).then(r => r.json());
+}
+function send(path) { return api(path, 'POST').then(show); }
+function refresh() {
+  api('/tracks', 'GET').then(showTracks);
+  api('/markers', 'GET').then(showMarkers);
+  api('/notes', 'GET').then(showNotes);
+}
+setInterval(() => api('/status', 'GET').then(show), 200);
+setInterval(refresh, 2000);
+refresh();
+</script>
+</body></html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(head: &str) -> Option<Request> {
+        parse_request(head.as_bytes())
+    }
+
+    #[test]
+    fn decode_takes_escapes_and_plus() {
+        assert_eq!(decode("loop%2033+41").as_deref(), Some("loop 33 41"));
+        assert_eq!(decode("%C3%A9").as_deref(), Some("é"));
+    }
+
+    #[test]
+    fn decode_refuses_malformed_escapes() {
+        for bad in ["%+1", "%2", "%", "%zz", "%-1", "%FF"] {
+            assert_eq!(decode(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn token_comes_from_the_header_or_the_query() {
+        let header = parse("POST /stop HTTP/1.1\r\nAuthorization: Bearer abc\r\n\r\n").unwrap();
+        assert!(header.authorized("abc"));
+        assert!(!header.authorized("abd"));
+        let query = parse("GET /?token=abc HTTP/1.1\r\n\r\n").unwrap();
+        assert!(query.authorized("abc"));
+        let none = parse("POST /stop HTTP/1.1\r\nHost: x\r\n\r\n").unwrap();
+        assert!(!none.authorized("abc"));
+        assert!(!none.authorized(""));
+    }
+
+    #[test]
+    fn head_must_end_within_the_limit() {
+        assert!(parse("GET / HTTP/1.1\r\nHost: x\r\n").is_none());
+        let long = format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "a".repeat(9000));
+        let cut = BufReader::new(long.as_bytes().take(MAX_HEAD_BYTES));
+        assert!(parse_request(cut).is_none());
+    }
+
+    #[test]
+    fn malformed_query_is_a_bad_request() {
+        assert!(parse("POST /command?line=%+1 HTTP/1.1\r\n\r\n").is_none());
+    }
+}
@@ -0,0 +1,72 @@
+//! UI string tables.
+//!
+//! A locale is a flat JSON object of `"key": "text"` pairs. English, German
+//! and Spanish are built in from `player/locales/`; a file at
+//! `state/locales/<lang>.json` adds a new language or overrides built-in
+//! entries without rebuilding. Keys missing from a table fall back to
+//! English. Placeholders are written `{name}` and filled by [`trf`].
+
+use std::{collections::HashMap, env, fmt::Display, fs, path::Path, sync::OnceLock};
+
+const EN: &str = include_str!("../locales/en.json");
+const BUILT_IN: [(&str, &str); 3] = [
+    ("en", EN),
+    ("de", include_str!("../locales/de.json")),
+    ("es", include_str!("../locales/es.json")),
+];
+
+static STRINGS: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+fn parse(json: &str) -> HashMap<String, String> {
+    serde_json::from_str(json).unwrap_or_default()
+}
+
+/// Load the table for `locale` (or the environment's language when unset).
+/// Later calls have no effect.
+pub fn init(state_dir: &Path, locale: Option<&str>) {
+    let lang = locale.map(str::to_string).or_else(env_language);
+    let mut strings = parse(EN);
+    if let Some(lang) = lang.filter(|l| l != "en") {
+        if let Some((_, json)) = BUILT_IN.iter().find(|(l, _)| *l == lang) {
+            strings.extend(parse(json));
+        }
+        let path = state_dir.join("locales").join(format!("{}.json", lang));
+        if let Ok(json) = fs::read_to_string(path) {
+            strings.extend(parse(&json));
+        }
+    }
+    let _ = STRINGS.set(strings);
+}
+
+/// Language code from `LC_ALL`, `LC_MESSAGES` or `LANG` (`de_AT.UTF-8` -> `de`).
+fn env_language() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|v| !v.is_empty())
+        .map(|v| {
+            v.split(['_', '.', '@'])
+                .next()
+                .unwrap_or_default()
+                .to_lowercase()
+        })
+        .filter(|l| !l.is_empty() && l != "c" && l != "posix")
+}
+
+/// Text for `key`, or the key itself if no table has it.
+pub fn tr(key: &'static str) -> &'static str {
+    STRINGS
+        .get_or_init(|| parse(EN))
+        .get(key)
+        .map(String::as_str)
+        .unwrap_or(key)
+}
+
+/// [`tr`] with `{name}` placeholders replaced from `args`.
+pub fn trf(key: &'static str, args: &[(&str, &dyn Display)]) -> String {
+    let mut text = tr(key).to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    text
+}
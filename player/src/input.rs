@@ -0,0 +1,85 @@
+//! Audio input for the tuner and room measurement: the first channel of an
+//! input device, the latest few seconds of it kept in memory.
+
+use rodio::cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    SampleFormat, Stream, StreamConfig,
+};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+/// An open input device; closed when dropped.
+pub struct Input {
+    _stream: Stream,
+    samples: Arc<Mutex<VecDeque<f32>>>,
+    pub sample_rate: u32,
+}
+
+impl Input {
+    /// Open the input whose name contains `device`, or the default input,
+    /// keeping the last `keep_secs` of its first channel.
+    pub fn open(device: Option<&str>, keep_secs: f64) -> Result<Input, String> {
+        let host = rodio::cpal::default_host();
+        let device = match device {
+            Some(name) => host
+                .input_devices()
+                .map_err(|e| e.to_string())?
+                .find(|d| d.name().map(|n| n.contains(name)).unwrap_or(false))
+                .ok_or_else(|| format!("no input device matching \"{}\"", name))?,
+            None => host
+                .default_input_device()
+                .ok_or("no default input device")?,
+        };
+        let config = device.default_input_config().map_err(|e| e.to_string())?;
+        let format = config.sample_format();
+        let channels = config.channels().max(1) as usize;
+        let sample_rate = config.sample_rate().0;
+        let keep = (sample_rate as f64 * keep_secs) as usize;
+        let config: StreamConfig = config.into();
+
+        let samples = Arc::new(Mutex::new(VecDeque::with_capacity(keep)));
+        let shared = samples.clone();
+        let push = move |first: &mut dyn Iterator<Item = f32>| {
+            // The input thread never waits on the UI; a busy lock drops a block
+            if let Ok(mut samples) = shared.try_lock() {
+                samples.extend(first);
+                let excess = samples.len().saturating_sub(keep);
+                samples.drain(..excess);
+            }
+        };
+        let error = |_| {};
+        let stream = match format {
+            SampleFormat::F32 => device.build_input_stream(
+                &config,
+                move |data: &[f32], _: &_| push(&mut data.iter().step_by(channels).copied()),
+                error,
+                None,
+            ),
+            SampleFormat::I16 => device.build_input_stream(
+                &config,
+                move |data: &[i16], _: &_| {
+                    push(&mut data.iter().step_by(channels).map(|&s| s as f32 / 32768.0))
+                },
+                error,
+                None,
+            ),
+            format => return Err(format!("unsupported input sample format {:?}", format)),
+        }
+        .map_err(|e| e.to_string())?;
+        stream.play().map_err(|e| e.to_string())?;
+
+        Ok(Input {
+            _stream: stream,
+            samples,
+            sample_rate,
+        })
+    }
+
+    /// The samples kept so far, oldest first.
+    pub fn samples(&self) -> Vec<f64> {
+        let samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        samples.iter().map(|&s| s as f64).collect()
+    }
+}
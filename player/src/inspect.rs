@@ -0,0 +1,244 @@
+//! File inspector (`i`): what the loaded file is, read from its RIFF
+//! chunks rather than trusted from the decoder: container, codec, format,
+//! length, the text in its `LIST/INFO` and Broadcast Wave `bext` chunks, and
+//! a CRC-32 of the whole file for telling copies apart.
+
+use crate::{i18n::tr, utilities::centered_rect};
+use cornwall_player::text;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use std::{fs, path::Path};
+
+/// Cells for a value in the popup.
+const VALUE_WIDTH: usize = 47;
+
+/// WAVE_FORMAT_EXTENSIBLE, whose real format tag starts its subformat GUID.
+const EXTENSIBLE: u16 = 0xFFFE;
+
+/// `LIST/INFO` fields shown, with their labels.
+const INFO_FIELDS: [(&[u8; 4], &str); 7] = [
+    (b"INAM", "Title"),
+    (b"IART", "Artist"),
+    (b"IPRD", "Album"),
+    (b"ICRD", "Date"),
+    (b"IGNR", "Genre"),
+    (b"ICMT", "Comment"),
+    (b"ISFT", "Software"),
+];
+
+pub struct FileInfo {
+    pub container: String,
+    pub codec: String,
+    pub sample_rate: u32,
+    pub bits_per_sample: u16,
+    pub channels: u16,
+    /// Length in sample frames.
+    pub frames: u64,
+    /// File size in bytes.
+    pub size: u64,
+    /// Embedded text, label and value.
+    pub metadata: Vec<(String, String)>,
+    pub crc32: u32,
+}
+
+/// Reads `path` and walks its chunks.
+pub fn inspect(path: &Path) -> Result<FileInfo, String> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let not_wav = || format!("{}: not a RIFF WAVE file", path.display());
+    let container = match bytes.get(..4) {
+        Some(b"RIFF") => "RIFF WAVE",
+        Some(b"RF64") => "RF64 WAVE",
+        Some(b"BW64") => "BW64 WAVE",
+        _ => return Err(not_wav()),
+    };
+    if bytes.get(8..12) != Some(b"WAVE") {
+        return Err(not_wav());
+    }
+
+    let mut info = FileInfo {
+        container: container.to_string(),
+        codec: String::new(),
+        sample_rate: 0,
+        bits_per_sample: 0,
+        channels: 0,
+        frames: 0,
+        size: bytes.len() as u64,
+        metadata: Vec::new(),
+        crc32: crc32(&bytes),
+    };
+    let mut block_align = 0;
+    let mut data_len = 0;
+    let mut has_bext = false;
+    for (id, body) in chunks(&bytes[12..]) {
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let mut tag = u16_at(body, 0);
+                if tag == EXTENSIBLE && body.len() >= 26 {
+                    tag = u16_at(body, 24);
+                    info.codec.push_str("Extensible, ");
+                }
+                info.codec.push_str(match tag {
+                    1 => "PCM",
+                    3 => "IEEE float",
+                    6 => "A-law",
+                    7 => "µ-law",
+                    _ => "unknown",
+                });
+                info.channels = u16_at(body, 2);
+                info.sample_rate = u32_at(body, 4);
+                block_align = u16_at(body, 12) as u64;
+                info.bits_per_sample = u16_at(body, 14);
+            }
+            b"data" => data_len = body.len() as u64,
+            // RF64 keeps the real data size here when it won't fit in 32 bits
+            b"ds64" if body.len() >= 16 => data_len = u64_at(body, 8),
+            b"LIST" if body.get(..4) == Some(b"INFO") => {
+                for (field, value) in chunks(&body[4..]) {
+                    if let Some((_, label)) = INFO_FIELDS.iter().find(|(id, _)| *id == field) {
+                        push_text(&mut info.metadata, label, value);
+                    }
+                }
+            }
+            b"bext" if body.len() >= 338 => {
+                has_bext = true;
+                push_text(&mut info.metadata, "Description", &body[..256]);
+                push_text(&mut info.metadata, "Originator", &body[256..288]);
+                let date = String::from_utf8_lossy(&body[320..330]);
+                let time = String::from_utf8_lossy(&body[330..338]);
+                let recorded = format!("{} {}", date, time);
+                push_text(&mut info.metadata, "Recorded", recorded.as_bytes());
+            }
+            _ => {}
+        }
+    }
+    if has_bext {
+        info.container.push_str(" (Broadcast Wave)");
+    }
+    info.frames = data_len.checked_div(block_align).unwrap_or(0);
+    Ok(info)
+}
+
+/// The chunks in `bytes` as ID and body, stopping at the first truncated
+/// one. Bodies are padded to an even length.
+fn chunks(mut bytes: &[u8]) -> Vec<(&[u8; 4], &[u8])> {
+    let mut chunks = Vec::new();
+    while bytes.len() >= 8 {
+        let id: &[u8; 4] = bytes[..4].try_into().unwrap_or(b"    ");
+        let len = u32_at(bytes, 4) as usize;
+        // RF64 marks its oversized data chunk 0xFFFFFFFF; take what's there
+        let body = &bytes[8..(8 + len).min(bytes.len())];
+        chunks.push((id, body));
+        let next = 8 + len + len % 2;
+        if next > bytes.len() {
+            break;
+        }
+        bytes = &bytes[next..];
+    }
+    chunks
+}
+
+/// Adds NUL-padded text as a metadata row, skipping empty fields.
+fn push_text(metadata: &mut Vec<(String, String)>, label: &str, value: &[u8]) {
+    let value = String::from_utf8_lossy(value);
+    let value = value.trim_end_matches('\0').trim();
+    if !value.is_empty() {
+        metadata.push((label.to_string(), value.replace('\0', " ")));
+    }
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+    u32_at(bytes, at) as u64 | ((u32_at(bytes, at + 4) as u64) << 32)
+}
+
+/// CRC-32 (IEEE 802.3), as `crc32` and zip report it.
+fn crc32(bytes: &[u8]) -> u32 {
+    let table: Vec<u32> = (0..256u32)
+        .map(|n| {
+            (0..8).fold(n, |c, _| {
+                if c & 1 == 1 {
+                    0xEDB8_8320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                }
+            })
+        })
+        .collect();
+    !bytes.iter().fold(!0u32, |crc, &b| {
+        table[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// The inspector popup for `path`.
+pub fn render(f: &mut Frame, path: &Path, info: &Result<FileInfo, String>) {
+    let key = Style::default()
+        .fg(Color::White)
+        .add_modifier(Modifier::BOLD);
+    let dim = Style::default().fg(Color::DarkGray);
+    let row = |label: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("  {:<13}", label), dim),
+            Span::styled(text::truncate(&value, VALUE_WIDTH), key),
+        ])
+    };
+    let name = path
+        .file_name()
+        .map_or_else(String::new, |n| n.to_string_lossy().to_string());
+    let mut lines = vec![Line::from(""), row(tr("inspect.file"), name)];
+    match info {
+        Ok(info) => {
+            let secs = info.frames as f64 / info.sample_rate.max(1) as f64;
+            lines.extend([
+                row(tr("inspect.container"), info.container.clone()),
+                row(tr("inspect.codec"), info.codec.clone()),
+                row(
+                    tr("inspect.sample_rate"),
+                    format!("{} Hz", info.sample_rate),
+                ),
+                row(
+                    tr("inspect.bit_depth"),
+                    format!("{} bit", info.bits_per_sample),
+                ),
+                row(tr("inspect.channels"), info.channels.to_string()),
+                row(
+                    tr("inspect.length"),
+                    format!("{} ({:.3} s)", info.frames, secs),
+                ),
+                row(tr("inspect.size"), format!("{} bytes", info.size)),
+                row("CRC-32", format!("{:08x}", info.crc32)),
+            ]);
+            if !info.metadata.is_empty() {
+                lines.push(Line::from(""));
+                for (label, value) in &info.metadata {
+                    lines.push(row(label, value.clone()));
+                }
+            }
+        }
+        Err(e) => lines.push(Line::from(Span::styled(
+            format!("  {}", e),
+            Style::default().fg(Color::Red),
+        ))),
+    }
+    lines.push(Line::from(""));
+
+    let area = centered_rect(64, lines.len() as u16 + 2, f.area());
+    let popup = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(dim)
+            .title(format!(" {} ", tr("inspect.title"))),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
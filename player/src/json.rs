@@ -0,0 +1,208 @@
+//! Strict reading of Cornwall's JSON state files. A missing file is an
+//! empty state, but one that doesn't parse is an error naming the file, the
+//! line and column, the field (`[1].volume`) and what was expected, rather
+//! than quietly reading as empty and hiding the damage.
+
+use serde::de::DeserializeOwned;
+use std::{fs, io::ErrorKind, path::Path};
+
+/// `path` as a `T`, or `T::default()` when there's no such file.
+pub fn load<T: DeserializeOwned + Default>(path: &Path) -> Result<T, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse(path, &text),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// `text`, read from `path`, as a `T`.
+pub fn parse<T: DeserializeOwned>(path: &Path, text: &str) -> Result<T, String> {
+    serde_json::from_str(text).map_err(|e| describe(path, text, &e))
+}
+
+/// `state/tracks.json line 5 column 17, [1].volume: invalid type: string
+/// "loud", expected f64`.
+fn describe(path: &Path, text: &str, e: &serde_json::Error) -> String {
+    let message = e.to_string();
+    // The message ends with the position, which goes up front instead
+    let message = message
+        .rsplit_once(" at line ")
+        .map_or(message.as_str(), |(m, _)| m);
+    if e.line() == 0 {
+        return format!("{}: {}", path.display(), message);
+    }
+    // A missing field is reported at the closing brace, which closes the
+    // object here too and leaves the path to it
+    let field = field_path(&fields_at(text, e.line(), e.column()));
+    format!(
+        "{} line {} column {}{}: {}",
+        path.display(),
+        e.line(),
+        e.column(),
+        if field.is_empty() {
+            String::new()
+        } else {
+            format!(", {}", field)
+        },
+        message
+    )
+}
+
+/// Where the parser is inside an object or array.
+enum Field {
+    /// The member being read, once its key has been.
+    Key(Option<String>),
+    Index(usize),
+}
+
+/// The objects and arrays open at 1-based `line` and `column`.
+fn fields_at(text: &str, line: usize, column: usize) -> Vec<Field> {
+    let offset = text
+        .split_inclusive('\n')
+        .take(line - 1)
+        .map(str::len)
+        .sum::<usize>()
+        + column;
+    let mut fields = Vec::new();
+    // Set after `{` and `,` in an object, where the next string is a key
+    let mut expect_key = false;
+    let mut chars = text.char_indices().take_while(|(i, _)| *i < offset);
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '{' => {
+                fields.push(Field::Key(None));
+                expect_key = true;
+            }
+            '[' => fields.push(Field::Index(0)),
+            '}' | ']' => {
+                fields.pop();
+                expect_key = false;
+            }
+            ',' => match fields.last_mut() {
+                Some(Field::Index(i)) => *i += 1,
+                Some(Field::Key(key)) => {
+                    *key = None;
+                    expect_key = true;
+                }
+                None => {}
+            },
+            '"' => {
+                let mut string = String::new();
+                let mut escaped = false;
+                for (_, c) in chars.by_ref() {
+                    match c {
+                        _ if escaped => {
+                            string.push(c);
+                            escaped = false;
+                        }
+                        '\\' => escaped = true,
+                        '"' => break,
+                        _ => string.push(c),
+                    }
+                }
+                if expect_key {
+                    if let Some(Field::Key(key)) = fields.last_mut() {
+                        *key = Some(string);
+                    }
+                    expect_key = false;
+                }
+            }
+            _ => {}
+        }
+    }
+    fields
+}
+
+fn field_path(fields: &[Field]) -> String {
+    let mut path = String::new();
+    for field in fields {
+        match field {
+            Field::Key(Some(key)) if path.is_empty() => path.push_str(key),
+            Field::Key(Some(key)) => {
+                path.push('.');
+                path.push_str(key);
+            }
+            Field::Key(None) => break,
+            Field::Index(i) => path.push_str(&format!("[{}]", i)),
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    /// The path to where `needle` starts in one-line `text`.
+    fn path_at(text: &str, needle: &str) -> String {
+        let column = text.find(needle).unwrap();
+        field_path(&fields_at(text, 1, column))
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Track {
+        id: u32,
+        volume: f64,
+    }
+
+    fn error(text: &str) -> String {
+        parse::<Vec<Track>>(Path::new("state/tracks.json"), text).unwrap_err()
+    }
+
+    #[test]
+    fn path_runs_through_nested_objects_and_arrays() {
+        let text = r#"{"a": {"b": [1, {"c": "x"}]}}"#;
+        assert_eq!(path_at(text, r#""x""#), "a.b[1].c");
+        assert_eq!(path_at(text, "1,"), "a.b[0]");
+    }
+
+    #[test]
+    fn path_counts_array_elements_but_not_commas_in_strings() {
+        assert_eq!(path_at(r#"["a,b", [1, 2], 3]"#, "3"), "[2]");
+        assert_eq!(path_at(r#"["a,b", [1, 2], 3]"#, "2"), "[1][1]");
+        assert_eq!(
+            path_at(r#"[{"volume": 1}, {"volume": "loud"}]"#, r#""loud""#),
+            "[1].volume"
+        );
+    }
+
+    #[test]
+    fn path_reads_escaped_keys_and_brackets_in_strings() {
+        let text = r#"{"a\"b": {"c}": "]", "d": 0}}"#;
+        assert_eq!(path_at(text, "0"), r#"a"b.d"#);
+        assert_eq!(path_at(text, r#""]""#), r#"a"b.c}"#);
+    }
+
+    #[test]
+    fn path_stops_before_a_key_not_yet_read() {
+        assert_eq!(path_at(r#"{"a": {"b": 1, "#, "1"), "a.b");
+        assert_eq!(field_path(&fields_at(r#"{"a": {"b": 1, "#, 1, 15)), "a");
+        assert_eq!(path_at("[1]", "["), "");
+    }
+
+    #[test]
+    fn missing_field_names_the_object_it_is_missing_from() {
+        assert_eq!(
+            error("[{\"id\": 1, \"volume\": 0.5},\n {\"id\": 2}]"),
+            "state/tracks.json line 2 column 10, [1]: missing field `volume`"
+        );
+    }
+
+    #[test]
+    fn wrong_type_names_the_field() {
+        assert_eq!(
+            error(r#"[{"id": 1, "volume": "loud"}]"#),
+            "state/tracks.json line 1 column 27, [0].volume: invalid type: string \"loud\", expected f64"
+        );
+    }
+
+    #[test]
+    fn error_in_an_empty_file_names_no_field() {
+        assert_eq!(
+            error(""),
+            "state/tracks.json line 1 column 0: EOF while parsing a value"
+        );
+    }
+}
@@ -0,0 +1,520 @@
+//! Configurable key bindings. A profile supplies the base map and the
+//! `keys.bindings` table in `player-config.json` overrides single keys, e.g.
+//! `{"keys": {"profile": "vim", "bindings": {"x": "toggle_mono"}}}`.
+
+use crate::macros::SLOTS;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Play, pause, or resume from where it paused.
+    TogglePlay,
+    /// Stop, sending the playhead where `"stop"` in the config says.
+    Stop,
+    Quit,
+    ToggleLoop,
+    MonitorDown,
+    MonitorUp,
+    ToggleMute,
+    ToggleDim,
+    ToggleMono,
+    Utilities,
+    ToggleClock,
+    CueDown,
+    CueUp,
+    BarBack,
+    BarForward,
+    /// Seek 5 s back or forward.
+    SeekBack,
+    SeekForward,
+    /// Seek 30 s back or forward.
+    JumpBack,
+    JumpForward,
+    /// Put the playhead back at the start of the file.
+    GoToStart,
+    NextTrack,
+    PrevTrack,
+    CommandLine,
+    TrackMute,
+    TrackSolo,
+    TrackVolumeDown,
+    TrackVolumeUp,
+    TrackPanLeft,
+    TrackPanRight,
+    AddMarker,
+    Undo,
+    Redo,
+    /// Write track and marker edits back to `state/`.
+    Save,
+    /// Start recording a macro (then press the slot digit), or stop.
+    MacroRecord,
+    /// Start or stop the MIDI metronome.
+    ToggleMetronome,
+    /// Map a hardware control to a track parameter: press the parameter's
+    /// key, then move the control.
+    MidiLearn,
+    /// Silence hung notes on the MIDI outputs and the SoundFont tracks.
+    MidiPanic,
+    /// Show or hide the incoming MIDI monitor.
+    MidiMonitor,
+    /// Play 5% slower or faster, keeping the pitch.
+    SpeedDown,
+    SpeedUp,
+    /// Shift the pitch a semitone down or up, keeping the speed.
+    PitchDown,
+    PitchUp,
+    /// Jump over silence while playing, or stop doing so.
+    ToggleSkipSilence,
+    /// Play the loop region once, stopping at its end.
+    PlayRegion,
+    /// Prelisten to the selected track's file, or stop.
+    PreviewTrack,
+    /// Show or hide the oscilloscope.
+    ToggleScope,
+    /// Show or hide the stereo vectorscope.
+    ToggleVectorscope,
+    /// Show or hide the loudness gauge.
+    ToggleLoudness,
+    /// Switch the level meters between RMS bars and VU needles.
+    ToggleVu,
+    /// Hold the meters and save their readings, or let them run.
+    FreezeMeters,
+    /// Show the tuner, listening to the input, or hide it.
+    ToggleTuner,
+    /// Show or hide what the loaded file is.
+    ToggleInspector,
+    /// Write the audible tracks trimmed to the loop region.
+    ExportStems,
+    /// Type a session note at the playhead.
+    WriteNote,
+    /// Show or hide the session notes beside the track list.
+    ToggleNotes,
+    /// Show or hide frame, loop and audio callback timings.
+    ToggleDiagnostics,
+    /// Open the switcher to move to another registered project.
+    SwitchProject,
+    /// Replay the macro in slot 1-9; bound as `{"play_macro": 1}`.
+    PlayMacro(u8),
+    /// Go to marker 1-9; bound as `{"hot_cue": 1}`.
+    HotCue(u8),
+    /// Unbinds a key inherited from the profile.
+    None,
+}
+
+impl Action {
+    /// Actions without arguments, for name lookup.
+    pub const ALL: [Action; 57] = [
+        Action::TogglePlay,
+        Action::Stop,
+        Action::Quit,
+        Action::ToggleLoop,
+        Action::MonitorDown,
+        Action::MonitorUp,
+        Action::ToggleMute,
+        Action::ToggleDim,
+        Action::ToggleMono,
+        Action::Utilities,
+        Action::ToggleClock,
+        Action::CueDown,
+        Action::CueUp,
+        Action::BarBack,
+        Action::BarForward,
+        Action::SeekBack,
+        Action::SeekForward,
+        Action::JumpBack,
+        Action::JumpForward,
+        Action::GoToStart,
+        Action::NextTrack,
+        Action::PrevTrack,
+        Action::CommandLine,
+        Action::TrackMute,
+        Action::TrackSolo,
+        Action::TrackVolumeDown,
+        Action::TrackVolumeUp,
+        Action::TrackPanLeft,
+        Action::TrackPanRight,
+        Action::AddMarker,
+        Action::Undo,
+        Action::Redo,
+        Action::Save,
+        Action::MacroRecord,
+        Action::ToggleMetronome,
+        Action::MidiLearn,
+        Action::MidiPanic,
+        Action::MidiMonitor,
+        Action::SpeedDown,
+        Action::SpeedUp,
+        Action::PitchDown,
+        Action::PitchUp,
+        Action::ToggleSkipSilence,
+        Action::PlayRegion,
+        Action::PreviewTrack,
+        Action::ToggleScope,
+        Action::ToggleVectorscope,
+        Action::ToggleLoudness,
+        Action::ToggleVu,
+        Action::FreezeMeters,
+        Action::ToggleTuner,
+        Action::ToggleInspector,
+        Action::ExportStems,
+        Action::WriteNote,
+        Action::ToggleNotes,
+        Action::ToggleDiagnostics,
+        Action::SwitchProject,
+    ];
+
+    /// Config and command-line name, matching the serde spelling.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::TogglePlay => "toggle_play",
+            Action::Stop => "stop",
+            Action::Quit => "quit",
+            Action::ToggleLoop => "toggle_loop",
+            Action::MonitorDown => "monitor_down",
+            Action::MonitorUp => "monitor_up",
+            Action::ToggleMute => "toggle_mute",
+            Action::ToggleDim => "toggle_dim",
+            Action::ToggleMono => "toggle_mono",
+            Action::Utilities => "utilities",
+            Action::ToggleClock => "toggle_clock",
+            Action::CueDown => "cue_down",
+            Action::CueUp => "cue_up",
+            Action::BarBack => "bar_back",
+            Action::BarForward => "bar_forward",
+            Action::SeekBack => "seek_back",
+            Action::SeekForward => "seek_forward",
+            Action::JumpBack => "jump_back",
+            Action::JumpForward => "jump_forward",
+            Action::GoToStart => "go_to_start",
+            Action::NextTrack => "next_track",
+            Action::PrevTrack => "prev_track",
+            Action::CommandLine => "command_line",
+            Action::TrackMute => "track_mute",
+            Action::TrackSolo => "track_solo",
+            Action::TrackVolumeDown => "track_volume_down",
+            Action::TrackVolumeUp => "track_volume_up",
+            Action::TrackPanLeft => "track_pan_left",
+            Action::TrackPanRight => "track_pan_right",
+            Action::AddMarker => "add_marker",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::Save => "save",
+            Action::MacroRecord => "macro_record",
+            Action::ToggleMetronome => "toggle_metronome",
+            Action::MidiLearn => "midi_learn",
+            Action::MidiPanic => "midi_panic",
+            Action::MidiMonitor => "midi_monitor",
+            Action::SpeedDown => "speed_down",
+            Action::SpeedUp => "speed_up",
+            Action::PitchDown => "pitch_down",
+            Action::PitchUp => "pitch_up",
+            Action::ToggleSkipSilence => "toggle_skip_silence",
+            Action::PlayRegion => "play_region",
+            Action::PreviewTrack => "preview_track",
+            Action::ToggleScope => "toggle_scope",
+            Action::ToggleVectorscope => "toggle_vectorscope",
+            Action::ToggleLoudness => "toggle_loudness",
+            Action::ToggleVu => "toggle_vu",
+            Action::FreezeMeters => "freeze_meters",
+            Action::ToggleTuner => "toggle_tuner",
+            Action::ToggleInspector => "toggle_inspector",
+            Action::ExportStems => "export_stems",
+            Action::WriteNote => "write_note",
+            Action::ToggleNotes => "toggle_notes",
+            Action::ToggleDiagnostics => "toggle_diagnostics",
+            Action::SwitchProject => "switch_project",
+            Action::PlayMacro(_) => "play_macro",
+            Action::HotCue(_) => "hot_cue",
+            Action::None => "none",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|a| a.name() == name)
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    #[default]
+    Default,
+    /// hjkl navigation and a `:` command line; quit with `:q`.
+    Vim,
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct KeysConfig {
+    pub profile: Profile,
+    /// Key name (`"x"`, `"space"`, `"ctrl+r"`, `"f5"`) to action name.
+    pub bindings: HashMap<String, Action>,
+}
+
+type Binding = (KeyCode, KeyModifiers);
+
+pub struct Keymap {
+    bindings: HashMap<Binding, Action>,
+}
+
+impl Keymap {
+    pub fn new(config: &KeysConfig) -> Result<Self, String> {
+        let mut keymap = Keymap {
+            bindings: HashMap::new(),
+        };
+        let base: &[(&str, Action)] = match config.profile {
+            Profile::Default => &DEFAULT,
+            Profile::Vim => &VIM,
+        };
+        for (key, action) in base {
+            keymap.bind(key, *action)?;
+        }
+        // F1-F9 replay macros and 1-9 go to markers in every profile
+        for slot in 1..=SLOTS {
+            keymap.bind(&format!("f{}", slot), Action::PlayMacro(slot))?;
+            keymap.bind(&slot.to_string(), Action::HotCue(slot))?;
+        }
+        for (key, action) in &config.bindings {
+            keymap.bind(key, *action)?;
+        }
+        Ok(keymap)
+    }
+
+    fn bind(&mut self, key: &str, action: Action) -> Result<(), String> {
+        let binding = parse_key(key)?;
+        if action == Action::None {
+            self.bindings.remove(&binding);
+        } else {
+            self.bindings.insert(binding, action);
+        }
+        Ok(())
+    }
+
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .get(&normalize(key.code, key.modifiers))
+            .copied()
+    }
+}
+
+const DEFAULT: [(&str, Action); 59] = [
+    ("space", Action::TogglePlay),
+    ("enter", Action::Stop),
+    ("q", Action::Quit),
+    ("esc", Action::Quit),
+    ("l", Action::ToggleLoop),
+    ("L", Action::ToggleLoop),
+    ("-", Action::MonitorDown),
+    ("=", Action::MonitorUp),
+    ("+", Action::MonitorUp),
+    ("m", Action::ToggleMute),
+    ("d", Action::ToggleDim),
+    ("o", Action::ToggleMono),
+    ("U", Action::Utilities),
+    ("c", Action::ToggleClock),
+    ("[", Action::CueDown),
+    ("]", Action::CueUp),
+    ("down", Action::NextTrack),
+    ("up", Action::PrevTrack),
+    ("M", Action::MacroRecord),
+    ("x", Action::TrackMute),
+    ("s", Action::TrackSolo),
+    (",", Action::TrackVolumeDown),
+    (".", Action::TrackVolumeUp),
+    ("<", Action::TrackPanLeft),
+    (">", Action::TrackPanRight),
+    ("n", Action::AddMarker),
+    ("u", Action::Undo),
+    ("ctrl+r", Action::Redo),
+    ("ctrl+s", Action::Save),
+    ("t", Action::ToggleMetronome),
+    ("ctrl+l", Action::MidiLearn),
+    ("P", Action::MidiPanic),
+    ("I", Action::MidiMonitor),
+    ("{", Action::SpeedDown),
+    ("}", Action::SpeedUp),
+    ("(", Action::PitchDown),
+    (")", Action::PitchUp),
+    ("S", Action::ToggleSkipSilence),
+    ("R", Action::PlayRegion),
+    ("p", Action::PreviewTrack),
+    ("O", Action::ToggleScope),
+    ("V", Action::ToggleVectorscope),
+    ("G", Action::ToggleLoudness),
+    ("v", Action::ToggleVu),
+    ("F", Action::FreezeMeters),
+    ("T", Action::ToggleTuner),
+    ("i", Action::ToggleInspector),
+    ("E", Action::ExportStems),
+    ("N", Action::WriteNote),
+    ("ctrl+n", Action::ToggleNotes),
+    ("f12", Action::ToggleDiagnostics),
+    ("ctrl+o", Action::SwitchProject),
+    ("left", Action::SeekBack),
+    ("right", Action::SeekForward),
+    ("shift+left", Action::BarBack),
+    ("shift+right", Action::BarForward),
+    ("pageup", Action::JumpBack),
+    ("pagedown", Action::JumpForward),
+    ("home", Action::GoToStart),
+];
+
+const VIM: [(&str, Action); 62] = [
+    ("space", Action::TogglePlay),
+    ("enter", Action::Stop),
+    ("L", Action::ToggleLoop),
+    ("-", Action::MonitorDown),
+    ("=", Action::MonitorUp),
+    ("+", Action::MonitorUp),
+    ("m", Action::ToggleMute),
+    ("d", Action::ToggleDim),
+    ("o", Action::ToggleMono),
+    ("U", Action::Utilities),
+    ("c", Action::ToggleClock),
+    ("[", Action::CueDown),
+    ("]", Action::CueUp),
+    ("x", Action::TrackMute),
+    ("s", Action::TrackSolo),
+    (",", Action::TrackVolumeDown),
+    (".", Action::TrackVolumeUp),
+    ("<", Action::TrackPanLeft),
+    (">", Action::TrackPanRight),
+    ("n", Action::AddMarker),
+    ("u", Action::Undo),
+    ("ctrl+r", Action::Redo),
+    ("ctrl+s", Action::Save),
+    ("h", Action::BarBack),
+    ("l", Action::BarForward),
+    ("j", Action::NextTrack),
+    ("k", Action::PrevTrack),
+    ("down", Action::NextTrack),
+    ("up", Action::PrevTrack),
+    (":", Action::CommandLine),
+    ("ctrl+c", Action::Quit),
+    ("q", Action::MacroRecord),
+    ("t", Action::ToggleMetronome),
+    ("ctrl+l", Action::MidiLearn),
+    ("P", Action::MidiPanic),
+    ("I", Action::MidiMonitor),
+    ("{", Action::SpeedDown),
+    ("}", Action::SpeedUp),
+    ("(", Action::PitchDown),
+    (")", Action::PitchUp),
+    ("S", Action::ToggleSkipSilence),
+    ("R", Action::PlayRegion),
+    ("p", Action::PreviewTrack),
+    ("O", Action::ToggleScope),
+    ("V", Action::ToggleVectorscope),
+    ("G", Action::ToggleLoudness),
+    ("v", Action::ToggleVu),
+    ("F", Action::FreezeMeters),
+    ("T", Action::ToggleTuner),
+    ("i", Action::ToggleInspector),
+    ("E", Action::ExportStems),
+    ("N", Action::WriteNote),
+    ("ctrl+n", Action::ToggleNotes),
+    ("f12", Action::ToggleDiagnostics),
+    ("ctrl+o", Action::SwitchProject),
+    ("left", Action::SeekBack),
+    ("right", Action::SeekForward),
+    ("shift+left", Action::BarBack),
+    ("shift+right", Action::BarForward),
+    ("pageup", Action::JumpBack),
+    ("pagedown", Action::JumpForward),
+    ("home", Action::GoToStart),
+];
+
+/// Shift is part of the character for printable keys (`L` vs `l`), and
+/// terminals disagree on whether they report it, so drop it there.
+fn normalize(code: KeyCode, mut modifiers: KeyModifiers) -> Binding {
+    if let KeyCode::Char(_) = code {
+        modifiers.remove(KeyModifiers::SHIFT);
+    }
+    (code, modifiers)
+}
+
+/// Key press for a key name, as stored in macros.
+pub fn parse_key_event(name: &str) -> Result<KeyEvent, String> {
+    let (code, modifiers) = parse_key(name)?;
+    Ok(KeyEvent::new(code, modifiers))
+}
+
+/// Inverse of [`parse_key`].
+pub fn key_name(key: &KeyEvent) -> String {
+    let (code, modifiers) = normalize(key.code, key.modifiers);
+    let mut name = String::new();
+    for (modifier, prefix) in [
+        (KeyModifiers::CONTROL, "ctrl+"),
+        (KeyModifiers::ALT, "alt+"),
+        (KeyModifiers::SHIFT, "shift+"),
+    ] {
+        if modifiers.contains(modifier) {
+            name.push_str(prefix);
+        }
+    }
+    let key = match code {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("f{}", n),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Delete => "delete".to_string(),
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        KeyCode::Home => "home".to_string(),
+        KeyCode::End => "end".to_string(),
+        KeyCode::PageUp => "pageup".to_string(),
+        KeyCode::PageDown => "pagedown".to_string(),
+        _ => "none".to_string(),
+    };
+    name + &key
+}
+
+fn parse_key(name: &str) -> Result<Binding, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = name;
+    while let Some((prefix, tail)) = rest
+        .split_once('+')
+        .filter(|(p, t)| !p.is_empty() && !t.is_empty())
+    {
+        modifiers.insert(match prefix.to_ascii_lowercase().as_str() {
+            "ctrl" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return Err(format!("unknown modifier in key {:?}", name)),
+        });
+        rest = tail;
+    }
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match rest.to_ascii_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "esc" => KeyCode::Esc,
+            "enter" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            f => match f.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                Some(n @ 1..=12) => KeyCode::F(n),
+                _ => return Err(format!("unknown key {:?}", name)),
+            },
+        },
+    };
+    Ok(normalize(code, modifiers))
+}
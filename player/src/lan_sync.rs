@@ -0,0 +1,177 @@
+//! Players following each other over the LAN (`--lead`, `--follow`): the
+//! leader broadcasts its transport (playing, position, speed) as a UDP
+//! datagram every tick, and followers, say one in the live room and one in
+//! the control room, chase it with their own copies of the files. A
+//! follower starts, stops and seeks with the leader, and re-seeks when it
+//! drifts more than `TOLERANCE_SECS` away.
+//!
+//! ```json
+//! "sync": {"port": 47810, "address": "255.255.255.255"}
+//! ```
+//!
+//! `address` is where the leader sends: the broadcast address by default,
+//! or one follower's address, or a multicast group. Followers listen on
+//! `port` on every interface. No authentication, like the HTTP API.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    net::UdpSocket,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How far a follower may drift before it re-seeks. Restarting playback
+/// costs a buffer's worth of audio, so it stays above the output latency.
+pub const TOLERANCE_SECS: f64 = 0.015;
+
+/// Least time between re-seeks, for the output to settle after one.
+const RESEEK_HOLDOFF: Duration = Duration::from_secs(1);
+
+/// A leader not heard from for this long has gone; the follower keeps
+/// playing on its own.
+const LEADER_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct SyncConfig {
+    pub port: u16,
+    pub address: String,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        SyncConfig {
+            port: 47810,
+            address: "255.255.255.255".to_string(),
+        }
+    }
+}
+
+/// Which side of the link this player is.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Role {
+    Lead,
+    Follow,
+}
+
+/// The leader's transport, one datagram's worth.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct Transport {
+    pub playing: bool,
+    /// Seconds into the file.
+    pub position: f64,
+    pub speed: f64,
+}
+
+pub enum LanSync {
+    Leader {
+        socket: UdpSocket,
+        target: String,
+    },
+    Follower {
+        /// The latest transport heard and when.
+        latest: Arc<Mutex<Option<(Transport, Instant)>>>,
+        /// When the last transport acted on arrived.
+        heard: Option<Instant>,
+        reseeked: Option<Instant>,
+    },
+}
+
+impl LanSync {
+    pub fn start(role: Role, config: &SyncConfig) -> Result<LanSync, String> {
+        match role {
+            Role::Lead => {
+                let socket = UdpSocket::bind(("0.0.0.0", 0)).map_err(|e| e.to_string())?;
+                socket.set_broadcast(true).map_err(|e| e.to_string())?;
+                Ok(LanSync::Leader {
+                    socket,
+                    target: format!("{}:{}", config.address, config.port),
+                })
+            }
+            Role::Follow => {
+                let socket = UdpSocket::bind(("0.0.0.0", config.port))
+                    .map_err(|e| format!("port {}: {}", config.port, e))?;
+                let latest = Arc::new(Mutex::new(None));
+                let shared = latest.clone();
+                thread::spawn(move || {
+                    let mut buf = [0u8; 512];
+                    while let Ok(len) = socket.recv(&mut buf) {
+                        // Stamped on arrival, so the UI thread can tell how
+                        // far the leader has moved on since
+                        if let Ok(transport) = serde_json::from_slice(&buf[..len]) {
+                            let mut latest = shared.lock().unwrap_or_else(|e| e.into_inner());
+                            *latest = Some((transport, Instant::now()));
+                        }
+                    }
+                });
+                Ok(LanSync::Follower {
+                    latest,
+                    heard: None,
+                    reseeked: None,
+                })
+            }
+        }
+    }
+
+    pub fn role(&self) -> Role {
+        match self {
+            LanSync::Leader { .. } => Role::Lead,
+            LanSync::Follower { .. } => Role::Follow,
+        }
+    }
+
+    /// Broadcast the transport, as the leader.
+    pub fn send(&self, transport: Transport) {
+        if let LanSync::Leader { socket, target } = self {
+            if let Ok(json) = serde_json::to_vec(&transport) {
+                let _ = socket.send_to(&json, target);
+            }
+        }
+    }
+
+    /// The leader's transport as of now, with its position moved on by the
+    /// time since it arrived; `None` when nothing new came or as the leader.
+    pub fn receive(&mut self) -> Option<Transport> {
+        let LanSync::Follower { latest, heard, .. } = self else {
+            return None;
+        };
+        let (mut transport, received) = (*latest.lock().unwrap_or_else(|e| e.into_inner()))?;
+        if *heard == Some(received) {
+            return None;
+        }
+        *heard = Some(received);
+        if transport.playing {
+            transport.position += received.elapsed().as_secs_f64() * transport.speed;
+        }
+        Some(transport)
+    }
+
+    /// Whether a follower `off` seconds from the leader should re-seek now.
+    pub fn should_reseek(&mut self, off: f64) -> bool {
+        let LanSync::Follower { reseeked, .. } = self else {
+            return false;
+        };
+        if off.abs() <= TOLERANCE_SECS || reseeked.is_some_and(|t| t.elapsed() < RESEEK_HOLDOFF) {
+            return false;
+        }
+        *reseeked = Some(Instant::now());
+        true
+    }
+
+    /// Whether a follower is hearing its leader.
+    pub fn connected(&self) -> bool {
+        match self {
+            LanSync::Leader { .. } => true,
+            LanSync::Follower { heard, .. } => heard.is_some_and(|t| t.elapsed() < LEADER_TIMEOUT),
+        }
+    }
+
+    /// Note a start or seek by the follower, so it doesn't re-seek straight
+    /// after.
+    pub fn started(&mut self) {
+        if let LanSync::Follower { reseeked, .. } = self {
+            *reseeked = Some(Instant::now());
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! Level meters fed from the audio path. A `LevelTap` on the main output
+//! copies every frame it passes on into a ring buffer, and the meters read
+//! the latest 50 ms (the bars) and 300 ms (the VU needles) back from it, so
+//! they show what is going to the device: after seeks, speed and pitch
+//! changes, the mono fold-down, file swaps and test signals alike.
+//!
+//! The ring never blocks either side. The output thread writes over the
+//! oldest frames and then publishes how far it has got; the UI reads back
+//! from there. A write overlapping a read can only reach the far end of the
+//! ring, further back than any window the meters take.
+
+use rodio::Source;
+use std::{
+    sync::{
+        atomic::{AtomicU32, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Frames the ring holds, a little over 300 ms at 192 kHz.
+const CAPACITY: usize = 1 << 16;
+
+/// The latest frames played on the main output, shared between the output
+/// thread and the UI.
+pub struct LiveLevels {
+    sample_rate: AtomicU32,
+    /// Left and right of each frame, as `f32` bits.
+    frames: Box<[[AtomicU32; 2]]>,
+    /// Frames written so far; the next goes in at this, modulo the length.
+    written: AtomicUsize,
+}
+
+impl Default for LiveLevels {
+    fn default() -> Self {
+        LiveLevels {
+            sample_rate: AtomicU32::new(0),
+            frames: (0..CAPACITY)
+                .map(|_| [AtomicU32::new(0), AtomicU32::new(0)])
+                .collect(),
+            written: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl LiveLevels {
+    /// Only the output thread writes.
+    fn push(&self, left: f32, right: f32) {
+        let at = self.written.load(Ordering::Relaxed);
+        let [l, r] = &self.frames[at % CAPACITY];
+        l.store(left.to_bits(), Ordering::Relaxed);
+        r.store(right.to_bits(), Ordering::Relaxed);
+        self.written.store(at + 1, Ordering::Release);
+    }
+
+    /// A mark for `rms` to read after, so frames from before a stop don't
+    /// show.
+    pub fn written(&self) -> usize {
+        self.written.load(Ordering::Acquire)
+    }
+
+    /// Left and right RMS over the last `secs` written, linear. Frames
+    /// before `since` count as silence.
+    pub fn rms(&self, secs: f64, since: usize) -> (f64, f64) {
+        let written = self.written();
+        let rate = self.sample_rate.load(Ordering::Relaxed) as f64;
+        let window = ((secs * rate) as usize).clamp(1, CAPACITY);
+        let start = written.saturating_sub(window).max(since);
+        let (left, right) = (start..written).fold((0.0, 0.0), |(left, right), at| {
+            let [l, r] = &self.frames[at % CAPACITY];
+            let l = f32::from_bits(l.load(Ordering::Relaxed)) as f64;
+            let r = f32::from_bits(r.load(Ordering::Relaxed)) as f64;
+            (left + l * l, right + r * r)
+        });
+        (
+            (left / window as f64).sqrt(),
+            (right / window as f64).sqrt(),
+        )
+    }
+}
+
+/// Passes a source through, copying its first two channels into a
+/// `LiveLevels`.
+pub struct LevelTap<S> {
+    inner: S,
+    levels: Arc<LiveLevels>,
+    channels: usize,
+    frame: Vec<f32>,
+}
+
+impl<S: Source<Item = f32>> LevelTap<S> {
+    pub fn new(inner: S, levels: Arc<LiveLevels>) -> Self {
+        levels
+            .sample_rate
+            .store(inner.sample_rate(), Ordering::Relaxed);
+        let channels = inner.channels().max(1) as usize;
+        LevelTap {
+            inner,
+            levels,
+            channels,
+            frame: Vec::with_capacity(channels),
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for LevelTap<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next()?;
+        self.frame.push(sample);
+        if self.frame.len() == self.channels {
+            // Mono plays on both sides
+            let left = self.frame[0];
+            let right = self.frame.get(1).copied().unwrap_or(left);
+            self.levels.push(left, right);
+            self.frame.clear();
+        }
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for LevelTap<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One second a thousand frames, so windows read in frames.
+    const RATE: u32 = 1000;
+
+    fn levels() -> LiveLevels {
+        let levels = LiveLevels::default();
+        levels.sample_rate.store(RATE, Ordering::Relaxed);
+        levels
+    }
+
+    fn secs(frames: usize) -> f64 {
+        frames as f64 / RATE as f64
+    }
+
+    fn assert_near(read: (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (read.0 - expected.0).abs() < 1e-9 && (read.1 - expected.1).abs() < 1e-9,
+            "read {:?}, expected {:?}",
+            read,
+            expected
+        );
+    }
+
+    /// Interleaved samples with a channel count, as a decoder gives them.
+    struct Samples {
+        samples: std::vec::IntoIter<f32>,
+        channels: u16,
+    }
+
+    impl Iterator for Samples {
+        type Item = f32;
+
+        fn next(&mut self) -> Option<f32> {
+            self.samples.next()
+        }
+    }
+
+    impl Source for Samples {
+        fn current_frame_len(&self) -> Option<usize> {
+            None
+        }
+
+        fn channels(&self) -> u16 {
+            self.channels
+        }
+
+        fn sample_rate(&self) -> u32 {
+            RATE
+        }
+
+        fn total_duration(&self) -> Option<Duration> {
+            None
+        }
+    }
+
+    fn tap(samples: Vec<f32>, channels: u16, levels: &Arc<LiveLevels>) -> Vec<f32> {
+        let source = Samples {
+            samples: samples.into_iter(),
+            channels,
+        };
+        LevelTap::new(source, levels.clone()).collect()
+    }
+
+    #[test]
+    fn empty_reads_silence() {
+        assert_near(levels().rms(0.05, 0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn reads_the_latest_window() {
+        let levels = levels();
+        for _ in 0..100 {
+            levels.push(0.5, 0.25);
+        }
+        for _ in 0..50 {
+            levels.push(1.0, -1.0);
+        }
+        assert_near(levels.rms(secs(50), 0), (1.0, 1.0));
+        // Half and half: the mean of the squares
+        let left = ((1.0 + 0.25) / 2.0f64).sqrt();
+        let right = ((1.0 + 0.0625) / 2.0f64).sqrt();
+        assert_near(levels.rms(secs(100), 0), (left, right));
+    }
+
+    #[test]
+    fn wraps_around_at_capacity() {
+        let levels = levels();
+        for _ in 0..CAPACITY {
+            levels.push(0.5, 0.5);
+        }
+        for _ in 0..100 {
+            levels.push(1.0, 0.0);
+        }
+        assert_eq!(levels.written(), CAPACITY + 100);
+        // The newest frames went over the oldest
+        assert_near(levels.rms(secs(100), 0), (1.0, 0.0));
+        // Half new, half old: the mean of the squares
+        assert_near(levels.rms(secs(200), 0), (0.625f64.sqrt(), 0.125f64.sqrt()));
+        // The whole ring: 100 new frames and the rest old
+        let old = (CAPACITY - 100) as f64 / CAPACITY as f64;
+        let new = 100.0 / CAPACITY as f64;
+        assert_near(
+            levels.rms(secs(CAPACITY), 0),
+            ((new + old * 0.25).sqrt(), (old * 0.25).sqrt()),
+        );
+    }
+
+    #[test]
+    fn a_window_longer_than_the_audio_counts_the_rest_as_silence() {
+        let levels = levels();
+        for _ in 0..100 {
+            levels.push(0.5, 0.5);
+        }
+        // 100 frames in a 400-frame window
+        assert_near(levels.rms(secs(400), 0), (0.25, 0.25));
+        // No further back than the ring holds, however long the window
+        for _ in 0..CAPACITY {
+            levels.push(0.5, 0.5);
+        }
+        assert_near(levels.rms(secs(CAPACITY * 4), 0), (0.5, 0.5));
+    }
+
+    #[test]
+    fn frames_before_the_mark_count_as_silence() {
+        let levels = levels();
+        for _ in 0..100 {
+            levels.push(1.0, 1.0);
+        }
+        let since = levels.written();
+        assert_near(levels.rms(secs(100), since), (0.0, 0.0));
+        for _ in 0..25 {
+            levels.push(1.0, 1.0);
+        }
+        assert_near(levels.rms(secs(100), since), (0.5, 0.5));
+    }
+
+    #[test]
+    fn stereo_tap_meters_each_side() {
+        let levels = Arc::new(LiveLevels::default());
+        let samples: Vec<f32> = (0..100).flat_map(|_| [0.5, -0.25]).collect();
+        assert_eq!(tap(samples.clone(), 2, &levels), samples);
+        assert_eq!(levels.written(), 100);
+        assert_near(levels.rms(secs(100), 0), (0.5, 0.25));
+    }
+
+    #[test]
+    fn mono_tap_meters_both_sides() {
+        let levels = Arc::new(LiveLevels::default());
+        let samples = vec![0.5; 100];
+        assert_eq!(tap(samples.clone(), 1, &levels), samples);
+        assert_eq!(levels.written(), 100);
+        assert_near(levels.rms(secs(100), 0), (0.5, 0.5));
+    }
+
+    #[test]
+    fn a_tap_meters_only_the_first_two_channels() {
+        let levels = Arc::new(LiveLevels::default());
+        let samples: Vec<f32> = (0..100).flat_map(|_| [0.5, 0.25, 1.0, 1.0]).collect();
+        tap(samples, 4, &levels);
+        assert_eq!(levels.written(), 100);
+        assert_near(levels.rms(secs(100), 0), (0.5, 0.25));
+    }
+}
@@ -0,0 +1,21 @@
+//! Library side of the player: the playback engine with its C ABI and
+//! Python bindings (`player/python`) for embedding in other front-ends
+//! ([`engine`], [`ffi`]), a null audio output for running the engine
+//! headless and testing it ([`null`]), loudness analysis and offline mix
+//! renders for scripted QA ([`analysis`], [`render`]), and widgets for
+//! embedding a player pane in other ratatui apps ([`widgets`]). The player
+//! binary reads projects and draws its transport, meters and track list
+//! with the same code.
+
+pub mod analysis;
+pub mod engine;
+pub mod ffi;
+pub mod json;
+pub mod null;
+pub mod project;
+pub mod render;
+pub mod status;
+pub mod text;
+pub mod time_sig;
+pub mod wav;
+pub mod widgets;
@@ -0,0 +1,93 @@
+//! One player per state directory. `state/.player.lock` holds the PID of
+//! the player that owns `.player.json`. A lock left by a process that is
+//! gone is stale and taken over silently; `--take-over` replaces a live
+//! player, which notices on its next check and exits.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, Instant},
+};
+
+/// How often a running player looks for a takeover.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct Lock {
+    path: PathBuf,
+    pid: u32,
+    checked: Instant,
+    lost: bool,
+}
+
+impl Lock {
+    pub fn acquire(state_dir: &Path, take_over: bool) -> Result<Self, String> {
+        let path = state_dir.join(".player.lock");
+        let pid = process::id();
+        if let Some(other) = read_pid(&path).filter(|&p| p != pid && alive(p)) {
+            if !take_over {
+                return Err(format!(
+                    "another player (pid {}) is already running for {}; pass --take-over to replace it",
+                    other,
+                    state_dir.display()
+                ));
+            }
+        }
+        fs::write(&path, format!("{}\n", pid)).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Lock {
+            path,
+            pid,
+            checked: Instant::now(),
+            lost: false,
+        })
+    }
+
+    /// Whether the lock file still names this process.
+    pub fn held(&self) -> bool {
+        read_pid(&self.path).is_none_or(|p| p == self.pid)
+    }
+
+    /// True once another player has taken over. Cheap to call every tick:
+    /// the file is only read once per `CHECK_INTERVAL`.
+    pub fn taken_over(&mut self) -> bool {
+        if !self.lost && self.checked.elapsed() >= CHECK_INTERVAL {
+            self.checked = Instant::now();
+            self.lost = !self.held();
+        }
+        self.lost
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        if self.held() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Whether this process holds the lock for `state_dir`, for cleanup paths
+/// that can't reach the `Lock` itself.
+pub fn held_by_this_process(state_dir: &Path) -> bool {
+    read_pid(&state_dir.join(".player.lock")) == Some(process::id())
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Whether process `pid` is still running.
+#[cfg(unix)]
+pub fn alive(pid: u32) -> bool {
+    process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(process::Stdio::null())
+        .status()
+        .map_or(true, |s| s.success())
+}
+
+/// No portable check elsewhere; assume the owner is running.
+#[cfg(not(unix))]
+pub fn alive(_pid: u32) -> bool {
+    true
+}
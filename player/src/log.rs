@@ -0,0 +1,36 @@
+//! `state/.player.log`: errors worth keeping after the notice that showed
+//! them is gone, such as a state file that didn't parse and was read as
+//! empty. One `[unix time] message` line each, appended.
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub fn write(state_dir: &Path, message: &str) {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    // Best effort: the notice has already said it
+    let _ = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(state_dir.join(".player.log"))
+        .and_then(|mut f| writeln!(f, "[{}] {}", time, message));
+}
+
+/// The loaded value, or the default with the error logged and added to
+/// `errors` for the UI.
+pub fn or_default<T: Default>(
+    state_dir: &Path,
+    loaded: Result<T, String>,
+    errors: &mut Vec<String>,
+) -> T {
+    loaded.unwrap_or_else(|e| {
+        write(state_dir, &e);
+        errors.push(e);
+        T::default()
+    })
+}
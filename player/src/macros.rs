@@ -0,0 +1,106 @@
+//! Keyboard macros: record a run of key presses into one of nine slots and
+//! replay it with a single key. Slots are saved per project in
+//! `projects/<name>/player-macros.json` as lists of key names.
+
+use crate::keymap::{key_name, parse_key_event};
+use cornwall_player::json;
+use crossterm::event::{KeyCode, KeyEvent};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const SLOTS: u8 = 9;
+
+#[derive(Default)]
+pub struct Macros {
+    path: PathBuf,
+    slots: BTreeMap<u8, Vec<KeyEvent>>,
+    /// Waiting for the slot digit after the record key.
+    pub choosing_slot: bool,
+    pub recording: Option<(u8, Vec<KeyEvent>)>,
+    /// Set while a macro is being replayed, so it isn't recorded again and
+    /// can't start another replay.
+    pub replaying: bool,
+}
+
+impl Macros {
+    /// No macros, saving to `project_dir`.
+    pub fn new(project_dir: &Path) -> Self {
+        Macros {
+            path: project_dir.join("player-macros.json"),
+            ..Macros::default()
+        }
+    }
+
+    pub fn load(project_dir: &Path) -> Result<Self, String> {
+        let path = project_dir.join("player-macros.json");
+        let saved: BTreeMap<String, Vec<String>> = json::load(&path)?;
+        let slots = saved
+            .into_iter()
+            .filter_map(|(slot, keys)| {
+                let slot = slot.parse().ok().filter(|s| (1..=SLOTS).contains(s))?;
+                let keys = keys
+                    .iter()
+                    .filter_map(|k| parse_key_event(k).ok())
+                    .collect();
+                Some((slot, keys))
+            })
+            .collect();
+        Ok(Macros {
+            path,
+            slots,
+            ..Macros::default()
+        })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let saved: BTreeMap<String, Vec<String>> = self
+            .slots
+            .iter()
+            .map(|(slot, keys)| (slot.to_string(), keys.iter().map(key_name).collect()))
+            .collect();
+        let json = serde_json::to_string_pretty(&saved).map_err(|e| e.to_string())?;
+        if let Some(dir) = self.path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        fs::write(&self.path, json).map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+
+    /// Pick the slot for a pending recording from a digit key. Any other key
+    /// cancels; returns the slot when recording starts.
+    pub fn choose_slot(&mut self, key: &KeyEvent) -> Option<u8> {
+        self.choosing_slot = false;
+        let slot = match key.code {
+            KeyCode::Char(c) => c.to_digit(10).filter(|d| (1..=SLOTS as u32).contains(d))? as u8,
+            _ => return None,
+        };
+        self.recording = Some((slot, Vec::new()));
+        Some(slot)
+    }
+
+    pub fn record(&mut self, key: &KeyEvent) {
+        if let Some((_, keys)) = &mut self.recording {
+            if !self.replaying {
+                keys.push(*key);
+            }
+        }
+    }
+
+    /// Finish recording and save. Returns the slot and number of keys.
+    pub fn finish(&mut self) -> Option<Result<(u8, usize), String>> {
+        let (slot, keys) = self.recording.take()?;
+        let count = keys.len();
+        if keys.is_empty() {
+            self.slots.remove(&slot);
+        } else {
+            self.slots.insert(slot, keys);
+        }
+        Some(self.save().map(|_| (slot, count)))
+    }
+
+    pub fn keys(&self, slot: u8) -> Option<Vec<KeyEvent>> {
+        self.slots.get(&slot).cloned()
+    }
+}
@@ -1,136 +1,261 @@
+mod accessible;
+mod beat_out;
+mod cli;
+mod clock;
+mod command_line;
+mod config;
+mod control;
+mod convert;
+mod daw;
+mod diagnostics;
+mod drift;
+mod edits;
+mod fifo;
+mod gauge;
+mod generator;
+mod hooks;
+mod http;
+mod i18n;
+mod input;
+mod inspect;
+mod keymap;
+mod lan_sync;
+mod levels;
+mod lock;
+mod log;
+mod macros;
+mod markers;
+mod mdns;
+mod measure;
+mod metrics;
+mod midi;
+mod midi_control;
+mod midi_monitor;
+mod notes;
+mod notice;
+mod output;
+mod peaks;
+mod piano_roll;
+mod practice;
+mod program;
+mod quantize;
+mod readings;
+mod recovery;
+mod replay;
+mod resample;
+#[cfg(feature = "rtp")]
+mod rtp;
+mod scope;
+mod scripting;
+mod silence;
+mod snapshot;
+mod stems;
+mod stretch;
+#[cfg(test)]
+mod tests;
+mod theme;
+mod tuner;
+mod utilities;
+mod watchdog;
+mod waveform;
+mod workspaces;
+
+use beat_out::BeatOutput;
+use cli::Command;
+use config::{CueSource, MeterConfig, MeterMode, PlayerConfig, StopPosition, WaveformColor};
+use cornwall_dsp::levels::{chunk_brightness, chunk_levels, dbfs};
+use cornwall_player::{
+    analysis::{self, Target},
+    project::{self, ProjectState, TrackState},
+    status::{LoopMode, PlayerStatus, Transport as TransportState, JSON_SCHEMA},
+    text,
+    time_sig::TimeSig,
+    widgets::{
+        self, format_position, Labels, LoudnessGauge, Meter, TrackList, TrackRow, Transport,
+        VuMeter,
+    },
+};
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
+use diagnostics::{Bench, Diagnostics};
+use drift::Drift;
+use edits::{Edit, History};
+use generator::{Generator, Signal};
+use hooks::Hooks;
+use i18n::{tr, trf};
+use inspect::FileInfo;
+use keymap::{Action, Keymap};
+use lan_sync::{LanSync, Role};
+use levels::{LevelTap, LiveLevels};
+use lock::Lock;
+use macros::Macros;
+use markers::{HotCues, Marker};
+use measure::{MeasureConfig, Measurement};
+use midi::MidiMix;
+use midi_control::{Learn, MidiControl, Param};
+use notice::Notice;
+use output::{ChannelMap, CueOutput, Device, Monitor, MonoFold, Sinks};
+use piano_roll::PianoRoll;
+use practice::{Practice, PracticeConfig};
+use program::{Bounds, Playback, Program};
+use quantize::{Quantize, Queued};
 use ratatui::{
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
     prelude::CrosstermBackend,
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Clear, Paragraph},
     Frame, Terminal,
 };
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
-use serde::{Deserialize, Serialize};
+use readings::Readings;
+use recovery::{Checkpoint, Edits};
+use replay::{Recorder, Replay};
+use resample::{Quality, Resample};
+use rodio::{Decoder, Sink, Source};
+#[cfg(feature = "rtp")]
+use rtp::StreamTap;
+use scope::{Capture, Tap};
+use scripting::{Call, Script, TrackPatch, View};
+use silence::SkipSilenceConfig;
+use snapshot::Snapshot;
 use std::{
+    collections::HashMap,
     env,
     fs,
-    io::{self, BufReader, Cursor},
+    io::{self, BufReader},
     path::{Path, PathBuf},
-    time::{Duration, Instant},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
-
-// --- State structures (mirror Cornwall's JSON) ---
-
-#[derive(Deserialize, Default, Clone)]
-struct ProjectState {
-    name: String,
-    bpm: f64,
-    #[allow(dead_code)]
-    sample_rate: u32,
-    time_sig: String,
-}
-
-#[derive(Deserialize, Default, Clone)]
-struct TrackState {
-    id: u32,
-    name: String,
-    #[allow(dead_code)]
-    #[serde(rename = "type")]
-    track_type: String,
-    source: Option<String>,
-    volume: f64,
-    pan: f64,
-    mute: bool,
-    solo: bool,
-}
-
-#[derive(Serialize)]
-struct PlayerStatus {
-    playing: bool,
-    position_secs: f64,
-    bar: u32,
-    beat: u32,
-    bpm: f64,
-    time_sig: String,
-    level_l: f64,
-    level_r: f64,
-    file: String,
-}
-
-// --- Audio level metering via pre-scanned peaks ---
+use theme::Theme;
+use tuner::{Tuner, TunerConfig};
+use unicode_width::UnicodeWidthStr;
+use utilities::UtilityMenu;
+use watchdog::Watchdog;
+use workspaces::Switcher;
+
+/// How often a stopped player rewrites its status, as a heartbeat.
+const STATUS_HEARTBEAT: Duration = Duration::from_secs(1);
+/// A status not rewritten for this long is left over from a dead player.
+const STATUS_STALE_SECS: f64 = 5.0;
+/// How long `discover` listens for players answering.
+const DISCOVER_WAIT: Duration = Duration::from_secs(2);
+/// Seconds the arrow keys, and Page Up and Down, seek by.
+const SEEK_STEP: f64 = 5.0;
+const JUMP_STEP: f64 = 30.0;
+
+// --- Audio level metering: the overview pre-scanned, the meters live ---
+
+/// Levels and brightness of one decoded block's chunks.
+type BlockLevels = (Vec<f64>, Vec<f64>, Vec<f64>);
 
 struct LevelMeter {
     levels_l: Vec<f64>,
     levels_r: Vec<f64>,
+    /// Brightness of each chunk in Hz, for colouring the waveform.
+    tones: Vec<f64>,
     chunk_duration: f64,
+    /// RMS over the last chunk played, for the meter bars.
     current_l: f64,
     current_r: f64,
+    /// RMS over the last `VU_SECS` played, for the VU needles.
+    vu_l: f64,
+    vu_r: f64,
+    /// Readings from the loader while the file is still decoding.
+    incoming: Option<mpsc::Receiver<BlockLevels>>,
+    /// Frames tapped from the main output, kept across file swaps.
+    live: Arc<LiveLevels>,
+    /// Where the readings start, so a stop or pause shows silence.
+    since: usize,
 }
 
+/// Integration time of a VU meter.
+const VU_SECS: f64 = 0.3;
+
 impl LevelMeter {
-    fn from_wav(path: &Path, chunk_ms: u32) -> Self {
-        let reader = hound::WavReader::open(path).expect("Failed to read WAV for metering");
+    /// Silences in the file worth skipping.
+    fn gaps(&self, config: &SkipSilenceConfig) -> Vec<(f64, f64)> {
+        silence::gaps(&self.levels_l, &self.levels_r, self.chunk_duration, config)
+    }
+
+    /// Start decoding `path` for playback, scanning each block as it comes
+    /// in, so playback and the UI don't wait for the whole file.
+    /// Over `budget` bytes, the file plays from disk.
+    fn load(
+        path: &Path,
+        chunk_ms: u32,
+        budget: usize,
+        live: Arc<LiveLevels>,
+    ) -> Result<(Program, Self), String> {
+        let reader = hound::WavReader::open(path).map_err(|e| e.to_string())?;
         let spec = reader.spec();
+        let (tx, rx) = mpsc::channel();
         let channels = spec.channels as usize;
-        let sample_rate = spec.sample_rate as usize;
-        let chunk_samples = (sample_rate * chunk_ms as usize) / 1000;
-
-        let samples: Vec<f64> = if spec.bits_per_sample <= 16 {
-            reader
-                .into_samples::<i16>()
-                .filter_map(|s| s.ok())
-                .map(|s| s as f64 / 32768.0)
-                .collect()
-        } else {
-            reader
-                .into_samples::<i32>()
-                .filter_map(|s| s.ok())
-                .map(|s| s as f64 / 2147483648.0)
-                .collect()
-        };
-
-        let frames = samples.len() / channels.max(1);
-        let mut levels_l = Vec::new();
-        let mut levels_r = Vec::new();
-
-        let mut i = 0;
-        while i < frames {
-            let end = (i + chunk_samples).min(frames);
-            let mut sum_l = 0.0_f64;
-            let mut sum_r = 0.0_f64;
-            let count = (end - i) as f64;
-
-            for f in i..end {
-                let l = samples[f * channels];
-                let r = if channels > 1 {
-                    samples[f * channels + 1]
-                } else {
-                    l
-                };
-                sum_l += l * l;
-                sum_r += r * r;
-            }
-
-            levels_l.push((sum_l / count).sqrt());
-            levels_r.push((sum_r / count).sqrt());
-            i = end;
-        }
-
-        LevelMeter {
-            levels_l,
-            levels_r,
+        let chunk = (spec.sample_rate as usize * chunk_ms as usize / 1000).max(1);
+        let program = Program::stream(path, budget, chunk, move |block| {
+            let (levels_l, levels_r) = chunk_levels(block, channels, spec.sample_rate, chunk_ms);
+            let tones = chunk_brightness(block, channels, spec.sample_rate, chunk_ms);
+            let _ = tx.send((levels_l, levels_r, tones));
+        })?;
+
+        let meter = LevelMeter {
+            levels_l: Vec::new(),
+            levels_r: Vec::new(),
+            tones: Vec::new(),
             chunk_duration: chunk_ms as f64 / 1000.0,
             current_l: 0.0,
             current_r: 0.0,
+            vu_l: 0.0,
+            vu_r: 0.0,
+            incoming: Some(rx),
+            since: live.written(),
+            live,
+        };
+        Ok((program, meter))
+    }
+
+    /// Take in the readings decoded since the last call. True once, when
+    /// the last of the file has come in.
+    fn receive(&mut self) -> bool {
+        let Some(incoming) = &self.incoming else {
+            return false;
+        };
+        loop {
+            match incoming.try_recv() {
+                Ok((levels_l, levels_r, tones)) => {
+                    self.levels_l.extend(levels_l);
+                    self.levels_r.extend(levels_r);
+                    self.tones.extend(tones);
+                }
+                Err(mpsc::TryRecvError::Empty) => return false,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.incoming = None;
+                    return true;
+                }
+            }
         }
     }
 
-    fn update(&mut self, position_secs: f64) {
-        let idx = (position_secs / self.chunk_duration) as usize;
-        self.current_l = self.levels_l.get(idx).copied().unwrap_or(0.0);
-        self.current_r = self.levels_r.get(idx).copied().unwrap_or(0.0);
+    /// Read the meters from what the main output has played.
+    fn update(&mut self) {
+        (self.current_l, self.current_r) = self.live.rms(self.chunk_duration, self.since);
+        (self.vu_l, self.vu_r) = self.live.rms(VU_SECS, self.since);
+    }
+
+    /// Clear the readings on a stop or pause; only frames played after
+    /// this count.
+    fn reset(&mut self) {
+        self.since = self.live.written();
+        self.current_l = 0.0;
+        self.current_r = 0.0;
+        self.vu_l = 0.0;
+        self.vu_r = 0.0;
     }
 }
 
@@ -140,386 +265,2950 @@ struct App {
     project: ProjectState,
     tracks: Vec<TrackState>,
     audio_file: PathBuf,
-    audio_data: Vec<u8>,
+    program: Program,
+    /// Bytes of decoded audio to hold before playing from disk.
+    memory_budget: usize,
+    /// MIDI tracks rendered through the SoundFont, mixed over the audio.
+    midi: Option<MidiMix>,
+    soundfont: Option<PathBuf>,
+    /// Notes of each MIDI track, by track ID.
+    piano_rolls: HashMap<u32, PianoRoll>,
+    /// Level envelopes of the audio tracks, by track ID.
+    track_peaks: HashMap<u32, Vec<f64>>,
     audio_duration: f64,
     playing: bool,
     position: f64,
     play_started: Option<Instant>,
     play_offset: f64,
+    /// When playback was paused, the sink holding the source where it
+    /// stopped; `playing` is false meanwhile.
+    paused: Option<Instant>,
+    /// Where play was last pressed, and where stop sends the playhead.
+    play_origin: f64,
+    stop_position: StopPosition,
+    quantize: Quantize,
+    hot_cues: HotCues,
+    /// Whether playback jumps over silence, how, and where it is.
+    skip_silence: bool,
+    silence_config: SkipSilenceConfig,
+    gaps: Vec<(f64, f64)>,
+    /// Output frames for the oscilloscope, vectorscope and loudness gauge,
+    /// collected while one is shown, and the time the oscilloscope spans.
+    scope: Arc<Capture>,
+    show_scope: bool,
+    show_vectorscope: bool,
+    show_gauge: bool,
+    scope_window_ms: f64,
+    /// LUFS the loudness gauge colours against.
+    loudness_target: f64,
+    meter_config: MeterConfig,
+    waveform_color: WaveformColor,
+    /// Changes since the latest snapshot, while shown.
+    diff: Option<snapshot::Diff>,
+    /// What the file inspector found, while it's shown.
+    inspector: Option<Result<FileInfo, String>>,
+    /// Room measurement under way.
+    measurement: Option<Measurement>,
+    measure_config: MeasureConfig,
+    /// Input being listened to by the tuner while it's shown.
+    tuner: Option<Tuner>,
+    tuner_config: TunerConfig,
+    /// Meter and analysis readings held on screen by `F`.
+    frozen: Option<Readings>,
+    /// Speed ramp running over the loop, and its settings.
+    practice: Option<Practice>,
+    practice_config: PracticeConfig,
+    /// Change waiting for the next beat or bar, and when that comes.
+    queued: Option<(Instant, Queued)>,
+    /// Span the playing source goes round, and how many times it has.
+    play_loop: Option<(f64, f64)>,
+    laps: u64,
+    /// Where playing the loop region once stops.
+    play_once: Option<f64>,
+    status_written: Instant,
+    drift: Drift,
+    /// Notices the output stalling for good and reopens it.
+    watchdog: Watchdog,
+    audio_stats: Arc<metrics::AudioStats>,
+    /// Timings for the diagnostics overlay and `--bench`.
+    diagnostics: Diagnostics,
+    /// Whether the diagnostics overlay is shown.
+    show_diagnostics: bool,
     meter: LevelMeter,
-    beats_per_bar: u32,
+    time_sig: TimeSig,
     state_dir: PathBuf,
-    _stream: OutputStream,
-    stream_handle: OutputStreamHandle,
+    /// Ownership of `state_dir` for this player; `None` when not locked.
+    lock: Option<Lock>,
+    /// What the sinks play on, and the transport's clock.
+    device: Box<dyn Device>,
+    /// The configured device and rate, for reopening it after a stall.
+    output_device: Option<String>,
+    output_rate: Option<u32>,
+    output_channels: u16,
+    main_channels: [u16; 2],
+    file_rate: u32,
+    device_rate: u32,
+    resample_quality: Quality,
+    stretch_quality: stretch::Quality,
+    /// Playback speed, 1 for normal; the pitch stays put.
+    speed: f64,
+    /// Pitch shift, independent of the speed.
+    semitones: i32,
     sink: Sink,
+    monitor: Monitor,
+    mono: Arc<AtomicBool>,
+    cue: Option<CueOutput>,
+    /// What the cue output plays when it isn't the main program.
+    cue_program: Option<Program>,
+    utilities: UtilityMenu,
+    /// The project switcher, and the state directory picked in it for
+    /// the main loop to move to.
+    switcher: Switcher,
+    switch_to: Option<PathBuf>,
+    generator: Option<Signal>,
+    theme: Theme,
+    clock: bool,
+    keymap: Keymap,
+    macros: Macros,
+    script: Script,
+    hooks: Hooks,
+    /// RTP stream of the main output, with `--stream`.
+    #[cfg(feature = "rtp")]
+    stream: Option<rtp::Stream>,
+    /// Leading or following other players, with `--lead` or `--follow`.
+    lan_sync: Option<LanSync>,
+    /// mDNS announcement of this player, unless turned off.
+    advertiser: Option<mdns::Advertiser>,
+    /// Remote-control server when started with `--http`.
+    http: Option<http::Server>,
+    /// Status stream when started with `--status-fifo`.
+    status_fifo: Option<fifo::StatusFifo>,
+    /// Command socket for the Cornwall CLI.
+    control: Option<control::Control>,
+    /// Foot controller or other MIDI input, when configured.
+    midi_control: Option<MidiControl>,
+    /// MIDI learn in progress.
+    learn: Option<Learn>,
+    /// Whether the MIDI input monitor is shown.
+    midi_monitor: bool,
+    /// Session notes for the project, in time order.
+    notes: Vec<notes::Note>,
+    /// Whether the notes pane is shown.
+    show_notes: bool,
+    /// Where the note being typed goes; the playhead moves on while typing.
+    note_at: Option<f64>,
+    /// Virtual MIDI port marking beats, when configured.
+    beat_out: Option<BeatOutput>,
+    /// MIDI metronome, when configured, and whether it's clicking.
+    metronome: Option<BeatOutput>,
+    metronome_on: bool,
+    /// Tempo measured from incoming MIDI clock, overriding the project's.
+    clock_bpm: Option<f64>,
+    /// Track being auditioned in place of the mix, and the file to go back
+    /// to.
+    audition: Option<(u32, PathBuf)>,
+    /// Track whose source is prelistening on its own sink, beside the
+    /// transport, and its material, held against the memory budget while
+    /// it plays.
+    preview: Option<(u32, Program)>,
+    preview_sink: Sink,
+    markers: Vec<Marker>,
+    history: History,
+    /// Text typed after `:`, while the command line is open.
+    command: Option<String>,
+    /// Asking whether to save unsaved edits before quitting.
+    quit_prompt: bool,
+    /// The session a player that didn't exit cleanly left, until the user
+    /// says whether to restore it. Not checkpointed over meanwhile.
+    recovery: Option<Checkpoint>,
+    checkpointed: Instant,
+    notice: Option<Notice>,
+    selected_track: usize,
     looping: bool,
+    /// Inclusive bar range to loop instead of the whole file.
+    loop_region: Option<(u32, u32)>,
 }
 
 impl App {
-    fn new(state_dir: PathBuf, audio_file: PathBuf) -> Self {
-        let project: ProjectState = fs::read_to_string(state_dir.join("project.json"))
-            .ok()
-            .and_then(|s| serde_json::from_str(&s).ok())
-            .unwrap_or_default();
-
-        let tracks: Vec<TrackState> = fs::read_to_string(state_dir.join("tracks.json"))
-            .ok()
-            .and_then(|s| serde_json::from_str(&s).ok())
-            .unwrap_or_default();
-
-        let beats_per_bar: u32 = project
-            .time_sig
-            .split('/')
-            .next()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(4);
-
-        let reader = hound::WavReader::open(&audio_file).expect("Cannot open WAV file");
+    /// `errors` are state files that already failed to parse, shown along
+    /// with any found here. Fails when the audio file, key bindings or an
+    /// output device can't be opened.
+    fn new(
+        state_dir: PathBuf,
+        audio_file: PathBuf,
+        config: PlayerConfig,
+        errors: Vec<String>,
+    ) -> Result<Self, String> {
+        let output = config.output.clone();
+        Self::open(state_dir, audio_file, config, errors, |_| {
+            Sinks::open(output.device.as_deref(), output.main, output.sample_rate)
+        })
+    }
+
+    /// [`Self::new`] with the sinks from `open`, given the audio file's
+    /// format.
+    fn open(
+        state_dir: PathBuf,
+        audio_file: PathBuf,
+        config: PlayerConfig,
+        mut errors: Vec<String>,
+        open: impl FnOnce(hound::WavSpec) -> Result<Sinks, String>,
+    ) -> Result<Self, String> {
+        let project = log::or_default(&state_dir, ProjectState::load(&state_dir), &mut errors);
+        let tracks = log::or_default(&state_dir, project::load_tracks(&state_dir), &mut errors);
+
+        let time_sig = TimeSig::parse(&project.time_sig);
+        // Shown once the UI is up; an empty signature just means no project
+        let time_sig_error = (time_sig.is_none() && !project.time_sig.is_empty())
+            .then(|| Notice::error(trf("error.time_sig", &[("value", &project.time_sig)])));
+        let time_sig = time_sig.unwrap_or_default();
+
+        let reader = hound::WavReader::open(&audio_file)
+            .map_err(|e| format!("Cannot open {}: {}", audio_file.display(), e))?;
         let spec = reader.spec();
         let total_samples = reader.len() as f64;
         let audio_duration = total_samples / (spec.sample_rate as f64 * spec.channels as f64);
 
-        let meter = LevelMeter::from_wav(&audio_file, 50);
-        let audio_data = fs::read(&audio_file).expect("Cannot read audio file");
+        let (midi, midi_error) =
+            match MidiMix::render(&tracks, config.soundfont.as_deref(), spec.sample_rate) {
+                Ok(midi) => (midi, None),
+                Err(e) => (
+                    None,
+                    Some(Notice::error(trf("error.midi", &[("error", &e)]))),
+                ),
+            };
+        let memory_budget = config.memory.bytes();
+        let midi_memory = midi.as_ref().map_or(0, MidiMix::memory);
+        let (program, meter) = LevelMeter::load(
+            &audio_file,
+            50,
+            memory_budget.saturating_sub(midi_memory),
+            Arc::new(LiveLevels::default()),
+        )
+        .map_err(|e| format!("Cannot read {}: {}", audio_file.display(), e))?;
+        // Playing from disk is the default; only worth saying when the file
+        // was meant to be held
+        let disk_notice = (program.on_disk() && config.memory.budget_mb > 0)
+            .then(|| Notice::info(trf("notice.from_disk", &[("mb", &config.memory.budget_mb)])));
+
+        let sinks = open(spec).map_err(|e| format!("Cannot open audio output: {}", e))?;
+
+        let keymap =
+            Keymap::new(&config.keys).map_err(|e| format!("Invalid key binding: {}", e))?;
+
+        let project_dir = project.dir(&state_dir);
+        let macros = Macros::load(&project_dir).unwrap_or_else(|e| {
+            log::write(&state_dir, &e);
+            errors.push(e);
+            Macros::new(&project_dir)
+        });
+        let script = Script::new();
+        let script_error = script
+            .run_file(&project_dir)
+            .err()
+            .map(|e| Notice::error(trf("error.script", &[("error", &e)])));
+        let markers = log::or_default(&state_dir, markers::load(&state_dir), &mut errors);
+        let notes = log::or_default(&state_dir, notes::load(&project_dir), &mut errors);
+        let recovery = log::or_default(&state_dir, recovery::load(&state_dir), &mut errors);
+        // Read as empty so the player still opens; the log has them all
+        let state_error = errors
+            .first()
+            .map(|e| Notice::error(trf("error.state", &[("error", e)])));
+        let piano_rolls = piano_roll::load_tracks(&tracks);
+        let track_peaks = peaks::load_tracks(&state_dir, &tracks);
+
+        let (midi_control, midi_control_error) =
+            match config.midi_input.as_ref().map(MidiControl::open) {
+                Some(Ok(midi_control)) => (Some(midi_control), None),
+                Some(Err(e)) => (
+                    None,
+                    Some(Notice::error(trf("error.midi_input", &[("error", &e)]))),
+                ),
+                None => (None, None),
+            };
+
+        let (beat_out, beat_out_error) = match config.midi_output.as_ref().map(BeatOutput::open) {
+            Some(Ok(beat_out)) => (Some(beat_out), None),
+            Some(Err(e)) => (
+                None,
+                Some(Notice::error(trf("error.midi_output", &[("error", &e)]))),
+            ),
+            None => (None, None),
+        };
+
+        let (metronome, metronome_error) =
+            match config.metronome.as_ref().map(BeatOutput::metronome) {
+                Some(Ok(metronome)) => (Some(metronome), None),
+                Some(Err(e)) => (
+                    None,
+                    Some(Notice::error(trf("error.metronome", &[("error", &e)]))),
+                ),
+                None => (None, None),
+            };
 
-        let (_stream, stream_handle) =
-            OutputStream::try_default().expect("Cannot open audio output");
-        let sink = Sink::try_new(&stream_handle).expect("Cannot create audio sink");
-        sink.pause();
+        let resample_quality = config.output.resample;
+        let cue = config
+            .cue
+            .as_ref()
+            .map(|c| CueOutput::open(c, resample_quality, config.output.stretch))
+            .transpose()
+            .map_err(|e| format!("Cannot open cue output: {}", e))?;
+        let cue_program = match &config.cue {
+            Some(c) if c.source == CueSource::Solo => tracks
+                .iter()
+                .filter(|t| t.solo)
+                .filter_map(|t| t.source.as_ref())
+                .find_map(|s| {
+                    let left = memory_budget.saturating_sub(midi_memory + program.memory());
+                    Program::load(Path::new(s), left).ok()
+                }),
+            _ => None,
+        };
 
-        App {
+        Ok(App {
             project,
             tracks,
             audio_file,
-            audio_data,
+            program,
+            memory_budget,
+            midi,
+            soundfont: config.soundfont,
+            piano_rolls,
+            track_peaks,
             audio_duration,
             playing: false,
             position: 0.0,
             play_started: None,
+            paused: None,
             play_offset: 0.0,
+            play_loop: None,
+            laps: 0,
+            play_once: None,
+            quantize: config.quantize,
+            hot_cues: config.hot_cues,
+            play_origin: 0.0,
+            stop_position: config.stop,
+            skip_silence: config.skip_silence.enabled,
+            silence_config: config.skip_silence,
+            scope: Arc::new(Capture::new(
+                config.scope.enabled || config.scope.vectorscope || config.loudness.enabled,
+            )),
+            show_scope: config.scope.enabled,
+            show_vectorscope: config.scope.vectorscope,
+            show_gauge: config.loudness.enabled,
+            loudness_target: config.loudness.target,
+            meter_config: config.meter,
+            waveform_color: config.waveform_color,
+            diff: None,
+            inspector: None,
+            measurement: None,
+            measure_config: config.measure.clone(),
+            tuner: None,
+            tuner_config: config.tuner.clone(),
+            frozen: None,
+            scope_window_ms: config
+                .scope
+                .window_ms
+                .clamp(scope::MIN_WINDOW_MS, scope::MAX_WINDOW_MS),
+            gaps: Vec::new(),
+            practice: None,
+            practice_config: config.practice,
+            queued: None,
+            status_written: Instant::now(),
+            drift: Drift::default(),
+            watchdog: Watchdog::new(&config.watchdog),
+            audio_stats: Arc::default(),
+            diagnostics: Diagnostics::default(),
+            show_diagnostics: false,
             meter,
-            beats_per_bar,
+            time_sig,
             state_dir,
-            _stream,
-            stream_handle,
-            sink,
+            lock: None,
+            device: sinks.device,
+            output_device: config.output.device.clone(),
+            output_rate: config.output.sample_rate,
+            output_channels: sinks.channels,
+            main_channels: config.output.main,
+            file_rate: spec.sample_rate,
+            device_rate: sinks.sample_rate,
+            resample_quality,
+            stretch_quality: config.output.stretch,
+            speed: 1.0,
+            semitones: 0,
+            sink: sinks.main,
+            monitor: Monitor::default(),
+            mono: Arc::new(AtomicBool::new(false)),
+            cue,
+            cue_program,
+            utilities: UtilityMenu::default(),
+            switcher: Switcher::default(),
+            switch_to: None,
+            generator: None,
+            theme: Theme::default(),
+            clock: false,
+            keymap,
+            macros,
+            script,
+            hooks: Hooks::new(config.hooks),
+            http: None,
+            lan_sync: None,
+            advertiser: None,
+            #[cfg(feature = "rtp")]
+            stream: None,
+            status_fifo: None,
+            control: None,
+            midi_control,
+            learn: None,
+            midi_monitor: false,
+            show_notes: !notes.is_empty(),
+            notes,
+            note_at: None,
+            beat_out,
+            metronome,
+            metronome_on: config.metronome.as_ref().is_some_and(|m| m.enabled),
+            clock_bpm: None,
+            audition: None,
+            preview: None,
+            preview_sink: sinks.preview,
+            markers,
+            history: History::default(),
+            command: None,
+            quit_prompt: false,
+            recovery,
+            checkpointed: Instant::now(),
+            notice: state_error
+                .or(script_error)
+                .or(midi_error)
+                .or(midi_control_error)
+                .or(beat_out_error)
+                .or(metronome_error)
+                .or(time_sig_error)
+                .or(disk_notice),
+            selected_track: 0,
             looping: true,
-        }
+            loop_region: None,
+        })
     }
 
     fn toggle_play(&mut self) {
-        if self.playing {
+        let measuring = matches!(self.measurement, Some(Measurement::Recording { .. }));
+        if self.generator.is_some() || measuring {
             self.stop();
+        } else if self.playing {
+            self.pause();
+        } else if self.paused.is_some() {
+            self.resume();
         } else {
+            if let Some(step) = self.grid_step() {
+                self.position = quantize::previous(self.position, step);
+            }
             self.play();
         }
     }
 
-    fn play(&mut self) {
-        self.playing = true;
-        self.play_offset = 0.0;
-        self.position = 0.0;
-        self.play_started = Some(Instant::now());
+    /// Length of the launch quantization grid, when on and there's a tempo.
+    fn grid_step(&self) -> Option<f64> {
+        let bar_secs = self.bar_secs()?;
+        let beat_secs = bar_secs / self.time_sig.beats_per_bar() as f64;
+        self.quantize.step(beat_secs, bar_secs)
+    }
+
+    /// Make a transport change now, or while playing with quantization on,
+    /// at the next beat or bar.
+    fn queue(&mut self, change: Queued) {
+        let step = self.grid_step().filter(|_| self.playing);
+        let Some(step) = step else {
+            self.apply_queued(change);
+            return;
+        };
+        let wait = (quantize::next(self.position, step) - self.position) / self.speed;
+        self.queued = Some((self.now() + Duration::from_secs_f64(wait), change));
+        self.notify(tr(match self.quantize {
+            Quantize::Beat => "notice.queued_beat",
+            _ => "notice.queued_bar",
+        }));
+    }
+
+    fn apply_queued(&mut self, change: Queued) {
+        match change {
+            Queued::ToggleLoop => {
+                self.looping = !self.looping;
+                self.notify(self.loop_label());
+            }
+            Queued::Seek(secs) => {
+                self.seek(secs);
+                self.notify(self.position_label());
+            }
+        }
+    }
 
-        self.sink.stop();
-        self.sink = Sink::try_new(&self.stream_handle).expect("Cannot create audio sink");
+    fn play(&mut self) {
+        // Stopped at the end, play starts over
+        if self.position >= self.audio_duration {
+            self.position = 0.0;
+        }
+        self.play_origin = self.position;
+        self.scope.reset_loudness();
+        self.start_at(self.position);
+    }
 
-        let cursor = Cursor::new(self.audio_data.clone());
-        let source = Decoder::new(BufReader::new(cursor)).expect("Cannot decode audio");
-        self.sink.append(source);
+    /// Start playback `offset` seconds into the file.
+    fn start_at(&mut self, offset: f64) {
+        let span = self.wanted_loop().filter(|_| self.play_once.is_none());
+        // Past the loop's end, looping starts it over
+        let offset = match span {
+            Some((start, end)) if offset >= end => start,
+            _ => offset,
+        };
+        let bounds = match (self.play_once, span) {
+            (Some(end), _) => Bounds::Until(end),
+            (None, Some((start, end))) => Bounds::Loop(start, end),
+            (None, None) => Bounds::Open,
+        };
+        self.generator = None;
+        self.playing = true;
+        self.paused = None;
+        self.play_offset = offset;
+        self.play_loop = span;
+        self.laps = 0;
+        self.position = offset;
+        self.play_started = Some(self.now());
+        self.drift.reset();
+        self.watchdog.reset();
+
+        self.device.clear(&self.sink);
+
+        let source = self.source(&self.program, offset, bounds);
+        let (speed, semitones) = (self.speed, self.semitones);
+        let quality = self.stretch_quality;
+        match &self.midi {
+            Some(midi) if self.audition.is_none() => self.append_main(stretch::shift(
+                source.mix(midi.source(offset, bounds)),
+                speed,
+                semitones,
+                quality,
+            )),
+            _ => self.append_main(stretch::shift(source, speed, semitones, quality)),
+        }
         self.sink.play();
+
+        if let Some(cue) = &mut self.cue {
+            let program = self.cue_program.as_ref().unwrap_or(&self.program);
+            cue.play(program, offset, bounds, speed, semitones);
+        }
     }
 
-    fn stop(&mut self) {
-        self.playing = false;
-        self.position = 0.0;
-        self.play_started = None;
-        self.sink.stop();
-        self.sink = Sink::try_new(&self.stream_handle).expect("Cannot create audio sink");
-        self.meter.current_l = 0.0;
-        self.meter.current_r = 0.0;
+    /// Play the loop region from its start, stopping at its end instead of
+    /// going round.
+    fn play_region(&mut self) {
+        let (Some(_), Some(_)) = (self.loop_region, self.bar_secs()) else {
+            self.notify_error(tr("error.no_loop_region"));
+            return;
+        };
+        let (start, end) = self.loop_span();
+        self.play_once = Some(end);
+        self.play_origin = start;
+        self.start_at(start);
+        self.notify(self.position_label());
     }
 
-    fn tick(&mut self) {
-        if self.playing {
-            if let Some(started) = self.play_started {
-                self.position = self.play_offset + started.elapsed().as_secs_f64();
-
-                if self.position >= self.audio_duration {
-                    if self.looping {
-                        self.play();
-                    } else {
-                        self.stop();
-                    }
-                    return;
-                }
+    /// The span playback should go round: the loop region, or the whole
+    /// file, while looping.
+    fn wanted_loop(&self) -> Option<(f64, f64)> {
+        let (start, end) = self.loop_span();
+        (self.looping && end > start).then_some((start, end))
+    }
 
-                self.meter.update(self.position);
+    /// Where the current pass through the loop began, as the instant and
+    /// the position played from then, and how many times playback has gone
+    /// round. Without a loop it's where playback started.
+    fn pass(&self) -> Option<(Instant, f64, u64)> {
+        let started = self.play_started?;
+        let played = self.play_offset + self.played_since(started);
+        match self.play_loop {
+            Some((start, end)) if played >= end => {
+                let laps = ((played - end) / (end - start)).floor();
+                let secs = (end - self.play_offset + laps * (end - start)) / self.speed;
+                Some((
+                    started + Duration::from_secs_f64(secs),
+                    start,
+                    laps as u64 + 1,
+                ))
             }
-
-            self.write_status();
+            _ => Some((started, self.play_offset, 0)),
         }
     }
 
-    fn current_bar(&self) -> u32 {
-        if self.project.bpm <= 0.0 {
-            return 1;
+    /// Change the playback speed, picking up from the same place.
+    fn set_speed(&mut self, speed: f64) {
+        // Whole percent, so stepping back to 100% lands exactly on it
+        self.speed =
+            ((speed * 100.0).round() / 100.0).clamp(stretch::MIN_SPEED, stretch::MAX_SPEED);
+        if self.playing {
+            self.start_at(self.position);
         }
-        let beat = self.position * self.project.bpm / 60.0;
-        (beat / self.beats_per_bar as f64) as u32 + 1
+        self.drop_paused();
+        let percent = format!("{:.0}", self.speed * 100.0);
+        self.notify(trf("notice.speed", &[("percent", &percent)]));
     }
 
-    fn current_beat(&self) -> u32 {
-        if self.project.bpm <= 0.0 {
-            return 1;
+    /// Loop bars `start`-`end` (the current loop when unset) from their
+    /// start at the practice speed, then ramp up.
+    fn start_practice(&mut self, bars: Option<(u32, u32)>) {
+        if bars.is_some() {
+            self.loop_region = bars;
         }
-        let beat = self.position * self.project.bpm / 60.0;
-        (beat % self.beats_per_bar as f64) as u32 + 1
+        self.looping = true;
+        let practice = Practice::new(self.practice_config);
+        self.speed = practice
+            .start()
+            .clamp(stretch::MIN_SPEED, stretch::MAX_SPEED);
+        self.practice = Some(practice);
+        self.start_at(self.loop_span().0);
+        let percent = format!("{:.0}", self.speed * 100.0);
+        self.notify(trf("notice.practice", &[("percent", &percent)]));
     }
 
-    fn write_status(&self) {
-        let status = PlayerStatus {
-            playing: self.playing,
-            position_secs: self.position,
-            bar: self.current_bar(),
-            beat: self.current_beat(),
-            bpm: self.project.bpm,
-            time_sig: self.project.time_sig.clone(),
-            level_l: self.meter.current_l,
-            level_r: self.meter.current_r,
-            file: self.audio_file.to_string_lossy().to_string(),
-        };
-        let json = serde_json::to_string(&status).unwrap_or_default();
-        let _ = fs::write(self.state_dir.join(".player.json"), json);
+    fn stop_practice(&mut self) {
+        self.practice = None;
+        let percent = format!("{:.0}", self.speed * 100.0);
+        self.notify(trf("notice.practice_off", &[("percent", &percent)]));
     }
 
-    fn clear_status(&self) {
-        let _ = fs::remove_file(self.state_dir.join(".player.json"));
+    /// Shift the pitch, keeping the speed.
+    fn set_pitch(&mut self, semitones: i32) {
+        self.semitones = semitones.clamp(-stretch::MAX_SEMITONES, stretch::MAX_SEMITONES);
+        if self.playing {
+            self.start_at(self.position);
+        }
+        self.drop_paused();
+        let semitones = format!("{:+}", self.semitones);
+        self.notify(trf("notice.pitch", &[("semitones", &semitones)]));
     }
-}
 
-// --- UI rendering ---
-
-fn render_meter_bar(level: f64, width: u16) -> Vec<Span<'static>> {
-    let filled = ((level * 3.0).min(1.0) * width as f64) as u16;
-    let mut spans = Vec::new();
-
-    for i in 0..width {
-        if i < filled {
-            let ratio = i as f64 / width as f64;
-            let color = if ratio < 0.6 {
-                Color::Green
-            } else if ratio < 0.85 {
-                Color::Yellow
-            } else {
-                Color::Red
-            };
-            spans.push(Span::styled("█", Style::default().fg(color)));
+    /// Move the playhead, restarting playback there if running.
+    fn seek(&mut self, secs: f64) {
+        let secs = secs.clamp(0.0, self.audio_duration);
+        if self.playing {
+            self.start_at(secs);
         } else {
-            spans.push(Span::styled("░", Style::default().fg(Color::DarkGray)));
+            self.drop_paused();
+            self.position = secs;
         }
     }
-    spans
-}
 
-fn ui(f: &mut Frame, app: &App) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),  // header
-            Constraint::Length(5),  // transport
-            Constraint::Length(5),  // meters
-            Constraint::Min(3),    // track list
-            Constraint::Length(3), // footer
-        ])
-        .split(f.area());
+    /// Jump to the start of a neighbouring bar. Stepping back from more than
+    /// a moment into a bar lands on its own downbeat first.
+    fn step_bar(&mut self, delta: i32) {
+        let Some(bar) = self.bar_secs() else {
+            return;
+        };
+        let mut index = (self.position / bar).floor();
+        if delta < 0 && self.position - index * bar > 0.25 {
+            index += 1.0;
+        }
+        self.seek((index + delta as f64) * bar);
+    }
 
-    // --- Header ---
-    let title = format!(
-        "  C O R N W A L L   ─   {}",
-        app.project.name.to_uppercase()
-    );
-    let header = Paragraph::new(Line::from(vec![Span::styled(
-        title,
-        Style::default()
-            .fg(Color::White)
-            .add_modifier(Modifier::BOLD),
-    )]))
-    .block(
-        Block::default()
-            .borders(Borders::BOTTOM)
-            .border_style(Style::default().fg(Color::DarkGray)),
-    );
-    f.render_widget(header, chunks[0]);
+    fn bar_secs(&self) -> Option<f64> {
+        self.time_sig.bar_secs(self.bpm())
+    }
 
-    // --- Transport ---
-    let state_indicator = if app.playing {
-        Span::styled(
-            "  ▶ PLAYING ",
-            Style::default()
-                .fg(Color::Black)
-                .bg(Color::Green)
-                .add_modifier(Modifier::BOLD),
-        )
-    } else {
-        Span::styled(
-            "  ■ STOPPED ",
-            Style::default()
-                .fg(Color::White)
-                .bg(Color::DarkGray)
-                .add_modifier(Modifier::BOLD),
-        )
-    };
+    /// The beat grid for the MIDI beat output, while playing with a tempo.
+    fn beat_schedule(&self) -> Option<beat_out::Schedule> {
+        let (started, offset, _) = self.pass().filter(|_| self.playing)?;
+        let bar_secs = self.bar_secs()?;
+        let beats_per_bar = self.time_sig.beats_per_bar();
+        // In wall-clock time, which runs slower or faster than the file
+        Some(beat_out::Schedule {
+            started,
+            offset: offset / self.speed,
+            beat_secs: bar_secs / beats_per_bar as f64 / self.speed,
+            beats_per_bar,
+        })
+    }
 
-    let bar_display = format!(
-        "  BAR {:>3} . {}   ",
-        app.current_bar(),
-        app.current_beat()
-    );
+    /// Tempo for bars and beats: the MIDI clock's when following one, to a
+    /// tenth of a BPM, else the project's.
+    fn bpm(&self) -> f64 {
+        self.clock_bpm
+            .map_or(self.project.bpm, |bpm| (bpm * 10.0).round() / 10.0)
+    }
 
-    let time_display = format!(
-        "{:02}:{:02}.{:01}",
-        (app.position as u32) / 60,
-        (app.position as u32) % 60,
-        ((app.position * 10.0) as u32) % 10
-    );
+    /// Loop start and end in seconds: the bar region if set, else the file.
+    fn loop_span(&self) -> (f64, f64) {
+        match (self.loop_region, self.bar_secs()) {
+            (Some((start, end)), Some(bar)) => (
+                (start - 1) as f64 * bar,
+                (end as f64 * bar).min(self.audio_duration),
+            ),
+            _ => (0.0, self.audio_duration),
+        }
+    }
 
-    let tempo_display = format!("  {} BPM  {}  ", app.project.bpm, app.project.time_sig);
+    fn select_track(&mut self, delta: i32) {
+        let last = self.tracks.len().saturating_sub(1) as i32;
+        self.selected_track = (self.selected_track as i32 + delta).clamp(0, last) as usize;
+    }
 
-    let progress = if app.audio_duration > 0.0 {
-        (app.position / app.audio_duration).min(1.0)
-    } else {
-        0.0
-    };
+    /// Run a bound action. Returns false when the player should quit.
+    fn perform(&mut self, action: Action) -> bool {
+        match action {
+            Action::TogglePlay => self.toggle_play(),
+            Action::Stop => self.stop(),
+            Action::Quit => return self.request_quit(),
+            Action::ToggleLoop => self.queue(Queued::ToggleLoop),
+            Action::MonitorDown => self.update_monitor(|m| m.nudge(-1.0)),
+            Action::MonitorUp => self.update_monitor(|m| m.nudge(1.0)),
+            Action::ToggleMute => self.update_monitor(|m| m.muted = !m.muted),
+            Action::ToggleDim => self.update_monitor(|m| m.dimmed = !m.dimmed),
+            Action::ToggleMono => self.toggle_mono(),
+            Action::Utilities => self.utilities.open = true,
+            Action::ToggleClock => self.clock = !self.clock,
+            Action::CueDown | Action::CueUp => {
+                let Some(cue) = &mut self.cue else {
+                    self.notify_error(tr("error.no_cue"));
+                    return true;
+                };
+                cue.nudge_level(if action == Action::CueUp { 1.0 } else { -1.0 });
+                let text = trf("notice.cue", &[("db", &format!("{:+.0}", cue.level_db))]);
+                self.notify(text);
+            }
+            Action::BarBack | Action::BarForward => {
+                if self.bar_secs().is_none() {
+                    self.notify_error(tr("error.no_tempo"));
+                    return true;
+                }
+                self.step_bar(if action == Action::BarForward { 1 } else { -1 });
+                self.notify(self.position_label());
+            }
+            Action::SeekBack
+            | Action::SeekForward
+            | Action::JumpBack
+            | Action::JumpForward
+            | Action::GoToStart => {
+                let secs = match action {
+                    Action::SeekBack => self.position - SEEK_STEP,
+                    Action::SeekForward => self.position + SEEK_STEP,
+                    Action::JumpBack => self.position - JUMP_STEP,
+                    Action::JumpForward => self.position + JUMP_STEP,
+                    // Go to start
+                    _ => 0.0,
+                };
+                self.seek(secs);
+                self.notify(self.position_label());
+            }
+            Action::NextTrack | Action::PrevTrack => {
+                self.select_track(if action == Action::NextTrack { 1 } else { -1 });
+                if let Some(t) = self.tracks.get(self.selected_track) {
+                    self.notify(trf("notice.track", &[("id", &t.id), ("name", &t.name)]));
+                }
+            }
+            Action::CommandLine => self.command = Some(String::new()),
+            Action::TrackMute => self.edit_track(Edit::Mute, |t| t.mute = !t.mute),
+            Action::TrackSolo => self.edit_track(Edit::Solo, |t| t.solo = !t.solo),
+            Action::TrackVolumeDown => {
+                self.edit_track(Edit::Volume, |t| nudge(&mut t.volume, -0.1, 0.0, 2.0))
+            }
+            Action::TrackVolumeUp => {
+                self.edit_track(Edit::Volume, |t| nudge(&mut t.volume, 0.1, 0.0, 2.0))
+            }
+            Action::TrackPanLeft => {
+                self.edit_track(Edit::Pan, |t| nudge(&mut t.pan, -0.1, -1.0, 1.0))
+            }
+            Action::TrackPanRight => {
+                self.edit_track(Edit::Pan, |t| nudge(&mut t.pan, 0.1, -1.0, 1.0))
+            }
+            Action::AddMarker => self.add_marker(None),
+            Action::Undo => {
+                if self.history.undo(&mut self.tracks, &mut self.markers) {
+                    self.notify(tr("notice.undo"));
+                } else {
+                    self.notify_error(tr("error.nothing_to_undo"));
+                }
+            }
+            Action::Redo => {
+                if self.history.redo(&mut self.tracks, &mut self.markers) {
+                    self.notify(tr("notice.redo"));
+                } else {
+                    self.notify_error(tr("error.nothing_to_redo"));
+                }
+            }
+            Action::Save => self.save_edits(),
+            Action::MacroRecord => self.toggle_macro_recording(),
+            Action::ToggleMetronome => {
+                if self.metronome.is_none() {
+                    self.notify_error(tr("error.no_metronome"));
+                    return true;
+                }
+                self.metronome_on = !self.metronome_on;
+                self.notify(tr(if self.metronome_on {
+                    "notice.metronome_on"
+                } else {
+                    "notice.metronome_off"
+                }));
+            }
+            Action::MidiLearn => {
+                if self.midi_control.is_none() {
+                    self.notify_error(tr("error.no_midi_input"));
+                } else if self.learn.is_some() {
+                    self.cancel_learn();
+                } else {
+                    self.learn = Some(Learn::Parameter);
+                    self.notify(tr("notice.learn_param"));
+                }
+            }
+            Action::MidiPanic => self.midi_panic(),
+            Action::SpeedDown => self.set_speed(self.speed - 0.05),
+            Action::SpeedUp => self.set_speed(self.speed + 0.05),
+            Action::PitchDown => self.set_pitch(self.semitones - 1),
+            Action::PitchUp => self.set_pitch(self.semitones + 1),
+            Action::MidiMonitor => {
+                if self.midi_control.is_none() {
+                    self.notify_error(tr("error.no_midi_input"));
+                } else {
+                    self.midi_monitor = !self.midi_monitor;
+                }
+            }
+            Action::PlayMacro(slot) => return self.play_macro(slot),
+            Action::HotCue(n) => self.hot_cue(n),
+            Action::PlayRegion => self.play_region(),
+            Action::PreviewTrack => self.preview_track(),
+            Action::ToggleScope => {
+                self.show_scope = !self.show_scope;
+                self.capture_output();
+            }
+            Action::ToggleVectorscope => {
+                self.show_vectorscope = !self.show_vectorscope;
+                self.capture_output();
+            }
+            Action::ToggleVu => {
+                self.meter_config.mode = match self.meter_config.mode {
+                    MeterMode::Rms => MeterMode::Vu,
+                    MeterMode::Vu => MeterMode::Rms,
+                };
+                self.notify(tr(match self.meter_config.mode {
+                    MeterMode::Rms => "notice.meter_rms",
+                    MeterMode::Vu => "notice.meter_vu",
+                }));
+            }
+            Action::ToggleLoudness => {
+                self.show_gauge = !self.show_gauge;
+                self.capture_output();
+            }
+            Action::FreezeMeters => self.freeze_meters(),
+            Action::ExportStems => self.export_stems(),
+            Action::WriteNote => {
+                self.note_at = Some(self.position);
+                self.command = Some("note ".to_string());
+            }
+            Action::ToggleNotes => self.show_notes = !self.show_notes,
+            Action::ToggleDiagnostics => self.show_diagnostics = !self.show_diagnostics,
+            Action::SwitchProject => self.open_switcher(),
+            Action::ToggleTuner => self.toggle_tuner(),
+            Action::ToggleInspector => {
+                self.inspector = match self.inspector {
+                    Some(_) => None,
+                    None => Some(inspect::inspect(&self.audio_file)),
+                };
+            }
+            Action::ToggleSkipSilence => {
+                self.skip_silence = !self.skip_silence;
+                self.notify(tr(if self.skip_silence {
+                    "notice.skip_silence_on"
+                } else {
+                    "notice.skip_silence_off"
+                }));
+            }
+            Action::None => {}
+        }
+        true
+    }
 
-    let transport_line1 = Line::from(vec![
-        state_indicator,
-        Span::raw("  "),
-        Span::styled(
-            bar_display,
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        ),
-        Span::styled(time_display, Style::default().fg(Color::White)),
-        Span::styled(tempo_display, Style::default().fg(Color::DarkGray)),
-    ]);
-
-    let prog_width = chunks[1].width.saturating_sub(4) as usize;
-    let filled = (progress * prog_width as f64) as usize;
-    let mut prog_spans = vec![Span::raw("  ")];
-    for i in 0..prog_width {
-        if i < filled {
-            prog_spans.push(Span::styled("━", Style::default().fg(Color::Cyan)));
-        } else if i == filled && app.playing {
-            prog_spans.push(Span::styled("╸", Style::default().fg(Color::White)));
-        } else {
-            prog_spans.push(Span::styled("─", Style::default().fg(Color::DarkGray)));
+    /// Silence the MIDI outputs and the rendered MIDI tracks.
+    fn midi_panic(&mut self) {
+        let outputs: Vec<&BeatOutput> = self.beat_out.iter().chain(&self.metronome).collect();
+        if outputs.is_empty() && self.midi.is_none() {
+            self.notify_error(tr("error.no_midi"));
+            return;
+        }
+        for output in outputs {
+            output.panic();
+        }
+        if let Some(midi) = &self.midi {
+            midi.silence();
         }
+        self.notify(tr("notice.midi_panic"));
     }
 
-    let transport = Paragraph::new(vec![
-        Line::from(""),
-        transport_line1,
-        Line::from(""),
-        Line::from(prog_spans),
-    ])
-    .block(
-        Block::default()
-            .borders(Borders::BOTTOM)
-            .border_style(Style::default().fg(Color::DarkGray)),
-    );
-    f.render_widget(transport, chunks[1]);
+    /// Keys during MIDI learn: a track parameter's key picks what the next
+    /// control move maps to, `Esc` or the learn key again cancels, and the
+    /// rest work as usual.
+    fn handle_learn_key(&mut self, key: &KeyEvent) -> bool {
+        let action = self.keymap.action(key);
+        if key.code == KeyCode::Esc || action == Some(Action::MidiLearn) {
+            self.cancel_learn();
+            return true;
+        }
+        let param = match action {
+            Some(Action::TrackVolumeDown | Action::TrackVolumeUp) => Param::Volume,
+            Some(Action::TrackPanLeft | Action::TrackPanRight) => Param::Pan,
+            Some(Action::TrackMute) => Param::Mute,
+            Some(Action::TrackSolo) => Param::Solo,
+            Some(action) => return self.perform(action),
+            None => return true,
+        };
+        let Some(track) = self.tracks.get(self.selected_track) else {
+            self.notify_error(tr("error.no_track"));
+            return true;
+        };
+        let (id, name) = (track.id, track.name.clone());
+        if let Some(midi_control) = &mut self.midi_control {
+            midi_control.learn(Some((id, param)));
+        }
+        self.learn = Some(Learn::Control { track: id, param });
+        self.notify(trf(
+            "notice.learn_control",
+            &[("param", &param.label()), ("id", &id), ("name", &name)],
+        ));
+        true
+    }
 
-    // --- Level Meters ---
-    let meter_width = chunks[2].width.saturating_sub(8);
-    let l_spans = render_meter_bar(app.meter.current_l, meter_width);
-    let r_spans = render_meter_bar(app.meter.current_r, meter_width);
-
-    let mut l_line = vec![Span::styled("  L ", Style::default().fg(Color::DarkGray))];
-    l_line.extend(l_spans);
-
-    let mut r_line = vec![Span::styled("  R ", Style::default().fg(Color::DarkGray))];
-    r_line.extend(r_spans);
-
-    let meters = Paragraph::new(vec![
-        Line::from(""),
-        Line::from(l_line),
-        Line::from(""),
-        Line::from(r_line),
-    ])
-    .block(
-        Block::default()
-            .borders(Borders::BOTTOM)
-            .border_style(Style::default().fg(Color::DarkGray)),
-    );
-    f.render_widget(meters, chunks[2]);
+    fn cancel_learn(&mut self) {
+        if let Some(midi_control) = &mut self.midi_control {
+            midi_control.learn(None);
+        }
+        self.learn = None;
+        self.notify(tr("notice.learn_cancelled"));
+    }
 
-    // --- Track List ---
-    let mut track_lines = vec![Line::from("")];
-    for t in &app.tracks {
-        let mute_solo = match (t.mute, t.solo) {
-            (_, true) => {
-                Span::styled(" S ", Style::default().fg(Color::Black).bg(Color::Yellow))
-            }
-            (true, _) => Span::styled(" M ", Style::default().fg(Color::Black).bg(Color::Red)),
-            _ => Span::styled("   ", Style::default()),
+    /// Save a mapping MIDI learn caught and say what it does.
+    fn learned(&mut self, mapping: midi_control::Mapping, profile: String) {
+        self.learn = None;
+        if let Err(e) = midi_control::save_mapping(&self.state_dir, &profile, &mapping) {
+            self.notify_error(trf("error.learn_save", &[("error", &e)]));
+            return;
+        }
+        self.notify(trf(
+            "notice.learned",
+            &[
+                ("cc", &mapping.cc),
+                ("channel", &mapping.channel.unwrap_or_default()),
+                ("param", &mapping.param.label()),
+                ("id", &mapping.track),
+                ("profile", &profile),
+            ],
+        ));
+    }
+
+    /// Apply `f` to the selected track as one undoable edit.
+    fn edit_track(&mut self, edit: fn(u32) -> Edit, f: impl FnOnce(&mut TrackState)) {
+        if self.selected_track >= self.tracks.len() {
+            self.notify_error(tr("error.no_track"));
+            return;
+        }
+        self.edit_track_at(self.selected_track, edit, f);
+    }
+
+    /// Apply `f` to the track at `index` as one undoable edit and show the
+    /// result.
+    fn edit_track_at(
+        &mut self,
+        index: usize,
+        edit: fn(u32) -> Edit,
+        f: impl FnOnce(&mut TrackState),
+    ) {
+        let id = self.tracks[index].id;
+        self.history.record(edit(id), &self.tracks, &self.markers);
+        let t = &mut self.tracks[index];
+        f(t);
+        let key = match edit(id) {
+            Edit::Mute(_) if t.mute => "notice.track_muted",
+            Edit::Mute(_) => "notice.track_unmuted",
+            Edit::Solo(_) if t.solo => "notice.track_soloed",
+            Edit::Solo(_) => "notice.track_unsoloed",
+            Edit::Volume(_) => "notice.track_volume",
+            _ => "notice.track_pan",
         };
-        let source_name = t
-            .source
-            .as_ref()
-            .map(|s| {
-                Path::new(s)
-                    .file_name()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string()
-            })
-            .unwrap_or_else(|| "(empty)".to_string());
+        let text = trf(
+            key,
+            &[
+                ("id", &t.id),
+                ("name", &t.name),
+                ("volume", &format!("{:.1}", t.volume)),
+                ("pan", &format!("{:+.1}", t.pan)),
+            ],
+        );
+        self.notify(text);
+    }
 
-        track_lines.push(Line::from(vec![
-            Span::styled(format!("  {:>2} ", t.id), Style::default().fg(Color::DarkGray)),
-            mute_solo,
-            Span::raw(" "),
-            Span::styled(format!("{:<16}", t.name), Style::default().fg(Color::White)),
-            Span::styled(
-                format!("  vol {:<4}", format!("{:.1}", t.volume)),
-                Style::default().fg(Color::DarkGray),
-            ),
-            Span::styled(
-                format!("  pan {:<5}", format!("{:.1}", t.pan)),
-                Style::default().fg(Color::DarkGray),
-            ),
-            Span::styled(
-                format!("  {}", source_name),
-                Style::default().fg(Color::DarkGray),
-            ),
-        ]));
+    /// Apply a mixer controller move to its track.
+    fn mixer_control(&mut self, mapping: midi_control::Mapping, value: u8) {
+        let Some(index) = self.tracks.iter().position(|t| t.id == mapping.track) else {
+            self.notify_error(trf("error.unknown_track", &[("id", &mapping.track)]));
+            return;
+        };
+        match mapping.param {
+            Param::Volume => self.edit_track_at(index, Edit::Volume, |t| {
+                t.volume = midi_control::fader_volume(value)
+            }),
+            Param::Pan => {
+                self.edit_track_at(index, Edit::Pan, |t| t.pan = midi_control::knob_pan(value))
+            }
+            Param::Mute if midi_control::pressed(value) => {
+                self.edit_track_at(index, Edit::Mute, |t| t.mute = !t.mute)
+            }
+            Param::Solo if midi_control::pressed(value) => {
+                self.edit_track_at(index, Edit::Solo, |t| t.solo = !t.solo)
+            }
+            Param::Mute | Param::Solo => {}
+        }
     }
 
-    let tracks_widget = Paragraph::new(track_lines).block(
-        Block::default()
-            .borders(Borders::BOTTOM)
-            .border_style(Style::default().fg(Color::DarkGray)),
-    );
-    f.render_widget(tracks_widget, chunks[3]);
+    /// Set several fields of track `id` as one undoable edit.
+    fn patch_track(&mut self, id: u32, patch: TrackPatch) {
+        let Some(index) = self.tracks.iter().position(|t| t.id == id) else {
+            self.notify_error(trf("error.unknown_track", &[("id", &id)]));
+            return;
+        };
+        self.history
+            .record(Edit::Track(id), &self.tracks, &self.markers);
+        let t = &mut self.tracks[index];
+        t.mute = patch.mute.unwrap_or(t.mute);
+        t.solo = patch.solo.unwrap_or(t.solo);
+        t.volume = patch.volume.map_or(t.volume, |v| v.clamp(0.0, 2.0));
+        t.pan = patch.pan.map_or(t.pan, |p| p.clamp(-1.0, 1.0));
+    }
 
-    // --- Footer ---
-    let footer = Paragraph::new(Line::from(vec![
-        Span::styled(
-            "  SPACE",
+    /// Jump to just before the end of the silence the playhead is in.
+    fn skip_gap(&mut self) -> bool {
+        let preroll = self.silence_config.preroll_secs;
+        let position = self.position;
+        let Some(&(_, end)) = self
+            .gaps
+            .iter()
+            .find(|(start, end)| position >= *start && position < end - preroll)
+        else {
+            return false;
+        };
+        self.seek(end - preroll);
+        let secs = format!("{:.1}", end - preroll - position);
+        self.notify(trf("notice.skipped_silence", &[("secs", &secs)]));
+        true
+    }
+
+    /// Go to marker `n`, starting playback there in `play` mode.
+    fn hot_cue(&mut self, n: u8) {
+        let Some(marker) = self.markers.get((n as usize).saturating_sub(1)) else {
+            self.notify_error(trf("error.no_marker", &[("value", &n)]));
+            return;
+        };
+        let position = marker.position;
+        if self.hot_cues == HotCues::Play && !self.playing {
+            self.position = position;
+            self.play();
+            self.notify(self.position_label());
+        } else {
+            self.queue(Queued::Seek(position));
+        }
+    }
+
+    /// Add the labels in an Audacity label file as markers.
+    fn import_labels(&mut self, path: &Path) -> Result<(), String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let imported =
+            markers::parse_audacity(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if imported.is_empty() {
+            return Err(trf("error.no_labels", &[("path", &path.display())]));
+        }
+        self.history
+            .record(Edit::Markers, &self.tracks, &self.markers);
+        let count = imported.len().to_string();
+        self.markers.extend(imported);
+        markers::sort(&mut self.markers);
+        self.notify(trf("notice.imported_labels", &[("count", &count)]));
+        Ok(())
+    }
+
+    fn add_marker(&mut self, name: Option<String>) {
+        self.history
+            .record(Edit::Markers, &self.tracks, &self.markers);
+        let name =
+            name.unwrap_or_else(|| trf("marker.default_name", &[("n", &(self.markers.len() + 1))]));
+        self.markers.push(Marker {
+            name,
+            position: self.position,
+        });
+        markers::sort(&mut self.markers);
+        let number = self
+            .markers
+            .iter()
+            .position(|m| m.position == self.position)
+            .unwrap_or_default()
+            + 1;
+        self.notify(trf(
+            "notice.marker_set",
+            &[("n", &number), ("time", &format_position(self.position))],
+        ));
+    }
+
+    fn save_edits(&mut self) {
+        let result = edits::write_tracks(&self.state_dir, &self.tracks)
+            .and_then(|_| markers::save(&self.state_dir, &self.markers));
+        match result {
+            Ok(()) => {
+                self.history.dirty = false;
+                self.notify(tr("notice.saved"));
+            }
+            Err(e) => self.notify_error(trf("error.save", &[("error", &e)])),
+        }
+    }
+
+    /// Switch playback to `path`, keeping the playhead where it is.
+    fn load_audio(&mut self, path: PathBuf) -> Result<(), String> {
+        validate_audio(&path)?;
+        let reader = hound::WavReader::open(&path).map_err(|e| e.to_string())?;
+        let spec = reader.spec();
+        self.audio_duration =
+            reader.len() as f64 / (spec.sample_rate as f64 * spec.channels as f64);
+        // The file being replaced doesn't count
+        let others = self.memory_used() - self.program.memory();
+        let budget = self.memory_budget.saturating_sub(others);
+        (self.program, self.meter) = LevelMeter::load(&path, 50, budget, self.meter.live.clone())?;
+        if self.program.on_disk() && self.memory_budget > 0 {
+            let mb = self.memory_budget >> 20;
+            self.notify(trf("notice.from_disk", &[("mb", &mb)]));
+        }
+        // Found once the meter has seen the whole file
+        self.gaps.clear();
+        self.file_rate = spec.sample_rate;
+        self.audio_file = path;
+        let position = self.position.min(self.audio_duration);
+        if self.playing {
+            self.start_at(position);
+        } else {
+            self.drop_paused();
+            self.position = position;
+        }
+        Ok(())
+    }
+
+    /// Bytes of decoded audio held for playback, by everything loaded: the
+    /// mix, the MIDI instruments, the cue program and a prelisten share the
+    /// one budget.
+    fn memory_used(&self) -> usize {
+        self.program.memory()
+            + self.midi.as_ref().map_or(0, MidiMix::memory)
+            + self.cue_program.as_ref().map_or(0, Program::memory)
+            + self
+                .preview
+                .as_ref()
+                .map_or(0, |(_, program)| program.memory())
+    }
+
+    /// Whether the main output is going out over RTP.
+    #[cfg(feature = "rtp")]
+    fn streaming(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    #[cfg(not(feature = "rtp"))]
+    fn streaming(&self) -> bool {
+        false
+    }
+
+    /// Reread the project, tracks, markers and audio file after the Cornwall
+    /// CLI changed them.
+    fn reload(&mut self) -> Result<(), String> {
+        if self.history.dirty {
+            return Err(tr("error.reload_dirty").to_string());
+        }
+        // All or nothing, so a file that doesn't parse leaves the player as
+        // it was
+        let state_dir = &self.state_dir;
+        let loaded = ProjectState::load(state_dir).and_then(|project| {
+            let tracks = project::load_tracks(state_dir)?;
+            let markers = markers::load(state_dir)?;
+            let notes = notes::load(&project.dir(state_dir))?;
+            Ok((project, tracks, markers, notes))
+        });
+        let (project, tracks, markers, notes) = loaded.map_err(|e| {
+            log::write(state_dir, &e);
+            trf("error.reload", &[("error", &e)])
+        })?;
+        self.project = project;
+        self.time_sig = TimeSig::parse(&self.project.time_sig).unwrap_or_default();
+        self.tracks = tracks;
+        self.selected_track = self.selected_track.min(self.tracks.len().saturating_sub(1));
+        self.markers = markers;
+        self.notes = notes;
+        self.inspector = None;
+        self.history = History::default();
+        self.piano_rolls = piano_roll::load_tracks(&self.tracks);
+        self.track_peaks = peaks::load_tracks(&self.state_dir, &self.tracks);
+        self.midi = MidiMix::render(&self.tracks, self.soundfont.as_deref(), self.file_rate)
+            .map_err(|e| trf("error.midi", &[("error", &e)]))?;
+        self.load_audio(self.audio_file.clone())
+    }
+
+    /// Play track `id`'s source in place of the mix, or go back to the mix.
+    fn audition(&mut self, id: Option<u32>) -> Result<(), String> {
+        let Some(id) = id else {
+            if let Some((_, file)) = self.audition.take() {
+                self.load_audio(file)?;
+            }
+            self.notify(tr("notice.audition_off"));
+            return Ok(());
+        };
+        let track = self
+            .tracks
+            .iter()
+            .find(|t| t.id == id)
+            .ok_or_else(|| trf("error.unknown_track", &[("id", &id)]))?;
+        let source = track
+            .source
+            .clone()
+            .ok_or_else(|| trf("error.no_source", &[("id", &id)]))?;
+        let name = track.name.clone();
+        let back = match self.audition.take() {
+            Some((_, file)) => file,
+            None => self.audio_file.clone(),
+        };
+        // Set first so the MIDI tracks stay out of the auditioned track
+        self.audition = Some((id, back.clone()));
+        if let Err(e) = self.load_audio(PathBuf::from(source)) {
+            self.audition = None;
+            self.load_audio(back)?;
+            return Err(e);
+        }
+        self.notify(trf("notice.audition", &[("id", &id), ("name", &name)]));
+        Ok(())
+    }
+
+    /// Collect output frames only while a pane showing them is up.
+    fn capture_output(&mut self) {
+        let collect = self.show_scope || self.show_vectorscope || self.show_gauge;
+        self.scope.enabled.store(collect, Ordering::Relaxed);
+        if !collect {
+            self.scope.clear();
+            self.scope.reset_loudness();
+        }
+    }
+
+    /// Play the measurement sweep and record the room's response to it.
+    fn start_measurement(&mut self) {
+        if self.measurement.is_some() {
+            return self.notify_error(tr("error.measuring"));
+        }
+        self.stop();
+        match Measurement::start(&self.measure_config, self.device_rate) {
+            Ok((measurement, sweep)) => {
+                self.append_main(sweep);
+                self.sink.play();
+                self.measurement = Some(measurement);
+                self.notify(tr("notice.measuring"));
+            }
+            Err(e) => self.notify_error(trf("error.measure", &[("error", &e)])),
+        }
+    }
+
+    /// Open the tuner on the configured input, or close it.
+    fn toggle_tuner(&mut self) {
+        if self.tuner.take().is_some() {
+            return;
+        }
+        match Tuner::open(self.tuner_config.device.as_deref()) {
+            Ok(tuner) => self.tuner = Some(tuner),
+            Err(e) => self.notify_error(trf("error.tuner", &[("error", &e)])),
+        }
+    }
+
+    /// What the meters and analysis panes show now.
+    fn readings(&self) -> Readings {
+        let chunk = (self.position / self.meter.chunk_duration) as usize;
+        Readings {
+            position: self.position,
+            level: (self.meter.current_l, self.meter.current_r),
+            vu: (self.meter.vu_l, self.meter.vu_r),
+            loudness: self.scope.loudness(),
+            scope: self.scope.window(self.scope_window_ms),
+            vector: self.scope.recent(scope::VECTOR_MS),
+            brightness: self.meter.tones.get(chunk).copied().unwrap_or(0.0),
+        }
+    }
+
+    /// Hold the meters on their readings and write them to a notes file,
+    /// or let them run again.
+    fn freeze_meters(&mut self) {
+        if self.frozen.take().is_some() {
+            self.notify(tr("notice.meters_running"));
+            return;
+        }
+        let readings = self.readings();
+        let report = readings.report(
+            &self.audio_file,
+            self.loudness_target,
+            self.meter_config.reference_dbfs,
+        );
+        self.frozen = Some(readings);
+        match readings::save(&self.state_dir, &report, unix_time()) {
+            Ok(path) => self.notify(trf("notice.meters_frozen", &[("path", &path.display())])),
+            Err(e) => self.notify_error(trf("error.readings", &[("error", &e)])),
+        }
+    }
+
+    /// Save the project and tracks as they stand, unsaved edits included.
+    fn take_snapshot(&mut self) {
+        let snapshot = Snapshot {
+            taken: unix_time(),
+            project: self.project.clone(),
+            tracks: self.tracks.clone(),
+        };
+        match snapshot::save(&self.state_dir, &snapshot) {
+            Ok(path) => self.notify(trf("notice.snapshot", &[("path", &path.display())])),
+            Err(e) => self.notify_error(trf("error.snapshot", &[("error", &e)])),
+        }
+    }
+
+    /// Show or hide what changed since the latest snapshot.
+    fn toggle_diff(&mut self) {
+        if self.diff.take().is_some() {
+            return;
+        }
+        match snapshot::latest(&self.state_dir) {
+            Ok(Some(old)) => self.diff = Some(snapshot::diff(&old, &self.project, &self.tracks)),
+            Ok(None) => self.notify_error(tr("error.no_snapshot")),
+            Err(e) => self.notify_error(trf("error.snapshot", &[("error", &e)])),
+        }
+    }
+
+    /// Write the audible tracks' sources trimmed to the loop region.
+    fn export_stems(&mut self) {
+        let (Some((first, last)), Some(_)) = (self.loop_region, self.bar_secs()) else {
+            self.notify_error(tr("error.no_loop_region"));
+            return;
+        };
+        let (start, end) = self.loop_span();
+        let dir = self
+            .project
+            .dir(&self.state_dir)
+            .join("stems")
+            .join(format!("bars-{}-{}", first, last));
+        match stems::export(&self.tracks, &dir, start, end) {
+            Ok(export) => {
+                let count = export.written.to_string();
+                let skipped = export.skipped.to_string();
+                let path = export.dir.display();
+                self.notify(if export.skipped > 0 {
+                    trf(
+                        "notice.stems_skipped",
+                        &[("count", &count), ("skipped", &skipped), ("path", &path)],
+                    )
+                } else {
+                    trf("notice.stems", &[("count", &count), ("path", &path)])
+                });
+            }
+            Err(e) => self.notify_error(trf("error.stems", &[("error", &e)])),
+        }
+    }
+
+    /// Write the markers and loop region for `daw` (`reaper` or `ardour`)
+    /// into the project folder.
+    fn export_locations(&mut self, daw: &str) -> Result<(), String> {
+        let loop_span = self
+            .loop_region
+            .and(self.bar_secs())
+            .map(|_| self.loop_span());
+        let locations = daw::locations(&self.markers, loop_span);
+        if locations.is_empty() {
+            return Err(tr("error.nothing_to_export").to_string());
+        }
+        let (file, contents) = match daw {
+            "reaper" => ("reaper-markers.csv", daw::reaper(&locations)),
+            "ardour" => {
+                // The session runs at the project rate, whatever the file's
+                let rate = match self.project.sample_rate {
+                    0 => self.file_rate,
+                    rate => rate,
+                };
+                ("ardour-locations.xml", daw::ardour(&locations, rate))
+            }
+            _ => return Err(trf("error.usage", &[("usage", &":export reaper | ardour")])),
+        };
+        let path = self.project.dir(&self.state_dir).join(file);
+        daw::write(&path, &contents)?;
+        let count = locations.len().to_string();
+        let path = path.display().to_string();
+        self.notify(trf(
+            "notice.exported_locations",
+            &[("count", &count), ("path", &path)],
+        ));
+        Ok(())
+    }
+
+    /// Save `text` as a note at `position`; `author` is a remote listener.
+    fn add_note(&mut self, text: String, position: f64, author: Option<String>) {
+        let bar = self.bar_secs().map(|_| {
+            let (bar, beat) = self.bar_beat_at(position);
+            format!("{}.{}", bar, beat)
+        });
+        let at = bar.clone().unwrap_or_else(|| format_position(position));
+        // A listener's comment is shown in full, as it arrives
+        let notice = match &author {
+            Some(name) => trf(
+                "notice.remote_note",
+                &[("name", name), ("at", &at), ("text", &text)],
+            ),
+            None => trf("notice.note", &[("at", &at)]),
+        };
+        let note = notes::Note {
+            position,
+            bar,
+            text,
+            author,
+            written: unix_time(),
+        };
+        let project_dir = self.project.dir(&self.state_dir);
+        match notes::add(&project_dir, &mut self.notes, note) {
+            Ok(()) => {
+                self.show_notes = true;
+                self.notify(notice);
+            }
+            Err(e) => self.notify_error(trf("error.notes", &[("error", &e)])),
+        }
+    }
+
+    /// Colour the loudness gauge against `target` LUFS, showing it.
+    fn set_loudness_target(&mut self, target: f64) {
+        self.loudness_target = target;
+        self.show_gauge = true;
+        self.capture_output();
+        let lufs = format!("{:.0}", target);
+        self.notify(match gauge::preset_name(target) {
+            Some(name) => trf(
+                "notice.loudness_preset",
+                &[("lufs", &lufs), ("name", &name)],
+            ),
+            None => trf("notice.loudness", &[("lufs", &lufs)]),
+        });
+    }
+
+    /// Span the oscilloscope over `window_ms`, showing it.
+    fn set_scope_window(&mut self, window_ms: f64) {
+        self.scope_window_ms = window_ms.clamp(scope::MIN_WINDOW_MS, scope::MAX_WINDOW_MS);
+        self.show_scope = true;
+        self.capture_output();
+        let ms = format!("{:.0}", self.scope_window_ms);
+        self.notify(trf("notice.scope", &[("ms", &ms)]));
+    }
+
+    /// Prelisten to the selected track's source from the top, over the
+    /// transport, or stop the prelisten.
+    fn preview_track(&mut self) {
+        let Some(track) = self.tracks.get(self.selected_track) else {
+            self.notify_error(tr("error.no_track"));
+            return;
+        };
+        let (id, name) = (track.id, track.name.clone());
+        self.device.clear(&self.preview_sink);
+        if self
+            .preview
+            .take()
+            .is_some_and(|(playing, _)| playing == id)
+        {
+            self.notify(tr("notice.preview_off"));
+            return;
+        }
+        let Some(source) = track.source.clone().filter(|_| track.track_type != "midi") else {
+            self.notify_error(trf("error.no_source", &[("id", &id)]));
+            return;
+        };
+        let path = PathBuf::from(source);
+        let left = self.memory_budget.saturating_sub(self.memory_used());
+        let program = match validate_audio(&path).and_then(|_| Program::load(&path, left)) {
+            Ok(program) => program,
+            Err(e) => {
+                self.notify_error(trf("error.preview", &[("id", &id), ("error", &e)]));
+                return;
+            }
+        };
+        let source = Resample::new(
+            self.source(&program, 0.0, Bounds::Open),
+            self.device_rate,
+            self.resample_quality,
+        );
+        let source = MonoFold::new(source, self.mono.clone());
+        self.preview_sink.append(ChannelMap::new(
+            source,
+            self.main_channels,
+            self.output_channels,
+        ));
+        self.preview_sink.set_volume(self.monitor.gain());
+        self.preview_sink.play();
+        self.preview = Some((id, program));
+        self.notify(trf("notice.preview", &[("id", &id), ("name", &name)]));
+    }
+
+    fn script_view(&self) -> View {
+        View {
+            position: self.position,
+            bar: self.current_bar(),
+            beat: self.current_beat(),
+            playing: self.playing,
+            tracks: self.tracks.clone(),
+            markers: self.markers.clone(),
+        }
+    }
+
+    /// Apply what a script call asked for. Returns false when the player
+    /// should quit.
+    fn apply_script(&mut self, result: Result<Vec<Call>, String>) -> bool {
+        let calls = match result {
+            Ok(calls) => calls,
+            Err(e) => {
+                self.notify_error(trf("error.script", &[("error", &e)]));
+                return true;
+            }
+        };
+        for call in calls {
+            let keep_running = match call {
+                Call::Action(action) => self.perform(action),
+                Call::Command(line) => command_line::execute(self, &line).unwrap_or_else(|e| {
+                    self.notify_error(e);
+                    true
+                }),
+                Call::SetTrack(id, patch) => {
+                    self.patch_track(id, patch);
+                    true
+                }
+                Call::Notify(text) => {
+                    self.notify(text);
+                    true
+                }
+            };
+            if !keep_running {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Fire `on_play`/`on_stop`/`on_bar` for changes since the last tick.
+    /// Hooks can't quit the player; only bound keys and `:lua` can.
+    fn run_script_hooks(&mut self) {
+        let bar = self.current_bar();
+        for event in self.script.events(self.playing, bar) {
+            let result = self.script.fire(event, self.script_view());
+            self.apply_script(result);
+        }
+    }
+
+    fn serve_control(&mut self) {
+        let Some(control) = &self.control else {
+            return;
+        };
+        for (line, reply) in control.pending() {
+            let _ = reply.send(control::respond(self, &line));
+        }
+    }
+
+    fn serve_midi_control(&mut self) {
+        let Some(midi_control) = &mut self.midi_control else {
+            return;
+        };
+        for message in midi_control.pending() {
+            match message {
+                midi_control::Message::Command(line) => {
+                    if let Err(e) = command_line::execute(self, &line) {
+                        self.notify_error(e);
+                    }
+                }
+                midi_control::Message::Play if !self.playing => self.play(),
+                midi_control::Message::Stop if self.playing => self.stop(),
+                midi_control::Message::Locate(secs) => self.seek(secs),
+                midi_control::Message::Start => {
+                    self.seek(0.0);
+                    self.play();
+                }
+                midi_control::Message::Halt => {
+                    let position = self.position;
+                    self.stop();
+                    self.position = position;
+                }
+                midi_control::Message::SongPosition(sixteenths) if self.bpm() > 0.0 => {
+                    self.seek(sixteenths as f64 / 4.0 * 60.0 / self.bpm())
+                }
+                midi_control::Message::Tempo(bpm) => {
+                    // Smoothed, as clock from USB interfaces jitters
+                    let old = self.clock_bpm.unwrap_or(bpm);
+                    self.clock_bpm = Some(old + (bpm - old) * 0.25);
+                }
+                midi_control::Message::Mixer(mapping, value) => self.mixer_control(mapping, value),
+                midi_control::Message::Learned(mapping, profile) => self.learned(mapping, profile),
+                _ => {}
+            }
+        }
+    }
+
+    /// Send the transport to the followers, or chase the leader's.
+    fn sync_lan(&mut self) {
+        let leader = match &mut self.lan_sync {
+            Some(link) if link.role() == Role::Lead => {
+                link.send(lan_sync::Transport {
+                    playing: self.playing,
+                    position: self.position,
+                    speed: self.speed,
+                });
+                return;
+            }
+            Some(link) => link.receive(),
+            None => return,
+        };
+        let Some(leader) = leader else {
+            return;
+        };
+        let speed = leader.speed.clamp(stretch::MIN_SPEED, stretch::MAX_SPEED);
+        let speed_changed = (speed - self.speed).abs() > 1e-3;
+        self.speed = speed;
+        let position = leader.position.clamp(0.0, self.audio_duration);
+        match (leader.playing, self.playing) {
+            (true, false) => {
+                self.position = position;
+                self.play();
+                if let Some(link) = &mut self.lan_sync {
+                    link.started();
+                }
+            }
+            (false, true) => {
+                self.stop();
+                self.position = position;
+            }
+            (false, false) => self.position = position,
+            (true, true) => {
+                let off = self.position - position;
+                let drifted = self
+                    .lan_sync
+                    .as_mut()
+                    .is_some_and(|link| link.should_reseek(off));
+                if speed_changed || drifted {
+                    self.start_at(position);
+                }
+            }
+        }
+    }
+
+    fn serve_http(&mut self) {
+        let Some(server) = &self.http else {
+            return;
+        };
+        for (request, reply) in server.pending() {
+            let _ = reply.send(http::respond(self, &request));
+        }
+    }
+
+    /// Start the shell hook for `event` with the current position.
+    fn run_hook(&self, event: hooks::Event) {
+        let env = [
+            ("CORNWALL_POSITION", format!("{:.3}", self.position)),
+            ("CORNWALL_BAR", self.current_bar().to_string()),
+            ("CORNWALL_BEAT", self.current_beat().to_string()),
+            ("CORNWALL_FILE", self.audio_file.display().to_string()),
+            ("CORNWALL_PROJECT", self.project.name.clone()),
+        ];
+        self.hooks.run(&event, &env);
+    }
+
+    fn run_script_key(&mut self, key: &KeyEvent) -> bool {
+        let result = self.script.press(key, self.script_view());
+        self.apply_script(result)
+    }
+
+    /// Quit, or ask first if there are unsaved edits. Returns false when
+    /// the player should quit.
+    fn request_quit(&mut self) -> bool {
+        if !self.history.dirty {
+            return false;
+        }
+        self.quit_prompt = true;
+        self.notify(tr("quit.prompt"));
+        true
+    }
+
+    /// Answer the unsaved-changes prompt. Returns false when the player
+    /// should quit.
+    fn handle_quit_key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char('s') | KeyCode::Char('S') | KeyCode::Enter => {
+                self.quit_prompt = false;
+                self.save_edits();
+                // Stay open if the save failed, so the error can be read
+                return self.history.dirty;
+            }
+            KeyCode::Char('d') | KeyCode::Char('D') => return false,
+            KeyCode::Char('c') | KeyCode::Char('C') | KeyCode::Esc => {
+                self.quit_prompt = false;
+                self.notify(tr("quit.cancelled"));
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// The session as it stands, for restoring after a crash.
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            pid: std::process::id(),
+            audio_file: self.audio_file.clone(),
+            audition: self.audition.as_ref().map(|(id, _)| *id),
+            position: self.position,
+            playing: self.playing,
+            looping: self.looping,
+            loop_region: self.loop_region,
+            play_once: self.play_once,
+            speed: self.speed,
+            semitones: self.semitones,
+            selected_track: self.selected_track,
+            queued: self.queued.map(|(_, change)| change),
+            edits: self.history.dirty.then(|| Edits {
+                tracks: self.tracks.clone(),
+                markers: self.markers.clone(),
+            }),
+        }
+    }
+
+    /// Answer the offer to restore the last session.
+    fn handle_recovery_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                if let Some(checkpoint) = self.recovery.take() {
+                    self.restore(checkpoint);
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.recovery = None;
+                self.notify(tr("recovery.discarded"));
+            }
+            _ => {}
+        }
+    }
+
+    /// Put the session back as `checkpoint` had it.
+    fn restore(&mut self, checkpoint: Checkpoint) {
+        let switched = match checkpoint.audition {
+            Some(id) => self.audition(Some(id)),
+            None if checkpoint.audio_file != self.audio_file => {
+                self.load_audio(checkpoint.audio_file)
+            }
+            None => Ok(()),
+        };
+        if let Some(edits) = checkpoint.edits {
+            self.history
+                .record(Edit::Restore, &self.tracks, &self.markers);
+            // Only what the player edits, onto tracks as the CLI left them
+            for t in &mut self.tracks {
+                if let Some(edited) = edits.tracks.iter().find(|e| e.id == t.id) {
+                    t.mute = edited.mute;
+                    t.solo = edited.solo;
+                    t.volume = edited.volume;
+                    t.pan = edited.pan;
+                }
+            }
+            self.markers = edits.markers;
+        }
+        self.looping = checkpoint.looping;
+        self.loop_region = checkpoint.loop_region;
+        self.play_once = checkpoint.play_once;
+        self.speed = checkpoint
+            .speed
+            .clamp(stretch::MIN_SPEED, stretch::MAX_SPEED);
+        self.semitones = checkpoint
+            .semitones
+            .clamp(-stretch::MAX_SEMITONES, stretch::MAX_SEMITONES);
+        self.selected_track = checkpoint
+            .selected_track
+            .min(self.tracks.len().saturating_sub(1));
+        self.position = checkpoint.position.clamp(0.0, self.audio_duration);
+        if checkpoint.playing {
+            self.play();
+        }
+        if let Some(change) = checkpoint.queued {
+            self.apply_queued(change);
+        }
+        match switched {
+            Ok(()) => self.notify(trf(
+                "recovery.restored",
+                &[("position", &self.position_label())],
+            )),
+            Err(e) => self.notify_error(trf("error.restore", &[("error", &e)])),
+        }
+    }
+
+    fn toggle_macro_recording(&mut self) {
+        match self.macros.finish() {
+            Some(Ok((slot, count))) => self.notify(trf(
+                "notice.macro_saved",
+                &[("slot", &slot), ("count", &count)],
+            )),
+            Some(Err(e)) => self.notify_error(trf("error.macro_save", &[("error", &e)])),
+            None => {
+                self.macros.choosing_slot = true;
+                self.notify(tr("notice.macro_choose_slot"));
+            }
+        }
+    }
+
+    /// Feed a recorded macro back through the key handler. Returns false if
+    /// it quit the player.
+    fn play_macro(&mut self, slot: u8) -> bool {
+        if self.macros.replaying {
+            return true;
+        }
+        let Some(keys) = self.macros.keys(slot) else {
+            self.notify_error(trf("error.macro_empty", &[("slot", &slot)]));
+            return true;
+        };
+        self.macros.replaying = true;
+        let keep_running = keys.into_iter().all(|key| handle_key(self, key));
+        self.macros.replaying = false;
+        keep_running
+    }
+
+    fn notify(&mut self, text: impl Into<String>) {
+        self.notice = Some(Notice::info(text));
+    }
+
+    fn notify_error(&mut self, text: impl Into<String>) {
+        self.notice = Some(Notice::error(text));
+    }
+
+    fn loop_label(&self) -> String {
+        match (self.looping, self.loop_region) {
+            (false, _) => tr("notice.loop_off").to_string(),
+            (true, Some((start, end))) => {
+                trf("notice.loop_bars", &[("start", &start), ("end", &end)])
+            }
+            (true, None) => tr("notice.loop_file").to_string(),
+        }
+    }
+
+    fn position_label(&self) -> String {
+        trf(
+            "notice.position",
+            &[
+                ("bar", &self.current_bar()),
+                ("beat", &self.current_beat()),
+                ("time", &format_position(self.position)),
+            ],
+        )
+    }
+
+    /// Queue a source on the main output through the monitor path.
+    fn append_main<S: Source<Item = f32> + Send + 'static>(&self, source: S) {
+        let source = Resample::new(source, self.device_rate, self.resample_quality);
+        let source = Tap::new(MonoFold::new(source, self.mono.clone()), self.scope.clone());
+        let source = LevelTap::new(source, self.meter.live.clone());
+        #[cfg(feature = "rtp")]
+        let source = StreamTap::new(source, self.stream.as_ref().map(|s| s.feed()));
+        let source = ChannelMap::new(source, self.main_channels, self.output_channels);
+        self.sink
+            .append(metrics::Probe::new(source, self.audio_stats.clone()));
+        self.sink.set_volume(self.monitor.gain());
+    }
+
+    /// Open the output device again after a stall and play on from the
+    /// playhead. On failure the watchdog tries again.
+    fn reopen_output(&mut self) {
+        match self.open_output() {
+            Ok(()) => self.start_at(self.position),
+            Err(e) => {
+                log::write(
+                    &self.state_dir,
+                    &format!("cannot reopen audio output: {}", e),
+                );
+                self.notify_error(trf("error.reopen", &[("error", &e)]));
+            }
+        }
+    }
+
+    fn open_output(&mut self) -> Result<(), String> {
+        let sinks = Sinks::open(
+            self.output_device.as_deref(),
+            self.main_channels,
+            self.output_rate,
+        )?;
+        self.preview = None;
+        self.sink = sinks.main;
+        self.preview_sink = sinks.preview;
+        self.device = sinks.device;
+        self.output_channels = sinks.channels;
+        self.device_rate = sinks.sample_rate;
+        Ok(())
+    }
+
+    /// `program` from `offset` within `bounds`, for the main or preview
+    /// sink.
+    fn source(&self, program: &Program, offset: f64, bounds: Bounds) -> Playback {
+        let source = program.source(offset, bounds);
+        if self.device.realtime() {
+            source
+        } else {
+            source.waiting()
+        }
+    }
+
+    /// The transport's clock, as the device keeps it.
+    fn now(&self) -> Instant {
+        self.device.now()
+    }
+
+    /// Seconds of the file played since `since` on the clock, at the
+    /// current speed.
+    fn played_since(&self, since: Instant) -> f64 {
+        self.now().saturating_duration_since(since).as_secs_f64() * self.speed
+    }
+
+    fn toggle_mono(&mut self) {
+        let mono = !self.mono.fetch_xor(true, Ordering::Relaxed);
+        self.notify(tr(if mono {
+            "notice.mono_on"
+        } else {
+            "notice.mono_off"
+        }));
+    }
+
+    fn stop(&mut self) {
+        self.generator = None;
+        if matches!(self.measurement, Some(Measurement::Recording { .. })) {
+            self.measurement = None;
+        }
+        self.queued = None;
+        self.play_once = None;
+        self.playing = false;
+        self.paused = None;
+        self.position = match self.stop_position {
+            StopPosition::Origin => self.play_origin,
+            StopPosition::Zero => 0.0,
+            StopPosition::Stay => self.position.min(self.audio_duration),
+        };
+        self.play_started = None;
+        self.watchdog.clear();
+        // Emptying the sink keeps it and the device stream alive; the next
+        // play refills it
+        self.device.clear(&self.sink);
+        if let Some(cue) = &mut self.cue {
+            cue.stop();
+        }
+        self.meter.reset();
+        self.scope.clear();
+    }
+
+    /// Hold playback where it is. The source stays in the paused sink, so
+    /// resuming carries on from the same sample.
+    fn pause(&mut self) {
+        if let Some((since, from, _)) = self.pass() {
+            self.position = from + self.played_since(since);
+        }
+        self.queued = None;
+        self.playing = false;
+        self.paused = Some(self.now());
+        self.watchdog.clear();
+        self.sink.pause();
+        if let Some(cue) = &mut self.cue {
+            cue.pause();
+        }
+        self.meter.reset();
+    }
+
+    fn resume(&mut self) {
+        let Some(paused) = self.paused.take() else {
+            return;
+        };
+        // Looping turned on or off, a new region or a tempo change while
+        // paused: the held source would still go round the old loop
+        if self.play_once.is_none() && !same_span(self.play_loop, self.wanted_loop()) {
+            self.start_at(self.position);
+            return;
+        }
+        // The clock ran on while the sink didn't
+        let held = self.now().saturating_duration_since(paused);
+        self.play_started = self.play_started.map(|started| started + held);
+        self.playing = true;
+        self.watchdog.reset();
+        self.sink.play();
+        if let Some(cue) = &mut self.cue {
+            cue.resume();
+        }
+    }
+
+    /// Let go of a paused source that no longer matches the playhead,
+    /// speed, pitch or file, so play starts afresh from the playhead.
+    fn drop_paused(&mut self) {
+        if self.paused.take().is_none() {
+            return;
+        }
+        self.play_started = None;
+        self.device.clear(&self.sink);
+        if let Some(cue) = &mut self.cue {
+            cue.stop();
+        }
+    }
+
+    fn toggle_generator(&mut self) {
+        let signal = self.utilities.signal();
+        if self.generator == Some(signal) {
+            self.stop();
+        } else {
+            self.start_generator(signal, self.utilities.level());
+        }
+    }
+
+    /// Play `signal` at `level` dBFS on the main output in place of the
+    /// file, routed as the utilities menu says.
+    fn start_generator(&mut self, signal: Signal, level: f32) {
+        self.stop();
+        let source = Generator::new(signal, level, self.utilities.routing, self.device_rate);
+        self.append_main(source);
+        self.sink.play();
+        self.generator = Some(signal);
+    }
+
+    fn handle_utility_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Up => self.utilities.select(-1),
+            KeyCode::Down => self.utilities.select(1),
+            KeyCode::Left => self.utilities.change_level(-1),
+            KeyCode::Right => self.utilities.change_level(1),
+            KeyCode::Char('c') => self.utilities.routing = self.utilities.routing.next(),
+            KeyCode::Enter => return self.toggle_generator(),
+            KeyCode::Char('u') | KeyCode::Char('U') | KeyCode::Esc => self.utilities.open = false,
+            _ => {}
+        }
+        // Level and routing changes apply to a running tone immediately
+        let changed = matches!(code, KeyCode::Left | KeyCode::Right | KeyCode::Char('c'));
+        if let Some(signal) = self.generator.filter(|_| changed) {
+            self.start_generator(signal, self.utilities.level());
+        }
+    }
+
+    fn open_switcher(&mut self) {
+        if self.history.dirty {
+            return self.notify_error(tr("error.switch_dirty"));
+        }
+        if let Err(e) = self.switcher.open(&self.state_dir) {
+            self.notify_error(trf("error.switch", &[("error", &e)]));
+        }
+    }
+
+    fn handle_switcher_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Up => self.switcher.select(-1),
+            KeyCode::Down => self.switcher.select(1),
+            KeyCode::Enter => {
+                self.switcher.open = false;
+                match self.switcher.chosen() {
+                    Some(w) if workspaces::is_current(w, &self.state_dir) => {}
+                    Some(w) => self.switch_to = Some(w.state_dir.clone()),
+                    None => {}
+                }
+            }
+            KeyCode::Esc => self.switcher.open = false,
+            _ => {}
+        }
+    }
+
+    fn update_monitor(&mut self, f: impl FnOnce(&mut Monitor)) {
+        f(&mut self.monitor);
+        self.sink.set_volume(self.monitor.gain());
+        self.preview_sink.set_volume(self.monitor.gain());
+        let m = &self.monitor;
+        let db = format!("{:+.0}", m.volume_db);
+        let text = if m.muted {
+            tr("notice.monitor_muted").to_string()
+        } else if m.dimmed {
+            trf("notice.monitor_dimmed", &[("db", &db)])
+        } else {
+            trf("notice.monitor", &[("db", &db)])
+        };
+        self.notify(text);
+    }
+
+    fn tick(&mut self) {
+        if self.notice.as_ref().is_some_and(|n| n.expired()) {
+            self.notice = None;
+        }
+        if self.recovery.is_none() && self.checkpointed.elapsed() >= recovery::INTERVAL {
+            self.checkpointed = Instant::now();
+            let _ = recovery::save(&self.state_dir, &self.checkpoint());
+        }
+        if self.preview.is_some() && self.preview_sink.empty() {
+            self.preview = None;
+        }
+        if let Some(tuner) = &mut self.tuner {
+            tuner.update();
+        }
+        self.diagnostics.update(&self.audio_stats);
+        if self.meter.receive() {
+            self.gaps = self.meter.gaps(&self.silence_config);
+        }
+        match self.measurement.take() {
+            Some(Measurement::Recording { input, until }) if Instant::now() >= until => {
+                self.measurement = Some(measure::process(input, &self.state_dir, unix_time()));
+            }
+            Some(Measurement::Processing(work)) if work.is_finished() => match work.join() {
+                Ok(Ok(path)) => {
+                    self.notify(trf("notice.measured", &[("path", &path.display())]));
+                }
+                Ok(Err(e)) => self.notify_error(trf("error.measure", &[("error", &e)])),
+                Err(_) => self.notify_error(trf("error.measure", &[("error", &"crashed")])),
+            },
+            measurement => self.measurement = measurement,
+        }
+        if self.playing {
+            if let Some(mut started) = self.play_started {
+                let played = self.sink.get_pos().as_secs_f64();
+                // Nothing to compare until the device pulls its first buffer
+                if played > 0.0 {
+                    self.drift.watch(played);
+                    let clock = self.now().saturating_duration_since(started);
+                    if self.drift.check(clock.as_secs_f64(), played) {
+                        started = self
+                            .now()
+                            .checked_sub(Duration::from_secs_f64(played))
+                            .unwrap_or(started);
+                        self.play_started = Some(started);
+                    }
+                }
+                // An empty sink has played to its end, which isn't a stall
+                let event = (!self.sink.empty())
+                    .then(|| self.watchdog.watch(played))
+                    .flatten();
+                if self.watchdog.stalled {
+                    // Hold the playhead where the sound stopped
+                    started = self
+                        .now()
+                        .checked_sub(Duration::from_secs_f64(played))
+                        .unwrap_or(started);
+                    self.play_started = Some(started);
+                }
+                match event {
+                    Some(watchdog::Event::Stalled | watchdog::Event::Retry) => {
+                        if let Some((since, from, _)) = self.pass() {
+                            self.position = from + self.played_since(since);
+                        }
+                        if event == Some(watchdog::Event::Stalled) {
+                            let at = self.position_label();
+                            log::write(&self.state_dir, &format!("audio output stalled at {}", at));
+                            self.notify_error(trf("error.stalled", &[("position", &at)]));
+                        }
+                        self.reopen_output();
+                        return;
+                    }
+                    Some(watchdog::Event::Resumed) => {
+                        self.notify(trf(
+                            "notice.resumed",
+                            &[("position", &self.position_label())],
+                        ));
+                    }
+                    None => {}
+                }
+                if let Some((since, from, laps)) = self.pass() {
+                    self.position = from + self.played_since(since);
+                    // The source has already wrapped; this only follows it
+                    if laps != self.laps {
+                        self.laps = laps;
+                        self.run_hook(hooks::Event::Loop);
+                        let faster = self.practice.as_mut().and_then(|p| p.lap(self.speed));
+                        if let Some(speed) = faster {
+                            self.set_speed(speed);
+                            return;
+                        }
+                    }
+                }
+                if let Some((at, change)) = self.queued {
+                    if self.now() >= at {
+                        self.queued = None;
+                        self.apply_queued(change);
+                    }
+                }
+                // Looping turned on or off, a new region or a tempo change
+                if self.play_once.is_none() && !same_span(self.play_loop, self.wanted_loop()) {
+                    self.start_at(self.position);
+                    return;
+                }
+                let end = self.play_once.unwrap_or(self.audio_duration);
+                if self.play_loop.is_none() && self.position >= end {
+                    self.stop();
+                    return;
+                }
+                if self.skip_silence && self.skip_gap() {
+                    return;
+                }
+            }
+        }
+        self.meter.update();
+        self.sync_lan();
+        let schedule = self.beat_schedule();
+        if let Some(beat_out) = &mut self.beat_out {
+            beat_out.update(schedule);
+        }
+        if let Some(metronome) = &mut self.metronome {
+            metronome.update(schedule.filter(|_| self.metronome_on));
+        }
+        self.run_script_hooks();
+        self.serve_http();
+        self.serve_control();
+        self.serve_midi_control();
+        let events = self
+            .hooks
+            .events(self.playing, self.position, &self.markers);
+        for event in events {
+            self.run_hook(event);
+        }
+        // Stopped players keep a heartbeat so `--status` can tell a live
+        // player from one that crashed
+        if self.playing || self.status_written.elapsed() >= STATUS_HEARTBEAT {
+            let started = Instant::now();
+            self.write_status();
+            self.diagnostics.status_write.push(started.elapsed());
+        }
+        if let Some(fifo) = &self.status_fifo {
+            fifo.send(serde_json::to_string(&self.status()).unwrap_or_default());
+        }
+    }
+
+    fn current_bar(&self) -> u32 {
+        self.bar_beat_at(self.position).0
+    }
+
+    fn current_beat(&self) -> u32 {
+        self.bar_beat_at(self.position).1
+    }
+
+    /// 1-based bar and beat at `secs`.
+    fn bar_beat_at(&self, secs: f64) -> (u32, u32) {
+        self.time_sig.bar_beat(secs, self.bpm())
+    }
+
+    fn status(&self) -> PlayerStatus {
+        PlayerStatus {
+            transport: match (self.playing, self.watchdog.stalled) {
+                (false, _) if self.paused.is_some() => TransportState::Paused,
+                (false, _) => TransportState::Stopped,
+                (true, false) => TransportState::Playing,
+                (true, true) => TransportState::Stalled,
+            },
+            playing: self.playing,
+            loop_mode: match (self.play_once, self.looping, self.loop_region) {
+                (Some(_), _, _) => LoopMode::Once,
+                (None, false, _) => LoopMode::Off,
+                (None, true, None) => LoopMode::File,
+                (None, true, Some(_)) => LoopMode::Region,
+            },
+            loop_bars: self.loop_region.map(|(start, end)| [start, end]),
+            position_secs: self.position,
+            bar: self.current_bar(),
+            beat: self.current_beat(),
+            bpm: self.bpm(),
+            time_sig: self.time_sig.to_string(),
+            level_l: self.meter.current_l,
+            level_r: self.meter.current_r,
+            file: self.audio_file.to_string_lossy().to_string(),
+            drift_ms: self.drift.worst * 1000.0,
+            drift_corrections: self.drift.corrections,
+            pid: std::process::id(),
+            updated: unix_time(),
+            ..PlayerStatus::default()
+        }
+    }
+
+    fn write_status(&mut self) {
+        let json = serde_json::to_string(&self.status()).unwrap_or_default();
+        let _ = fs::write(self.state_dir.join(".player.json"), json);
+        self.status_written = Instant::now();
+    }
+
+    fn clear_status(&self) {
+        // After a takeover the status file belongs to the other player
+        if self.lock.as_ref().is_some_and(|l| !l.held()) {
+            return;
+        }
+        let _ = fs::remove_file(self.state_dir.join(".player.json"));
+        if let Some(control) = &self.control {
+            let _ = fs::remove_file(&control.path);
+        }
+        recovery::remove(&self.state_dir);
+    }
+}
+
+// --- UI rendering ---
+
+fn format_rate(rate: u32) -> String {
+    format!("{} kHz", rate as f64 / 1000.0)
+}
+
+fn state_indicator(app: &App) -> Span<'static> {
+    if let Some(signal) = app.generator {
+        Span::styled(
+            format!("  ◉ {} ", signal.label().to_uppercase()),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else if app.paused.is_some() {
+        Span::styled(
+            format!("  ❚❚ {} ", tr("transport.paused")),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        widgets::play_state(app.playing, &labels())
+    }
+}
+
+/// Widget labels in the player's language.
+fn labels() -> Labels<'static> {
+    Labels {
+        playing: tr("transport.playing"),
+        stopped: tr("transport.stopped"),
+        bar: tr("transport.bar"),
+        bpm: tr("transport.bpm"),
+        solo: tr("badge.solo"),
+        muted: tr("badge.muted"),
+        empty: tr("track.empty"),
+        volume: tr("track.volume"),
+        pan: tr("track.pan"),
+    }
+}
+
+/// Whether two loop spans are the same to within a millisecond.
+fn same_span(a: Option<(f64, f64)>, b: Option<(f64, f64)>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => (a.0 - b.0).abs() < 0.001 && (a.1 - b.1).abs() < 0.001,
+        (a, b) => a.is_none() && b.is_none(),
+    }
+}
+
+fn nudge(value: &mut f64, step: f64, min: f64, max: f64) {
+    // Round so repeated steps land on clean values
+    *value = ((*value + step) * 100.0).round() / 100.0;
+    *value = value.clamp(min, max);
+}
+
+fn ui(f: &mut Frame, app: &App) {
+    if app.clock {
+        clock::render(f, app);
+    } else {
+        render_full(f, app);
+    }
+
+    if app.utilities.open {
+        utilities::render(f, &app.utilities, app.generator);
+    }
+    if app.switcher.open {
+        workspaces::render(f, &app.switcher, &app.state_dir);
+    }
+    if let Some(tuner) = &app.tuner {
+        tuner::render(f, tuner, app.tuner_config.a4);
+    }
+    if let Some(info) = &app.inspector {
+        inspect::render(f, &app.audio_file, info);
+    }
+    if let Some(diff) = &app.diff {
+        snapshot::render(f, diff, unix_time());
+    }
+    if app.show_diagnostics {
+        diagnostics::render(f, app);
+    }
+
+    let question = match &app.recovery {
+        Some(checkpoint) => Some(checkpoint.prompt()),
+        None => app.quit_prompt.then(|| tr("quit.prompt").to_string()),
+    };
+    let prompt = if let Some(question) = question {
+        Some(Line::from(Span::styled(
+            format!(" {}", question),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )))
+    } else if let Some(line) = &app.command {
+        Some(Line::from(vec![
+            Span::raw(":"),
+            Span::raw(line.as_str()),
+            Span::styled("█", Style::default().fg(Color::White)),
+        ]))
+    } else {
+        app.notice.as_ref().map(|n| {
+            let color = if n.error { Color::Red } else { Color::Cyan };
+            Line::from(Span::styled(
+                format!(" {}", n.text),
+                Style::default().fg(color),
+            ))
+        })
+    };
+    if let Some(prompt) = prompt {
+        let area = f.area();
+        let row = Rect {
+            y: area.bottom().saturating_sub(1),
+            height: 1.min(area.height),
+            ..area
+        };
+        f.render_widget(Clear, row);
+        f.render_widget(Paragraph::new(prompt), row);
+    }
+
+    app.theme.apply(f.buffer_mut());
+}
+
+fn render_full(f: &mut Frame, app: &App) {
+    let piano_roll = app
+        .tracks
+        .get(app.selected_track)
+        .and_then(|t| app.piano_rolls.get(&t.id));
+    let roll_height = if piano_roll.is_some() { 7 } else { 0 };
+    let wave_height = if app.bar_secs().is_some() { 5 } else { 4 };
+    let scope_height = if app.show_scope || app.show_vectorscope {
+        8
+    } else {
+        0
+    };
+    let gauge_height = if app.show_gauge { 5 } else { 0 };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),  // header
+            Constraint::Length(5),  // transport
+            Constraint::Length(wave_height), // waveform
+            Constraint::Length(roll_height), // piano roll
+            Constraint::Length(5),  // meters
+            Constraint::Length(scope_height), // scopes
+            Constraint::Length(gauge_height), // loudness
+            Constraint::Min(3),    // track list
+            Constraint::Length(3), // footer
+        ])
+        .split(f.area());
+
+    // --- Header ---
+    let rates = if app.file_rate == app.device_rate {
+        format!("   {}", format_rate(app.device_rate))
+    } else {
+        format!(
+            "   {} → {} ({})",
+            format_rate(app.file_rate),
+            format_rate(app.device_rate),
+            app.resample_quality.label()
+        )
+    };
+    let modified = if app.history.dirty {
+        format!("   ● {}", tr("header.modified"))
+    } else {
+        String::new()
+    };
+    let prefix = "  C O R N W A L L   ─   ";
+    // A long project name gives way to the rate and modified badge
+    let name_width = (chunks[0].width as usize)
+        .saturating_sub(prefix.width() + rates.width() + modified.width());
+    let title = format!(
+        "{}{}",
+        prefix,
+        text::truncate(&app.project.name.to_uppercase(), name_width)
+    );
+    let header = Paragraph::new(Line::from(vec![
+        Span::styled(
+            title,
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(rates, Style::default().fg(Color::DarkGray)),
+        Span::styled(modified, Style::default().fg(Color::Yellow)),
+    ]))
+    .block(
+        Block::default()
+            .borders(Borders::BOTTOM)
+            .border_style(Style::default().fg(Color::DarkGray)),
+    );
+    f.render_widget(header, chunks[0]);
+
+    // --- Transport ---
+    let progress = if app.audio_duration > 0.0 {
+        (app.position / app.audio_duration).min(1.0)
+    } else {
+        0.0
+    };
+
+    let mono_label = tr("badge.mono");
+    let mono_badge = if app.mono.load(Ordering::Relaxed) {
+        Span::styled(
+            format!(" {} ", mono_label),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        Span::raw(" ".repeat(mono_label.chars().count() + 2))
+    };
+
+    let rec_badge = match &app.macros.recording {
+        Some((slot, _)) => Span::styled(
+            format!(" {} {} ", tr("badge.rec"), slot),
+            Style::default()
+                .fg(Color::White)
+                .bg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+        ),
+        None => Span::raw(""),
+    };
+
+    let audition_badge = match &app.audition {
+        Some((id, _)) => Span::styled(
+            format!(" {} {} ", tr("badge.audition"), id),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ),
+        None => Span::raw(""),
+    };
+
+    let preview_badge = match &app.preview {
+        Some((id, _)) => Span::styled(
+            format!(" {} {} ", tr("badge.preview"), id),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ),
+        None => Span::raw(""),
+    };
+
+    let clock_badge = match app.clock_bpm {
+        Some(_) => Span::styled(
+            format!(" {} ", tr("badge.clock")),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        ),
+        None => Span::raw(""),
+    };
+
+    let speed_badge = if app.speed != 1.0 {
+        Span::styled(
+            format!(" {:.0}% ", app.speed * 100.0),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        Span::raw("")
+    };
+
+    let pitch_badge = if app.semitones != 0 {
+        Span::styled(
+            format!(" {:+} {} ", app.semitones, tr("badge.semitones")),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        Span::raw("")
+    };
+
+    let practice_badge = match app.practice {
+        Some(_) => Span::styled(
+            format!(" {} ", tr("badge.practice")),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        ),
+        None => Span::raw(""),
+    };
+
+    let learn_badge = match app.learn {
+        Some(_) => Span::styled(
+            format!(" {} ", tr("badge.learn")),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        ),
+        None => Span::raw(""),
+    };
+
+    let measure_badge = match app.measurement {
+        Some(_) => Span::styled(
+            format!(" {} ", tr("badge.measure")),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        ),
+        None => Span::raw(""),
+    };
+
+    let sync_badge = match &app.lan_sync {
+        Some(link) => {
+            let (label, color) = match (link.role(), link.connected()) {
+                (Role::Lead, _) => ("badge.lead", Color::Blue),
+                (Role::Follow, true) => ("badge.follow", Color::Blue),
+                (Role::Follow, false) => ("badge.follow", Color::Red),
+            };
+            Span::styled(
+                format!(" {} ", tr(label)),
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(color)
+                    .add_modifier(Modifier::BOLD),
+            )
+        }
+        None => Span::raw(""),
+    };
+
+    let stream_badge = if app.streaming() {
+        Span::styled(
+            format!(" {} ", tr("badge.stream")),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        Span::raw("")
+    };
+
+    let loading_badge = match &app.meter.incoming {
+        Some(_) => Span::styled(
+            format!(" {} ", tr("badge.loading")),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        ),
+        None => Span::raw(""),
+    };
+
+    let frozen_badge = match app.frozen {
+        Some(_) => Span::styled(
+            format!(" {} ", tr("badge.frozen")),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ),
+        None => Span::raw(""),
+    };
+
+    let stalled_badge = if app.watchdog.stalled {
+        Span::styled(
+            format!(" {} ", tr("badge.stalled")),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        Span::raw("")
+    };
+
+    let time_sig = app.time_sig.to_string();
+    let transport = Transport::new(app.position, app.audio_duration)
+        .playing(app.playing)
+        .bar(app.current_bar(), app.current_beat())
+        .tempo(app.bpm(), &time_sig)
+        .indicator(state_indicator(app))
+        .badges(vec![
+            mono_badge,
+            rec_badge,
+            audition_badge,
+            preview_badge,
+            clock_badge,
+            speed_badge,
+            pitch_badge,
+            practice_badge,
+            learn_badge,
+            frozen_badge,
+            measure_badge,
+            sync_badge,
+            stream_badge,
+            loading_badge,
+            stalled_badge,
+        ])
+        .labels(labels())
+        .block(
+            Block::default()
+                .borders(Borders::BOTTOM)
+                .border_style(Style::default().fg(Color::DarkGray)),
+        );
+    f.render_widget(transport, chunks[1]);
+
+    // --- Waveform ---
+    let marker_fractions: Vec<f64> = app
+        .markers
+        .iter()
+        .map(|m| m.position / app.audio_duration.max(f64::EPSILON))
+        .collect();
+    let wave_width = chunks[2].width.saturating_sub(4) as usize;
+    let ruler = app
+        .bar_secs()
+        .map(|bar| waveform::ruler(app.audio_duration, bar, wave_width, progress));
+    let levels = waveform::Levels {
+        left: &app.meter.levels_l,
+        right: &app.meter.levels_r,
+        tones: (app.waveform_color == WaveformColor::Tone).then_some(&app.meter.tones),
+    };
+    let mut wave_lines = waveform::overview(
+        levels,
+        wave_width,
+        chunks[2].height.saturating_sub(1 + ruler.is_some() as u16) as usize,
+        progress,
+        &marker_fractions,
+        app.theme.braille,
+    );
+    wave_lines.splice(0..0, ruler);
+    for line in &mut wave_lines {
+        line.spans.insert(0, Span::raw("  "));
+    }
+    let wave = Paragraph::new(wave_lines).block(
+        Block::default()
+            .borders(Borders::BOTTOM)
+            .border_style(Style::default().fg(Color::DarkGray)),
+    );
+    f.render_widget(wave, chunks[2]);
+
+    // --- Piano Roll ---
+    if let Some(roll) = piano_roll {
+        let mut roll_lines = roll.lines(
+            app.position,
+            chunks[3].width.saturating_sub(4) as usize,
+            chunks[3].height.saturating_sub(1) as usize,
+        );
+        for line in &mut roll_lines {
+            line.spans.insert(0, Span::raw("  "));
+        }
+        let roll = Paragraph::new(roll_lines).block(
+            Block::default()
+                .borders(Borders::BOTTOM)
+                .border_style(Style::default().fg(Color::DarkGray)),
+        );
+        f.render_widget(roll, chunks[3]);
+    }
+
+    // --- Level Meters ---
+    let readings = app.frozen.clone().unwrap_or_else(|| app.readings());
+    let meter_block = Block::default()
+        .borders(Borders::BOTTOM)
+        .border_style(Style::default().fg(Color::DarkGray));
+    match app.meter_config.mode {
+        MeterMode::Rms => {
+            let meters = Meter::new(readings.level.0, readings.level.1)
+                .braille(app.theme.braille)
+                .block(meter_block);
+            f.render_widget(meters, chunks[4]);
+        }
+        MeterMode::Vu => {
+            let reference = app.meter_config.reference_dbfs;
+            let vu = |level: f64| dbfs(level) - reference;
+            let meters = VuMeter::new(vu(readings.vu.0), vu(readings.vu.1))
+                .braille(app.theme.braille)
+                .block(meter_block);
+            f.render_widget(meters, chunks[4]);
+        }
+    }
+
+    // --- Oscilloscope and Vectorscope ---
+    let (scope_area, vector_area) = match (app.show_scope, app.show_vectorscope) {
+        (true, true) => {
+            let halves = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(20), Constraint::Length(30)])
+                .split(chunks[5]);
+            (Some(halves[0]), Some(halves[1]))
+        }
+        (true, false) => (Some(chunks[5]), None),
+        (false, true) => (None, Some(chunks[5])),
+        (false, false) => (None, None),
+    };
+    if let Some(area) = scope_area {
+        let title = trf(
+            "scope.title",
+            &[("ms", &format!("{:.0}", app.scope_window_ms))],
+        );
+        let mut scope_lines = scope::trace(
+            &readings.scope,
+            area.width.saturating_sub(4) as usize,
+            area.height.saturating_sub(2) as usize,
+            app.theme.braille,
+        );
+        for line in &mut scope_lines {
+            line.spans.insert(0, Span::raw("  "));
+        }
+        let pane = Paragraph::new(scope_lines).block(
+            Block::default()
+                .title(Span::styled(
+                    format!(" {} ", title),
+                    Style::default().fg(Color::DarkGray),
+                ))
+                .borders(Borders::TOP | Borders::BOTTOM)
+                .border_style(Style::default().fg(Color::DarkGray)),
+        );
+        f.render_widget(pane, area);
+    }
+    if let Some(area) = vector_area {
+        let frames = &readings.vector;
+        let (correlation, color) = match scope::correlation(frames) {
+            Some(r) if r < 0.0 => (format!("{:+.2}", r), Color::Red),
+            Some(r) => (format!("{:+.2}", r), Color::Green),
+            None => ("--".to_string(), Color::DarkGray),
+        };
+        let lines = scope::vectorscope(
+            frames,
+            area.width.saturating_sub(2) as usize,
+            area.height.saturating_sub(2) as usize,
+            app.theme.braille,
+        );
+        let pane = Paragraph::new(lines).block(
+            Block::default()
+                .title(vec![
+                    Span::styled(
+                        format!(" {} ", tr("vectorscope.title")),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(format!("{} ", correlation), Style::default().fg(color)),
+                ])
+                .borders(Borders::LEFT | Borders::TOP | Borders::BOTTOM)
+                .border_style(Style::default().fg(Color::DarkGray)),
+        );
+        f.render_widget(pane, area);
+    }
+
+    // --- Loudness ---
+    if app.show_gauge {
+        let (momentary, short_term, integrated) = readings.loudness;
+        let target = format!("{:.0}", app.loudness_target);
+        let title = match gauge::preset_name(app.loudness_target) {
+            Some(name) => trf(
+                "loudness.title_preset",
+                &[("lufs", &target), ("name", &name)],
+            ),
+            None => trf("loudness.title", &[("lufs", &target)]),
+        };
+        let gauge = LoudnessGauge::new(momentary, short_term, integrated, app.loudness_target)
+            .braille(app.theme.braille)
+            .block(
+                Block::default()
+                    .title(Span::styled(
+                        format!(" {} ", title),
+                        Style::default().fg(Color::DarkGray),
+                    ))
+                    .borders(Borders::TOP | Borders::BOTTOM)
+                    .border_style(Style::default().fg(Color::DarkGray)),
+            );
+        f.render_widget(gauge, chunks[6]);
+    }
+
+    // --- Track List ---
+    let rows = app
+        .tracks
+        .iter()
+        .map(|t| TrackRow {
+            id: t.id,
+            name: &t.name,
+            mute: t.mute,
+            solo: t.solo,
+            volume: t.volume,
+            pan: t.pan,
+            source: t.source.as_deref(),
+            levels: app.track_peaks.get(&t.id).map(Vec::as_slice),
+        })
+        .collect();
+    let tracks_widget = TrackList::new(rows)
+        .selected(Some(app.selected_track))
+        .labels(labels())
+        .block(
+            Block::default()
+                .borders(Borders::BOTTOM)
+                .border_style(Style::default().fg(Color::DarkGray)),
+        );
+    let monitor = app.midi_control.as_ref().filter(|_| app.midi_monitor);
+    match monitor {
+        _ if app.show_notes => {
+            let halves = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(20), Constraint::Length(40)])
+                .split(chunks[7]);
+            f.render_widget(tracks_widget, halves[0]);
+            let rows = halves[1].height.saturating_sub(2) as usize;
+            let lines = if app.notes.is_empty() {
+                vec![Line::from(Span::styled(
+                    format!(" {}", tr("notes.empty")),
+                    Style::default().fg(Color::DarkGray),
+                ))]
+            } else {
+                notes::lines(&app.notes, app.position, rows)
+            };
+            let pane = Paragraph::new(lines).block(
+                Block::default()
+                    .title(Span::styled(
+                        format!(" {} ", tr("notes.title")),
+                        Style::default().fg(Color::DarkGray),
+                    ))
+                    .borders(Borders::LEFT | Borders::BOTTOM)
+                    .border_style(Style::default().fg(Color::DarkGray)),
+            );
+            f.render_widget(pane, halves[1]);
+        }
+        Some(midi_control) => {
+            let halves = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(20), Constraint::Length(40)])
+                .split(chunks[7]);
+            f.render_widget(tracks_widget, halves[0]);
+            let rows = halves[1].height.saturating_sub(2) as usize;
+            let title = trf("monitor.title", &[("port", &midi_control.port)]);
+            let pane = Paragraph::new(midi_control.monitor.lines(rows)).block(
+                Block::default()
+                    .title(Span::styled(
+                        format!(" {} ", title),
+                        Style::default().fg(Color::DarkGray),
+                    ))
+                    .borders(Borders::LEFT | Borders::BOTTOM)
+                    .border_style(Style::default().fg(Color::DarkGray)),
+            );
+            f.render_widget(pane, halves[1]);
+        }
+        None => f.render_widget(tracks_widget, chunks[7]),
+    }
+
+    // --- Footer ---
+    let mut footer_spans = vec![
+        Span::styled(
+            "  SPACE",
             Style::default()
                 .fg(Color::White)
                 .add_modifier(Modifier::BOLD),
         ),
-        Span::styled(" play/stop", Style::default().fg(Color::DarkGray)),
+        Span::styled(
+            format!(" {}", tr("footer.play_stop")),
+            Style::default().fg(Color::DarkGray),
+        ),
         Span::styled(
             "    q",
             Style::default()
                 .fg(Color::White)
                 .add_modifier(Modifier::BOLD),
         ),
-        Span::styled(" quit", Style::default().fg(Color::DarkGray)),
+        Span::styled(
+            format!(" {}", tr("footer.quit")),
+            Style::default().fg(Color::DarkGray),
+        ),
         Span::styled(
             "    L",
             Style::default()
@@ -527,130 +3216,763 @@ fn ui(f: &mut Frame, app: &App) {
                 .add_modifier(Modifier::BOLD),
         ),
         Span::styled(
-            if app.looping { " loop ◆" } else { " loop ◇" },
+            match (app.looping, app.loop_region) {
+                (true, Some((start, end))) => {
+                    format!(" {} {}-{} ◆", tr("footer.loop"), start, end)
+                }
+                (true, None) => format!(" {} ◆", tr("footer.loop")),
+                (false, _) => format!(" {} ◇", tr("footer.loop")),
+            },
             Style::default().fg(if app.looping {
                 Color::Cyan
             } else {
                 Color::DarkGray
             }),
         ),
-    ]));
-    f.render_widget(footer, chunks[4]);
+    ];
+    footer_spans.push(Span::styled(
+        "    U",
+        Style::default()
+            .fg(Color::White)
+            .add_modifier(Modifier::BOLD),
+    ));
+    footer_spans.push(Span::styled(
+        format!(" {}", tr("footer.utilities")),
+        Style::default().fg(Color::DarkGray),
+    ));
+    footer_spans.push(Span::styled(
+        "    c",
+        Style::default()
+            .fg(Color::White)
+            .add_modifier(Modifier::BOLD),
+    ));
+    footer_spans.push(Span::styled(
+        format!(" {}", tr("footer.clock")),
+        Style::default().fg(Color::DarkGray),
+    ));
+    footer_spans.push(Span::styled(
+        "    -/+",
+        Style::default()
+            .fg(Color::White)
+            .add_modifier(Modifier::BOLD),
+    ));
+    footer_spans.push(Span::styled(
+        format!(
+            " {} {:+.0} dB ",
+            tr("footer.monitor"),
+            app.monitor.volume_db
+        ),
+        Style::default().fg(Color::DarkGray),
+    ));
+    if app.monitor.muted {
+        footer_spans.push(Span::styled(
+            format!(" {} ", tr("badge.mute")),
+            Style::default().fg(Color::Black).bg(Color::Red),
+        ));
+    } else if app.monitor.dimmed {
+        footer_spans.push(Span::styled(
+            format!(" {} ", tr("badge.dim")),
+            Style::default().fg(Color::Black).bg(Color::Yellow),
+        ));
+    } else {
+        footer_spans.push(Span::styled(
+            format!(" m/d {}", tr("footer.mute_dim")),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if let Some(cue) = &app.cue {
+        footer_spans.push(Span::styled(
+            "    [ ]",
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        ));
+        footer_spans.push(Span::styled(
+            format!(" {} {:+.0} dB", tr("footer.cue"), cue.level_db),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    let footer = Paragraph::new(Line::from(footer_spans));
+    f.render_widget(footer, chunks[8]);
 }
 
 // --- Status query mode ---
 
+fn unix_time() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64())
+}
+
+/// The running player's status, brought up to the current schema when an
+/// older player wrote it.
 fn print_status(state_dir: &Path) {
-    let status_path = state_dir.join(".player.json");
-    if status_path.exists() {
-        let content = fs::read_to_string(&status_path).unwrap_or_default();
-        println!("{}", content);
+    let path = state_dir.join(".player.json");
+    let status = fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| PlayerStatus::parse(&path, &s).ok());
+    let status = match status {
+        Some(mut status) => {
+            let alive = lock::alive(status.pid);
+            let fresh = unix_time() - status.updated < STATUS_STALE_SECS;
+            if !(alive && fresh) {
+                status.transport = TransportState::Stopped;
+                status.playing = false;
+                status.stale = true;
+            }
+            status
+        }
+        None => PlayerStatus::default(),
+    };
+    println!("{}", serde_json::to_string(&status).unwrap());
+}
+
+/// A table of loudness, peak and length for every track source and the
+/// project's mix.
+fn print_scan(state_dir: &Path) {
+    let loaded =
+        ProjectState::load(state_dir).and_then(|p| Ok((p, project::load_tracks(state_dir)?)));
+    let (project, tracks) = loaded.unwrap_or_else(|e| {
+        eprintln!("Cannot read the project: {}", e);
+        std::process::exit(1);
+    });
+    let mut files: Vec<(String, PathBuf)> = tracks
+        .into_iter()
+        .filter(|t| t.track_type != "midi")
+        .filter_map(|t| Some((format!("{} {}", t.id, t.name), PathBuf::from(t.source?))))
+        .collect();
+    let mix = project.dir(state_dir).join("mix.wav");
+    if mix.exists() {
+        files.push(("mix".to_string(), mix));
+    }
+    if files.is_empty() {
+        println!("No track sources or mix to scan");
+        return;
+    }
+
+    // Wide enough for most track names without pushing the numbers off
+    let width = 24;
+    println!(
+        "{}  {:>8}  {:>8}  {:>9}  File",
+        text::fit("Track", width),
+        "LUFS",
+        "Peak",
+        "Length"
+    );
+    for (name, path) in &files {
+        let file = path.file_name().unwrap_or_default().to_string_lossy();
+        match analysis::analyze(path) {
+            Ok(a) => println!(
+                "{}  {:>8}  {:>8}  {:>9}  {}",
+                text::fit(name, width),
+                scan_level(a.integrated_lufs),
+                scan_level(a.peak_dbfs),
+                format_position(a.duration_secs),
+                file
+            ),
+            Err(e) => println!("{}  {}", text::fit(name, width), e),
+        }
+    }
+}
+
+/// A level to one place, or `-inf` for silence.
+fn scan_level(db: f64) -> String {
+    if db.is_finite() {
+        format!("{:.1}", db)
     } else {
-        let status = PlayerStatus {
-            playing: false,
-            position_secs: 0.0,
-            bar: 0,
-            beat: 0,
-            bpm: 0.0,
-            time_sig: String::new(),
-            level_l: 0.0,
-            level_r: 0.0,
-            file: String::new(),
-        };
-        println!("{}", serde_json::to_string(&status).unwrap());
+        "-inf".to_string()
     }
 }
 
-// --- Main ---
+/// Write a normalized copy of `file` and say what was done.
+fn print_normalized(file: &Path, target: Target) {
+    match analysis::normalize(file, target) {
+        Ok(normalized) => {
+            println!(
+                "{}: {:+.1} dB, peak {:.1} dBFS",
+                normalized.path.display(),
+                normalized.gain_db,
+                normalized.peak_dbfs
+            );
+            if normalized.peak_dbfs > 0.0 {
+                eprintln!("warning: peaks above 0 dBFS clip unless the file is float");
+            }
+        }
+        Err(e) => {
+            eprintln!("Cannot normalize: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
 
-fn main() -> io::Result<()> {
-    let args: Vec<String> = env::args().collect();
+/// Convert `input` to `output` and say what was written.
+fn print_converted(input: &Path, output: &Path, conversion: &convert::Conversion) {
+    match convert::convert(input, output, conversion) {
+        Ok(spec) => {
+            let format = match spec.sample_format {
+                hound::SampleFormat::Float => "float".to_string(),
+                hound::SampleFormat::Int => format!("{}-bit", spec.bits_per_sample),
+            };
+            println!(
+                "{}: {} Hz, {}, {} channels",
+                output.display(),
+                spec.sample_rate,
+                format,
+                spec.channels
+            );
+        }
+        Err(e) => {
+            eprintln!("Cannot convert: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
 
-    let state_dir = if args.len() > 1 && args[1] == "--status" {
-        let dir = if args.len() > 2 {
-            PathBuf::from(&args[2])
-        } else {
-            find_state_dir()
-        };
-        print_status(&dir);
-        return Ok(());
-    } else {
-        find_state_dir()
+/// List the players announced on the network, as a table or as JSON.
+fn print_workspaces(command: cli::Workspace) {
+    let result = match command {
+        cli::Workspace::Add(dir, name) => {
+            workspaces::add(&dir.unwrap_or_else(find_state_dir), name).map(|w| {
+                println!("Registered {} ({})", w.name, w.state_dir.display());
+            })
+        }
+        cli::Workspace::Remove(name) => workspaces::remove(&name),
+        cli::Workspace::List => workspaces::load().map(|found| {
+            if found.is_empty() {
+                println!("No workspaces registered");
+            }
+            for workspace in &found {
+                let missing = if workspace.state_dir.exists() {
+                    ""
+                } else {
+                    "  (missing)"
+                };
+                println!(
+                    "{}  {}{}",
+                    text::fit(&workspace.name, 24),
+                    workspace.state_dir.display(),
+                    missing
+                );
+            }
+        }),
     };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
 
-    let audio_file = if args.len() > 1 && args[1] != "--status" {
-        PathBuf::from(&args[1])
-    } else {
-        let project: ProjectState = fs::read_to_string(state_dir.join("project.json"))
-            .ok()
-            .and_then(|s| serde_json::from_str(&s).ok())
-            .unwrap_or_default();
-
-        let project_dir = state_dir
-            .parent()
-            .unwrap()
-            .join("projects")
-            .join(&project.name);
-        let mix = project_dir.join("mix.wav");
-        if mix.exists() {
-            mix
-        } else {
-            let tracks: Vec<TrackState> = fs::read_to_string(state_dir.join("tracks.json"))
-                .ok()
-                .and_then(|s| serde_json::from_str(&s).ok())
-                .unwrap_or_default();
+fn print_discovered(json: bool) {
+    let found = mdns::discover(DISCOVER_WAIT).unwrap_or_else(|e| {
+        eprintln!("Cannot search the network: {}", e);
+        std::process::exit(1);
+    });
+    if json {
+        println!("{}", serde_json::to_string(&found).unwrap());
+        return;
+    }
+    if found.is_empty() {
+        println!("No players found");
+        return;
+    }
+    let width = 32;
+    println!(
+        "{}  {:<21}  {:>5}  {:<6}  Project",
+        text::fit("Name", width),
+        "Address",
+        "HTTP",
+        "Role"
+    );
+    for player in &found {
+        let http = match player.port {
+            0 => "-".to_string(),
+            port => port.to_string(),
+        };
+        let field = |key: &str| player.txt.get(key).map_or("-", |v| v.as_str());
+        println!(
+            "{}  {:<21}  {:>5}  {:<6}  {}",
+            text::fit(&player.name, width),
+            player.address,
+            http,
+            field("role"),
+            field("project")
+        );
+    }
+}
 
-            tracks
-                .iter()
-                .filter_map(|t| t.source.as_ref())
-                .map(PathBuf::from)
-                .find(|p| p.exists())
-                .expect(
-                    "No audio file found. Pass a WAV file as argument or create a mix first.",
-                )
+/// The TXT entries this player announces itself with.
+fn advertised(app: &App, sync_port: u16) -> Vec<(String, String)> {
+    let mut txt = vec![
+        ("project".to_string(), app.project.name.clone()),
+        ("version".to_string(), env!("CARGO_PKG_VERSION").to_string()),
+    ];
+    if let Some(link) = &app.lan_sync {
+        let role = match link.role() {
+            Role::Lead => "lead",
+            Role::Follow => "follow",
+        };
+        txt.push(("role".to_string(), role.to_string()));
+        txt.push(("sync".to_string(), sync_port.to_string()));
+    }
+    txt
+}
+
+// --- Input ---
+
+/// Apply a key press to the app. Returns false when the player should quit.
+fn handle_key(app: &mut App, key: KeyEvent) -> bool {
+    if app.macros.choosing_slot {
+        match app.macros.choose_slot(&key) {
+            Some(slot) => app.notify(trf("notice.macro_recording", &[("slot", &slot)])),
+            None => app.notify(tr("notice.macro_cancelled")),
+        }
+        return true;
+    }
+    if app.recovery.is_some() {
+        app.handle_recovery_key(key.code);
+        return true;
+    }
+    if app.quit_prompt {
+        return app.handle_quit_key(key.code);
+    }
+    if app.switcher.open {
+        app.handle_switcher_key(key.code);
+        return true;
+    }
+    let modal = app.utilities.open || app.command.is_some();
+    if modal || app.keymap.action(&key) != Some(Action::MacroRecord) {
+        app.macros.record(&key);
+    }
+    if app.utilities.open {
+        app.handle_utility_key(key.code);
+        return true;
+    }
+    if app.command.is_some() {
+        return command_line::handle_key(app, key.code);
+    }
+    if app.learn.is_some() {
+        return app.handle_learn_key(&key);
+    }
+    if app.script.binds(&key) {
+        return app.run_script_key(&key);
+    }
+    match app.keymap.action(&key) {
+        Some(action) => app.perform(action),
+        None => true,
+    }
+}
+
+/// [`handle_key`] for a typed or replayed key, recording it with
+/// `--record`.
+fn press(app: &mut App, key: KeyEvent, recorder: &mut Option<Recorder>) -> bool {
+    if let Some(Err(e)) = recorder.as_mut().map(|r| r.record(&key)) {
+        // Once is enough; the keys up to here are in the file
+        *recorder = None;
+        app.notify_error(trf("error.record", &[("error", &e)]));
+    }
+    handle_key(app, key)
+}
+
+// --- Main ---
+
+fn main() -> io::Result<()> {
+    let options = cli::parse(env::args()).unwrap_or_else(|e| {
+        eprintln!("{}\n\n{}", e, cli::USAGE);
+        std::process::exit(2);
+    });
+
+    let state_dir = match options.command {
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Command::Status(dir) => {
+            print_status(&dir.unwrap_or_else(find_state_dir));
+            return Ok(());
+        }
+        Command::Schema => {
+            println!("{}", JSON_SCHEMA);
+            return Ok(());
+        }
+        Command::Scan(dir) => {
+            print_scan(&dir.unwrap_or_else(find_state_dir));
+            return Ok(());
+        }
+        Command::Normalize(file, target) => {
+            print_normalized(&file, target);
+            return Ok(());
+        }
+        Command::Convert(input, output, conversion) => {
+            print_converted(&input, &output, &conversion);
+            return Ok(());
+        }
+        Command::Discover(json) => {
+            print_discovered(json);
+            return Ok(());
+        }
+        Command::Workspace(command) => {
+            print_workspaces(command);
+            return Ok(());
         }
+        Command::Play => find_state_dir(),
     };
 
+    let audio_file = options.file.unwrap_or_else(|| {
+        project::audio_file(&state_dir)
+            .unwrap_or_else(|e| {
+                log::write(&state_dir, &e);
+                eprintln!("Cannot read the project: {}", e);
+                std::process::exit(1);
+            })
+            .expect("No audio file found. Pass a WAV file as argument or create a mix first.")
+    });
+
     if !audio_file.exists() {
         eprintln!("Audio file not found: {}", audio_file.display());
         std::process::exit(1);
     }
+    if let Err(e) = validate_audio(&audio_file) {
+        eprintln!("Cannot play {}: {}", audio_file.display(), e);
+        std::process::exit(1);
+    }
+
+    let mut errors = Vec::new();
+    let config = log::or_default(&state_dir, PlayerConfig::load(&state_dir), &mut errors);
+    i18n::init(&state_dir, config.locale.as_deref());
+
+    let lock = Lock::acquire(&state_dir, options.take_over).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    install_panic_hook(state_dir.clone());
+
+    // Opened before raw mode so a startup failure prints normally
+    let http_config = config.http.clone();
+    let sync_config = config.sync.clone();
+    let discovery = config.discovery.clone();
+    let mut app = App::new(state_dir.clone(), audio_file, config, errors).unwrap_or_else(|e| {
+        log::write(&state_dir, &e);
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    app.lock = Some(lock);
+    if let Some(port) = options.http {
+        match http::Server::start(port, &http_config) {
+            Ok(server) => {
+                app.notify(trf("notice.http", &[("url", &server.url)]));
+                app.http = Some(server);
+            }
+            Err(e) => {
+                eprintln!("Cannot start HTTP server on {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    #[cfg(not(feature = "rtp"))]
+    if options.stream.is_some() {
+        eprintln!("Cannot stream: built without the rtp feature (cargo build --features rtp)");
+        std::process::exit(1);
+    }
+    #[cfg(feature = "rtp")]
+    if let Some(target) = options.stream {
+        match rtp::Stream::start(&target, app.device_rate, &app.state_dir) {
+            Ok(stream) => {
+                let target = stream.target.to_string();
+                let sdp = stream.sdp.display().to_string();
+                app.notify(trf(
+                    "notice.streaming",
+                    &[("target", &target), ("sdp", &sdp)],
+                ));
+                app.stream = Some(stream);
+            }
+            Err(e) => {
+                eprintln!("Cannot stream to {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(role) = options.sync {
+        match LanSync::start(role, &sync_config) {
+            Ok(link) => app.lan_sync = Some(link),
+            Err(e) => {
+                eprintln!("Cannot start LAN sync: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    // Announced only with something on the network to find: an HTTP server
+    // others can reach, or LAN sync
+    let http_port = options
+        .http
+        .filter(|_| app.http.as_ref().is_some_and(|server| server.on_network));
+    if discovery.advertise && (http_port.is_some() || app.lan_sync.is_some()) {
+        let service = mdns::Service {
+            name: discovery
+                .name
+                .unwrap_or_else(|| mdns::default_name(&app.project.name)),
+            port: http_port.unwrap_or(0),
+            txt: advertised(&app, sync_config.port),
+        };
+        // Not being found is no reason to stop
+        match mdns::Advertiser::start(service) {
+            Ok(advertiser) => app.advertiser = Some(advertiser),
+            Err(e) => app.notify_error(trf("error.advertise", &[("error", &e)])),
+        }
+    }
+    // Without the socket the player still works, just without remote control
+    app.control = control::Control::start(&app.state_dir).ok();
+    if let Some(path) = options.status_fifo {
+        match fifo::StatusFifo::open(&path) {
+            Ok(fifo) => app.status_fifo = Some(fifo),
+            Err(e) => {
+                eprintln!("Cannot open status pipe {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Opened before raw mode so a bad path prints normally
+    let mut recorder = options.record.map(|path| {
+        Recorder::create(&path).unwrap_or_else(|e| {
+            eprintln!("Cannot record keys to {}", e);
+            std::process::exit(1);
+        })
+    });
+    let mut replay = options.replay.map(|path| {
+        Replay::load(&path).unwrap_or_else(|e| {
+            eprintln!("Cannot replay {}", e);
+            std::process::exit(1);
+        })
+    });
+
+    if options.accessible {
+        enable_raw_mode()?;
+        // Returns to switch projects, then carries on in the new one
+        let result = loop {
+            let result = accessible::run(&mut app);
+            match app.switch_to.take() {
+                Some(dir) if result.is_ok() => match switch_project(app, dir) {
+                    Ok(switched) => app = switched,
+                    Err(e) => {
+                        disable_raw_mode()?;
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                },
+                _ => break result,
+            }
+        };
+        app.stop();
+        app.clear_status();
+        disable_raw_mode()?;
+        return result;
+    }
 
     enable_raw_mode()?;
     io::stdout().execute(EnterAlternateScreen)?;
     let backend = CrosstermBackend::new(io::stdout());
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new(state_dir, audio_file);
+    app.theme = Theme::new(options.ascii, options.no_color, options.no_braille);
+    app.clock = options.clock;
 
     let tick_rate = Duration::from_millis(33);
+    let mut bench = options.bench.then(Bench::new);
 
     loop {
+        let started = Instant::now();
         terminal.draw(|f| ui(f, &app))?;
+        let drawn = Instant::now();
+        app.diagnostics.frame.push(drawn - started);
 
-        if event::poll(tick_rate)? {
+        let input = event::poll(tick_rate)?;
+        let woken = Instant::now();
+        if input {
             if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    match key.code {
-                        KeyCode::Char(' ') => app.toggle_play(),
-                        KeyCode::Char('q') | KeyCode::Esc => break,
-                        KeyCode::Char('l') | KeyCode::Char('L') => {
-                            app.looping = !app.looping;
-                        }
-                        _ => {}
-                    }
+                if key.kind == KeyEventKind::Press && !press(&mut app, key, &mut recorder) {
+                    break;
                 }
             }
         }
+        // One a pass, as typed keys come
+        if let Some(key) = replay.as_mut().and_then(Replay::next_due) {
+            if !press(&mut app, key, &mut recorder) {
+                break;
+            }
+        }
+        if replay.as_ref().is_some_and(Replay::finished) {
+            replay = None;
+            app.notify(tr("notice.replay_done"));
+        }
 
         app.tick();
+        app.diagnostics
+            .event_loop
+            .push(drawn - started + woken.elapsed());
+        if bench.as_mut().is_some_and(|b| !b.step(&mut app)) {
+            break;
+        }
+        if app.lock.as_mut().is_some_and(Lock::taken_over) {
+            break;
+        }
+        if let Some(dir) = app.switch_to.take() {
+            match switch_project(app, dir) {
+                Ok(switched) => app = switched,
+                // Neither project could open its output
+                Err(e) => {
+                    disable_raw_mode()?;
+                    io::stdout().execute(LeaveAlternateScreen)?;
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
     }
 
     app.stop();
     app.clear_status();
     disable_raw_mode()?;
     io::stdout().execute(LeaveAlternateScreen)?;
+    if app.lock.as_ref().is_some_and(|l| !l.held()) {
+        eprintln!("Another player took over {}", app.state_dir.display());
+    }
+    if let Some(bench) = bench {
+        print!("{}", bench.report(&app));
+    }
+
+    Ok(())
+}
+
+/// Move the player to the project in `state_dir`: the old project's engine
+/// and output are torn down and the new one's state, mix and config loaded,
+/// keeping the servers and display options the player was started with.
+/// `app` is left as it was, with the reason shown, when the project can't
+/// be opened. When its output devices fail to open, the old project is
+/// opened again; only if that fails too is there an error.
+fn switch_project(mut app: App, state_dir: PathBuf) -> Result<App, String> {
+    let opened = match open_project(&state_dir) {
+        Ok(opened) => opened,
+        Err(e) => {
+            log::write(&app.state_dir, &e);
+            app.notify_error(trf("error.switch", &[("error", &e)]));
+            return Ok(app);
+        }
+    };
+    app.stop();
+    app.clear_status();
+    let previous = app.state_dir.clone();
+    let http = app.http.take();
+    #[cfg(feature = "rtp")]
+    let stream = app.stream.take();
+    let lan_sync = app.lan_sync.take();
+    let advertiser = app.advertiser.take();
+    let status_fifo = app.status_fifo.take();
+    let (theme, clock) = (app.theme, app.clock);
+    // Closes the output device before the new project opens it
+    drop(app);
+
+    let reopen = |(audio_file, config, errors, lock): (_, _, _, Lock), state_dir| {
+        App::new(state_dir, audio_file, config, errors).map(|mut app| {
+            app.lock = Some(lock);
+            app
+        })
+    };
+    let mut app = match reopen(opened, state_dir) {
+        Ok(app) => app,
+        Err(e) => {
+            log::write(&previous, &e);
+            let mut app = open_project(&previous).and_then(|opened| reopen(opened, previous))?;
+            app.notify_error(trf("error.switch", &[("error", &e)]));
+            app
+        }
+    };
+    app.http = http;
+    #[cfg(feature = "rtp")]
+    {
+        app.stream = stream;
+    }
+    app.lan_sync = lan_sync;
+    app.advertiser = advertiser;
+    app.status_fifo = status_fifo;
+    app.theme = theme;
+    app.clock = clock;
+    app.control = control::Control::start(&app.state_dir).ok();
+    // Replaced rather than chained, so only the new project's status is
+    // cleaned up
+    let _ = std::panic::take_hook();
+    install_panic_hook(app.state_dir.clone());
+    if app.notice.is_none() {
+        let name = app.project.name.clone();
+        app.notify(trf("notice.switched", &[("name", &name)]));
+    }
+    Ok(app)
+}
+
+/// What opening the project in `state_dir` needs, checked before the
+/// current one is torn down.
+fn open_project(state_dir: &Path) -> Result<(PathBuf, PlayerConfig, Vec<String>, Lock), String> {
+    let audio_file = project::audio_file(state_dir)?
+        .ok_or_else(|| format!("{}: no audio file; create a mix first", state_dir.display()))?;
+    validate_audio(&audio_file).map_err(|e| format!("{}: {}", audio_file.display(), e))?;
+    let mut errors = Vec::new();
+    let config = log::or_default(state_dir, PlayerConfig::load(state_dir), &mut errors);
+    // Checked here so a bad binding leaves the current project playing
+    Keymap::new(&config.keys)?;
+    let lock = Lock::acquire(state_dir, false)?;
+    Ok((audio_file, config, errors, lock))
+}
 
+/// Put the terminal back and drop our status file before the panic
+/// message prints, so a crash doesn't leave raw mode on or a stale
+/// "playing" status behind.
+fn install_panic_hook(state_dir: PathBuf) {
+    let default = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        let _ = io::stdout().execute(LeaveAlternateScreen);
+        if lock::held_by_this_process(&state_dir) {
+            let _ = fs::remove_file(state_dir.join(".player.json"));
+        }
+        default(info);
+    }));
+}
+
+/// Check that `path` is a WAV file the player can play: a header it can
+/// decode, in a sample format it reads, with as much data on disk as the
+/// header promises. The samples themselves aren't read, so this is quick
+/// on long files; a bad file is reported before the terminal is taken
+/// over.
+fn validate_audio(path: &Path) -> Result<(), String> {
+    let reader =
+        hound::WavReader::open(path).map_err(|e| format!("not a readable WAV file ({})", e))?;
+    let spec = reader.spec();
+    if spec.channels == 0 || spec.sample_rate == 0 {
+        return Err("header has no channels or sample rate".into());
+    }
+    match (spec.sample_format, spec.bits_per_sample) {
+        (hound::SampleFormat::Int, 8..=32) | (hound::SampleFormat::Float, 32) => {}
+        (format, bits) => {
+            return Err(format!(
+                "unsupported sample format ({}-bit {:?})",
+                bits, format
+            ));
+        }
+    }
+    if reader.len() == 0 {
+        return Err("no audio data".into());
+    }
+    // A cut-off download or copy still has a header promising the full data
+    let data_bytes = reader.len() as u64 * spec.bits_per_sample.div_ceil(8) as u64;
+    let file_bytes = fs::metadata(path).map_err(|e| e.to_string())?.len();
+    if file_bytes < data_bytes {
+        return Err(format!(
+            "file is truncated ({} of {} data bytes)",
+            file_bytes, data_bytes
+        ));
+    }
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    Decoder::new(BufReader::new(file)).map_err(|e| format!("cannot decode ({})", e))?;
     Ok(())
 }
 
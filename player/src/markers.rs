@@ -0,0 +1,71 @@
+//! Named positions in the file, kept in `state/markers.json` sorted by time.
+//! Markers are numbered from 1 in that order, and keys 1-9 are hot cues
+//! for the first nine. Audacity label tracks exported as text can be read
+//! in as markers (`:import labels.txt`).
+
+use cornwall_player::json;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Marker {
+    pub name: String,
+    /// Seconds from the start of the file.
+    pub position: f64,
+}
+
+/// What a hot cue key does.
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HotCues {
+    /// Move the playhead, playing or not.
+    #[default]
+    Locate,
+    /// Move the playhead and start playing from there.
+    Play,
+}
+
+pub fn load(state_dir: &Path) -> Result<Vec<Marker>, String> {
+    let mut markers: Vec<Marker> = json::load(&state_dir.join("markers.json"))?;
+    sort(&mut markers);
+    Ok(markers)
+}
+
+pub fn save(state_dir: &Path, markers: &[Marker]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(markers).map_err(|e| e.to_string())?;
+    fs::write(state_dir.join("markers.json"), json + "\n").map_err(|e| e.to_string())
+}
+
+/// Markers from an Audacity label file: `start<TAB>end<TAB>text` per
+/// label, in seconds. A region label becomes a marker at its start, and
+/// the frequency lines spectral labels add are skipped.
+pub fn parse_audacity(text: &str) -> Result<Vec<Marker>, String> {
+    let mut markers = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('\\') {
+            continue;
+        }
+        let mut fields = line.splitn(3, '\t');
+        // Some locales' Audacity builds write decimal commas
+        let start = fields
+            .next()
+            .and_then(|s| s.trim().replace(',', ".").parse::<f64>().ok())
+            .filter(|s| s.is_finite() && *s >= 0.0)
+            .ok_or_else(|| format!("line {}: not an Audacity label", i + 1))?;
+        // Past the end time
+        let name = fields.nth(1).unwrap_or_default().trim();
+        markers.push(Marker {
+            name: if name.is_empty() {
+                format!("{:.1}s", start)
+            } else {
+                name.to_string()
+            },
+            position: start,
+        });
+    }
+    Ok(markers)
+}
+
+pub fn sort(markers: &mut [Marker]) {
+    markers.sort_by(|a, b| a.position.total_cmp(&b.position));
+}
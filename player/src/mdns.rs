@@ -0,0 +1,526 @@
+//! Running players announced over mDNS as `_cornwall._tcp` services, so
+//! remote controls and other players on the LAN can find them
+//! (`cornwall-player discover`) without being told an address:
+//!
+//! ```json
+//! "discovery": {"advertise": true, "name": "Studio B"}
+//! ```
+//!
+//! A player is only announced while there's something to reach it by: an
+//! `--http` server listening beyond localhost, or `--lead` or `--follow`.
+//! The service's port is the `--http` port (0 with sync alone), and its TXT
+//! record has `project` and `version`, plus `role` (`lead` or `follow`) and
+//! `sync` (the LAN sync port) with `--lead` or `--follow`. The name
+//! defaults to "<project> on <host>".
+//!
+//! The player answers queries itself on port 5353. Where the system's
+//! responder (Avahi, Bonjour) already holds that port, it registers through
+//! that instead, with `avahi-publish-service` or `dns-sd`. Discovery asks
+//! from a port of its own, which every responder answers directly.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+const GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const PORT: u16 = 5353;
+const SERVICE: &str = "_cornwall._tcp.local";
+
+/// Seconds other hosts may cache the records.
+const TTL: u32 = 120;
+/// What a reply to a one-off query (not from port 5353) may carry.
+const UNICAST_TTL: u32 = 10;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Set on records only this host answers for.
+const CACHE_FLUSH: u16 = 0x8000;
+/// Set on a question to ask for the reply straight back.
+const UNICAST_RESPONSE: u16 = 0x8000;
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct DiscoveryConfig {
+    /// Announce the player while it serves HTTP on the network or syncs.
+    pub advertise: bool,
+    /// Service name. "<project> on <host>" when unset.
+    pub name: Option<String>,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        DiscoveryConfig {
+            advertise: true,
+            name: None,
+        }
+    }
+}
+
+/// What a player says about itself.
+pub struct Service {
+    pub name: String,
+    pub port: u16,
+    pub txt: Vec<(String, String)>,
+}
+
+/// A player's announcement, kept until it exits.
+pub enum Advertiser {
+    /// Answering queries on port 5353.
+    Responder { socket: UdpSocket, records: Records },
+    /// Registered with the system's responder.
+    Daemon(Child),
+}
+
+impl Advertiser {
+    pub fn start(service: Service) -> Result<Advertiser, String> {
+        let host = host_name();
+        let address = local_address()?;
+        let records = Records::new(&service, &host, address);
+        let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, PORT)) {
+            Ok(socket) => socket,
+            Err(e) if e.kind() == ErrorKind::AddrInUse => return register(&service),
+            Err(e) => return Err(format!("port {}: {}", PORT, e)),
+        };
+        socket
+            .join_multicast_v4(&GROUP, &Ipv4Addr::UNSPECIFIED)
+            .map_err(|e| e.to_string())?;
+        let responder = socket.try_clone().map_err(|e| e.to_string())?;
+        let answers = records.clone();
+        thread::spawn(move || respond(responder, answers));
+        Ok(Advertiser::Responder { socket, records })
+    }
+}
+
+impl Drop for Advertiser {
+    fn drop(&mut self) {
+        match self {
+            // A zero TTL tells caches the player has gone
+            Advertiser::Responder { socket, records } => {
+                let goodbye = records.response(0, &[], 0, true);
+                let _ = socket.send_to(&goodbye, (GROUP, PORT));
+            }
+            Advertiser::Daemon(child) => {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+}
+
+/// Hand `service` to Avahi or Bonjour, whichever is installed.
+fn register(service: &Service) -> Result<Advertiser, String> {
+    let txt = service
+        .txt
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value));
+    let port = service.port.to_string();
+    let avahi = Command::new("avahi-publish-service")
+        .args([&service.name, "_cornwall._tcp", &port])
+        .args(txt.clone())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    let child = avahi.or_else(|_| {
+        Command::new("dns-sd")
+            .args(["-R", &service.name, "_cornwall._tcp", "local", &port])
+            .args(txt)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+    });
+    child.map(Advertiser::Daemon).map_err(|_| {
+        format!(
+            "port {} is in use and neither avahi-publish-service nor dns-sd runs",
+            PORT
+        )
+    })
+}
+
+/// This machine's name, without a domain.
+fn host_name() -> String {
+    let name = fs::read_to_string("/etc/hostname")
+        .ok()
+        .or_else(|| {
+            let output = Command::new("hostname").output().ok()?;
+            String::from_utf8(output.stdout).ok()
+        })
+        .unwrap_or_default();
+    let name: String = name
+        .trim()
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    if name.is_empty() {
+        "cornwall".to_string()
+    } else {
+        name
+    }
+}
+
+/// The address multicast goes out from, which is the one to be reached on.
+fn local_address() -> Result<Ipv4Addr, String> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(|e| e.to_string())?;
+    socket
+        .connect((GROUP, PORT))
+        .map_err(|e| format!("no network for multicast: {}", e))?;
+    match socket.local_addr().map_err(|e| e.to_string())?.ip() {
+        IpAddr::V4(address) => Ok(address),
+        IpAddr::V6(_) => Err("no IPv4 address".to_string()),
+    }
+}
+
+/// The default service name for `project` on this machine.
+pub fn default_name(project: &str) -> String {
+    format!("{} on {}", project, host_name())
+}
+
+/// The four records a player answers with.
+#[derive(Clone)]
+pub struct Records {
+    instance: String,
+    host: String,
+    address: Ipv4Addr,
+    port: u16,
+    txt: Vec<String>,
+}
+
+impl Records {
+    fn new(service: &Service, host: &str, address: Ipv4Addr) -> Self {
+        Records {
+            instance: format!("{}.{}", label(&service.name), SERVICE),
+            host: format!("{}.local", host),
+            address,
+            port: service.port,
+            txt: service
+                .txt
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect(),
+        }
+    }
+
+    /// Whether a question for `name` is about this player.
+    fn answers(&self, name: &str) -> bool {
+        [SERVICE, &self.instance, &self.host]
+            .iter()
+            .any(|ours| ours.eq_ignore_ascii_case(name))
+    }
+
+    /// A response carrying every record. `multicast` responses are shared
+    /// with everyone's caches; others echo the query's `id` and `question`.
+    fn response(&self, id: u16, question: &[u8], ttl: u32, multicast: bool) -> Vec<u8> {
+        let unique = if multicast {
+            CLASS_IN | CACHE_FLUSH
+        } else {
+            CLASS_IN
+        };
+        let mut packet = Vec::with_capacity(512);
+        packet.extend(id.to_be_bytes());
+        packet.extend(0x8400_u16.to_be_bytes());
+        let questions: u16 = if question.is_empty() { 0 } else { 1 };
+        for count in [questions, 4, 0, 0] {
+            packet.extend(count.to_be_bytes());
+        }
+        packet.extend(question);
+
+        let mut ptr = Vec::new();
+        put_name(&mut ptr, &self.instance);
+        put_record(&mut packet, SERVICE, TYPE_PTR, CLASS_IN, ttl, &ptr);
+
+        let mut srv = Vec::new();
+        srv.extend([0, 0, 0, 0]);
+        srv.extend(self.port.to_be_bytes());
+        put_name(&mut srv, &self.host);
+        put_record(&mut packet, &self.instance, TYPE_SRV, unique, ttl, &srv);
+
+        let mut txt = Vec::new();
+        for entry in &self.txt {
+            let entry = &entry.as_bytes()[..entry.len().min(255)];
+            txt.push(entry.len() as u8);
+            txt.extend(entry);
+        }
+        put_record(&mut packet, &self.instance, TYPE_TXT, unique, ttl, &txt);
+
+        let a = self.address.octets();
+        put_record(&mut packet, &self.host, TYPE_A, unique, ttl, &a);
+        packet
+    }
+}
+
+/// `name` cut to one DNS label's worth, on a character boundary.
+fn label(name: &str) -> String {
+    let mut label = String::new();
+    for c in name.chars().filter(|c| *c != '.') {
+        if label.len() + c.len_utf8() > 63 {
+            break;
+        }
+        label.push(c);
+    }
+    label
+}
+
+/// Answer queries about the player until the socket fails, announcing it
+/// twice first as hosts joining a network do.
+fn respond(socket: UdpSocket, records: Records) {
+    for _ in 0..2 {
+        let _ = socket.send_to(&records.response(0, &[], TTL, true), (GROUP, PORT));
+        thread::sleep(Duration::from_secs(1));
+    }
+    let mut buf = [0u8; 1500];
+    while let Ok((len, from)) = socket.recv_from(&mut buf) {
+        let Some(query) = parse_query(&buf[..len]) else {
+            continue;
+        };
+        let Some((_, _, class, question)) = query
+            .questions
+            .into_iter()
+            .find(|(name, kind, ..)| records.answers(name) && wanted(*kind))
+        else {
+            continue;
+        };
+        if from.port() != PORT {
+            // A one-off query expects its own ID and question back
+            let reply = records.response(query.id, &question, UNICAST_TTL, false);
+            let _ = socket.send_to(&reply, from);
+        } else if class & UNICAST_RESPONSE != 0 {
+            let _ = socket.send_to(&records.response(0, &[], TTL, true), from);
+        } else {
+            let _ = socket.send_to(&records.response(0, &[], TTL, true), (GROUP, PORT));
+        }
+    }
+}
+
+fn wanted(kind: u16) -> bool {
+    matches!(kind, TYPE_A | TYPE_PTR | TYPE_TXT | TYPE_SRV | TYPE_ANY)
+}
+
+struct Query {
+    id: u16,
+    /// Name, type, class and the question as sent.
+    questions: Vec<(String, u16, u16, Vec<u8>)>,
+}
+
+fn parse_query(packet: &[u8]) -> Option<Query> {
+    let id = read_u16(packet, 0)?;
+    let flags = read_u16(packet, 2)?;
+    if flags & 0x8000 != 0 {
+        return None;
+    }
+    let count = read_u16(packet, 4)?;
+    let mut at = 12;
+    let mut questions = Vec::new();
+    for _ in 0..count {
+        let (name, next) = read_name(packet, at)?;
+        let kind = read_u16(packet, next)?;
+        let class = read_u16(packet, next + 2)?;
+        at = next + 4;
+        // Echoed questions carry the name uncompressed and without the
+        // unicast bit
+        let mut question = Vec::new();
+        put_name(&mut question, &name);
+        question.extend(kind.to_be_bytes());
+        question.extend((class & !UNICAST_RESPONSE).to_be_bytes());
+        questions.push((name, kind, class, question));
+    }
+    Some(Query { id, questions })
+}
+
+fn put_name(out: &mut Vec<u8>, name: &str) {
+    for part in name.split('.').filter(|p| !p.is_empty()) {
+        let part = &part.as_bytes()[..part.len().min(63)];
+        out.push(part.len() as u8);
+        out.extend(part);
+    }
+    out.push(0);
+}
+
+fn put_record(out: &mut Vec<u8>, name: &str, kind: u16, class: u16, ttl: u32, data: &[u8]) {
+    put_name(out, name);
+    out.extend(kind.to_be_bytes());
+    out.extend(class.to_be_bytes());
+    out.extend(ttl.to_be_bytes());
+    out.extend((data.len() as u16).to_be_bytes());
+    out.extend(data);
+}
+
+fn read_u16(packet: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*packet.get(at)?, *packet.get(at + 1)?]))
+}
+
+/// The name at `at`, following compression pointers, and where the data
+/// after it starts.
+fn read_name(packet: &[u8], mut at: usize) -> Option<(String, usize)> {
+    let mut parts: Vec<String> = Vec::new();
+    let mut end = None;
+    // Bounds pointer loops in a malformed packet
+    for _ in 0..64 {
+        let len = *packet.get(at)? as usize;
+        match len {
+            0 => {
+                return Some((parts.join("."), end.unwrap_or(at + 1)));
+            }
+            l if l & 0xC0 == 0xC0 => {
+                let pointer = (read_u16(packet, at)? & 0x3FFF) as usize;
+                end.get_or_insert(at + 2);
+                at = pointer;
+            }
+            l => {
+                let part = packet.get(at + 1..at + 1 + l)?;
+                parts.push(String::from_utf8_lossy(part).to_string());
+                at += 1 + l;
+            }
+        }
+    }
+    None
+}
+
+/// A player found on the network.
+#[derive(Serialize)]
+pub struct Found {
+    pub name: String,
+    pub host: String,
+    pub address: String,
+    /// HTTP port, `0` when it isn't serving one.
+    pub port: u16,
+    pub txt: HashMap<String, String>,
+}
+
+/// Ask the network for players and collect the answers for `wait`.
+pub fn discover(wait: Duration) -> Result<Vec<Found>, String> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(|e| e.to_string())?;
+    socket
+        .set_read_timeout(Some(Duration::from_millis(100)))
+        .map_err(|e| e.to_string())?;
+    let mut query = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+    put_name(&mut query, SERVICE);
+    query.extend(TYPE_PTR.to_be_bytes());
+    query.extend((CLASS_IN | UNICAST_RESPONSE).to_be_bytes());
+
+    let mut seen = Answers::default();
+    let start = Instant::now();
+    let mut sent = 0;
+    let mut buf = [0u8; 9000];
+    while start.elapsed() < wait {
+        // Asked again half way, in case the first went missing
+        if sent == 0 || (sent == 1 && start.elapsed() > wait / 2) {
+            socket
+                .send_to(&query, (GROUP, PORT))
+                .map_err(|e| format!("cannot send query: {}", e))?;
+            sent += 1;
+        }
+        if let Ok((len, from)) = socket.recv_from(&mut buf) {
+            seen.read(&buf[..len], from);
+        }
+    }
+    Ok(seen.found())
+}
+
+/// Records gathered from responses.
+#[derive(Default)]
+struct Answers {
+    instances: Vec<String>,
+    srv: HashMap<String, (u16, String)>,
+    txt: HashMap<String, HashMap<String, String>>,
+    a: HashMap<String, Ipv4Addr>,
+    /// Where each instance's answer came from, should it lack an A record.
+    from: HashMap<String, IpAddr>,
+}
+
+impl Answers {
+    fn read(&mut self, packet: &[u8], from: SocketAddr) -> Option<()> {
+        let flags = read_u16(packet, 2)?;
+        if flags & 0x8000 == 0 {
+            return None;
+        }
+        let questions = read_u16(packet, 4)?;
+        let records = (6..12)
+            .step_by(2)
+            .map(|at| read_u16(packet, at).unwrap_or(0) as usize)
+            .sum::<usize>();
+        let mut at = 12;
+        for _ in 0..questions {
+            at = read_name(packet, at)?.1 + 4;
+        }
+        for _ in 0..records {
+            let (name, next) = read_name(packet, at)?;
+            let kind = read_u16(packet, next)?;
+            let len = read_u16(packet, next + 8)? as usize;
+            let data = next + 10;
+            at = data + len;
+            let rdata = packet.get(data..at)?;
+            match kind {
+                TYPE_PTR if name.eq_ignore_ascii_case(SERVICE) => {
+                    let (instance, _) = read_name(packet, data)?;
+                    self.from.insert(instance.clone(), from.ip());
+                    if !self.instances.contains(&instance) {
+                        self.instances.push(instance);
+                    }
+                }
+                TYPE_SRV if len > 6 => {
+                    let port = read_u16(packet, data + 4)?;
+                    let (target, _) = read_name(packet, data + 6)?;
+                    self.srv.insert(name, (port, target));
+                }
+                TYPE_TXT => {
+                    let mut entries = HashMap::new();
+                    let mut i = 0;
+                    while let Some(&l) = rdata.get(i) {
+                        let entry = rdata.get(i + 1..i + 1 + l as usize)?;
+                        let entry = String::from_utf8_lossy(entry);
+                        if let Some((key, value)) = entry.split_once('=') {
+                            entries.insert(key.to_string(), value.to_string());
+                        }
+                        i += 1 + l as usize;
+                    }
+                    self.txt.insert(name, entries);
+                }
+                TYPE_A if len == 4 => {
+                    let a = Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3]);
+                    self.a.insert(name.to_ascii_lowercase(), a);
+                }
+                _ => {}
+            }
+        }
+        Some(())
+    }
+
+    fn found(mut self) -> Vec<Found> {
+        self.instances
+            .iter()
+            .map(|instance| {
+                let (port, host) = self.srv.remove(instance).unwrap_or_default();
+                let address = self
+                    .a
+                    .get(&host.to_ascii_lowercase())
+                    .map(|a| IpAddr::V4(*a))
+                    .or_else(|| self.from.get(instance).copied())
+                    .map_or_else(String::new, |a| a.to_string());
+                let name = instance
+                    .strip_suffix(&format!(".{}", SERVICE))
+                    .unwrap_or(instance)
+                    .to_string();
+                Found {
+                    name,
+                    host,
+                    address,
+                    port,
+                    txt: self.txt.remove(instance).unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+}
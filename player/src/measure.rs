@@ -0,0 +1,114 @@
+//! Room measurement (`:measure`): plays a 20 Hz-20 kHz log sweep through
+//! the monitors, records it with a measurement microphone and saves the
+//! room's impulse response to `state/measurements/room-<unix time>.wav`,
+//! a first step towards monitor correction. Keep the room quiet while the
+//! sweep plays; stopping playback cancels it.
+//!
+//! ```json
+//! "measure": {"device": "Scarlett", "level_dbfs": -12}
+//! ```
+//!
+//! `device` is the microphone's input (a name substring, the default input
+//! when unset) and has to run at the output's sample rate. `level_dbfs` is
+//! the sweep's peak before the monitor volume.
+
+use crate::{input::Input, output::db_to_gain};
+use cornwall_dsp::room;
+use cornwall_player::wav;
+use rodio::buffer::SamplesBuffer;
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// Length of the sweep.
+const SWEEP_SECS: f64 = 5.0;
+
+/// Recording after the sweep, for the room to die away and the output's
+/// latency.
+const TAIL_SECS: f64 = 2.0;
+
+/// Length of the impulse response saved.
+const IR_SECS: f64 = 1.5;
+
+/// Recordings that never get louder than this (about -80 dBFS) have no sweep
+/// in them.
+const SILENCE: f64 = 1e-4;
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct MeasureConfig {
+    /// Substring of the microphone's input name; the default input when
+    /// absent.
+    pub device: Option<String>,
+    /// Peak level of the sweep.
+    pub level_dbfs: f32,
+}
+
+impl Default for MeasureConfig {
+    fn default() -> Self {
+        MeasureConfig {
+            device: None,
+            level_dbfs: -12.0,
+        }
+    }
+}
+
+pub enum Measurement {
+    /// The sweep is playing and the input recording until `until`.
+    Recording { input: Input, until: Instant },
+    /// Working out the response and saving it, off the UI thread.
+    Processing(JoinHandle<Result<PathBuf, String>>),
+}
+
+impl Measurement {
+    /// Start recording the input, returning the stereo sweep to play at
+    /// `sample_rate` alongside.
+    pub fn start(
+        config: &MeasureConfig,
+        sample_rate: u32,
+    ) -> Result<(Measurement, SamplesBuffer<f32>), String> {
+        let input = Input::open(config.device.as_deref(), SWEEP_SECS + TAIL_SECS + 1.0)?;
+        if input.sample_rate != sample_rate {
+            return Err(format!(
+                "the input runs at {} Hz and the output at {} Hz",
+                input.sample_rate, sample_rate
+            ));
+        }
+        let gain = db_to_gain(config.level_dbfs);
+        let frames: Vec<f32> = room::sweep(sample_rate, SWEEP_SECS)
+            .into_iter()
+            .flat_map(|s| [s as f32 * gain; 2])
+            .collect();
+        let until = Instant::now() + Duration::from_secs_f64(SWEEP_SECS + TAIL_SECS);
+        Ok((
+            Measurement::Recording { input, until },
+            SamplesBuffer::new(2, sample_rate, frames),
+        ))
+    }
+}
+
+/// Close the input and work out and save the response from what it
+/// recorded.
+pub fn process(input: Input, state_dir: &Path, unix_time: f64) -> Measurement {
+    let recording = input.samples();
+    let sample_rate = input.sample_rate;
+    drop(input);
+    let path = state_dir
+        .join("measurements")
+        .join(format!("room-{}.wav", unix_time as u64));
+    Measurement::Processing(thread::spawn(move || {
+        if recording.iter().all(|s| s.abs() < SILENCE) {
+            return Err("the microphone picked up nothing".to_string());
+        }
+        let response = room::impulse_response(&recording, sample_rate, SWEEP_SECS, IR_SECS);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+        wav::write_mono(&path, sample_rate, &response)?;
+        Ok(path)
+    }))
+}
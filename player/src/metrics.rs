@@ -0,0 +1,217 @@
+//! Prometheus metrics, served as `GET /metrics` by the `--http` server, for
+//! watching long unattended sessions in Grafana.
+//!
+//! rodio doesn't expose the device buffer, so underruns are counted as
+//! output stalls: stretches where the sink stops consuming audio while
+//! playing. Audio thread load comes from timing the playback chain
+//! (decode, resample, routing) as the device pulls samples; its rate is
+//! the share of one core spent producing audio. The same timings, gathered
+//! into bursts, give how long each device callback takes for the
+//! diagnostics overlay.
+
+use crate::App;
+use rodio::Source;
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Time one sample in this many and scale up; timing every sample would
+/// cost more than the chain itself.
+const SAMPLE_EVERY: u64 = 64;
+
+/// A pause this long between timed samples means the device buffer was
+/// full and the next one is a new callback.
+const CALLBACK_GAP: Duration = Duration::from_millis(1);
+
+/// Counters shared with the audio thread.
+#[derive(Default)]
+pub struct AudioStats {
+    samples: AtomicU64,
+    busy_ns: AtomicU64,
+    /// Callbacks seen since the last [`AudioStats::take_callbacks`], with
+    /// their total and longest time.
+    callbacks: AtomicU64,
+    callback_ns: AtomicU64,
+    callback_max_ns: AtomicU64,
+    /// Time between the starts of the last two callbacks: what each has to
+    /// finish within.
+    period_ns: AtomicU64,
+}
+
+impl AudioStats {
+    fn record_callback(&self, took: Duration, period: Duration) {
+        let ns = took.as_nanos() as u64;
+        self.callbacks.fetch_add(1, Ordering::Relaxed);
+        self.callback_ns.fetch_add(ns, Ordering::Relaxed);
+        self.callback_max_ns.fetch_max(ns, Ordering::Relaxed);
+        // Longer is playback resuming, not a callback's budget
+        if period < Duration::from_secs(1) {
+            self.period_ns
+                .store(period.as_nanos() as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Mean and longest callback since the last call, if there were any.
+    pub fn take_callbacks(&self) -> Option<(Duration, Duration)> {
+        let count = self.callbacks.swap(0, Ordering::Relaxed);
+        let total = self.callback_ns.swap(0, Ordering::Relaxed);
+        let max = self.callback_max_ns.swap(0, Ordering::Relaxed);
+        (count > 0).then(|| {
+            (
+                Duration::from_nanos(total / count),
+                Duration::from_nanos(max),
+            )
+        })
+    }
+
+    pub fn period(&self) -> Duration {
+        Duration::from_nanos(self.period_ns.load(Ordering::Relaxed))
+    }
+}
+
+/// Passes samples through, counting them and the time spent producing them.
+pub struct Probe<S> {
+    inner: S,
+    stats: Arc<AudioStats>,
+    count: u64,
+    /// First and latest timed sample of the callback under way. Up to
+    /// `SAMPLE_EVERY` samples either side go untimed, so short callbacks
+    /// read a little short.
+    burst: Option<(Instant, Instant)>,
+}
+
+impl<S: Source<Item = f32>> Probe<S> {
+    pub fn new(inner: S, stats: Arc<AudioStats>) -> Self {
+        Probe {
+            inner,
+            stats,
+            count: 0,
+            burst: None,
+        }
+    }
+
+    fn track_callback(&mut self, start: Instant, end: Instant) {
+        self.burst = match self.burst {
+            Some((first, last)) if start.saturating_duration_since(last) < CALLBACK_GAP => {
+                Some((first, end))
+            }
+            Some((first, last)) => {
+                self.stats.record_callback(
+                    last.saturating_duration_since(first),
+                    start.saturating_duration_since(first),
+                );
+                Some((start, end))
+            }
+            None => Some((start, end)),
+        };
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for Probe<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.count += 1;
+        if !self.count.is_multiple_of(SAMPLE_EVERY) {
+            return self.inner.next();
+        }
+        let start = Instant::now();
+        let sample = self.inner.next();
+        let end = Instant::now();
+        let ns = (end - start).as_nanos() as u64 * SAMPLE_EVERY;
+        self.stats.busy_ns.fetch_add(ns, Ordering::Relaxed);
+        self.stats
+            .samples
+            .fetch_add(SAMPLE_EVERY, Ordering::Relaxed);
+        self.track_callback(start, end);
+        sample
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Probe<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+/// The player's metrics in the Prometheus text format.
+pub fn render(app: &App) -> String {
+    let stats = &app.audio_stats;
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, values: &[(&str, f64)]| {
+        let _ = writeln!(out, "# HELP cornwall_player_{} {}", name, help);
+        let _ = writeln!(out, "# TYPE cornwall_player_{} {}", name, kind);
+        for (labels, value) in values {
+            let _ = writeln!(out, "cornwall_player_{}{} {}", name, labels, value);
+        }
+    };
+    metric(
+        "playing",
+        "gauge",
+        "1 while the transport is playing.",
+        &[("", app.playing as u8 as f64)],
+    );
+    metric(
+        "position_seconds",
+        "gauge",
+        "Playhead position in the file.",
+        &[("", app.position)],
+    );
+    metric(
+        "level",
+        "gauge",
+        "RMS level at the playhead, 0 to 1.",
+        &[
+            ("{channel=\"left\"}", app.meter.current_l),
+            ("{channel=\"right\"}", app.meter.current_r),
+        ],
+    );
+    metric(
+        "output_samples_total",
+        "counter",
+        "Samples delivered to the main output.",
+        &[("", stats.samples.load(Ordering::Relaxed) as f64)],
+    );
+    metric(
+        "audio_busy_seconds_total",
+        "counter",
+        "Time spent producing audio; its rate is the audio thread's CPU share.",
+        &[("", stats.busy_ns.load(Ordering::Relaxed) as f64 / 1e9)],
+    );
+    metric(
+        "output_stalls_total",
+        "counter",
+        "Times the output stopped consuming audio during playback (underruns).",
+        &[("", app.drift.stalls as f64)],
+    );
+    metric(
+        "drift_seconds",
+        "gauge",
+        "Worst gap between the playhead clock and the output since playback started.",
+        &[("", app.drift.worst)],
+    );
+    metric(
+        "drift_corrections",
+        "gauge",
+        "Playhead clock corrections since playback started.",
+        &[("", app.drift.corrections as f64)],
+    );
+    out
+}
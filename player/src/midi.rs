@@ -0,0 +1,176 @@
+//! MIDI tracks (`"type": "midi"`, `source` a Standard MIDI File), rendered
+//! through the SoundFont set in `player-config.json` and mixed over the
+//! audio file during playback:
+//!
+//! ```json
+//! { "soundfont": "/usr/share/sounds/sf2/FluidR3_GM.sf2" }
+//! ```
+//!
+//! Tracks are rendered once, when the player starts or reloads, with their
+//! volume, pan, mute and solo at that moment. The waveform and meters show
+//! the audio file only.
+//!
+//! MIDI panic silences the rendered tracks until playback next starts or
+//! seeks.
+
+use crate::program::{Bounds, Looped, Samples};
+use cornwall_player::project::TrackState;
+use rodio::Source;
+use rustysynth::{MidiFile, MidiFileSequencer, SoundFont, Synthesizer, SynthesizerSettings};
+use std::{
+    fs::File,
+    io::BufReader,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Rendered past the last event so releases and reverb ring out.
+const TAIL_SECS: f64 = 2.0;
+
+/// Frames rendered per synthesizer call.
+const BLOCK: usize = 1024;
+
+/// The project's MIDI tracks, mixed to interleaved stereo.
+pub struct MidiMix {
+    samples: Arc<Samples>,
+    sample_rate: u32,
+    /// Set by MIDI panic, cleared when playback restarts.
+    silenced: Arc<AtomicBool>,
+}
+
+impl MidiMix {
+    /// Render the audible MIDI tracks at `sample_rate`. `None` when there
+    /// are none to hear.
+    pub fn render(
+        tracks: &[TrackState],
+        soundfont: Option<&Path>,
+        sample_rate: u32,
+    ) -> Result<Option<Self>, String> {
+        let any_solo = tracks.iter().any(|t| t.solo);
+        let midi: Vec<&TrackState> = tracks
+            .iter()
+            .filter(|t| t.track_type == "midi" && t.source.is_some())
+            .filter(|t| if any_solo { t.solo } else { !t.mute })
+            .collect();
+        if midi.is_empty() {
+            return Ok(None);
+        }
+        let soundfont = soundfont
+            .ok_or_else(|| "MIDI tracks need \"soundfont\" in player-config.json".to_string())?;
+        let mut reader = open(soundfont)?;
+        let soundfont = Arc::new(
+            SoundFont::new(&mut reader).map_err(|e| format!("{}: {}", soundfont.display(), e))?,
+        );
+        let settings = SynthesizerSettings::new(sample_rate as i32);
+
+        let mut mix: Vec<f32> = Vec::new();
+        let mut left = vec![0.0_f32; BLOCK];
+        let mut right = vec![0.0_f32; BLOCK];
+        for track in midi {
+            let path = Path::new(track.source.as_deref().unwrap_or_default());
+            let file = Arc::new(
+                MidiFile::new(&mut open(path)?)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?,
+            );
+            let synth = Synthesizer::new(&soundfont, &settings).map_err(|e| e.to_string())?;
+            let mut sequencer = MidiFileSequencer::new(synth);
+            sequencer.play(&file, false);
+
+            let frames = ((file.get_length() + TAIL_SECS) * sample_rate as f64) as usize;
+            if mix.len() < frames * 2 {
+                mix.resize(frames * 2, 0.0);
+            }
+            let (gain_l, gain_r) = balance(track.volume, track.pan);
+            let mut frame = 0;
+            while frame < frames {
+                let n = BLOCK.min(frames - frame);
+                sequencer.render(&mut left[..n], &mut right[..n]);
+                for i in 0..n {
+                    mix[(frame + i) * 2] += left[i] * gain_l;
+                    mix[(frame + i) * 2 + 1] += right[i] * gain_r;
+                }
+                frame += n;
+            }
+        }
+        Ok(Some(MidiMix {
+            samples: Arc::new(mix.into()),
+            sample_rate,
+            silenced: Arc::new(AtomicBool::new(false)),
+        }))
+    }
+
+    /// Bytes of rendered audio held.
+    pub fn memory(&self) -> usize {
+        self.samples.memory()
+    }
+
+    /// Playback from `offset` seconds in, within the same `bounds` as the
+    /// audio.
+    pub fn source(&self, offset: f64, bounds: Bounds) -> MidiSource {
+        self.silenced.store(false, Ordering::Relaxed);
+        MidiSource {
+            inner: Looped::new(self.samples.clone(), 2, self.sample_rate, offset, bounds),
+            silenced: self.silenced.clone(),
+        }
+    }
+
+    /// Mute the playing source until the next one starts.
+    pub fn silence(&self) {
+        self.silenced.store(true, Ordering::Relaxed);
+    }
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, String> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Left and right gains for a track's volume and pan (-1 to 1); the far
+/// side fades out while the near side stays at full volume.
+fn balance(volume: f64, pan: f64) -> (f32, f32) {
+    let pan = pan.clamp(-1.0, 1.0);
+    (
+        (volume * (1.0 - pan).min(1.0)) as f32,
+        (volume * (1.0 + pan).min(1.0)) as f32,
+    )
+}
+
+pub struct MidiSource {
+    inner: Looped,
+    silenced: Arc<AtomicBool>,
+}
+
+impl Iterator for MidiSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next();
+        if self.silenced.load(Ordering::Relaxed) {
+            return sample.map(|_| 0.0);
+        }
+        sample
+    }
+}
+
+impl Source for MidiSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
@@ -0,0 +1,475 @@
+//! Transport control from a MIDI input, for a foot controller while both
+//! hands are on an instrument:
+//!
+//! ```json
+//! "midi_input": {
+//!     "port": "FCB1010",
+//!     "bindings": {"pc 0": "toggle_play", "cc 64": "toggle_loop", "note 36": "jump 1"},
+//!     "mmc": true
+//! }
+//! ```
+//!
+//! Bindings map a note-on (`note N`), a controller pressed past half way
+//! (`cc N`) or a program change (`pc N`), on any channel, to a `:` command
+//! line; action names work as commands. With `mmc`, MIDI Machine Control
+//! play, stop and locate from a DAW or controller drive the transport.
+//!
+//! Faders, knobs and buttons mix in the player through `mixers`, named
+//! mapping profiles of which `mixer` is active (`:mixer NAME` switches):
+//!
+//! ```json
+//! "mixer": "nano",
+//! "mixers": {"nano": [
+//!     {"cc": 0, "track": 1, "param": "volume"},
+//!     {"cc": 16, "track": 1, "param": "pan"},
+//!     {"cc": 48, "channel": 1, "track": 1, "param": "mute"}
+//! ]}
+//! ```
+//!
+//! A fader covers volume 0 to 2, unity at its middle; a knob's centre is pan
+//! centre; mute and solo buttons toggle on press, so set them to momentary.
+//! Moves are track edits like the keys', undoable and saved with `:w`.
+//!
+//! MIDI learn fills the profile without editing JSON: `ctrl+l`, then the key
+//! of the parameter on the selected track (`x` mute, `s` solo, `,`/`.`
+//! volume, `<`/`>` pan in the default keys), then move the control. The
+//! mapping is added to the active profile (`learned` if there is none) and
+//! written to `player-config.json`.
+//!
+//! With `"clock": true` the player follows a hardware sequencer: MIDI clock
+//! sets the tempo used for bars and beats, start, continue and stop drive
+//! the transport and song position pointers move the playhead. The audio
+//! isn't stretched, so the sequencer should run near the project tempo.
+//!
+//! Messages arrive on the MIDI thread and run on the UI thread once per
+//! tick.
+
+use crate::{
+    command_line,
+    i18n::{tr, trf},
+    midi_monitor::Monitor,
+};
+use cornwall_player::json;
+use midir::{Ignore, MidiInput, MidiInputConnection};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::mpsc::{self, Receiver},
+};
+
+/// Profile that learned mappings go to when none is active.
+const LEARNED: &str = "learned";
+
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct MidiInputConfig {
+    /// Input port name (substring match). Uses the first port when unset.
+    pub port: Option<String>,
+    /// `"note 36"`, `"cc 64"` or `"pc 3"` to a command line.
+    pub bindings: HashMap<String, String>,
+    /// Follow MIDI Machine Control play, stop and locate.
+    pub mmc: bool,
+    /// Follow MIDI clock, start, continue, stop and song position.
+    pub clock: bool,
+    /// Mixer profile in use, from `mixers`.
+    pub mixer: Option<String>,
+    /// Named sets of controller mappings, one per controller or layout.
+    pub mixers: HashMap<String, Vec<Mapping>>,
+}
+
+/// A fader, knob or button tied to a track parameter.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct Mapping {
+    pub cc: u8,
+    /// 1-based MIDI channel; any channel when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<u8>,
+    pub track: u32,
+    pub param: Param,
+}
+
+impl Mapping {
+    /// Whether `other` uses the same control or drives the same parameter,
+    /// so a new mapping replaces it.
+    fn overlaps(&self, other: &Mapping) -> bool {
+        (self.cc == other.cc && self.channel == other.channel)
+            || (self.track == other.track && self.param == other.param)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Param {
+    Volume,
+    Pan,
+    Mute,
+    Solo,
+}
+
+impl Param {
+    pub fn label(&self) -> &'static str {
+        tr(match self {
+            Param::Volume => "param.volume",
+            Param::Pan => "param.pan",
+            Param::Mute => "param.mute",
+            Param::Solo => "param.solo",
+        })
+    }
+}
+
+/// Progress of MIDI learn.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Learn {
+    /// Waiting for the key of a track parameter.
+    Parameter,
+    /// Waiting for a control to move.
+    Control { track: u32, param: Param },
+}
+
+/// A note, controller or program number, written `note 36`, `cc 64` or
+/// `pc 3`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Trigger {
+    Note(u8),
+    Cc(u8),
+    Program(u8),
+}
+
+impl Trigger {
+    pub fn parse(s: &str) -> Option<Trigger> {
+        let (kind, number) = s.split_once(' ')?;
+        let number: u8 = number.trim().parse().ok().filter(|n| *n <= 127)?;
+        match kind {
+            "note" => Some(Trigger::Note(number)),
+            "cc" => Some(Trigger::Cc(number)),
+            "pc" => Some(Trigger::Program(number)),
+            _ => None,
+        }
+    }
+}
+
+pub enum Message {
+    Command(String),
+    Play,
+    Stop,
+    /// Seconds from MMC locate.
+    Locate(f64),
+    /// Clock start: play from the top.
+    Start,
+    /// Clock stop: stop where the playhead is.
+    Halt,
+    /// Song position pointer, in sixteenth notes.
+    SongPosition(u16),
+    /// Tempo measured over the last beat of clock.
+    Tempo(f64),
+    /// A controller move, before it's looked up in the mixer profile.
+    Controller {
+        channel: u8,
+        cc: u8,
+        value: u8,
+    },
+    /// A mapped controller move, with its 0-127 value.
+    Mixer(Mapping, u8),
+    /// MIDI learn caught a control, added to the named profile.
+    Learned(Mapping, String),
+}
+
+pub struct MidiControl {
+    _connection: MidiInputConnection<()>,
+    messages: Receiver<Message>,
+    mixers: HashMap<String, Vec<Mapping>>,
+    mixer: Option<String>,
+    /// Track and parameter the next controller move is mapped to.
+    learning: Option<(u32, Param)>,
+    /// Name of the input port.
+    pub port: String,
+    raw: Receiver<Vec<u8>>,
+    pub monitor: Monitor,
+}
+
+impl MidiControl {
+    pub fn open(config: &MidiInputConfig) -> Result<Self, String> {
+        let bindings = parse_bindings(&config.bindings)?;
+        let mmc = config.mmc;
+        let mut clock = config.clock.then(Clock::default);
+        if let Some(mixer) = config.mixer.as_ref() {
+            if !config.mixers.contains_key(mixer) {
+                return Err(format!("no mixer profile \"{}\" in \"mixers\"", mixer));
+            }
+        }
+
+        let mut input = MidiInput::new("cornwall-player").map_err(|e| e.to_string())?;
+        // SysEx stays on for MMC, timing messages for clock
+        input.ignore(if clock.is_some() {
+            Ignore::ActiveSense
+        } else {
+            Ignore::TimeAndActiveSense
+        });
+        let ports = input.ports();
+        let named = ports
+            .iter()
+            .filter_map(|p| Some((p, input.port_name(p).ok()?)));
+        let (port, name) = match &config.port {
+            Some(wanted) => named
+                .clone()
+                .find(|(_, name)| name.contains(wanted.as_str()))
+                .ok_or_else(|| {
+                    let names: Vec<String> = named.map(|(_, name)| name).collect();
+                    format!(
+                        "no MIDI input matches \"{}\" ({})",
+                        wanted,
+                        names.join(", ")
+                    )
+                })?,
+            None => named
+                .clone()
+                .next()
+                .ok_or_else(|| "no MIDI inputs".to_string())?,
+        };
+        let port = port.clone();
+
+        let (tx, messages) = mpsc::channel();
+        let (raw_tx, raw) = mpsc::channel();
+        let connection = input
+            .connect(
+                &port,
+                "cornwall-player-control",
+                move |stamp, bytes, _| {
+                    let _ = raw_tx.send(bytes.to_vec());
+                    let message = match &mut clock {
+                        Some(clock) if bytes.first().is_some_and(|b| *b >= 0xF2) => {
+                            clock.decode(stamp, bytes)
+                        }
+                        _ => decode(bytes, &bindings, mmc),
+                    };
+                    if let Some(message) = message {
+                        let _ = tx.send(message);
+                    }
+                },
+                (),
+            )
+            .map_err(|e| format!("{}: {}", name, e))?;
+        Ok(MidiControl {
+            _connection: connection,
+            messages,
+            mixers: config.mixers.clone(),
+            mixer: config.mixer.clone(),
+            learning: None,
+            port: name,
+            raw,
+            monitor: Monitor::default(),
+        })
+    }
+
+    /// Messages waiting for the UI thread, with controller moves looked up
+    /// in the active mixer profile and unmapped ones dropped.
+    pub fn pending(&mut self) -> Vec<Message> {
+        for bytes in self.raw.try_iter() {
+            self.monitor.record(bytes);
+        }
+        let messages: Vec<Message> = self.messages.try_iter().collect();
+        messages
+            .into_iter()
+            .filter_map(|message| match message {
+                Message::Controller { channel, cc, .. } if self.learning.is_some() => {
+                    let (track, param) = self.learning.take()?;
+                    let mapping = Mapping {
+                        cc,
+                        channel: Some(channel + 1),
+                        track,
+                        param,
+                    };
+                    Some(Message::Learned(mapping, self.learn_mapping(mapping)))
+                }
+                Message::Controller { channel, cc, value } => self
+                    .mapping(channel, cc)
+                    .map(|mapping| Message::Mixer(mapping, value)),
+                message => Some(message),
+            })
+            .collect()
+    }
+
+    /// Map the next controller move to `param` of `track`, or stop
+    /// waiting for one.
+    pub fn learn(&mut self, target: Option<(u32, Param)>) {
+        self.learning = target;
+    }
+
+    /// Add `mapping` to the active profile, making `learned` active if none
+    /// is. Returns the profile's name.
+    fn learn_mapping(&mut self, mapping: Mapping) -> String {
+        let name = self
+            .mixer
+            .get_or_insert_with(|| LEARNED.to_string())
+            .clone();
+        let mappings = self.mixers.entry(name.clone()).or_default();
+        mappings.retain(|m| !m.overlaps(&mapping));
+        mappings.push(mapping);
+        name
+    }
+
+    /// Make `name` the active mixer profile.
+    pub fn select_mixer(&mut self, name: &str) -> Result<(), String> {
+        if !self.mixers.contains_key(name) {
+            return Err(trf("error.unknown_mixer", &[("name", &name)]));
+        }
+        self.mixer = Some(name.to_string());
+        Ok(())
+    }
+
+    /// The mapping for controller `cc` on 0-based `channel`.
+    fn mapping(&self, channel: u8, cc: u8) -> Option<Mapping> {
+        let mappings = self.mixers.get(self.mixer.as_deref()?)?;
+        mappings
+            .iter()
+            .find(|m| m.cc == cc && m.channel.is_none_or(|c| c == channel + 1))
+            .copied()
+    }
+}
+
+/// MIDI clock runs at 24 pulses per quarter note.
+const CLOCKS_PER_BEAT: u32 = 24;
+
+/// Clock pulses counted towards the current beat.
+#[derive(Default)]
+struct Clock {
+    pulses: u32,
+    /// Timestamp of the last beat, in microseconds.
+    beat_at: Option<u64>,
+}
+
+impl Clock {
+    fn decode(&mut self, stamp: u64, bytes: &[u8]) -> Option<Message> {
+        match *bytes {
+            [0xF8] => {
+                self.pulses += 1;
+                if self.pulses < CLOCKS_PER_BEAT {
+                    return None;
+                }
+                self.pulses = 0;
+                let last = self.beat_at.replace(stamp)?;
+                let beat_secs = stamp.saturating_sub(last) as f64 / 1_000_000.0;
+                (beat_secs > 0.0).then(|| Message::Tempo(60.0 / beat_secs))
+            }
+            // The first beat after a start has no previous one to time
+            [0xFA] => {
+                *self = Clock::default();
+                Some(Message::Start)
+            }
+            [0xFB] => {
+                *self = Clock::default();
+                Some(Message::Play)
+            }
+            [0xFC] => Some(Message::Halt),
+            [0xF2, lsb, msb] => Some(Message::SongPosition(lsb as u16 | (msb as u16) << 7)),
+            _ => None,
+        }
+    }
+}
+
+/// Write a learned mapping into the `profile` of `player-config.json`,
+/// replacing those it overlaps and leaving everything else as it was.
+pub fn save_mapping(state_dir: &Path, profile: &str, mapping: &Mapping) -> Result<(), String> {
+    let path = state_dir.join("player-config.json");
+    let mut config: Value = match fs::read_to_string(&path) {
+        Ok(s) => json::parse(&path, &s)?,
+        Err(_) => Value::Object(Map::new()),
+    };
+    let midi_input = &mut config["midi_input"];
+    if midi_input["mixer"].is_null() {
+        midi_input["mixer"] = Value::from(profile);
+    }
+    let saved = &mut midi_input["mixers"][profile];
+    let mut mappings: Vec<Value> = saved.as_array().cloned().unwrap_or_default();
+    mappings.retain(|m| {
+        serde_json::from_value::<Mapping>(m.clone()).map_or(true, |m| !m.overlaps(mapping))
+    });
+    mappings.push(serde_json::to_value(mapping).map_err(|e| e.to_string())?);
+    *saved = Value::Array(mappings);
+    let json = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    fs::write(&path, json + "\n").map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Whether a mute or solo button value is a press.
+pub fn pressed(value: u8) -> bool {
+    value >= 64
+}
+
+/// A fader value as track volume, 0 to 2 with unity at the middle.
+pub fn fader_volume(value: u8) -> f64 {
+    value as f64 / 127.0 * 2.0
+}
+
+/// A knob value as pan, centred at 64.
+pub fn knob_pan(value: u8) -> f64 {
+    ((value as f64 - 64.0) / 63.0).clamp(-1.0, 1.0)
+}
+
+fn parse_bindings(config: &HashMap<String, String>) -> Result<HashMap<Trigger, String>, String> {
+    let mut bindings = HashMap::new();
+    for (message, line) in config {
+        let trigger = Trigger::parse(message)
+            .ok_or_else(|| format!("invalid MIDI binding \"{}\"", message))?;
+        if command_line::quits(line) {
+            return Err(format!(
+                "MIDI binding \"{}\" can't quit the player",
+                message
+            ));
+        }
+        bindings.insert(trigger, line.clone());
+    }
+    Ok(bindings)
+}
+
+fn decode(bytes: &[u8], bindings: &HashMap<Trigger, String>, mmc: bool) -> Option<Message> {
+    if let Some(line) = trigger(bytes).and_then(|t| bindings.get(&t)) {
+        return Some(Message::Command(line.clone()));
+    }
+    match *bytes {
+        [status, cc, value] if status & 0xF0 == 0xB0 => Some(Message::Controller {
+            channel: status & 0x0F,
+            cc,
+            value,
+        }),
+        _ if mmc => machine_control(bytes),
+        _ => None,
+    }
+}
+
+/// The binding a channel message fires; releases and note-offs fire none.
+fn trigger(bytes: &[u8]) -> Option<Trigger> {
+    match *bytes {
+        [status, note, velocity] if status & 0xF0 == 0x90 && velocity > 0 => {
+            Some(Trigger::Note(note))
+        }
+        [status, cc, value] if status & 0xF0 == 0xB0 && pressed(value) => Some(Trigger::Cc(cc)),
+        [status, program] if status & 0xF0 == 0xC0 => Some(Trigger::Program(program)),
+        _ => None,
+    }
+}
+
+/// MMC `F0 7F <device> 06 <command> … F7`, for any device ID.
+fn machine_control(bytes: &[u8]) -> Option<Message> {
+    match *bytes {
+        [0xF0, 0x7F, _, 0x06, 0x01, 0xF7] => Some(Message::Stop),
+        [0xF0, 0x7F, _, 0x06, 0x02 | 0x03, 0xF7] => Some(Message::Play),
+        [0xF0, 0x7F, _, 0x06, 0x44, 0x06, 0x01, hours, minutes, seconds, frames, _, 0xF7] => {
+            // The hours byte carries the frame rate in bits 5-6
+            let fps = match (hours >> 5) & 0x03 {
+                0 => 24.0,
+                1 => 25.0,
+                2 => 29.97,
+                _ => 30.0,
+            };
+            let secs = (hours & 0x1F) as f64 * 3600.0
+                + minutes as f64 * 60.0
+                + seconds as f64
+                + frames as f64 / fps;
+            Some(Message::Locate(secs))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,109 @@
+//! Recent messages from the MIDI input, shown beside the track list with `I`
+//! to check what a controller really sends before mapping it, or whether
+//! clock is arriving (with `"clock": true`; otherwise the port filters it
+//! out). A run of clock pulses folds into one line with a count.
+
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+use std::collections::VecDeque;
+
+/// Messages kept.
+const KEEP: usize = 64;
+
+struct Activity {
+    bytes: Vec<u8>,
+    /// Times it arrived in a row; only clock pulses fold.
+    count: u32,
+}
+
+#[derive(Default)]
+pub struct Monitor {
+    recent: VecDeque<Activity>,
+}
+
+impl Monitor {
+    pub fn record(&mut self, bytes: Vec<u8>) {
+        if let Some(last) = self.recent.back_mut() {
+            if bytes == [0xF8] && last.bytes == bytes {
+                last.count += 1;
+                return;
+            }
+        }
+        if self.recent.len() == KEEP {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(Activity { bytes, count: 1 });
+    }
+
+    /// The latest `rows` messages, oldest first: channel, type and data.
+    pub fn lines(&self, rows: usize) -> Vec<Line<'static>> {
+        let skip = self.recent.len().saturating_sub(rows);
+        self.recent
+            .iter()
+            .skip(skip)
+            .map(|activity| {
+                let (channel, kind, data) = describe(&activity.bytes);
+                let channel = channel.map_or("  ".to_string(), |c| format!("{:>2}", c + 1));
+                let count = if activity.count > 1 {
+                    format!(" ×{}", activity.count)
+                } else {
+                    String::new()
+                };
+                Line::from(vec![
+                    Span::styled(channel, Style::default().fg(Color::DarkGray)),
+                    Span::styled(format!(" {:<11}", kind), Style::default().fg(Color::Cyan)),
+                    Span::raw(data),
+                    Span::styled(count, Style::default().fg(Color::DarkGray)),
+                ])
+            })
+            .collect()
+    }
+}
+
+/// Channel (0-based) for channel messages, a type name and the data bytes
+/// in words.
+fn describe(bytes: &[u8]) -> (Option<u8>, &'static str, String) {
+    let channel = Some(bytes.first().copied().unwrap_or_default() & 0x0F);
+    match *bytes {
+        [status, key, 0] if status & 0xF0 == 0x90 => (channel, "note off", key.to_string()),
+        [status, key, vel] if status & 0xF0 == 0x90 => {
+            (channel, "note on", format!("{} vel {}", key, vel))
+        }
+        [status, key, vel] if status & 0xF0 == 0x80 => {
+            (channel, "note off", format!("{} vel {}", key, vel))
+        }
+        [status, key, value] if status & 0xF0 == 0xA0 => {
+            (channel, "aftertouch", format!("{} = {}", key, value))
+        }
+        [status, cc, value] if status & 0xF0 == 0xB0 => {
+            (channel, "cc", format!("{} = {}", cc, value))
+        }
+        [status, program] if status & 0xF0 == 0xC0 => (channel, "program", program.to_string()),
+        [status, value] if status & 0xF0 == 0xD0 => (channel, "pressure", value.to_string()),
+        [status, lsb, msb] if status & 0xF0 == 0xE0 => {
+            let bend = (lsb as i32 | (msb as i32) << 7) - 8192;
+            (channel, "pitch bend", format!("{:+}", bend))
+        }
+        [0xF0, ..] => (None, "sysex", hex(bytes)),
+        [0xF2, lsb, msb] => (
+            None,
+            "song pos",
+            (lsb as u16 | (msb as u16) << 7).to_string(),
+        ),
+        [0xF8] => (None, "clock", String::new()),
+        [0xFA] => (None, "start", String::new()),
+        [0xFB] => (None, "continue", String::new()),
+        [0xFC] => (None, "stop", String::new()),
+        _ => (None, "other", hex(bytes)),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
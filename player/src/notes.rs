@@ -0,0 +1,89 @@
+//! Session notes: comments typed during playback (`N`, or `:note TEXT`),
+//! each stored with the bar.beat and time it was written at in
+//! `projects/<name>/notes.json`. The notes pane (`Ctrl+n`) lists them beside
+//! the track list and follows the playhead, so they come past as the song
+//! plays: passed notes dim, the latest one passed highlighted. Listeners
+//! on the remote page (`--http`) add notes under their name.
+
+use cornwall_player::{json, widgets::format_position};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Note {
+    /// Seconds from the start of the file.
+    pub position: f64,
+    /// Bar and beat, `33.2`, when the project has a tempo.
+    pub bar: Option<String>,
+    pub text: String,
+    /// Who wrote it, for notes from remote listeners.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Unix time it was written.
+    pub written: f64,
+}
+
+fn path(project_dir: &Path) -> PathBuf {
+    project_dir.join("notes.json")
+}
+
+pub fn load(project_dir: &Path) -> Result<Vec<Note>, String> {
+    json::load(&path(project_dir))
+}
+
+/// Add `note` in time order and write the file.
+pub fn add(project_dir: &Path, notes: &mut Vec<Note>, note: Note) -> Result<(), String> {
+    let at = notes.partition_point(|n| n.position <= note.position);
+    notes.insert(at, note);
+    fs::create_dir_all(project_dir).map_err(|e| format!("{}: {}", project_dir.display(), e))?;
+    let json = serde_json::to_string_pretty(notes).map_err(|e| e.to_string())?;
+    let path = path(project_dir);
+    fs::write(&path, json + "\n").map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Up to `rows` notes around `position`, the latest one passed a third of
+/// the way down so there's more to come than gone.
+pub fn lines(notes: &[Note], position: f64, rows: usize) -> Vec<Line<'static>> {
+    let passed = notes.partition_point(|n| n.position <= position);
+    let current = passed.checked_sub(1);
+    let start = current
+        .map_or(0, |c| c.saturating_sub(rows / 3))
+        .min(notes.len().saturating_sub(rows));
+    notes
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(rows)
+        .map(|(i, note)| {
+            let style = if Some(i) == current {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else if i < passed {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                Style::default()
+            };
+            let at = note
+                .bar
+                .clone()
+                .unwrap_or_else(|| format_position(note.position));
+            let author = note
+                .author
+                .as_ref()
+                .map_or_else(String::new, |a| format!("{}: ", a));
+            Line::from(vec![
+                Span::styled(format!("{:>7} ", at), Style::default().fg(Color::DarkGray)),
+                Span::styled(author, Style::default().fg(Color::Cyan)),
+                Span::styled(note.text.clone(), style),
+            ])
+        })
+        .collect()
+}
@@ -0,0 +1,36 @@
+//! One-line feedback for the last action ("Loop: bars 17–25", command
+//! errors), shown at the bottom of the screen until it times out.
+
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+/// Errors stay up longer so they can be read.
+const ERROR_TIMEOUT: Duration = Duration::from_secs(6);
+
+pub struct Notice {
+    pub text: String,
+    pub error: bool,
+    pub shown: Instant,
+}
+
+impl Notice {
+    pub fn info(text: impl Into<String>) -> Self {
+        Notice {
+            text: text.into(),
+            error: false,
+            shown: Instant::now(),
+        }
+    }
+
+    pub fn error(text: impl Into<String>) -> Self {
+        Notice {
+            error: true,
+            ..Notice::info(text)
+        }
+    }
+
+    pub fn expired(&self) -> bool {
+        let timeout = if self.error { ERROR_TIMEOUT } else { TIMEOUT };
+        self.shown.elapsed() > timeout
+    }
+}
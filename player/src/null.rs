@@ -0,0 +1,98 @@
+//! The null audio backend: an output that plays nothing by itself, but
+//! renders the engine's source into buffers when the host asks, in the
+//! file's own rate and channels. Its clock is the frames rendered rather
+//! than the wall clock, so the playhead, the meters and the audio come out
+//! the same on every run, on machines without a sound card too. The engine
+//! uses it when opened with [`crate::engine::Engine::open_null`], and the
+//! player binary's own tests run its UI's transport on it in place of a
+//! device.
+
+use cornwall_dsp::levels::chunk_levels;
+use rodio::Source;
+
+/// Meter chunk, as the player's level meters read.
+const METER_MS: u32 = 50;
+
+pub struct NullOutput {
+    source: Option<Box<dyn Source<Item = f32> + Send>>,
+    channels: u16,
+    sample_rate: u32,
+    /// Frames taken from the current source, the fake clock.
+    played: u64,
+    /// RMS of the first two channels over the last meter chunk rendered.
+    levels: (f64, f64),
+}
+
+impl NullOutput {
+    pub fn new(channels: u16, sample_rate: u32) -> Self {
+        NullOutput {
+            source: None,
+            channels: channels.max(1),
+            sample_rate: sample_rate.max(1),
+            played: 0,
+            levels: (0.0, 0.0),
+        }
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Play `source` from the next render on, in place of what was playing.
+    pub fn play(&mut self, source: impl Source<Item = f32> + Send + 'static) {
+        self.source = Some(Box::new(source));
+        self.played = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.source = None;
+        self.played = 0;
+        self.levels = (0.0, 0.0);
+    }
+
+    /// The next `frames` frames, interleaved. Silence once the source has
+    /// run out or when nothing is playing, as a device would get.
+    pub fn render(&mut self, frames: usize) -> Vec<f32> {
+        let channels = self.channels as usize;
+        let mut out = vec![0.0; frames * channels];
+        if let Some(source) = &mut self.source {
+            let mut taken = 0;
+            for (sample, next) in out.iter_mut().zip(source.by_ref()) {
+                *sample = next;
+                taken += 1;
+            }
+            self.played += (taken / channels) as u64;
+            if taken < out.len() {
+                self.source = None;
+            }
+        }
+        self.meter(&out);
+        out
+    }
+
+    /// Seconds of the current source rendered.
+    pub fn played_secs(&self) -> f64 {
+        self.played as f64 / self.sample_rate as f64
+    }
+
+    /// Left and right RMS, linear, as the status file's `level_l` and
+    /// `level_r`.
+    pub fn levels(&self) -> (f64, f64) {
+        self.levels
+    }
+
+    fn meter(&mut self, rendered: &[f32]) {
+        let channels = self.channels as usize;
+        let chunk = (self.sample_rate as usize * METER_MS as usize / 1000).max(1) * channels;
+        let start = rendered.len().saturating_sub(chunk);
+        let last: Vec<f64> = rendered[start..].iter().map(|&s| s as f64).collect();
+        let (left, right) = chunk_levels(&last, channels, self.sample_rate, METER_MS);
+        if let (Some(&l), Some(&r)) = (left.last(), right.last()) {
+            self.levels = (l, r);
+        }
+    }
+}
@@ -0,0 +1,394 @@
+//! Output device selection and channel routing.
+
+use crate::{
+    config::CueConfig,
+    program::{Bounds, Program},
+    resample::{Quality, Resample},
+    stretch,
+};
+use rodio::{
+    cpal::{traits::HostTrait, SampleRate},
+    DeviceTrait, OutputStream, OutputStreamHandle, Sink, Source,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+pub struct Output {
+    pub stream: OutputStream,
+    pub handle: OutputStreamHandle,
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+/// Open an output device by name (or the default device) with at least
+/// `min_channels` channels, optionally forcing its sample rate.
+pub fn open(
+    device: Option<&str>,
+    min_channels: u16,
+    sample_rate: Option<u32>,
+) -> Result<Output, String> {
+    let host = rodio::cpal::default_host();
+    let device = match device {
+        Some(name) => host
+            .output_devices()
+            .map_err(|e| e.to_string())?
+            .find(|d| d.name().map(|n| n.contains(name)).unwrap_or(false))
+            .ok_or_else(|| format!("no output device matching \"{}\"", name))?,
+        None => host
+            .default_output_device()
+            .ok_or("no default output device")?,
+    };
+    let device_name = device.name().unwrap_or_default();
+
+    let default = device.default_output_config().map_err(|e| e.to_string())?;
+    let rate = sample_rate.map(SampleRate).unwrap_or(default.sample_rate());
+    let config = if default.channels() >= min_channels && default.sample_rate() == rate {
+        default
+    } else {
+        device
+            .supported_output_configs()
+            .map_err(|e| e.to_string())?
+            .filter(|c| {
+                c.channels() >= min_channels
+                    && c.min_sample_rate() <= rate
+                    && c.max_sample_rate() >= rate
+            })
+            .min_by_key(|c| c.channels())
+            .map(|c| c.with_sample_rate(rate))
+            .ok_or_else(|| {
+                format!(
+                    "\"{}\" has no output config with {} channels at {} Hz",
+                    device_name, min_channels, rate.0
+                )
+            })?
+    };
+
+    let channels = config.channels();
+    let sample_rate = config.sample_rate().0;
+    let (stream, handle) =
+        OutputStream::try_from_device_config(&device, config).map_err(|e| e.to_string())?;
+
+    Ok(Output {
+        stream,
+        handle,
+        channels,
+        sample_rate,
+    })
+}
+
+/// What the main and preview sinks play on, and the transport's clock.
+/// The player runs on a [`Stream`]; the tests put in the null output,
+/// which plays only as they render it and keeps time by what it rendered.
+pub trait Device {
+    /// Empty `sink`. Clearing waits for the output to let go of what was
+    /// playing.
+    fn clear(&mut self, sink: &Sink) {
+        sink.clear();
+    }
+
+    /// Now, on the clock the playhead runs on.
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    /// Whether the output plays in real time. One that renders as fast as
+    /// it's asked has sources read from disk wait for the reader instead
+    /// of playing silence where it's behind.
+    fn realtime(&self) -> bool {
+        true
+    }
+}
+
+/// An output device, open for as long as this is kept.
+pub struct Stream {
+    _stream: OutputStream,
+}
+
+impl Device for Stream {}
+
+/// The player's main and preview sinks on an output.
+pub struct Sinks {
+    pub device: Box<dyn Device>,
+    /// Starts paused, for the transport to start.
+    pub main: Sink,
+    pub preview: Sink,
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+impl Sinks {
+    /// Open the device as [`open`] does, with the main mix on the 1-based
+    /// `main` channels.
+    pub fn open(
+        device: Option<&str>,
+        main: [u16; 2],
+        sample_rate: Option<u32>,
+    ) -> Result<Self, String> {
+        let output = open(device, main[0].max(main[1]), sample_rate)?;
+        validate_map(main, output.channels, "main")?;
+        let sink_error = |e: rodio::PlayError| format!("cannot create audio sink: {}", e);
+        let main = Sink::try_new(&output.handle).map_err(sink_error)?;
+        main.pause();
+        let preview = Sink::try_new(&output.handle).map_err(sink_error)?;
+        Ok(Sinks {
+            device: Box::new(Stream {
+                _stream: output.stream,
+            }),
+            main,
+            preview,
+            channels: output.channels,
+            sample_rate: output.sample_rate,
+        })
+    }
+}
+
+/// Places a mono or stereo source onto two chosen channels of a wider
+/// output frame. Unmapped channels are silent.
+pub struct ChannelMap<S> {
+    inner: S,
+    map: [usize; 2],
+    frame: Vec<f32>,
+    pos: usize,
+}
+
+impl<S: Source<Item = f32>> ChannelMap<S> {
+    /// `map` holds 1-based output channels for left and right.
+    pub fn new(inner: S, map: [u16; 2], out_channels: u16) -> Self {
+        let frame = vec![0.0; out_channels as usize];
+        let pos = frame.len();
+        ChannelMap {
+            inner,
+            map: [map[0] as usize - 1, map[1] as usize - 1],
+            frame,
+            pos,
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for ChannelMap<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.pos == self.frame.len() {
+            let in_channels = self.inner.channels();
+            let l = self.inner.next()?;
+            let r = if in_channels > 1 {
+                let r = self.inner.next().unwrap_or(0.0);
+                for _ in 2..in_channels {
+                    self.inner.next();
+                }
+                r
+            } else {
+                l
+            };
+            self.frame.fill(0.0);
+            self.frame[self.map[0]] += l;
+            self.frame[self.map[1]] += r;
+            self.pos = 0;
+        }
+        let sample = self.frame[self.pos];
+        self.pos += 1;
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for ChannelMap<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.frame.len() as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+/// Folds a stereo source to mono while the shared flag is set, so the
+/// switch takes effect without restarting playback.
+pub struct MonoFold<S> {
+    inner: S,
+    mono: Arc<AtomicBool>,
+    right: Option<f32>,
+}
+
+impl<S: Source<Item = f32>> MonoFold<S> {
+    pub fn new(inner: S, mono: Arc<AtomicBool>) -> Self {
+        MonoFold {
+            inner,
+            mono,
+            right: None,
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for MonoFold<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.inner.channels() != 2 {
+            return self.inner.next();
+        }
+        if let Some(r) = self.right.take() {
+            return Some(r);
+        }
+        let l = self.inner.next()?;
+        let r = self.inner.next().unwrap_or(0.0);
+        if self.mono.load(Ordering::Relaxed) {
+            let m = (l + r) * 0.5;
+            self.right = Some(m);
+            Some(m)
+        } else {
+            self.right = Some(r);
+            Some(l)
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Source for MonoFold<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+/// Check a 1-based channel pair against the device's channel count.
+pub fn validate_map(map: [u16; 2], channels: u16, what: &str) -> Result<(), String> {
+    if map.iter().any(|&c| c == 0 || c > channels) {
+        return Err(format!(
+            "{} channels {}/{} out of range (device has {})",
+            what, map[0], map[1], channels
+        ));
+    }
+    Ok(())
+}
+
+/// A second output (e.g. headphones) that plays alongside the main mix at
+/// its own level.
+pub struct CueOutput {
+    _stream: OutputStream,
+    channels: u16,
+    sample_rate: u32,
+    quality: Quality,
+    stretch: stretch::Quality,
+    map: [u16; 2],
+    sink: Sink,
+    pub level_db: f32,
+}
+
+impl CueOutput {
+    pub fn open(
+        config: &CueConfig,
+        quality: Quality,
+        stretch: stretch::Quality,
+    ) -> Result<Self, String> {
+        let map = config.channels;
+        let output = open(config.device.as_deref(), map[0].max(map[1]), None)?;
+        validate_map(map, output.channels, "cue")?;
+        let sink = Sink::try_new(&output.handle).map_err(|e| e.to_string())?;
+        Ok(CueOutput {
+            _stream: output.stream,
+            channels: output.channels,
+            sample_rate: output.sample_rate,
+            quality,
+            stretch,
+            map,
+            sink,
+            level_db: config.level_db,
+        })
+    }
+
+    /// Play `program` from `offset` within `bounds`, at `speed` and shifted
+    /// `semitones`, replacing whatever was queued.
+    pub fn play(
+        &mut self,
+        program: &Program,
+        offset: f64,
+        bounds: Bounds,
+        speed: f64,
+        semitones: i32,
+    ) {
+        self.sink.clear();
+        let source = Resample::new(
+            stretch::shift(
+                program.source(offset, bounds),
+                speed,
+                semitones,
+                self.stretch,
+            ),
+            self.sample_rate,
+            self.quality,
+        );
+        self.sink
+            .append(ChannelMap::new(source, self.map, self.channels));
+        self.sink.set_volume(db_to_gain(self.level_db));
+        self.sink.play();
+    }
+
+    pub fn stop(&mut self) {
+        self.sink.clear();
+    }
+
+    pub fn pause(&mut self) {
+        self.sink.pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.sink.play();
+    }
+
+    pub fn nudge_level(&mut self, db: f32) {
+        self.level_db = (self.level_db + db).clamp(-60.0, 12.0);
+        self.sink.set_volume(db_to_gain(self.level_db));
+    }
+}
+
+/// Monitor controller for the main output: volume trim plus mute and dim.
+#[derive(Default)]
+pub struct Monitor {
+    pub volume_db: f32,
+    pub muted: bool,
+    pub dimmed: bool,
+}
+
+impl Monitor {
+    pub const DIM_DB: f32 = -20.0;
+
+    pub fn gain(&self) -> f32 {
+        if self.muted {
+            return 0.0;
+        }
+        let dim = if self.dimmed { Self::DIM_DB } else { 0.0 };
+        db_to_gain(self.volume_db + dim)
+    }
+
+    pub fn nudge(&mut self, db: f32) {
+        self.volume_db = (self.volume_db + db).clamp(-60.0, 12.0);
+    }
+}
+
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
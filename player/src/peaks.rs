@@ -0,0 +1,70 @@
+//! Level envelopes of each track's source for the mini waveforms in the
+//! track list, so stems can be told apart without soloing them. Reading
+//! every stem at startup is slow, so envelopes are cached in
+//! `state/.peaks/`, keyed on the file's path, size and modification time;
+//! a changed file is read again.
+
+use crate::TrackState;
+use cornwall_dsp::levels::chunk_levels;
+use cornwall_player::wav;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// Length of each envelope point.
+const CHUNK_MS: u32 = 100;
+
+/// Envelopes of the tracks with an audio source, by track ID. Sources that
+/// can't be read get none.
+pub fn load_tracks(state_dir: &Path, tracks: &[TrackState]) -> HashMap<u32, Vec<f64>> {
+    tracks
+        .iter()
+        .filter(|t| t.track_type != "midi")
+        .filter_map(|t| Some((t.id, load(state_dir, Path::new(t.source.as_deref()?))?)))
+        .collect()
+}
+
+/// The cached envelope of `source`, reading the file when the cache is
+/// missing or stale.
+fn load(state_dir: &Path, source: &Path) -> Option<Vec<f64>> {
+    let cached = cache_path(state_dir, source)?;
+    if let Some(levels) = fs::read_to_string(&cached)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+    {
+        return Some(levels);
+    }
+    let (spec, samples) = wav::read(source).ok()?;
+    let (left, right) = chunk_levels(&samples, spec.channels as usize, spec.sample_rate, CHUNK_MS);
+    let levels: Vec<f64> = left
+        .iter()
+        .zip(&right)
+        // Three places is plenty for a few cells of height
+        .map(|(l, r)| (l.max(*r) * 1000.0).round() / 1000.0)
+        .collect();
+    // A cache that can't be written only costs the next startup a read
+    if fs::create_dir_all(cached.parent()?).is_ok() {
+        if let Ok(json) = serde_json::to_string(&levels) {
+            let _ = fs::write(&cached, json);
+        }
+    }
+    Some(levels)
+}
+
+fn cache_path(state_dir: &Path, source: &Path) -> Option<PathBuf> {
+    let meta = fs::metadata(source).ok()?;
+    let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    meta.len().hash(&mut hasher);
+    modified.as_nanos().hash(&mut hasher);
+    Some(
+        state_dir
+            .join(".peaks")
+            .join(format!("{:016x}.json", hasher.finish())),
+    )
+}
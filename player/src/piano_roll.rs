@@ -0,0 +1,171 @@
+//! Piano roll of a MIDI track's notes scrolling past the playhead, shown
+//! under the level overview while a MIDI track is selected. Pitch runs
+//! bottom to top over the notes the file uses; half blocks give two pitch
+//! rows per line.
+
+use crate::TrackState;
+use midly::{MetaMessage, MidiMessage, Smf, Timing, TrackEventKind};
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+use std::{cmp::Ordering, collections::HashMap, fs, path::Path};
+
+/// Piano rolls of the tracks whose source is a MIDI file, by track ID.
+/// Files that don't parse get no pane.
+pub fn load_tracks(tracks: &[TrackState]) -> HashMap<u32, PianoRoll> {
+    tracks
+        .iter()
+        .filter_map(|t| {
+            let source = Path::new(t.source.as_deref()?);
+            let ext = source.extension()?.to_str()?.to_ascii_lowercase();
+            if ext != "mid" && ext != "midi" {
+                return None;
+            }
+            Some((t.id, PianoRoll::load(source).ok()?))
+        })
+        .collect()
+}
+
+/// Seconds shown across the pane.
+const WINDOW_SECS: f64 = 8.0;
+/// Share of the window before the playhead.
+const LEAD: f64 = 0.125;
+
+struct Note {
+    start: f64,
+    end: f64,
+    key: u8,
+}
+
+pub struct PianoRoll {
+    notes: Vec<Note>,
+    low: u8,
+    high: u8,
+}
+
+impl PianoRoll {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let smf = Smf::parse(&bytes).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        // All tracks on one timeline, so tempo changes in the first track of
+        // a type 1 file apply to the others
+        let mut events = Vec::new();
+        for track in &smf.tracks {
+            let mut tick = 0_u64;
+            for event in track {
+                tick += event.delta.as_int() as u64;
+                events.push((tick, event.kind));
+            }
+        }
+        events.sort_by_key(|(tick, _)| *tick);
+
+        let (ticks_per_beat, mut secs_per_tick) = match smf.header.timing {
+            Timing::Metrical(tpq) => {
+                let tpq = tpq.as_int().max(1) as f64;
+                (Some(tpq), 0.5 / tpq)
+            }
+            Timing::Timecode(fps, subframes) => {
+                (None, 1.0 / (fps.as_f32() as f64 * subframes.max(1) as f64))
+            }
+        };
+        let mut notes = Vec::new();
+        let mut sounding: HashMap<(u8, u8), f64> = HashMap::new();
+        let (mut secs, mut last_tick) = (0.0, 0);
+        for (tick, kind) in events {
+            secs += (tick - last_tick) as f64 * secs_per_tick;
+            last_tick = tick;
+            match kind {
+                TrackEventKind::Midi { channel, message } => {
+                    let (key, on) = match message {
+                        MidiMessage::NoteOn { key, vel } => (key.as_int(), vel.as_int() > 0),
+                        MidiMessage::NoteOff { key, .. } => (key.as_int(), false),
+                        _ => continue,
+                    };
+                    let id = (channel.as_int(), key);
+                    if on {
+                        sounding.entry(id).or_insert(secs);
+                    } else if let Some(start) = sounding.remove(&id) {
+                        notes.push(Note {
+                            start,
+                            end: secs,
+                            key,
+                        });
+                    }
+                }
+                TrackEventKind::Meta(MetaMessage::Tempo(us_per_beat)) => {
+                    if let Some(tpq) = ticks_per_beat {
+                        secs_per_tick = us_per_beat.as_int() as f64 / 1_000_000.0 / tpq;
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Notes left hanging run to the end of the file
+        for ((_, key), start) in sounding {
+            notes.push(Note {
+                start,
+                end: secs,
+                key,
+            });
+        }
+
+        let low = notes.iter().map(|n| n.key).min().unwrap_or(60);
+        let high = notes.iter().map(|n| n.key).max().unwrap_or(72);
+        Ok(PianoRoll { notes, low, high })
+    }
+
+    /// `rows` lines of `width` cells around `position`, upcoming notes
+    /// bright, played ones dim and notes under the playhead white.
+    pub fn lines(&self, position: f64, width: usize, rows: usize) -> Vec<Line<'static>> {
+        let start = position - WINDOW_SECS * LEAD;
+        let cell_secs = WINDOW_SECS / width.max(1) as f64;
+        let end = start + WINDOW_SECS;
+        let visible: Vec<&Note> = self
+            .notes
+            .iter()
+            .filter(|n| n.end > start && n.start < end)
+            .collect();
+
+        // Pitch rows (two per line), bottom up; a wide range folds several
+        // keys into a row
+        let pitch_rows = rows * 2;
+        let span = (self.high - self.low) as usize + 1;
+        let keys_per_row = span.div_ceil(pitch_rows.max(1)).max(1);
+        let centre = (pitch_rows * keys_per_row).saturating_sub(span) / 2;
+        let lit = |cell: usize, pitch_row: usize| {
+            let from = start + cell as f64 * cell_secs;
+            let to = from + cell_secs;
+            visible.iter().any(|n| {
+                let row = ((n.key - self.low) as usize + centre) / keys_per_row;
+                row == pitch_row && n.start < to && n.end > from
+            })
+        };
+        let playhead = (WINDOW_SECS * LEAD / cell_secs) as usize;
+
+        (0..rows)
+            .map(|line| {
+                let top = pitch_rows - 1 - line * 2;
+                let spans = (0..width)
+                    .map(|cell| {
+                        let glyph = match (lit(cell, top), lit(cell, top - 1)) {
+                            (true, true) => '█',
+                            (true, false) => '▀',
+                            (false, true) => '▄',
+                            (false, false) if cell == playhead => '│',
+                            (false, false) => ' ',
+                        };
+                        let color = match cell.cmp(&playhead) {
+                            Ordering::Less => Color::DarkGray,
+                            Ordering::Equal => Color::White,
+                            Ordering::Greater => Color::Cyan,
+                        };
+                        Span::styled(glyph.to_string(), Style::default().fg(color))
+                    })
+                    .collect::<Vec<_>>();
+                Line::from(spans)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,63 @@
+//! Practice mode: `:practice 17 24` loops bars 17-24 starting slow and
+//! speeds up by `step` every `every` times round, up to `target`:
+//!
+//! ```json
+//! "practice": {"start": 0.7, "step": 0.05, "every": 2, "target": 1.0}
+//! ```
+//!
+//! Those are the defaults. `:practice off` ends it at the speed reached.
+
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct PracticeConfig {
+    /// Speed of the first pass.
+    pub start: f64,
+    /// Added every `every` passes.
+    pub step: f64,
+    pub every: u32,
+    /// Speed to stop speeding up at.
+    pub target: f64,
+}
+
+impl Default for PracticeConfig {
+    fn default() -> Self {
+        PracticeConfig {
+            start: 0.7,
+            step: 0.05,
+            every: 2,
+            target: 1.0,
+        }
+    }
+}
+
+pub struct Practice {
+    config: PracticeConfig,
+    /// Passes at the current speed.
+    laps: u32,
+}
+
+impl Practice {
+    pub fn new(config: PracticeConfig) -> Self {
+        Practice { config, laps: 0 }
+    }
+
+    pub fn start(&self) -> f64 {
+        self.config.start
+    }
+
+    /// Count a pass at `speed`. Returns the speed to go to when it's time
+    /// for the next step.
+    pub fn lap(&mut self, speed: f64) -> Option<f64> {
+        if speed >= self.config.target {
+            return None;
+        }
+        self.laps += 1;
+        if self.laps < self.config.every.max(1) {
+            return None;
+        }
+        self.laps = 0;
+        Some((speed + self.config.step).min(self.config.target))
+    }
+}
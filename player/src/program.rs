@@ -0,0 +1,376 @@
+//! Program material played from disk by default: each source reads the
+//! file on a thread of its own into a ring a second ahead of where it
+//! plays ([`wav::ReadAhead`]), so only that much is held per source however
+//! long the session, and the audio thread never waits on the disk. The
+//! reader goes round a loop and stops at the end of a region on their exact
+//! samples, as the audio will.
+//!
+//! With a memory budget (`"memory": {"budget_mb": 1024}` in the player
+//! config), material that fits in it is decoded into memory once instead,
+//! so a source starts anywhere without opening the file. It decodes on a
+//! background thread a block at a time, and sources play whatever is in so
+//! far: playback starts with the first block instead of waiting for the
+//! whole file, and a seek past what's decoded plays silence until the
+//! loader gets there. Either way the loader reads the whole file once, for
+//! whoever wants the blocks (the level meter).
+
+use cornwall_player::wav;
+use rodio::Source;
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Decoded per block, and how often sources see new material.
+const BLOCK_SECS: f64 = 0.5;
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct MemoryConfig {
+    /// Decoded audio kept in memory, in MB. Files play from disk unless
+    /// they fit in it, so none are held by default.
+    pub budget_mb: usize,
+}
+
+impl MemoryConfig {
+    pub fn bytes(&self) -> usize {
+        self.budget_mb.saturating_mul(1 << 20)
+    }
+}
+
+/// Where playback goes at the end of a region.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Bounds {
+    /// On to the end of the material.
+    Open,
+    /// Back from the end to the start.
+    Loop(f64, f64),
+    /// Stop there.
+    Until(f64),
+}
+
+/// Interleaved samples filling in from the loader while sources read them.
+pub struct Samples {
+    /// `f32` bits, each written once before `ready` covers it.
+    data: Box<[AtomicU32]>,
+    /// Samples decoded so far.
+    ready: AtomicUsize,
+    /// Where the material ends: the header's length until the loader finds
+    /// the file shorter.
+    len: AtomicUsize,
+}
+
+impl Samples {
+    fn empty(len: usize) -> Self {
+        Samples {
+            data: (0..len).map(|_| AtomicU32::new(0)).collect(),
+            ready: AtomicUsize::new(0),
+            len: AtomicUsize::new(len),
+        }
+    }
+
+    /// Sample `i`: silence while it's still to be decoded, `None` past the
+    /// end.
+    fn get(&self, i: usize) -> Option<f32> {
+        if i >= self.len.load(Ordering::Relaxed) {
+            return None;
+        }
+        if i >= self.ready.load(Ordering::Acquire) {
+            return Some(0.0);
+        }
+        Some(f32::from_bits(self.data[i].load(Ordering::Relaxed)))
+    }
+
+    /// Bytes held.
+    pub fn memory(&self) -> usize {
+        self.data.len() * size_of::<AtomicU32>()
+    }
+}
+
+impl From<Vec<f32>> for Samples {
+    fn from(samples: Vec<f32>) -> Self {
+        let len = samples.len();
+        Samples {
+            data: samples
+                .into_iter()
+                .map(|s| AtomicU32::new(s.to_bits()))
+                .collect(),
+            ready: AtomicUsize::new(len),
+            len: AtomicUsize::new(len),
+        }
+    }
+}
+
+/// A decoded file, shared by every source playing it.
+pub struct Program {
+    samples: Arc<Samples>,
+    /// The file, when it plays from disk instead.
+    disk: Option<PathBuf>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl Program {
+    /// Decode `path`, or play it from disk if it needs more than `budget`
+    /// bytes.
+    pub fn load(path: &Path, budget: usize) -> Result<Self, String> {
+        Self::open(path, budget, 1, None::<fn(&[f64])>)
+    }
+
+    /// Start decoding `path` in the background, handing each block to
+    /// `block` as well once it's playable. Blocks are whole multiples of
+    /// `chunk` frames, bar the last. Only the header is read here. Over
+    /// `budget` bytes, the blocks only go to `block` and sources read the
+    /// file themselves.
+    pub fn stream(
+        path: &Path,
+        budget: usize,
+        chunk: usize,
+        block: impl FnMut(&[f64]) + Send + 'static,
+    ) -> Result<Self, String> {
+        Self::open(path, budget, chunk, Some(block))
+    }
+
+    /// [`Self::stream`], without reading the file at all when it plays from
+    /// disk and nothing wants the blocks.
+    fn open(
+        path: &Path,
+        budget: usize,
+        chunk: usize,
+        mut block: Option<impl FnMut(&[f64]) + Send + 'static>,
+    ) -> Result<Self, String> {
+        let reader =
+            hound::WavReader::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let spec = reader.spec();
+        let len = reader.len() as usize;
+        let disk = (len * size_of::<AtomicU32>() > budget).then(|| path.to_path_buf());
+        let samples = Arc::new(Samples::empty(if disk.is_some() { 0 } else { len }));
+        let program = Program {
+            samples,
+            disk,
+            channels: spec.channels,
+            sample_rate: spec.sample_rate,
+        };
+        if program.disk.is_some() && block.is_none() {
+            return Ok(program);
+        }
+        let loading = program.samples.clone();
+        let chunk = chunk.max(1);
+        let frames = ((BLOCK_SECS * spec.sample_rate as f64) as usize).div_ceil(chunk) * chunk;
+        thread::Builder::new()
+            .name("program-loader".into())
+            .spawn(move || {
+                let mut written = 0;
+                wav::read_blocks(reader, frames, |decoded| {
+                    for (slot, &s) in loading.data[written..].iter().zip(decoded) {
+                        slot.store((s as f32).to_bits(), Ordering::Relaxed);
+                    }
+                    written = (written + decoded.len()).min(loading.data.len());
+                    loading.ready.store(written, Ordering::Release);
+                    if let Some(block) = &mut block {
+                        block(decoded);
+                    }
+                    // Nobody left to play it after a switch to another file
+                    Arc::strong_count(&loading) > 1
+                });
+                if written < loading.data.len() && Arc::strong_count(&loading) > 1 {
+                    loading.len.store(written, Ordering::Relaxed);
+                }
+            })
+            .map_err(|e| e.to_string())?;
+        Ok(program)
+    }
+
+    /// Playback from `offset` seconds within `bounds`.
+    pub fn source(&self, offset: f64, bounds: Bounds) -> Playback {
+        let looped =
+            |material| Looped::with(material, self.channels, self.sample_rate, offset, bounds);
+        match &self.disk {
+            Some(path) => match wav::Stream::open(path) {
+                Ok(stream) => Playback::Disk(wav::ReadAhead::new(looped(Material::Disk(stream)))),
+                // Gone since it was checked: plays as empty
+                Err(_) => Playback::Memory(looped(Material::Memory(Arc::new(Samples::empty(0))))),
+            },
+            None => Playback::Memory(looped(Material::Memory(self.samples.clone()))),
+        }
+    }
+
+    /// Bytes of decoded audio held, none when playing from disk.
+    pub fn memory(&self) -> usize {
+        self.samples.memory()
+    }
+
+    pub fn on_disk(&self) -> bool {
+        self.disk.is_some()
+    }
+}
+
+/// A source from [`Program::source`].
+pub enum Playback {
+    Memory(Looped),
+    /// A [`Looped`] over the file, run by the reader.
+    Disk(wav::ReadAhead),
+}
+
+impl Playback {
+    /// Wait for the disk rather than play silence, as [`wav::ReadAhead::waiting`].
+    pub fn waiting(self) -> Self {
+        match self {
+            Playback::Disk(disk) => Playback::Disk(disk.waiting()),
+            memory => memory,
+        }
+    }
+}
+
+impl Iterator for Playback {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        match self {
+            Playback::Memory(looped) => looped.next(),
+            Playback::Disk(disk) => disk.next(),
+        }
+    }
+}
+
+impl Source for Playback {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        match self {
+            Playback::Memory(looped) => looped.channels(),
+            Playback::Disk(disk) => disk.channels(),
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        match self {
+            Playback::Memory(looped) => looped.sample_rate(),
+            Playback::Disk(disk) => disk.sample_rate(),
+        }
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Where a source's samples come from.
+enum Material {
+    Memory(Arc<Samples>),
+    Disk(wav::Stream),
+}
+
+impl Material {
+    fn get(&mut self, i: usize) -> Option<f32> {
+        match self {
+            Material::Memory(samples) => samples.get(i),
+            Material::Disk(disk) => disk.get(i),
+        }
+    }
+}
+
+/// Interleaved samples played from memory or disk, jumping from the end of
+/// the loop back to its start or stopping at the end of the region.
+pub struct Looped {
+    material: Material,
+    channels: u16,
+    sample_rate: u32,
+    /// Index of the next sample.
+    pos: usize,
+    /// Sample indices of the loop's start and end.
+    span: Option<(usize, usize)>,
+    /// Sample index to stop at.
+    end: Option<usize>,
+}
+
+impl Looped {
+    pub fn new(
+        samples: Arc<Samples>,
+        channels: u16,
+        sample_rate: u32,
+        offset: f64,
+        bounds: Bounds,
+    ) -> Self {
+        Self::with(
+            Material::Memory(samples),
+            channels,
+            sample_rate,
+            offset,
+            bounds,
+        )
+    }
+
+    fn with(
+        material: Material,
+        channels: u16,
+        sample_rate: u32,
+        offset: f64,
+        bounds: Bounds,
+    ) -> Self {
+        // Whole frames, so a wrap never swaps the channels
+        let index =
+            |secs: f64| (secs.max(0.0) * sample_rate as f64).round() as usize * channels as usize;
+        Looped {
+            material,
+            channels,
+            sample_rate,
+            pos: index(offset),
+            span: match bounds {
+                Bounds::Loop(start, end) => Some((index(start), index(end))),
+                _ => None,
+            }
+            .filter(|(start, end)| end > start),
+            end: match bounds {
+                Bounds::Until(end) => Some(index(end)),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl Iterator for Looped {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = match self.span {
+            Some((start, end)) => {
+                if self.pos >= end {
+                    self.pos = start;
+                }
+                // A loop running past the material plays silence there
+                Some(self.material.get(self.pos).unwrap_or(0.0))
+            }
+            None if self.end.is_some_and(|end| self.pos >= end) => None,
+            None => self.material.get(self.pos),
+        };
+        self.pos += 1;
+        sample
+    }
+}
+
+impl Source for Looped {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
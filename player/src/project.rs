@@ -0,0 +1,80 @@
+//! Cornwall's project state as the Python side writes it
+//! (`state/project.json`, `state/tracks.json`), and which audio file a
+//! project plays.
+
+use crate::json;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct ProjectState {
+    pub name: String,
+    pub bpm: f64,
+    pub sample_rate: u32,
+    pub time_sig: String,
+}
+
+impl ProjectState {
+    /// The project in `state_dir`, or an empty one if there is none.
+    pub fn load(state_dir: &Path) -> Result<Self, String> {
+        json::load(&state_dir.join("project.json"))
+    }
+
+    /// `projects/<name>`, next to the state directory.
+    pub fn dir(&self, state_dir: &Path) -> PathBuf {
+        state_dir
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join("projects")
+            .join(&self.name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct TrackState {
+    pub id: u32,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub track_type: String,
+    pub source: Option<String>,
+    pub volume: f64,
+    pub pan: f64,
+    pub mute: bool,
+    pub solo: bool,
+}
+
+pub fn load_tracks(state_dir: &Path) -> Result<Vec<TrackState>, String> {
+    json::load(&state_dir.join("tracks.json"))
+}
+
+/// The project's `mix.wav`, or a track's source when nothing has been
+/// mixed yet.
+pub fn audio_file(state_dir: &Path) -> Result<Option<PathBuf>, String> {
+    let mix = ProjectState::load(state_dir)?
+        .dir(state_dir)
+        .join("mix.wav");
+    if mix.exists() {
+        return Ok(Some(mix));
+    }
+    Ok(fallback_source(&load_tracks(state_dir)?))
+}
+
+/// The track to play when there is no mix: a soloed track if any are
+/// soloed, else the first unmuted one, else any track with audio. MIDI
+/// sources aren't audio files.
+fn fallback_source(tracks: &[TrackState]) -> Option<PathBuf> {
+    let any_solo = tracks.iter().any(|t| t.solo);
+    let audible = |t: &&TrackState| if any_solo { t.solo } else { !t.mute };
+    let existing = |t: &TrackState| {
+        t.source
+            .as_ref()
+            .filter(|_| t.track_type != "midi")
+            .map(PathBuf::from)
+            .filter(|p| p.exists())
+    };
+    tracks
+        .iter()
+        .filter(audible)
+        .find_map(existing)
+        .or_else(|| tracks.iter().find_map(existing))
+}
@@ -0,0 +1,48 @@
+//! Launch quantization: with `"quantize": "beat"` or `"bar"` in the player
+//! config, loop toggles and marker jumps made while playing wait for the
+//! next beat or bar, and play starts from the beat or bar the playhead is
+//! in, so rehearsal jumps land on the grid like launching a clip.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Quantize {
+    /// Everything happens at once.
+    #[default]
+    Off,
+    Beat,
+    Bar,
+}
+
+impl Quantize {
+    /// Length of the grid step in seconds for a beat and bar length, or
+    /// `None` when off.
+    pub fn step(&self, beat_secs: f64, bar_secs: f64) -> Option<f64> {
+        match self {
+            Quantize::Off => None,
+            Quantize::Beat => Some(beat_secs),
+            Quantize::Bar => Some(bar_secs),
+        }
+    }
+}
+
+/// A transport change waiting for its grid line.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Queued {
+    ToggleLoop,
+    Seek(f64),
+}
+
+/// The first grid line after `position`.
+pub fn next(position: f64, step: f64) -> f64 {
+    // A line only just gone counts as passed, so a press right on the beat
+    // doesn't fire early
+    ((position + 1e-6) / step).ceil() * step
+}
+
+/// The grid line at or before `position`.
+pub fn previous(position: f64, step: f64) -> f64 {
+    ((position + 1e-6) / step).floor() * step
+}
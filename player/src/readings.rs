@@ -0,0 +1,110 @@
+//! Meter freeze for mix notes. `F` holds the level meters, scopes and
+//! loudness gauge on what they show and writes the readings as text to
+//! `state/notes/meters-<unix time>.txt`; `F` again lets them run. There is
+//! no spectrum analyser, so the tone is given as the brightness the
+//! waveform is coloured by.
+
+use crate::scope;
+use cornwall_dsp::levels::dbfs;
+use cornwall_player::widgets::format_position;
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// What the meters and analysis panes show at one moment.
+#[derive(Clone, Default)]
+pub struct Readings {
+    /// Seconds into the file.
+    pub position: f64,
+    /// RMS levels, 0 to 1.
+    pub level: (f64, f64),
+    /// Levels the VU needles show, before the reference.
+    pub vu: (f64, f64),
+    /// Momentary, short-term and integrated LUFS.
+    pub loudness: (f64, f64, f64),
+    /// The oscilloscope's window.
+    pub scope: Vec<[f32; 2]>,
+    /// The frames the vectorscope plots.
+    pub vector: Vec<[f32; 2]>,
+    /// Brightness at the playhead in Hz, 0 in silence.
+    pub brightness: f64,
+}
+
+impl Readings {
+    /// The readings as text, with the loudness `target` and VU
+    /// `reference_dbfs` they were shown against.
+    pub fn report(&self, file: &Path, target: f64, reference_dbfs: f64) -> String {
+        let peak = |channel: usize| {
+            let peak = self
+                .vector
+                .iter()
+                .map(|frame| frame[channel].abs())
+                .fold(0.0f32, f32::max);
+            decibels(dbfs(peak as f64))
+        };
+        let vu = |level: f64| decibels(dbfs(level) - reference_dbfs);
+        let (momentary, short_term, integrated) = self.loudness;
+        let correlation =
+            scope::correlation(&self.vector).map_or("--".to_string(), |r| format!("{:+.2}", r));
+        let brightness = if self.brightness > 0.0 {
+            format!("{:.0} Hz", self.brightness)
+        } else {
+            "--".to_string()
+        };
+
+        let mut text = String::new();
+        let _ = writeln!(text, "File:         {}", file.display());
+        let _ = writeln!(text, "Position:     {}", format_position(self.position));
+        let _ = writeln!(
+            text,
+            "RMS L/R:      {} / {} dBFS",
+            decibels(dbfs(self.level.0)),
+            decibels(dbfs(self.level.1))
+        );
+        let _ = writeln!(
+            text,
+            "Peak L/R:     {} / {} dBFS (last {:.0} ms)",
+            peak(0),
+            peak(1),
+            scope::VECTOR_MS
+        );
+        let _ = writeln!(
+            text,
+            "VU L/R:       {} / {} VU (0 VU = {:.0} dBFS)",
+            vu(self.vu.0),
+            vu(self.vu.1),
+            reference_dbfs
+        );
+        let _ = writeln!(
+            text,
+            "Loudness:     M {} / S {} / I {} LUFS (target {:.0})",
+            decibels(momentary),
+            decibels(short_term),
+            decibels(integrated),
+            target
+        );
+        let _ = writeln!(text, "Correlation:  {}", correlation);
+        let _ = writeln!(text, "Brightness:   {}", brightness);
+        text
+    }
+}
+
+/// Writes `report` to a new file under `state/notes/`, returning its path.
+pub fn save(state_dir: &Path, report: &str, unix_time: f64) -> Result<PathBuf, String> {
+    let dir = state_dir.join("notes");
+    fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let path = dir.join(format!("meters-{}.txt", unix_time as u64));
+    fs::write(&path, report).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// A level to one place, or `-inf` for silence.
+fn decibels(db: f64) -> String {
+    if db > -100.0 {
+        format!("{:.1}", db)
+    } else {
+        "-inf".to_string()
+    }
+}
@@ -0,0 +1,94 @@
+//! Crash recovery. Every few seconds the player checkpoints the session to
+//! `state/.player-recovery.json`: the file and where it was in it, the
+//! transport, loop, speed and pitch, the selected track, a quantized change
+//! still waiting for its beat, and any unsaved track and marker edits. A
+//! clean exit removes the file, so finding one left by a player that is no
+//! longer running means it crashed or was killed, and the next player
+//! offers to restore the session as it was.
+
+use crate::{
+    i18n::{tr, trf},
+    lock,
+    markers::Marker,
+    quantize::Queued,
+};
+use cornwall_player::{json, project::TrackState, widgets::format_position};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
+
+/// How often the session is checkpointed.
+pub const INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The player that wrote it.
+    pub pid: u32,
+    pub audio_file: PathBuf,
+    /// Track auditioned in place of the mix.
+    pub audition: Option<u32>,
+    pub position: f64,
+    pub playing: bool,
+    pub looping: bool,
+    pub loop_region: Option<(u32, u32)>,
+    /// Where playing the loop region once was to stop.
+    pub play_once: Option<f64>,
+    pub speed: f64,
+    pub semitones: i32,
+    pub selected_track: usize,
+    pub queued: Option<Queued>,
+    /// Present when there were unsaved edits.
+    pub edits: Option<Edits>,
+}
+
+/// Tracks and markers as edited but not saved.
+#[derive(Serialize, Deserialize)]
+pub struct Edits {
+    pub tracks: Vec<TrackState>,
+    pub markers: Vec<Marker>,
+}
+
+impl Checkpoint {
+    /// The question put to the user at startup.
+    pub fn prompt(&self) -> String {
+        let edits = if self.edits.is_some() {
+            tr("recovery.with_edits")
+        } else {
+            ""
+        };
+        trf(
+            "recovery.prompt",
+            &[("time", &format_position(self.position)), ("edits", &edits)],
+        )
+    }
+}
+
+fn path(state_dir: &Path) -> PathBuf {
+    state_dir.join(".player-recovery.json")
+}
+
+/// The checkpoint left by a player that didn't exit cleanly, if any.
+pub fn load(state_dir: &Path) -> Result<Option<Checkpoint>, String> {
+    let checkpoint: Option<Checkpoint> = json::load(&path(state_dir))?;
+    // A running player's own checkpoint, when taking over from it
+    Ok(checkpoint.filter(|c| c.pid != process::id() && !lock::alive(c.pid)))
+}
+
+/// Write `checkpoint`, replacing the last one in one step so a crash
+/// mid-write leaves the previous one whole.
+pub fn save(state_dir: &Path, checkpoint: &Checkpoint) -> Result<(), String> {
+    let path = path(state_dir);
+    let temp = path.with_extension("json.tmp");
+    let json = serde_json::to_string(checkpoint).map_err(|e| e.to_string())?;
+    fs::write(&temp, json).map_err(|e| format!("{}: {}", temp.display(), e))?;
+    fs::rename(&temp, &path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Forget the session, on a clean exit.
+pub fn remove(state_dir: &Path) {
+    let _ = fs::remove_file(path(state_dir));
+}
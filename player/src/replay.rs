@@ -0,0 +1,113 @@
+//! Key scripts for automated UI runs and shareable demos: `--record FILE`
+//! writes every key press with the time since the player opened, and
+//! `--replay FILE` presses them again at the same times. Keys are named as
+//! in key bindings and macros:
+//!
+//! ```json
+//! {"keys": [{"at_ms": 800, "key": "space"}, {"at_ms": 4200, "key": "q"}]}
+//! ```
+//!
+//! Keys typed during a replay still work, and with both options the
+//! recording has the replayed keys and the typed ones.
+
+use crate::keymap::{key_name, parse_key_event};
+use cornwall_player::json;
+use crossterm::event::KeyEvent;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct KeyScript {
+    keys: Vec<Step>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Step {
+    /// Milliseconds after the player opened.
+    at_ms: u64,
+    key: String,
+}
+
+pub struct Recorder {
+    path: PathBuf,
+    started: Instant,
+    script: KeyScript,
+}
+
+impl Recorder {
+    /// Start recording to `path`, which is written straight away so a path
+    /// that can't be is reported before the player opens.
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let recorder = Recorder {
+            path: path.to_path_buf(),
+            started: Instant::now(),
+            script: KeyScript::default(),
+        };
+        recorder.save()?;
+        Ok(recorder)
+    }
+
+    /// Add a key press. The file is rewritten each time, so a crash keeps
+    /// what led up to it.
+    pub fn record(&mut self, key: &KeyEvent) -> Result<(), String> {
+        self.script.keys.push(Step {
+            at_ms: self.started.elapsed().as_millis() as u64,
+            key: key_name(key),
+        });
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&self.script).map_err(|e| e.to_string())?;
+        fs::write(&self.path, json + "\n").map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+}
+
+pub struct Replay {
+    keys: Vec<(Duration, KeyEvent)>,
+    next: usize,
+    started: Instant,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let script: KeyScript = json::parse(path, &text)?;
+        let mut keys = script
+            .keys
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let key = parse_key_event(&step.key)
+                    .map_err(|e| format!("{}, keys[{}]: {}", path.display(), i, e))?;
+                Ok((Duration::from_millis(step.at_ms), key))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        // Hand-edited scripts needn't be in order
+        keys.sort_by_key(|(at, _)| *at);
+        Ok(Replay {
+            keys,
+            next: 0,
+            started: Instant::now(),
+        })
+    }
+
+    /// The next key whose time has come, if any.
+    pub fn next_due(&mut self) -> Option<KeyEvent> {
+        let (at, key) = self.keys.get(self.next)?;
+        if *at > self.started.elapsed() {
+            return None;
+        }
+        self.next += 1;
+        Some(*key)
+    }
+
+    pub fn finished(&self) -> bool {
+        self.next == self.keys.len()
+    }
+}
@@ -0,0 +1,215 @@
+//! Sample rate conversion for program material, so the device can run at a
+//! chosen rate instead of whatever rodio's linear converter would give us.
+
+use rodio::Source;
+use serde::Deserialize;
+use std::{f64::consts::PI, time::Duration};
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Quality {
+    /// Linear interpolation.
+    Fast,
+    /// 4-point cubic (Catmull-Rom).
+    #[default]
+    Medium,
+    /// 32-tap Blackman-windowed sinc, band-limited when downsampling.
+    Best,
+}
+
+impl Quality {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Quality::Fast => "fast",
+            Quality::Medium => "medium",
+            Quality::Best => "best",
+        }
+    }
+
+    /// Input frames needed on each side of the interpolation point.
+    fn half_width(&self) -> usize {
+        match self {
+            Quality::Fast => 1,
+            Quality::Medium => 2,
+            Quality::Best => 16,
+        }
+    }
+}
+
+/// Converts a source to `target_rate`. Passes samples through untouched when
+/// the rates already match.
+pub struct Resample<S> {
+    inner: S,
+    quality: Quality,
+    channels: usize,
+    target_rate: u32,
+    /// Input frames advanced per output frame.
+    step: f64,
+    /// Sinc cutoff relative to the input Nyquist (< 1 when downsampling).
+    cutoff: f64,
+    /// Interleaved input history; `buf[0]` is input frame `base`.
+    buf: Vec<f32>,
+    base: i64,
+    /// Read position in input frames.
+    pos: f64,
+    /// Total input frames, known once the inner source runs dry.
+    input_len: Option<i64>,
+    weights: Vec<f64>,
+    out: Vec<f32>,
+    out_pos: usize,
+    passthrough: bool,
+}
+
+impl<S: Source<Item = f32>> Resample<S> {
+    pub fn new(inner: S, target_rate: u32, quality: Quality) -> Self {
+        let channels = inner.channels().max(1) as usize;
+        let source_rate = inner.sample_rate();
+        let step = source_rate as f64 / target_rate as f64;
+        let h = quality.half_width();
+        Resample {
+            inner,
+            quality,
+            channels,
+            target_rate,
+            step,
+            cutoff: (1.0 / step).min(1.0),
+            // Frames before the start of the source are silence
+            buf: vec![0.0; (h - 1) * channels],
+            base: 1 - h as i64,
+            pos: 0.0,
+            input_len: None,
+            weights: vec![0.0; 2 * h],
+            out: vec![0.0; channels],
+            out_pos: channels,
+            passthrough: source_rate == target_rate,
+        }
+    }
+
+    /// Make sure input frames up to `last` (inclusive) are buffered.
+    fn fill_to(&mut self, last: i64) {
+        let h = self.quality.half_width() as i64;
+        let first = self.pos.floor() as i64 - h + 1;
+        let buffered = (self.buf.len() / self.channels) as i64;
+        let drop = (first - self.base).min(buffered);
+        if drop > 4096 {
+            self.buf.drain(..drop as usize * self.channels);
+            self.base += drop;
+        }
+        while self.base + (self.buf.len() / self.channels) as i64 <= last {
+            match self.input_len {
+                Some(_) => self.buf.resize(self.buf.len() + self.channels, 0.0),
+                None => {
+                    let start = self.buf.len();
+                    for _ in 0..self.channels {
+                        match self.inner.next() {
+                            Some(s) => self.buf.push(s),
+                            None => break,
+                        }
+                    }
+                    if self.buf.len() - start < self.channels {
+                        self.buf.truncate(start);
+                        self.input_len = Some(self.base + (start / self.channels) as i64);
+                    }
+                }
+            }
+        }
+    }
+
+    fn compute_weights(&mut self, frac: f64) {
+        let h = self.quality.half_width();
+        match self.quality {
+            Quality::Fast => {
+                self.weights[0] = 1.0 - frac;
+                self.weights[1] = frac;
+            }
+            Quality::Medium => {
+                let t = frac;
+                let t2 = t * t;
+                let t3 = t2 * t;
+                self.weights[0] = -0.5 * t3 + t2 - 0.5 * t;
+                self.weights[1] = 1.5 * t3 - 2.5 * t2 + 1.0;
+                self.weights[2] = -1.5 * t3 + 2.0 * t2 + 0.5 * t;
+                self.weights[3] = 0.5 * t3 - 0.5 * t2;
+            }
+            Quality::Best => {
+                let mut sum = 0.0;
+                for (j, w) in self.weights.iter_mut().enumerate() {
+                    let x = (j as f64 - (h as f64 - 1.0)) - frac;
+                    let arg = PI * x * self.cutoff;
+                    let sinc = if arg.abs() < 1e-9 {
+                        1.0
+                    } else {
+                        arg.sin() / arg
+                    };
+                    let n = x / h as f64;
+                    let window = 0.42 + 0.5 * (PI * n).cos() + 0.08 * (2.0 * PI * n).cos();
+                    *w = sinc * window;
+                    sum += *w;
+                }
+                // Normalize for unity gain at DC
+                for w in self.weights.iter_mut() {
+                    *w /= sum;
+                }
+            }
+        }
+    }
+
+    fn next_frame(&mut self) -> bool {
+        let h = self.quality.half_width() as i64;
+        let i = self.pos.floor() as i64;
+        self.fill_to(i + h);
+        if let Some(len) = self.input_len {
+            if i >= len {
+                return false;
+            }
+        }
+        self.compute_weights(self.pos - i as f64);
+        let first = (i - h + 1 - self.base) as usize;
+        for c in 0..self.channels {
+            let mut acc = 0.0;
+            for (j, w) in self.weights.iter().enumerate() {
+                acc += self.buf[(first + j) * self.channels + c] as f64 * w;
+            }
+            self.out[c] = acc as f32;
+        }
+        self.pos += self.step;
+        true
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for Resample<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.passthrough {
+            return self.inner.next();
+        }
+        if self.out_pos == self.channels {
+            if !self.next_frame() {
+                return None;
+            }
+            self.out_pos = 0;
+        }
+        let sample = self.out[self.out_pos];
+        self.out_pos += 1;
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Resample<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.target_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
@@ -0,0 +1,310 @@
+//! Streaming the main output over RTP (`--stream HOST:PORT`), so someone
+//! elsewhere hears what's being auditioned: Opus (RFC 7587), stereo at
+//! 48 kHz in 20 ms packets, sent to one address at about 128 kbit/s, which
+//! suits a home uplink. Other device rates are converted on the network
+//! thread, so the output thread only copies frames.
+//!
+//! An SDP description is written to `state/stream.sdp` for the listener,
+//! e.g. `ffplay -protocol_whitelist file,udp,rtp stream.sdp`. Frames are
+//! taken where the scope takes them, after the mono fold-down and before
+//! the monitor volume, so the listener sets their own level.
+//!
+//! Built only with the `rtp` feature, as the encoder needs libopus. The
+//! output thread fills batches the network thread has emptied and handed
+//! back, so it never allocates.
+
+use crate::resample::{Quality, Resample};
+use opus::{Application, Bitrate, Channels, Encoder};
+use rodio::Source;
+use std::{
+    fs,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Opus always runs at 48 kHz on the wire, whatever the device rate.
+const OPUS_RATE: u32 = 48000;
+
+/// Frames per packet: 20 ms, Opus's usual frame size.
+const PACKET_FRAMES: usize = 960;
+
+/// Encoded bits per second; transparent for stereo music.
+const BITRATE: i32 = 128_000;
+
+/// Largest Opus packet, so a payload always fits under a 1500-byte MTU.
+const MAX_PAYLOAD: usize = 1276;
+
+/// Batches queued for the network thread, and as many empty ones for the
+/// output thread to fill; it drops frames rather than wait or allocate when
+/// they run out.
+const BACKLOG: usize = 64;
+
+/// Frames the output thread collects before handing them over.
+const BATCH: usize = 256;
+
+/// Dynamic RTP payload type, mapped to Opus in the SDP.
+const PAYLOAD_TYPE: u8 = 96;
+
+/// Where a [`StreamTap`] sends its batches, and the emptied ones it fills
+/// next.
+#[derive(Clone)]
+pub struct Feed {
+    frames: SyncSender<Vec<[f32; 2]>>,
+    /// Shared by every tap, only ever tried from the output thread.
+    spare: Arc<Mutex<Receiver<Vec<[f32; 2]>>>>,
+}
+
+/// A running stream.
+pub struct Stream {
+    feed: Feed,
+    pub target: SocketAddr,
+    pub sdp: PathBuf,
+}
+
+impl Stream {
+    /// Start sending to `target` (`host:port`) from a device running at
+    /// `sample_rate`, writing the SDP for it into `state_dir`.
+    pub fn start(target: &str, sample_rate: u32, state_dir: &Path) -> Result<Stream, String> {
+        let target = target
+            .to_socket_addrs()
+            .map_err(|e| format!("{}: {}", target, e))?
+            .next()
+            .ok_or_else(|| format!("{}: no address", target))?;
+        let bind = if target.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(bind).map_err(|e| e.to_string())?;
+        socket
+            .connect(target)
+            .map_err(|e| format!("{}: {}", target, e))?;
+
+        let mut encoder = Encoder::new(OPUS_RATE, Channels::Stereo, Application::Audio)
+            .map_err(|e| format!("Opus encoder: {}", e))?;
+        encoder
+            .set_bitrate(Bitrate::Bits(BITRATE))
+            .map_err(|e| format!("Opus encoder: {}", e))?;
+        let sdp = state_dir.join("stream.sdp");
+        fs::write(&sdp, describe(target)).map_err(|e| format!("{}: {}", sdp.display(), e))?;
+
+        let (frames, rx) = mpsc::sync_channel(BACKLOG);
+        // Room for the taps' own batches too; one that doesn't fit is freed
+        // on the network thread
+        let (returned, spare) = mpsc::sync_channel(BACKLOG * 2 + 1);
+        for _ in 0..BACKLOG {
+            let _ = returned.try_send(Vec::with_capacity(BATCH));
+        }
+        let incoming = Incoming {
+            rx,
+            returned,
+            sample_rate,
+            batch: Vec::new(),
+            pos: 0,
+        };
+        let source = Resample::new(incoming, OPUS_RATE, Quality::Medium);
+        thread::spawn(move || send_packets(socket, encoder, source));
+        Ok(Stream {
+            feed: Feed {
+                frames,
+                spare: Arc::new(Mutex::new(spare)),
+            },
+            target,
+            sdp,
+        })
+    }
+
+    /// A handle for the output thread's `StreamTap`.
+    pub fn feed(&self) -> Feed {
+        self.feed.clone()
+    }
+}
+
+/// Session description for a listener at `target`.
+fn describe(target: SocketAddr) -> String {
+    let family = if target.is_ipv4() { "IP4" } else { "IP6" };
+    format!(
+        "v=0\r\n\
+         o=- 0 0 IN {family} {ip}\r\n\
+         s=Cornwall player\r\n\
+         c=IN {family} {ip}\r\n\
+         t=0 0\r\n\
+         m=audio {port} RTP/AVP {pt}\r\n\
+         a=rtpmap:{pt} opus/{rate}/2\r\n\
+         a=fmtp:{pt} stereo=1; sprop-stereo=1\r\n",
+        family = family,
+        ip = target.ip(),
+        port = target.port(),
+        pt = PAYLOAD_TYPE,
+        rate = OPUS_RATE,
+    )
+}
+
+/// Frames from the output thread as a source at the device rate, waiting
+/// for each batch and handing it back once read. Ends when the player
+/// exits.
+struct Incoming {
+    rx: Receiver<Vec<[f32; 2]>>,
+    returned: SyncSender<Vec<[f32; 2]>>,
+    sample_rate: u32,
+    batch: Vec<[f32; 2]>,
+    /// Sample index within `batch`.
+    pos: usize,
+}
+
+impl Iterator for Incoming {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        while self.pos == self.batch.len() * 2 {
+            let read = std::mem::replace(&mut self.batch, self.rx.recv().ok()?);
+            if read.capacity() > 0 {
+                let _ = self.returned.try_send(read);
+            }
+            self.pos = 0;
+        }
+        let sample = self.batch[self.pos / 2][self.pos % 2];
+        self.pos += 1;
+        Some(sample)
+    }
+}
+
+impl Source for Incoming {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Encode 48 kHz frames into RTP packets as they come, until the player
+/// exits.
+fn send_packets(socket: UdpSocket, mut encoder: Encoder, mut source: Resample<Incoming>) {
+    // Any value will do for the source ID; the process ID differs per run
+    let ssrc = std::process::id().wrapping_mul(0x9E37_79B9);
+    let mut sequence: u16 = 0;
+    let mut timestamp: u32 = 0;
+    let mut pcm = Vec::with_capacity(PACKET_FRAMES * 2);
+    let mut payload = [0; MAX_PAYLOAD];
+    let mut packet = Vec::with_capacity(12 + MAX_PAYLOAD);
+    loop {
+        pcm.clear();
+        pcm.extend(source.by_ref().take(PACKET_FRAMES * 2));
+        if pcm.len() < PACKET_FRAMES * 2 {
+            return;
+        }
+        // A frame that won't encode leaves a gap the listener conceals
+        if let Ok(len) = encoder.encode_float(&pcm, &mut payload) {
+            packet.clear();
+            packet.extend([0x80, PAYLOAD_TYPE]);
+            packet.extend(sequence.to_be_bytes());
+            packet.extend(timestamp.to_be_bytes());
+            packet.extend(ssrc.to_be_bytes());
+            packet.extend(&payload[..len]);
+            // Nobody listening yet is not an error worth stopping for
+            let _ = socket.send(&packet);
+            sequence = sequence.wrapping_add(1);
+        }
+        timestamp = timestamp.wrapping_add(PACKET_FRAMES as u32);
+    }
+}
+
+/// Passes a source through, copying its frames to a stream when there is
+/// one.
+pub struct StreamTap<S> {
+    inner: S,
+    feed: Option<Feed>,
+    channels: usize,
+    frame: Vec<f32>,
+    batch: Vec<[f32; 2]>,
+    /// An empty batch from the network thread, to fill after this one.
+    next: Option<Vec<[f32; 2]>>,
+}
+
+impl<S: Source<Item = f32>> StreamTap<S> {
+    pub fn new(inner: S, feed: Option<Feed>) -> Self {
+        let channels = inner.channels().max(1) as usize;
+        StreamTap {
+            inner,
+            feed,
+            channels,
+            frame: Vec::with_capacity(channels),
+            batch: Vec::with_capacity(BATCH),
+            next: None,
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for StreamTap<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next()?;
+        let Some(feed) = &self.feed else {
+            return Some(sample);
+        };
+        self.frame.push(sample);
+        if self.frame.len() == self.channels {
+            // Mono plays on both sides
+            let left = self.frame[0];
+            let right = self.frame.get(1).copied().unwrap_or(left);
+            self.batch.push([left, right]);
+            self.frame.clear();
+            if self.batch.len() == BATCH {
+                if self.next.is_none() {
+                    self.next = feed
+                        .spare
+                        .try_lock()
+                        .ok()
+                        .and_then(|spare| spare.try_recv().ok());
+                }
+                // With nothing to fill next, or the network thread behind,
+                // the batch's frames are dropped and it fills again
+                if let Some(next) = self.next.take() {
+                    let batch = std::mem::replace(&mut self.batch, next);
+                    if let Err(TrySendError::Full(batch) | TrySendError::Disconnected(batch)) =
+                        feed.frames.try_send(batch)
+                    {
+                        self.next = Some(std::mem::replace(&mut self.batch, batch));
+                    }
+                }
+                self.batch.clear();
+            }
+        }
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for StreamTap<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
@@ -0,0 +1,318 @@
+//! Oscilloscope of what the main output is playing, for looking at the shape
+//! of kick and bass transients while listening. `O` shows it under the
+//! meters; `:scope 40` sets how many milliseconds it spans:
+//!
+//! ```json
+//! "scope": {"window_ms": 20, "enabled": false, "vectorscope": false}
+//! ```
+//!
+//! The trace is triggered on a rising zero crossing of the channel sum, so
+//! a steady note stands still instead of scrolling. Without a crossing in
+//! reach (silence, DC) it runs free on the latest samples.
+//!
+//! The vectorscope (`V`) beside it plots left against right as a goniometer
+//! does: mono is a vertical line, wide material spreads sideways and
+//! out-of-phase material lies flat. The correlation of the same frames, +1
+//! for mono down to -1 for opposite polarity, is in its title.
+//!
+//! Samples are taken after the monitor's mono fold-down and before the
+//! volume, so the trace keeps its size whatever the monitor level. The
+//! loudness gauge measures the same frames.
+
+use crate::waveform::DOT_BITS;
+use cornwall_dsp::loudness::LoudnessMeter;
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+use rodio::Source;
+use serde::Deserialize;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+/// Shortest and longest window.
+pub const MIN_WINDOW_MS: f64 = 1.0;
+pub const MAX_WINDOW_MS: f64 = 200.0;
+
+/// Frames the output thread collects before handing them over.
+const BATCH: usize = 256;
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct ScopeConfig {
+    /// Time across the pane.
+    pub window_ms: f64,
+    /// Show the scope from startup.
+    pub enabled: bool,
+    /// Show the vectorscope from startup.
+    pub vectorscope: bool,
+}
+
+impl Default for ScopeConfig {
+    fn default() -> Self {
+        ScopeConfig {
+            window_ms: 20.0,
+            enabled: false,
+            vectorscope: false,
+        }
+    }
+}
+
+/// The latest output frames, shared between the output thread and the UI.
+#[derive(Default)]
+pub struct Capture {
+    /// Only collected while something shows them.
+    pub enabled: AtomicBool,
+    sample_rate: AtomicU32,
+    frames: Mutex<VecDeque<[f32; 2]>>,
+    /// Started on the first frames after a reset.
+    loudness: Mutex<Option<LoudnessMeter>>,
+}
+
+impl Capture {
+    pub fn new(enabled: bool) -> Self {
+        Capture {
+            enabled: AtomicBool::new(enabled),
+            ..Capture::default()
+        }
+    }
+
+    /// Frames to keep: twice the longest window, so a trigger can be found
+    /// a whole window back.
+    fn keep(&self) -> usize {
+        let rate = self.sample_rate.load(Ordering::Relaxed) as f64;
+        (rate * MAX_WINDOW_MS / 1000.0 * 2.0) as usize
+    }
+
+    fn push(&self, batch: &[[f32; 2]]) {
+        // The output thread never waits on the UI; a busy lock drops a batch
+        if let Ok(mut frames) = self.frames.try_lock() {
+            frames.extend(batch);
+            let excess = frames.len().saturating_sub(self.keep());
+            frames.drain(..excess);
+        }
+        if let Ok(mut meter) = self.loudness.try_lock() {
+            let rate = self.sample_rate.load(Ordering::Relaxed);
+            let meter = meter.get_or_insert_with(|| LoudnessMeter::new(2, rate));
+            let samples: Vec<f64> = batch.iter().flatten().map(|&s| s as f64).collect();
+            meter.push(&samples);
+        }
+    }
+
+    /// Momentary, short-term and integrated loudness in LUFS.
+    pub fn loudness(&self) -> (f64, f64, f64) {
+        let meter = self.loudness.lock().unwrap_or_else(|e| e.into_inner());
+        meter.as_ref().map_or(
+            (f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            |m| (m.momentary(), m.short_term(), m.integrated()),
+        )
+    }
+
+    /// Measure loudness afresh from the next frames.
+    pub fn reset_loudness(&self) {
+        *self.loudness.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// The last `window_ms` of output as `[left, right]` frames, starting
+    /// on a rising zero crossing when one is in reach.
+    pub fn window(&self, window_ms: f64) -> Vec<[f32; 2]> {
+        let rate = self.sample_rate.load(Ordering::Relaxed) as f64;
+        let frames = self.frames.lock().unwrap_or_else(|e| e.into_inner());
+        let need = ((rate * window_ms / 1000.0) as usize).clamp(1, frames.len().max(1));
+        let latest = frames.len().saturating_sub(need);
+        let sum = |i: usize| frames[i][0] + frames[i][1];
+        let start = (latest.saturating_sub(need).max(1)..=latest)
+            .rev()
+            .find(|&i| sum(i - 1) < 0.0 && sum(i) >= 0.0)
+            .unwrap_or(latest);
+        frames.range(start..).take(need).copied().collect()
+    }
+
+    /// The last `window_ms` of output as it came.
+    pub fn recent(&self, window_ms: f64) -> Vec<[f32; 2]> {
+        let rate = self.sample_rate.load(Ordering::Relaxed) as f64;
+        let frames = self.frames.lock().unwrap_or_else(|e| e.into_inner());
+        let need = (rate * window_ms / 1000.0) as usize;
+        let skip = frames.len().saturating_sub(need);
+        frames.range(skip..).copied().collect()
+    }
+
+    pub fn clear(&self) {
+        self.frames
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+}
+
+/// `rows` lines of `width` cells tracing the channel sum of `frames`, full
+/// scale at the top and bottom edges.
+pub fn trace(frames: &[[f32; 2]], width: usize, rows: usize, braille: bool) -> Vec<Line<'static>> {
+    let (dots_x, dots_y) = if braille { (2, 4) } else { (1, 2) };
+    let (cols, height) = (width * dots_x, rows * dots_y);
+    let mut lit = vec![vec![false; cols]; height];
+    if height > 0 && !frames.is_empty() {
+        let top = (height - 1) as f32;
+        let mut last: Option<usize> = None;
+        for (x, column) in (0..cols).map(|x| (x, x * frames.len() / cols.max(1))) {
+            let [l, r] = frames[column];
+            let value = ((l + r) / 2.0).clamp(-1.0, 1.0);
+            let y = ((1.0 - value) / 2.0 * top).round() as usize;
+            // Join steep edges up so a transient reads as a line, not dots
+            let (from, to) = match last {
+                Some(prev) => (prev.min(y), prev.max(y)),
+                None => (y, y),
+            };
+            for row in lit.iter_mut().take(to + 1).skip(from) {
+                row[x] = true;
+            }
+            last = Some(y);
+        }
+    }
+    let style = Style::default().fg(Color::Cyan);
+    (0..rows)
+        .map(|row| Line::from(Span::styled(cells(&lit, row, width, braille), style)))
+        .collect()
+}
+
+/// Milliseconds of output the vectorscope plots.
+pub const VECTOR_MS: f64 = 50.0;
+
+/// `rows` lines plotting `frames` as a goniometer, in a square centred in
+/// `width` cells: mid up, side across.
+pub fn vectorscope(
+    frames: &[[f32; 2]],
+    width: usize,
+    rows: usize,
+    braille: bool,
+) -> Vec<Line<'static>> {
+    let (dots_x, dots_y) = if braille { (2, 4) } else { (1, 2) };
+    let size = (rows * dots_y).min(width * dots_x);
+    let left = (width * dots_x - size) / 2;
+    let mut lit = vec![vec![false; width * dots_x]; rows * dots_y];
+    if size > 0 {
+        let scale = (size - 1) as f32 / 2.0;
+        for &[l, r] in frames {
+            // Rotated 45°, at half scale so full-scale mono reaches the top
+            let side = ((r - l) / 2.0).clamp(-1.0, 1.0);
+            let mid = ((l + r) / 2.0).clamp(-1.0, 1.0);
+            let x = left + ((side + 1.0) * scale).round() as usize;
+            let y = ((1.0 - mid) * scale).round() as usize;
+            lit[y][x] = true;
+        }
+    }
+    let style = Style::default().fg(Color::Green);
+    (0..rows)
+        .map(|row| Line::from(Span::styled(cells(&lit, row, width, braille), style)))
+        .collect()
+}
+
+/// Correlation between left and right over `frames`, or `None` in silence.
+pub fn correlation(frames: &[[f32; 2]]) -> Option<f32> {
+    let (lr, ll, rr) = frames
+        .iter()
+        .fold((0.0, 0.0, 0.0), |(lr, ll, rr), &[l, r]| {
+            (lr + l * r, ll + l * l, rr + r * r)
+        });
+    let energy: f32 = (ll * rr).sqrt();
+    (energy > 1e-9).then(|| (lr / energy).clamp(-1.0, 1.0))
+}
+
+/// Row `row` of a dot grid as braille or half-block characters.
+fn cells(lit: &[Vec<bool>], row: usize, width: usize, braille: bool) -> String {
+    (0..width)
+        .map(|cell| {
+            if braille {
+                let mut bits = 0;
+                for (dx, column) in DOT_BITS.iter().enumerate() {
+                    for (dy, bit) in column.iter().enumerate() {
+                        if lit[row * 4 + dy][cell * 2 + dx] {
+                            bits |= bit;
+                        }
+                    }
+                }
+                char::from_u32(0x2800 + bits).unwrap_or(' ')
+            } else {
+                match (lit[row * 2][cell], lit[row * 2 + 1][cell]) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                }
+            }
+        })
+        .collect()
+}
+
+/// Passes a source through, copying its frames into a `Capture`.
+pub struct Tap<S> {
+    inner: S,
+    capture: Arc<Capture>,
+    channels: usize,
+    frame: Vec<f32>,
+    batch: Vec<[f32; 2]>,
+}
+
+impl<S: Source<Item = f32>> Tap<S> {
+    pub fn new(inner: S, capture: Arc<Capture>) -> Self {
+        capture
+            .sample_rate
+            .store(inner.sample_rate(), Ordering::Relaxed);
+        let channels = inner.channels().max(1) as usize;
+        Tap {
+            inner,
+            capture,
+            channels,
+            frame: Vec::with_capacity(channels),
+            batch: Vec::with_capacity(BATCH),
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for Tap<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next()?;
+        if !self.capture.enabled.load(Ordering::Relaxed) {
+            return Some(sample);
+        }
+        self.frame.push(sample);
+        if self.frame.len() == self.channels {
+            // Mono plays on both sides
+            let left = self.frame[0];
+            let right = self.frame.get(1).copied().unwrap_or(left);
+            self.batch.push([left, right]);
+            self.frame.clear();
+            if self.batch.len() == BATCH {
+                self.capture.push(&self.batch);
+                self.batch.clear();
+            }
+        }
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Tap<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
@@ -0,0 +1,311 @@
+//! Lua scripting. `projects/<name>/player.lua` runs when the player starts
+//! (and `:lua CODE` runs a line at the command line). Scripts can define
+//! hooks:
+//!
+//! - `on_play()` and `on_stop()` when the transport starts or stops
+//! - `on_bar(bar)` at each bar boundary during playback
+//!
+//! and use the `player` table:
+//!
+//! - `player.position()`, `bar()`, `beat()`, `playing()`
+//! - `player.tracks()` (list of `{id, name, mute, solo, volume, pan}`) and
+//!   `player.markers()` (list of `{name, position}`)
+//! - `player.action("toggle_loop")` runs a keymap action,
+//!   `player.command("mark Chorus")` a `:` command
+//! - `player.set_track(2, {mute = true, volume = 0.8})`
+//! - `player.notify(text)`
+//! - `player.bind("ctrl+k", function() ... end)` binds a key, ahead of the
+//!   keymap
+//!
+//! Lua never touches the player directly: reads come from a snapshot taken
+//! before each call into the script, and changes are queued and applied
+//! once the script returns, the same way as key presses.
+//!
+//! `:lua` is reachable from the control socket, so scripts get the base,
+//! `table`, `string`, `math` and `utf8` libraries only: no `os`, `io`,
+//! `package`, `debug`, `dofile` or `loadfile` to reach the system with.
+
+use crate::{
+    keymap::{key_name, parse_key_event, Action},
+    markers::Marker,
+    TrackState,
+};
+use crossterm::event::KeyEvent;
+use mlua::{Function, Lua, LuaOptions, StdLib, Table};
+use std::{cell::RefCell, collections::HashMap, fs, path::Path, rc::Rc};
+
+/// Player state as the script sees it.
+#[derive(Default)]
+pub struct View {
+    pub position: f64,
+    pub bar: u32,
+    pub beat: u32,
+    pub playing: bool,
+    pub tracks: Vec<TrackState>,
+    pub markers: Vec<Marker>,
+}
+
+#[derive(Default)]
+pub struct TrackPatch {
+    pub mute: Option<bool>,
+    pub solo: Option<bool>,
+    pub volume: Option<f64>,
+    pub pan: Option<f64>,
+}
+
+/// A change requested by the script, applied after it returns.
+pub enum Call {
+    Action(Action),
+    Command(String),
+    SetTrack(u32, TrackPatch),
+    Notify(String),
+}
+
+pub enum Event {
+    Play,
+    Stop,
+    Bar(u32),
+}
+
+#[derive(Default)]
+struct Shared {
+    view: View,
+    calls: Vec<Call>,
+    bindings: HashMap<String, Function>,
+}
+
+pub struct Script {
+    lua: Lua,
+    shared: Rc<RefCell<Shared>>,
+    playing: bool,
+    bar: u32,
+}
+
+impl Script {
+    pub fn new() -> Self {
+        let libs = StdLib::TABLE | StdLib::STRING | StdLib::MATH | StdLib::UTF8;
+        let lua = Lua::new_with(libs, LuaOptions::default())
+            .expect("Cannot set up the Lua scripting API");
+        let script = Script {
+            lua,
+            shared: Rc::default(),
+            playing: false,
+            bar: 0,
+        };
+        script
+            .install_api()
+            .expect("Cannot set up the Lua scripting API");
+        script
+    }
+
+    /// Run the project's `player.lua`, if it has one.
+    pub fn run_file(&self, project_dir: &Path) -> Result<(), String> {
+        let Ok(source) = fs::read_to_string(project_dir.join("player.lua")) else {
+            return Ok(());
+        };
+        self.lua
+            .load(&source)
+            .set_name("player.lua")
+            .exec()
+            .map_err(message)
+    }
+
+    fn install_api(&self) -> mlua::Result<()> {
+        let lua = &self.lua;
+        // The base library's way into the file system
+        lua.globals().set("dofile", mlua::Nil)?;
+        lua.globals().set("loadfile", mlua::Nil)?;
+        let api = lua.create_table()?;
+
+        let shared = self.shared.clone();
+        api.set(
+            "position",
+            lua.create_function(move |_, ()| Ok(shared.borrow().view.position))?,
+        )?;
+        let shared = self.shared.clone();
+        api.set(
+            "bar",
+            lua.create_function(move |_, ()| Ok(shared.borrow().view.bar))?,
+        )?;
+        let shared = self.shared.clone();
+        api.set(
+            "beat",
+            lua.create_function(move |_, ()| Ok(shared.borrow().view.beat))?,
+        )?;
+        let shared = self.shared.clone();
+        api.set(
+            "playing",
+            lua.create_function(move |_, ()| Ok(shared.borrow().view.playing))?,
+        )?;
+
+        let shared = self.shared.clone();
+        api.set(
+            "tracks",
+            lua.create_function(move |lua, ()| {
+                let list = lua.create_table()?;
+                for (i, t) in shared.borrow().view.tracks.iter().enumerate() {
+                    let row = lua.create_table()?;
+                    row.set("id", t.id)?;
+                    row.set("name", t.name.as_str())?;
+                    row.set("mute", t.mute)?;
+                    row.set("solo", t.solo)?;
+                    row.set("volume", t.volume)?;
+                    row.set("pan", t.pan)?;
+                    list.set(i + 1, row)?;
+                }
+                Ok(list)
+            })?,
+        )?;
+        let shared = self.shared.clone();
+        api.set(
+            "markers",
+            lua.create_function(move |lua, ()| {
+                let list = lua.create_table()?;
+                for (i, m) in shared.borrow().view.markers.iter().enumerate() {
+                    let row = lua.create_table()?;
+                    row.set("name", m.name.as_str())?;
+                    row.set("position", m.position)?;
+                    list.set(i + 1, row)?;
+                }
+                Ok(list)
+            })?,
+        )?;
+
+        let shared = self.shared.clone();
+        api.set(
+            "action",
+            lua.create_function(move |_, name: String| {
+                let action = Action::from_name(&name)
+                    .ok_or_else(|| mlua::Error::RuntimeError(format!("unknown action {}", name)))?;
+                shared.borrow_mut().calls.push(Call::Action(action));
+                Ok(())
+            })?,
+        )?;
+        let shared = self.shared.clone();
+        api.set(
+            "command",
+            lua.create_function(move |_, line: String| {
+                let line = line.trim_start_matches(':').to_string();
+                shared.borrow_mut().calls.push(Call::Command(line));
+                Ok(())
+            })?,
+        )?;
+        let shared = self.shared.clone();
+        api.set(
+            "set_track",
+            lua.create_function(move |_, (id, fields): (u32, Table)| {
+                let patch = TrackPatch {
+                    mute: fields.get("mute")?,
+                    solo: fields.get("solo")?,
+                    volume: fields.get("volume")?,
+                    pan: fields.get("pan")?,
+                };
+                shared.borrow_mut().calls.push(Call::SetTrack(id, patch));
+                Ok(())
+            })?,
+        )?;
+        let shared = self.shared.clone();
+        api.set(
+            "notify",
+            lua.create_function(move |_, text: String| {
+                shared.borrow_mut().calls.push(Call::Notify(text));
+                Ok(())
+            })?,
+        )?;
+        let shared = self.shared.clone();
+        api.set(
+            "bind",
+            lua.create_function(move |_, (key, f): (String, Function)| {
+                let key = parse_key_event(&key).map_err(mlua::Error::RuntimeError)?;
+                shared.borrow_mut().bindings.insert(key_name(&key), f);
+                Ok(())
+            })?,
+        )?;
+
+        lua.globals().set("player", api)
+    }
+
+    /// Transport and bar changes since the last call.
+    pub fn events(&mut self, playing: bool, bar: u32) -> Vec<Event> {
+        let mut events = Vec::new();
+        if playing != self.playing {
+            events.push(if playing { Event::Play } else { Event::Stop });
+        }
+        if playing && (bar != self.bar || !self.playing) {
+            events.push(Event::Bar(bar));
+        }
+        self.playing = playing;
+        self.bar = bar;
+        events
+    }
+
+    pub fn binds(&self, key: &KeyEvent) -> bool {
+        self.shared.borrow().bindings.contains_key(&key_name(key))
+    }
+
+    /// Call the hook for `event`, if the script defines it.
+    pub fn fire(&self, event: Event, view: View) -> Result<Vec<Call>, String> {
+        let (name, bar) = match event {
+            Event::Play => ("on_play", None),
+            Event::Stop => ("on_stop", None),
+            Event::Bar(bar) => ("on_bar", Some(bar)),
+        };
+        let hook: Option<Function> = self.lua.globals().get(name).map_err(message)?;
+        match hook {
+            Some(hook) => self.call(view, || hook.call::<()>(bar)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Run the function bound to `key`.
+    pub fn press(&self, key: &KeyEvent, view: View) -> Result<Vec<Call>, String> {
+        let f = self.shared.borrow().bindings.get(&key_name(key)).cloned();
+        match f {
+            Some(f) => self.call(view, || f.call::<()>(())),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Run a line of Lua typed at the command line.
+    pub fn eval(&self, code: &str, view: View) -> Result<Vec<Call>, String> {
+        self.call(view, || self.lua.load(code).set_name("command line").exec())
+    }
+
+    fn call(&self, view: View, f: impl FnOnce() -> mlua::Result<()>) -> Result<Vec<Call>, String> {
+        self.shared.borrow_mut().view = view;
+        let result = f();
+        let calls = std::mem::take(&mut self.shared.borrow_mut().calls);
+        result.map(|_| calls).map_err(message)
+    }
+}
+
+/// First line of a Lua error, without the stack traceback, to fit the
+/// notification line.
+fn message(e: mlua::Error) -> String {
+    e.to_string().lines().next().unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_cannot_reach_the_system() {
+        let script = Script::new();
+        for global in [
+            "os", "io", "package", "debug", "require", "dofile", "loadfile",
+        ] {
+            let check = format!("assert({} == nil, '{} is loaded')", global, global);
+            assert!(script.eval(&check, View::default()).is_ok(), "{}", global);
+        }
+        assert!(script.eval("os.execute('true')", View::default()).is_err());
+    }
+
+    #[test]
+    fn scripts_keep_the_pure_libraries() {
+        let script = Script::new();
+        let code = "assert(string.upper('a') == 'A' and math.floor(1.5) == 1 \
+                    and table.concat({1, 2}) == '12' and utf8.len('é') == 1)";
+        assert!(script.eval(code, View::default()).is_ok());
+    }
+}
@@ -0,0 +1,67 @@
+//! Skip-silence playback, for reviewing long rehearsal recordings: with it
+//! on (`S`, or `"enabled": true`) playback jumps over stretches quieter
+//! than the threshold, landing a short pre-roll before the sound returns:
+//!
+//! ```json
+//! "skip_silence": {"threshold_db": -50, "min_secs": 2.0, "preroll_secs": 0.5}
+//! ```
+//!
+//! Silence is found from the meter's 50 ms RMS levels, so a click or a
+//! cough keeps a stretch from counting.
+
+use cornwall_dsp::levels::dbfs;
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct SkipSilenceConfig {
+    /// Louder than this on either side is sound.
+    pub threshold_db: f64,
+    /// Shorter silences play through.
+    pub min_secs: f64,
+    /// Played before the sound comes back.
+    pub preroll_secs: f64,
+    /// Skip from startup.
+    pub enabled: bool,
+}
+
+impl Default for SkipSilenceConfig {
+    fn default() -> Self {
+        SkipSilenceConfig {
+            threshold_db: -50.0,
+            min_secs: 2.0,
+            preroll_secs: 0.5,
+            enabled: false,
+        }
+    }
+}
+
+/// Start and end of each silence worth skipping, from per-chunk RMS levels.
+pub fn gaps(
+    left: &[f64],
+    right: &[f64],
+    chunk_secs: f64,
+    config: &SkipSilenceConfig,
+) -> Vec<(f64, f64)> {
+    let mut gaps = Vec::new();
+    let mut start = None;
+    let quiet = left
+        .iter()
+        .zip(right)
+        .map(|(l, r)| dbfs(l.max(*r)) < config.threshold_db)
+        .chain([false]);
+    for (i, quiet) in quiet.enumerate() {
+        match (quiet, start) {
+            (true, None) => start = Some(i),
+            (false, Some(from)) => {
+                start = None;
+                let (from, to) = (from as f64 * chunk_secs, i as f64 * chunk_secs);
+                if to - from >= config.min_secs.max(config.preroll_secs) {
+                    gaps.push((from, to));
+                }
+            }
+            _ => {}
+        }
+    }
+    gaps
+}
@@ -0,0 +1,226 @@
+//! Snapshots of the project and tracks (`:snapshot`), saved to
+//! `state/snapshots/<unix time>.json`, and what has changed since the
+//! latest one (`:diff`): tempo and format, tracks added or removed, and each
+//! track's name, source, volume, pan, mute and solo. Both use the player's
+//! state, unsaved edits included.
+
+use crate::{
+    i18n::{tr, trf},
+    utilities::centered_rect,
+};
+use cornwall_player::{
+    json,
+    project::{ProjectState, TrackState},
+};
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Unix time it was taken.
+    pub taken: f64,
+    pub project: ProjectState,
+    pub tracks: Vec<TrackState>,
+}
+
+/// How a line of the diff changed.
+#[derive(Clone, Copy)]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// What changed since a snapshot, ready to show.
+pub struct Diff {
+    pub taken: f64,
+    pub lines: Vec<(Change, String)>,
+}
+
+fn dir(state_dir: &Path) -> PathBuf {
+    state_dir.join("snapshots")
+}
+
+/// Write `snapshot` to a new file, returning its path.
+pub fn save(state_dir: &Path, snapshot: &Snapshot) -> Result<PathBuf, String> {
+    let dir = dir(state_dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let path = dir.join(format!("{}.json", snapshot.taken as u64));
+    let json = serde_json::to_string_pretty(snapshot).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// The most recent snapshot, if there is one.
+pub fn latest(state_dir: &Path) -> Result<Option<Snapshot>, String> {
+    let Ok(entries) = fs::read_dir(dir(state_dir)) else {
+        return Ok(None);
+    };
+    let newest = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter_map(|p| {
+            let taken: u64 = p.file_stem()?.to_str()?.parse().ok()?;
+            Some((taken, p))
+        })
+        .max_by_key(|(taken, _)| *taken);
+    let Some((_, path)) = newest else {
+        return Ok(None);
+    };
+    let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    json::parse(&path, &text).map(Some)
+}
+
+/// What changed from `old` to the current `project` and `tracks`.
+pub fn diff(old: &Snapshot, project: &ProjectState, tracks: &[TrackState]) -> Diff {
+    let mut lines = Vec::new();
+    let mut changed = |what: &str, from: String, to: String| {
+        if from != to {
+            lines.push((Change::Changed, format!("{}: {} → {}", what, from, to)));
+        }
+    };
+    changed(
+        tr("diff.bpm"),
+        old.project.bpm.to_string(),
+        project.bpm.to_string(),
+    );
+    changed(
+        tr("diff.time_sig"),
+        old.project.time_sig.clone(),
+        project.time_sig.clone(),
+    );
+    changed(
+        tr("diff.sample_rate"),
+        old.project.sample_rate.to_string(),
+        project.sample_rate.to_string(),
+    );
+
+    for before in &old.tracks {
+        match tracks.iter().find(|t| t.id == before.id) {
+            Some(after) => lines.extend(
+                track_changes(before, after)
+                    .into_iter()
+                    .map(|change| (Change::Changed, format!("{}: {}", label(after), change))),
+            ),
+            None => lines.push((Change::Removed, label(before))),
+        }
+    }
+    for after in tracks {
+        if !old.tracks.iter().any(|t| t.id == after.id) {
+            lines.push((Change::Added, label(after)));
+        }
+    }
+    Diff {
+        taken: old.taken,
+        lines,
+    }
+}
+
+fn label(track: &TrackState) -> String {
+    format!("{} {}", track.id, track.name)
+}
+
+/// How one track differs, one entry per setting.
+fn track_changes(before: &TrackState, after: &TrackState) -> Vec<String> {
+    let mut changes = Vec::new();
+    if before.name != after.name {
+        changes.push(trf("diff.renamed", &[("name", &before.name)]));
+    }
+    if before.source != after.source {
+        let file = |source: &Option<String>| {
+            source
+                .as_deref()
+                .and_then(|s| Path::new(s).file_name())
+                .map_or_else(|| "-".to_string(), |n| n.to_string_lossy().to_string())
+        };
+        changes.push(format!(
+            "{} {} → {}",
+            tr("diff.source"),
+            file(&before.source),
+            file(&after.source)
+        ));
+    }
+    if (before.volume - after.volume).abs() > 1e-6 {
+        let db = |v: f64| 20.0 * v.max(1e-5).log10();
+        changes.push(format!(
+            "{} {:.2} → {:.2} ({:+.1} dB)",
+            tr("diff.volume"),
+            before.volume,
+            after.volume,
+            db(after.volume) - db(before.volume)
+        ));
+    }
+    if (before.pan - after.pan).abs() > 1e-6 {
+        changes.push(format!(
+            "{} {:+.2} → {:+.2}",
+            tr("diff.pan"),
+            before.pan,
+            after.pan
+        ));
+    }
+    if before.mute != after.mute {
+        changes.push(
+            tr(if after.mute {
+                "diff.muted"
+            } else {
+                "diff.unmuted"
+            })
+            .to_string(),
+        );
+    }
+    if before.solo != after.solo {
+        changes.push(
+            tr(if after.solo {
+                "diff.soloed"
+            } else {
+                "diff.unsoloed"
+            })
+            .to_string(),
+        );
+    }
+    changes
+}
+
+/// The diff popup; `now` is the Unix time, for how old the snapshot is.
+pub fn render(f: &mut Frame, diff: &Diff, now: f64) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines = vec![Line::from("")];
+    if diff.lines.is_empty() {
+        lines.push(Line::from(Span::styled(
+            format!("  {}", tr("diff.none")),
+            dim,
+        )));
+    }
+    for (change, text) in &diff.lines {
+        let (mark, color) = match change {
+            Change::Added => ("+", Color::Green),
+            Change::Removed => ("-", Color::Red),
+            Change::Changed => ("~", Color::Yellow),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {} ", mark), Style::default().fg(color)),
+            Span::raw(text.clone()),
+        ]));
+    }
+    lines.push(Line::from(""));
+
+    let minutes = format!("{:.0}", ((now - diff.taken) / 60.0).max(0.0));
+    let area = centered_rect(64, lines.len() as u16 + 2, f.area());
+    let popup = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(dim)
+            .title(format!(" {} ", trf("diff.title", &[("minutes", &minutes)]))),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
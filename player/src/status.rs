@@ -0,0 +1,169 @@
+//! The player's status: `state/.player.json`, the `--status` and
+//! `--status-fifo` lines, `GET /status` and the command socket's replies.
+//!
+//! It's a versioned schema. `schema` goes up whenever a field changes
+//! shape, fields are only added within a version, and
+//! `cornwall-player schema` prints it as JSON Schema for scripts to check
+//! against. [`PlayerStatus::parse`] reads any version, filling in what
+//! older players didn't write.
+//!
+//! - 1: unversioned, with `playing` as the only transport state
+//! - 2: `schema`, `transport`, `loop_mode` and `loop_bars`
+//! - 3: `transport` can be `paused`
+
+use crate::json;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const SCHEMA_VERSION: u32 = 3;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Transport {
+    #[default]
+    Stopped,
+    Playing,
+    /// Stopped partway, to carry on from the same sample.
+    Paused,
+    /// Playing, but the output device has stopped taking audio.
+    Stalled,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopMode {
+    #[default]
+    Off,
+    /// Going round the whole file.
+    File,
+    /// Going round the bars in `loop_bars`.
+    Region,
+    /// Playing the bars in `loop_bars` once.
+    Once,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PlayerStatus {
+    /// Missing, so 0, in version 1.
+    #[serde(default)]
+    pub schema: u32,
+    pub transport: Transport,
+    /// `transport` is `playing` or `stalled`; kept for version 1 readers.
+    pub playing: bool,
+    pub loop_mode: LoopMode,
+    /// First and last bar of the loop region, when one is set.
+    pub loop_bars: Option<[u32; 2]>,
+    pub position_secs: f64,
+    pub bar: u32,
+    pub beat: u32,
+    pub bpm: f64,
+    pub time_sig: String,
+    pub level_l: f64,
+    pub level_r: f64,
+    pub file: String,
+    /// Worst gap between the playhead clock and the audio output since
+    /// playback started, and how often the clock was pulled back in line.
+    pub drift_ms: f64,
+    pub drift_corrections: u32,
+    /// Writer's process id and the Unix time of the last write.
+    pub pid: u32,
+    pub updated: f64,
+    /// Set by `--status` when the writer is gone.
+    pub stale: bool,
+}
+
+impl Default for PlayerStatus {
+    fn default() -> Self {
+        PlayerStatus {
+            schema: SCHEMA_VERSION,
+            transport: Transport::Stopped,
+            playing: false,
+            loop_mode: LoopMode::Off,
+            loop_bars: None,
+            position_secs: 0.0,
+            bar: 0,
+            beat: 0,
+            bpm: 0.0,
+            time_sig: String::new(),
+            level_l: 0.0,
+            level_r: 0.0,
+            file: String::new(),
+            drift_ms: 0.0,
+            drift_corrections: 0,
+            pid: 0,
+            updated: 0.0,
+            stale: false,
+        }
+    }
+}
+
+impl PlayerStatus {
+    /// A status of any version up to this one, read from `path`, as this
+    /// version.
+    pub fn parse(path: &Path, text: &str) -> Result<Self, String> {
+        let mut status: PlayerStatus = json::parse(path, text)?;
+        if status.schema > SCHEMA_VERSION {
+            return Err(format!(
+                "{}: status schema {} is newer than this player's {}",
+                path.display(),
+                status.schema,
+                SCHEMA_VERSION
+            ));
+        }
+        if status.schema < 2 {
+            status.transport = if status.playing {
+                Transport::Playing
+            } else {
+                Transport::Stopped
+            };
+        }
+        status.schema = SCHEMA_VERSION;
+        Ok(status)
+    }
+}
+
+/// [`PlayerStatus`] as JSON Schema (draft 2020-12). `schema` here is
+/// [`SCHEMA_VERSION`].
+pub const JSON_SCHEMA: &str = r#"{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "title": "Cornwall player status",
+  "type": "object",
+  "properties": {
+    "schema": {"const": 3, "description": "Schema version"},
+    "transport": {
+      "enum": ["stopped", "playing", "paused", "stalled"],
+      "description": "paused: stopped partway, resuming from the same sample; stalled: playing, but the output device has stopped taking audio"
+    },
+    "playing": {"type": "boolean", "description": "transport is playing or stalled"},
+    "loop_mode": {
+      "enum": ["off", "file", "region", "once"],
+      "description": "file: round the whole file; region: round loop_bars; once: loop_bars once"
+    },
+    "loop_bars": {
+      "type": ["array", "null"],
+      "items": {"type": "integer", "minimum": 1},
+      "minItems": 2,
+      "maxItems": 2,
+      "description": "First and last bar of the loop region"
+    },
+    "position_secs": {"type": "number", "description": "Playhead, seconds into the file"},
+    "bar": {"type": "integer", "minimum": 0, "description": "Bar at the playhead, from 1; 0 without a tempo"},
+    "beat": {"type": "integer", "minimum": 0, "description": "Beat in the bar, from 1"},
+    "bpm": {"type": "number", "description": "Tempo"},
+    "time_sig": {"type": "string", "description": "Time signature, such as 4/4"},
+    "level_l": {"type": "number", "description": "Left channel RMS level, linear"},
+    "level_r": {"type": "number", "description": "Right channel RMS level, linear"},
+    "file": {"type": "string", "description": "Audio file playing"},
+    "drift_ms": {"type": "number", "description": "Worst playhead clock drift since playback started"},
+    "drift_corrections": {"type": "integer", "minimum": 0, "description": "Times the clock was pulled back in line"},
+    "pid": {"type": "integer", "minimum": 0, "description": "Process id of the player that wrote it"},
+    "updated": {"type": "number", "description": "Unix time of the write"},
+    "stale": {"type": "boolean", "description": "The writer has exited (--status only)"}
+  },
+  "required": [
+    "schema", "transport", "playing", "loop_mode", "loop_bars", "position_secs", "bar", "beat",
+    "bpm", "time_sig", "level_l", "level_r", "file", "drift_ms", "drift_corrections", "pid",
+    "updated", "stale"
+  ]
+}"#;
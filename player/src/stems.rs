@@ -0,0 +1,64 @@
+//! Region stems (`E`): trimmed copies of the audible tracks' sources
+//! covering just the loop region, for handing off a section. They keep each
+//! source's sample rate, bit depth and channels, and go to
+//! `projects/<name>/stems/bars-<start>-<end>/<track>.wav`.
+
+use cornwall_player::{project::TrackState, wav};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// What an export wrote.
+pub struct Export {
+    pub dir: PathBuf,
+    pub written: usize,
+    /// Audible tracks whose source isn't a WAV file or is missing.
+    pub skipped: usize,
+}
+
+/// Trim the audible tracks' sources to `start`-`end` seconds into
+/// `dir`: the soloed tracks if any are soloed, else the unmuted ones.
+pub fn export(tracks: &[TrackState], dir: &Path, start: f64, end: f64) -> Result<Export, String> {
+    let any_solo = tracks.iter().any(|t| t.solo);
+    let audible = tracks
+        .iter()
+        .filter(|t| if any_solo { t.solo } else { !t.mute })
+        .filter(|t| t.track_type != "midi" && t.source.is_some());
+    fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+
+    let mut export = Export {
+        dir: dir.to_path_buf(),
+        written: 0,
+        skipped: 0,
+    };
+    for track in audible {
+        let source = track.source.as_deref().map(Path::new);
+        let Some(source) = source.filter(|s| is_wav(s) && s.exists()) else {
+            export.skipped += 1;
+            continue;
+        };
+        let dest = dir.join(format!("{:02}-{}.wav", track.id, file_name(&track.name)));
+        wav::trim(source, &dest, start, end)?;
+        export.written += 1;
+    }
+    Ok(export)
+}
+
+fn is_wav(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("wav"))
+}
+
+/// `name` with anything awkward in a file name replaced.
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,295 @@
+//! Playback speed without a pitch change, for slowing a passage down to
+//! transcribe it. WSOLA: Hann-windowed segments of the input overlap-add
+//! at a fixed output hop while the read position moves at the speed, each
+//! segment nudged to where the waveform best lines up with the one before
+//! so the joins don't beat or click.
+//!
+//! The quality (`"output": {"stretch": "best"}`) sets the segment length
+//! and how far and how finely to search. Longer segments keep the pitch of
+//! low notes steadier but smear drums, and a wider, finer search costs CPU.
+//!
+//! Pitch shift is the same stretch followed by playing the result faster
+//! or slower: stretched by the inverse of the pitch ratio and then resampled
+//! by it, the tempo comes back to where it was.
+
+use rodio::Source;
+use serde::Deserialize;
+use std::{f32::consts::PI, time::Duration};
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Quality {
+    /// 30 ms segments, coarse search.
+    Fast,
+    /// 50 ms segments.
+    #[default]
+    Medium,
+    /// 80 ms segments, every offset searched.
+    Best,
+}
+
+impl Quality {
+    /// Segment length and search range either side, in milliseconds, and
+    /// the step between candidate offsets in frames.
+    fn params(&self) -> (f64, f64, usize) {
+        match self {
+            Quality::Fast => (30.0, 5.0, 4),
+            Quality::Medium => (50.0, 10.0, 2),
+            Quality::Best => (80.0, 15.0, 1),
+        }
+    }
+}
+
+/// Slowest and fastest playback speeds.
+pub const MIN_SPEED: f64 = 0.5;
+pub const MAX_SPEED: f64 = 1.5;
+
+/// Largest pitch shift either way, in semitones.
+pub const MAX_SEMITONES: i32 = 12;
+
+/// `source` at `speed` times its tempo and `semitones` up or down.
+pub fn shift<S: Source<Item = f32>>(
+    source: S,
+    speed: f64,
+    semitones: i32,
+    quality: Quality,
+) -> Transpose<Stretch<S>> {
+    let ratio = 2_f64.powf(semitones as f64 / 12.0);
+    Transpose::new(Stretch::new(source, speed / ratio, quality), ratio)
+}
+
+/// Frames compared per candidate are taken one in this many.
+const DECIMATE: usize = 4;
+
+/// Plays a source at `speed` times its tempo, keeping its pitch. Passes
+/// samples through untouched at speed 1.
+pub struct Stretch<S> {
+    inner: S,
+    channels: usize,
+    sample_rate: u32,
+    /// Segment length and output hop, in frames.
+    len: usize,
+    hop: usize,
+    /// Input frames the read position moves per output hop.
+    step: f64,
+    search: i64,
+    search_step: usize,
+    window: Vec<f32>,
+    /// Interleaved input; `buf[0]` is input frame `base`.
+    buf: Vec<f32>,
+    base: i64,
+    /// Total input frames, known once the inner source runs dry.
+    input_len: Option<i64>,
+    /// Where the next segment would start without alignment.
+    nominal: f64,
+    /// Start of the previous segment.
+    prev: Option<i64>,
+    /// Overlap-add of the segments not yet output, one segment long.
+    acc: Vec<f32>,
+    out: Vec<f32>,
+    out_pos: usize,
+    passthrough: bool,
+}
+
+impl<S: Source<Item = f32>> Stretch<S> {
+    pub fn new(inner: S, speed: f64, quality: Quality) -> Self {
+        let channels = inner.channels().max(1) as usize;
+        let sample_rate = inner.sample_rate();
+        let (len_ms, search_ms, search_step) = quality.params();
+        let frames = |ms: f64| (ms / 1000.0 * sample_rate as f64) as usize;
+        let hop = (frames(len_ms) / 2).max(1);
+        let len = hop * 2;
+        // Periodic Hann: overlapping by half, the windows sum to one
+        let window = (0..len)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / len as f32).cos())
+            .collect();
+        Stretch {
+            inner,
+            channels,
+            sample_rate,
+            len,
+            hop,
+            step: hop as f64 * speed,
+            search: frames(search_ms) as i64,
+            search_step,
+            window,
+            buf: Vec::new(),
+            base: 0,
+            input_len: None,
+            nominal: 0.0,
+            prev: None,
+            acc: vec![0.0; len * channels],
+            out: Vec::new(),
+            out_pos: 0,
+            passthrough: speed == 1.0,
+        }
+    }
+
+    /// Make sure input frames up to `last` (exclusive) are buffered, and
+    /// forget those before `first`.
+    fn fill(&mut self, first: i64, last: i64) {
+        let buffered = (self.buf.len() / self.channels) as i64;
+        let drop = (first - self.base).clamp(0, buffered);
+        if drop > 4096 {
+            self.buf.drain(..drop as usize * self.channels);
+            self.base += drop;
+        }
+        let buffered_to = |s: &Self| s.base + (s.buf.len() / s.channels) as i64;
+        while self.input_len.is_none() && buffered_to(self) < last {
+            let start = self.buf.len();
+            for _ in 0..self.channels {
+                match self.inner.next() {
+                    Some(s) => self.buf.push(s),
+                    None => break,
+                }
+            }
+            if self.buf.len() - start < self.channels {
+                self.buf.truncate(start);
+                self.input_len = Some(self.base + (start / self.channels) as i64);
+            }
+        }
+    }
+
+    /// Input frame `frame`, channel `c`; silence outside the buffer.
+    fn sample(&self, frame: i64, c: usize) -> f32 {
+        let i = frame - self.base;
+        if i < 0 {
+            return 0.0;
+        }
+        self.buf
+            .get(i as usize * self.channels + c)
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Channel sum at `frame`, for comparing waveforms.
+    fn mono(&self, frame: i64) -> f32 {
+        (0..self.channels).map(|c| self.sample(frame, c)).sum()
+    }
+
+    /// Segment start near `nominal` whose first half best matches the
+    /// natural continuation of the previous segment.
+    fn align(&self, nominal: i64) -> i64 {
+        let Some(prev) = self.prev else {
+            return nominal;
+        };
+        let target = prev + self.hop as i64;
+        let lo = (nominal - self.search).max(0);
+        let hi = nominal + self.search;
+        let mut best = (f32::MIN, nominal);
+        let mut candidate = lo;
+        while candidate <= hi {
+            let mut score = 0.0;
+            let mut energy = 0.0;
+            for i in (0..self.hop as i64).step_by(DECIMATE) {
+                let x = self.mono(candidate + i);
+                score += x * self.mono(target + i);
+                energy += x * x;
+            }
+            let score = score / energy.sqrt().max(1e-9);
+            if score > best.0 {
+                best = (score, candidate);
+            }
+            candidate += self.search_step as i64;
+        }
+        best.1
+    }
+
+    fn next_hop(&mut self) -> bool {
+        let nominal = self.nominal.round() as i64;
+        let first = (nominal - self.search).min(self.prev.map_or(nominal, |p| p + self.hop as i64));
+        self.fill(first, nominal + self.search + self.len as i64);
+        if self.input_len.is_some_and(|len| nominal >= len) {
+            return false;
+        }
+        let start = self.align(nominal);
+        for (i, w) in self.window.iter().enumerate() {
+            for c in 0..self.channels {
+                self.acc[i * self.channels + c] += self.sample(start + i as i64, c) * w;
+            }
+        }
+        // The first hop has had every segment it will get
+        let done = self.hop * self.channels;
+        self.out.clear();
+        self.out.extend(self.acc.drain(..done));
+        self.acc.resize(self.len * self.channels, 0.0);
+        self.out_pos = 0;
+        self.prev = Some(start);
+        self.nominal += self.step;
+        true
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for Stretch<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.passthrough {
+            return self.inner.next();
+        }
+        if self.out_pos == self.out.len() && !self.next_hop() {
+            return None;
+        }
+        let sample = self.out[self.out_pos];
+        self.out_pos += 1;
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Stretch<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Relabels a source's sample rate so the resampler after it plays it
+/// `ratio` times faster, raising the pitch as much.
+pub struct Transpose<S> {
+    inner: S,
+    sample_rate: u32,
+}
+
+impl<S: Source<Item = f32>> Transpose<S> {
+    fn new(inner: S, ratio: f64) -> Self {
+        let sample_rate = (inner.sample_rate() as f64 * ratio).round() as u32;
+        Transpose { inner, sample_rate }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for Transpose<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.inner.next()
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Transpose<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
@@ -0,0 +1,344 @@
+//! The player end to end on the null output: the same `App` the terminal
+//! UI runs, driven by key presses and ticked as its event loop ticks it,
+//! with the main and preview sinks mixed into a [`NullOutput`] instead of
+//! a device. Time only passes as the tests render audio, so the playhead,
+//! the meters and the status file can be checked against what was heard.
+
+use crate::{
+    config::PlayerConfig,
+    handle_key,
+    keymap::parse_key_event,
+    output::{Device, Sinks},
+    App,
+};
+use cornwall_player::{
+    null::NullOutput,
+    status::{LoopMode, PlayerStatus, Transport},
+};
+use rodio::Sink;
+use std::{
+    cell::RefCell,
+    fs,
+    rc::Rc,
+    thread,
+    time::{Duration, Instant},
+};
+use support::{assert_silent, ramp, ramp_frame, tone, write_wav, TempProject, RAMP_FRAMES};
+
+#[path = "../tests/support/mod.rs"]
+mod support;
+
+/// 8 kHz keeps the mixes small.
+const RATE: u32 = 8000;
+/// Rendered between ticks: 25 ms, about as often as the UI ticks.
+const TICK_FRAMES: usize = 200;
+/// How far the playhead may be from the audio. The sink reports its
+/// position 5 ms at a time and starts on a buffer of silence, and the
+/// playhead follows it as it would a device.
+const TOLERANCE: f64 = 0.03;
+
+/// The null output as the player's device: both sinks mixed into it the
+/// way a device mixes them, playing only as the test renders, with the
+/// frames rendered since it opened as the transport's clock.
+struct NullDevice {
+    null: Rc<RefCell<NullOutput>>,
+    opened: Instant,
+}
+
+impl Device for NullDevice {
+    /// There's no output thread to pull the sink past what it was playing,
+    /// so render while it clears.
+    fn clear(&mut self, sink: &Sink) {
+        thread::scope(|scope| {
+            let clearing = scope.spawn(|| sink.clear());
+            while !clearing.is_finished() {
+                self.null.borrow_mut().render(1);
+            }
+        });
+    }
+
+    fn now(&self) -> Instant {
+        self.opened + Duration::from_secs_f64(self.null.borrow().played_secs())
+    }
+
+    fn realtime(&self) -> bool {
+        false
+    }
+}
+
+impl NullDevice {
+    /// Sinks on a new null output in `channels` at `sample_rate`, and the
+    /// output for the test to render.
+    fn sinks(channels: u16, sample_rate: u32) -> (Sinks, Rc<RefCell<NullOutput>>) {
+        let (main, main_queue) = Sink::new_idle();
+        main.pause();
+        let (preview, preview_queue) = Sink::new_idle();
+        let (mixer, mixed) = rodio::dynamic_mixer::mixer::<f32>(channels, sample_rate);
+        mixer.add(main_queue);
+        mixer.add(preview_queue);
+        let mut null = NullOutput::new(channels, sample_rate);
+        null.play(mixed);
+        let null = Rc::new(RefCell::new(null));
+        let device = NullDevice {
+            null: null.clone(),
+            opened: Instant::now(),
+        };
+        let sinks = Sinks {
+            device: Box::new(device),
+            main,
+            preview,
+            channels,
+            sample_rate,
+        };
+        (sinks, null)
+    }
+}
+
+struct Harness {
+    app: App,
+    null: Rc<RefCell<NullOutput>>,
+    _project: TempProject,
+}
+
+impl Harness {
+    /// A 4/4 project at `bpm` playing `samples`, stereo and interleaved,
+    /// in a directory of its own named after `test`, with the default
+    /// config.
+    fn new(test: &str, bpm: f64, samples: &[i16]) -> Self {
+        let project = TempProject::new(&format!("app-{}", test), bpm, RATE);
+        write_wav(&project.mix(), 2, RATE, samples);
+
+        let mut null = None;
+        let mut app = App::open(
+            project.state_dir(),
+            project.mix(),
+            PlayerConfig::default(),
+            Vec::new(),
+            |spec| {
+                let (sinks, output) = NullDevice::sinks(2, spec.sample_rate);
+                null = Some(output);
+                Ok(sinks)
+            },
+        )
+        .unwrap();
+        // Decoded in the background; a source plays silence where the
+        // loader hasn't got to yet
+        while app.meter.incoming.is_some() {
+            app.meter.receive();
+            thread::sleep(Duration::from_millis(1));
+        }
+        Harness {
+            app,
+            null: null.unwrap(),
+            _project: project,
+        }
+    }
+
+    /// Press `key`, named as in key bindings.
+    fn press(&mut self, key: &str) {
+        handle_key(&mut self.app, parse_key_event(key).unwrap());
+    }
+
+    /// The next `secs` of output, ticking the player after each
+    /// `TICK_FRAMES` of it.
+    fn render(&mut self, secs: f64) -> Vec<f32> {
+        let ticks = (secs * RATE as f64).round() as usize / TICK_FRAMES;
+        let mut rendered = Vec::new();
+        for _ in 0..ticks {
+            rendered.extend(self.null.borrow_mut().render(TICK_FRAMES));
+            self.app.tick();
+        }
+        rendered
+    }
+
+    fn status(&self) -> PlayerStatus {
+        self.app.status()
+    }
+
+    /// The status file as the player last wrote it.
+    fn status_file(&self) -> PlayerStatus {
+        let path = self.app.state_dir.join(".player.json");
+        PlayerStatus::parse(&path, &fs::read_to_string(&path).unwrap()).unwrap()
+    }
+}
+
+fn frames(secs: f64) -> usize {
+    (secs * RATE as f64) as usize
+}
+
+fn assert_near(read: f64, expected: f64, what: &str) {
+    assert!(
+        (read - expected).abs() <= TOLERANCE,
+        "{}: read {}, expected {}",
+        what,
+        read,
+        expected
+    );
+}
+
+/// Check the last frame of a `ramp` rendered came from `secs` into it.
+fn assert_heard_at(rendered: &[f32], secs: f64) {
+    let heard = ramp_frame(rendered[rendered.len() - 2]) as i64;
+    let expected = (secs * RATE as f64).round() as i64;
+    let period = RAMP_FRAMES as i64;
+    let off = (heard - expected).rem_euclid(period);
+    let off = off.min(period - off) as f64 / RATE as f64;
+    assert!(
+        off <= TOLERANCE,
+        "heard frame {}, expected {} s in",
+        heard,
+        secs
+    );
+}
+
+#[test]
+fn stopped_renders_silence_and_holds_the_playhead() {
+    let mut h = Harness::new("stopped", 120.0, &ramp(frames(2.0)));
+    assert_silent(&h.render(0.5));
+    let status = h.status();
+    assert_eq!(status.transport, Transport::Stopped);
+    assert_eq!(status.position_secs, 0.0);
+}
+
+#[test]
+fn play_key_plays_and_the_playhead_follows_the_audio() {
+    let mut h = Harness::new("play", 120.0, &ramp(frames(2.0)));
+    h.press("space");
+    let rendered = h.render(1.0);
+    let status = h.status();
+    assert_eq!(status.transport, Transport::Playing);
+    assert_near(status.position_secs, 1.0, "playhead");
+    assert_heard_at(&rendered, status.position_secs);
+}
+
+#[test]
+fn stop_key_silences_and_rewinds() {
+    let mut h = Harness::new("stop", 120.0, &ramp(frames(2.0)));
+    h.press("space");
+    h.render(1.0);
+    h.press("enter");
+    assert_silent(&h.render(0.5));
+    let status = h.status();
+    assert_eq!(status.transport, Transport::Stopped);
+    assert_eq!(status.position_secs, 0.0);
+    assert_eq!((status.level_l, status.level_r), (0.0, 0.0));
+}
+
+#[test]
+fn play_key_pauses_and_resumes_where_it_left_off() {
+    let mut h = Harness::new("pause", 120.0, &ramp(frames(2.0)));
+    h.press("space");
+    h.render(1.0);
+    h.press("space");
+    let held = h.status().position_secs;
+    // The sink takes up to 5 ms to hear of it
+    h.render(0.025);
+    assert_silent(&h.render(0.5));
+    let status = h.status();
+    assert_eq!(status.transport, Transport::Paused);
+    assert_eq!(status.position_secs, held);
+
+    h.press("space");
+    let rendered = h.render(0.5);
+    let status = h.status();
+    assert_eq!(status.transport, Transport::Playing);
+    assert_near(status.position_secs, held + 0.5, "after resuming");
+    assert_heard_at(&rendered, status.position_secs);
+}
+
+#[test]
+fn a_loop_change_while_paused_holds_on_resume() {
+    let mut h = Harness::new("pause-loop", 120.0, &ramp(frames(1.0)));
+    h.press("space");
+    h.render(0.5);
+    h.press("space");
+    h.press("l");
+    h.press("space");
+    // Not round the loop the paused source was playing
+    assert_eq!(h.app.play_loop, None);
+    h.render(1.0);
+    let status = h.status();
+    assert_eq!(status.transport, Transport::Stopped);
+    assert_eq!(status.position_secs, 0.0);
+}
+
+#[test]
+fn seek_keys_move_the_playhead_and_the_audio() {
+    let mut h = Harness::new("seek", 120.0, &ramp(frames(12.0)));
+    h.press("space");
+    h.render(1.0);
+    let from = h.status().position_secs;
+    h.press("right");
+    let rendered = h.render(0.5);
+    let position = h.status().position_secs;
+    assert_near(position, from + 5.5, "after seeking forward");
+    assert_heard_at(&rendered, position);
+
+    h.press("left");
+    let rendered = h.render(0.5);
+    let position = h.status().position_secs;
+    assert_near(position, from + 1.0, "after seeking back");
+    assert_heard_at(&rendered, position);
+}
+
+#[test]
+fn looping_goes_round_the_file_until_turned_off() {
+    let mut h = Harness::new("loop", 120.0, &ramp(frames(1.0)));
+    assert_eq!(h.status().loop_mode, LoopMode::File);
+    h.press("space");
+    let rendered = h.render(1.5);
+    let status = h.status();
+    assert_eq!(status.transport, Transport::Playing);
+    assert_near(status.position_secs, 0.5, "playhead after going round");
+    assert_heard_at(&rendered, status.position_secs);
+
+    h.press("l");
+    assert_eq!(h.status().loop_mode, LoopMode::Off);
+    h.render(1.0);
+    let status = h.status();
+    assert_eq!(status.transport, Transport::Stopped);
+    assert_eq!(status.position_secs, 0.0);
+}
+
+#[test]
+fn bar_and_beat_follow_the_playhead() {
+    // 120 BPM in 4/4: half a second a beat, two seconds a bar
+    let mut h = Harness::new("bars", 120.0, &ramp(frames(4.0)));
+    h.press("space");
+    h.render(2.75);
+    let status = h.status();
+    assert_eq!((status.bar, status.beat), (2, 2));
+    assert_eq!(status.bpm, 120.0);
+    assert_eq!(status.time_sig, "4/4");
+}
+
+#[test]
+fn meters_read_what_the_output_played() {
+    let mut h = Harness::new("meters", 120.0, &tone(440.0, 0.5, frames(2.0), RATE));
+    h.press("space");
+    h.render(0.5);
+    let status = h.status();
+    // A sine's RMS is its amplitude over √2
+    assert!(
+        (status.level_l - 0.5 / 2f64.sqrt()).abs() < 0.01,
+        "left level {}",
+        status.level_l
+    );
+    assert_eq!(status.level_r, 0.0);
+}
+
+#[test]
+fn status_file_follows_playback() {
+    let mut h = Harness::new("status", 120.0, &ramp(frames(4.0)));
+    h.press("space");
+    h.render(1.0);
+    let written = h.status_file();
+    let status = h.status();
+    assert_eq!(written.transport, Transport::Playing);
+    assert_eq!(written.position_secs, status.position_secs);
+    assert_eq!((written.bar, written.beat), (status.bar, status.beat));
+    // JSON reads a float back to within a rounding step
+    assert!((written.level_l - status.level_l).abs() < 1e-12);
+    assert_eq!(written.file, status.file);
+    assert_eq!(written.pid, std::process::id());
+}
@@ -0,0 +1,34 @@
+//! Fitting names into fixed-width columns by display width, so CJK and
+//! emoji (two cells each) keep the track list aligned.
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Cut `text` to at most `width` cells, ending in `…` if anything was cut.
+pub fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        // Leave room for the ellipsis
+        if used + w + 1 > width {
+            break;
+        }
+        out.push(c);
+        used += w;
+    }
+    if width > 0 {
+        out.push('…');
+    }
+    out
+}
+
+/// `text` truncated and then padded with spaces to exactly `width` cells.
+pub fn fit(text: &str, width: usize) -> String {
+    let mut out = truncate(text, width);
+    let pad = width.saturating_sub(out.width());
+    out.extend(std::iter::repeat_n(' ', pad));
+    out
+}
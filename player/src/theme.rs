@@ -0,0 +1,96 @@
+//! Fallback rendering for limited terminals (`--ascii`, `--no-color`).
+//!
+//! Widgets draw with unicode glyphs and colors as usual; the finished frame
+//! buffer is then rewritten cell by cell, so new panes get the fallback for
+//! free.
+
+use ratatui::{
+    buffer::Buffer,
+    style::{Color, Modifier},
+};
+
+#[derive(Clone, Copy, Default)]
+pub struct Theme {
+    pub ascii: bool,
+    pub no_color: bool,
+    /// Draw the waveform and meters with braille dots instead of blocks.
+    pub braille: bool,
+}
+
+impl Theme {
+    pub fn new(ascii: bool, no_color: bool, no_braille: bool) -> Self {
+        // https://no-color.org
+        let env_no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        Theme {
+            ascii,
+            no_color: no_color || env_no_color,
+            braille: !ascii && !no_braille && braille_supported(),
+        }
+    }
+
+    pub fn apply(&self, buf: &mut Buffer) {
+        if !self.ascii && !self.no_color {
+            return;
+        }
+        for cell in buf.content.iter_mut() {
+            if self.no_color {
+                if cell.bg != Color::Reset {
+                    // Badges keep their shape as reverse video
+                    cell.modifier.insert(Modifier::REVERSED);
+                }
+                cell.fg = Color::Reset;
+                cell.bg = Color::Reset;
+            }
+            if self.ascii {
+                let mut chars = cell.symbol().chars();
+                if let (Some(c), None) = (chars.next(), chars.next()) {
+                    if let Some(a) = ascii_fallback(c) {
+                        cell.set_char(a);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The Linux console font has no braille patterns, and neither do the
+/// minimal terminal types.
+fn braille_supported() -> bool {
+    match std::env::var("TERM") {
+        Ok(term) => !matches!(term.as_str(), "linux" | "dumb" | "vt100" | "vt220"),
+        Err(_) => false,
+    }
+}
+
+/// ASCII stand-in for a single-width symbol glyph. Letters (e.g. in track
+/// names) are left alone.
+fn ascii_fallback(c: char) -> Option<char> {
+    if c.is_ascii() {
+        return None;
+    }
+    Some(match c {
+        '█' | '▇' | '▆' | '▅' => '#',
+        '▄' | '▃' | '▂' | '▁' => '=',
+        '░' | '▒' | '▓' => '.',
+        '━' | '═' => '=',
+        '─' | '╌' | '┄' => '-',
+        '│' | '┃' | '║' => '|',
+        '╸' | '▶' | '▸' | '→' | '►' => '>',
+        '◂' | '◀' | '←' => '<',
+        '■' | '◼' => '#',
+        '◆' | '◉' | '●' | '★' => '*',
+        '◇' | '○' => 'o',
+        '·' | '•' => '.',
+        '…' => '~',
+        '–' | '—' => '-',
+        // Remaining box drawing corners and junctions
+        '\u{2500}'..='\u{257F}' => '+',
+        // Remaining block elements, shapes and braille
+        '\u{2580}'..='\u{259F}' => '#',
+        '\u{25A0}'..='\u{25FF}' => '*',
+        '\u{2800}' => ' ',
+        '\u{2801}'..='\u{28FF}' => ':',
+        '\u{2190}'..='\u{21FF}' => '>',
+        _ => return None,
+    })
+}
@@ -0,0 +1,168 @@
+//! Time signatures. The tempo counts the written note value (eighths in
+//! 6/8), so a bar always lasts `numerator` tempo beats; compound meters
+//! (6/8, 9/8, 12/8, 6/16 …) group those pulses in threes, so the beat
+//! display counts dotted beats: 6/8 has two beats per bar, 12/8 four.
+
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct TimeSig {
+    pub numerator: u32,
+    pub denominator: u32,
+}
+
+impl Default for TimeSig {
+    fn default() -> Self {
+        TimeSig {
+            numerator: 4,
+            denominator: 4,
+        }
+    }
+}
+
+impl TimeSig {
+    /// Parse `"6/8"`. The denominator must be a power of two up to 32.
+    pub fn parse(s: &str) -> Option<Self> {
+        let (num, den) = s.trim().split_once('/')?;
+        let numerator: u32 = num.trim().parse().ok().filter(|n| (1..=64).contains(n))?;
+        let denominator: u32 = den
+            .trim()
+            .parse()
+            .ok()
+            .filter(|d: &u32| d.is_power_of_two() && *d <= 32)?;
+        Some(TimeSig {
+            numerator,
+            denominator,
+        })
+    }
+
+    pub fn compound(&self) -> bool {
+        self.numerator > 3 && self.numerator.is_multiple_of(3) && self.denominator >= 8
+    }
+
+    /// Tempo beats (written notes) that make up one counted beat.
+    pub fn pulses_per_beat(&self) -> u32 {
+        if self.compound() {
+            3
+        } else {
+            1
+        }
+    }
+
+    /// Counted beats per bar: the numerator, or the dotted groups in a
+    /// compound meter.
+    pub fn beats_per_bar(&self) -> u32 {
+        self.numerator / self.pulses_per_beat()
+    }
+
+    /// Seconds per bar at `bpm`, `numerator` tempo beats; none without a
+    /// tempo.
+    pub fn bar_secs(&self, bpm: f64) -> Option<f64> {
+        (bpm > 0.0).then(|| self.numerator as f64 * 60.0 / bpm)
+    }
+
+    /// 1-based bar and counted beat `secs` in at `bpm`; bar 1, beat 1
+    /// without a tempo.
+    pub fn bar_beat(&self, secs: f64, bpm: f64) -> (u32, u32) {
+        if bpm <= 0.0 {
+            return (1, 1);
+        }
+        let pulse = secs * bpm / 60.0;
+        let bar = (pulse / self.numerator as f64) as u32 + 1;
+        let in_bar = pulse % self.numerator as f64;
+        let beat = (in_bar / self.pulses_per_beat() as f64) as u32 + 1;
+        (bar, beat)
+    }
+}
+
+impl fmt::Display for TimeSig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sig(s: &str) -> TimeSig {
+        TimeSig::parse(s).unwrap()
+    }
+
+    #[test]
+    fn parses_numerator_over_a_power_of_two() {
+        for (text, numerator, denominator) in [("6/8", 6, 8), ("7/8", 7, 8), (" 3 / 4 ", 3, 4)] {
+            let parsed = sig(text);
+            assert_eq!(
+                (parsed.numerator, parsed.denominator),
+                (numerator, denominator),
+                "{}",
+                text
+            );
+        }
+        assert_eq!(sig("12/8").to_string(), "12/8");
+    }
+
+    #[test]
+    fn rejects_empty_bars_and_odd_or_zero_denominators() {
+        for text in [
+            "0/4", "4/0", "4/6", "4/64", "65/4", "4", "4/", "/4", "a/b", "-3/4", "",
+        ] {
+            assert!(TimeSig::parse(text).is_none(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn compound_meters_count_dotted_beats() {
+        for (text, beats) in [("6/8", 2), ("9/8", 3), ("12/8", 4), ("6/16", 2)] {
+            assert!(sig(text).compound(), "{}", text);
+            assert_eq!(sig(text).beats_per_bar(), beats, "{}", text);
+        }
+        for (text, beats) in [("4/4", 4), ("3/8", 3), ("7/8", 7), ("6/4", 6)] {
+            assert!(!sig(text).compound(), "{}", text);
+            assert_eq!(sig(text).beats_per_bar(), beats, "{}", text);
+        }
+    }
+
+    #[test]
+    fn tempo_counts_the_written_note() {
+        // 120 eighths a minute: six to a bar is 3 s
+        assert_eq!(sig("6/8").bar_secs(120.0), Some(3.0));
+        assert_eq!(sig("7/8").bar_secs(120.0), Some(3.5));
+        assert_eq!(sig("4/4").bar_secs(120.0), Some(2.0));
+        assert_eq!(sig("4/4").bar_secs(0.0), None);
+    }
+
+    #[test]
+    fn bar_beat_in_six_eight() {
+        let six_eight = sig("6/8");
+        // Dotted quarters of 1.5 s at 120 BPM
+        for (secs, expected) in [
+            (0.0, (1, 1)),
+            (1.49, (1, 1)),
+            (1.5, (1, 2)),
+            (2.99, (1, 2)),
+            (3.0, (2, 1)),
+            (4.5, (2, 2)),
+            (30.0, (11, 1)),
+        ] {
+            assert_eq!(six_eight.bar_beat(secs, 120.0), expected, "{} s", secs);
+        }
+    }
+
+    #[test]
+    fn bar_beat_in_simple_and_odd_meters() {
+        assert_eq!(sig("4/4").bar_beat(0.0, 120.0), (1, 1));
+        assert_eq!(sig("4/4").bar_beat(1.75, 120.0), (1, 4));
+        assert_eq!(sig("4/4").bar_beat(2.0, 120.0), (2, 1));
+        // 7/8 counts every eighth
+        assert_eq!(sig("7/8").bar_beat(3.0, 120.0), (1, 7));
+        assert_eq!(sig("7/8").bar_beat(3.5, 120.0), (2, 1));
+    }
+
+    #[test]
+    fn bar_beat_without_a_tempo_stays_on_the_downbeat() {
+        assert_eq!(sig("4/4").bar_beat(10.0, 0.0), (1, 1));
+        assert_eq!(sig("6/8").bar_beat(10.0, -1.0), (1, 1));
+    }
+}
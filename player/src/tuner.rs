@@ -0,0 +1,174 @@
+//! Instrument tuner (`T`): listens to an input device, detects the pitch of
+//! its first channel and shows the nearest note and how many cents off it
+//! is, so nothing else needs starting between takes.
+//!
+//! ```json
+//! "tuner": {"device": "Scarlett", "a4": 440}
+//! ```
+//!
+//! `device` is a substring of the input name (the default input when
+//! unset). The input is only open while the tuner is shown.
+
+use crate::{i18n::tr, input::Input, utilities::centered_rect};
+use cornwall_dsp::pitch::{self, NOTE_NAMES};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+/// Time between pitch readings.
+const INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long the last pitch stays up after the note dies away.
+const HOLD: Duration = Duration::from_secs(1);
+
+/// Input kept for each reading: a little over two periods of the lowest
+/// pitch.
+const KEEP_SECS: f64 = 2.5 / pitch::MIN_HZ;
+
+/// Cents either side of the note the needle spans.
+const RANGE_CENTS: f64 = 50.0;
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct TunerConfig {
+    /// Substring of the input device name; the default input when absent.
+    pub device: Option<String>,
+    /// Concert pitch in Hz.
+    pub a4: f64,
+}
+
+impl Default for TunerConfig {
+    fn default() -> Self {
+        TunerConfig {
+            device: None,
+            a4: 440.0,
+        }
+    }
+}
+
+/// An open input and the pitch last heard on it.
+pub struct Tuner {
+    input: Input,
+    pitch: Option<f64>,
+    heard: Option<Instant>,
+    checked: Option<Instant>,
+}
+
+impl Tuner {
+    /// Open the input matching `device`, or the default input.
+    pub fn open(device: Option<&str>) -> Result<Tuner, String> {
+        Ok(Tuner {
+            input: Input::open(device, KEEP_SECS)?,
+            pitch: None,
+            heard: None,
+            checked: None,
+        })
+    }
+
+    /// Read the pitch again if it's time.
+    pub fn update(&mut self) {
+        if self.checked.is_some_and(|at| at.elapsed() < INTERVAL) {
+            return;
+        }
+        self.checked = Some(Instant::now());
+        let block = self.input.samples();
+        let rate = self.input.sample_rate;
+        if block.len() < (rate as f64 * KEEP_SECS) as usize {
+            return;
+        }
+        match pitch::detect(&block, rate) {
+            Some(hz) => {
+                self.pitch = Some(hz);
+                self.heard = Some(Instant::now());
+            }
+            None if self.heard.is_some_and(|at| at.elapsed() > HOLD) => self.pitch = None,
+            None => {}
+        }
+    }
+}
+
+/// The tuner popup: note, frequency and a needle across ±50 cents, green
+/// within 5 cents.
+pub fn render(f: &mut Frame, tuner: &Tuner, a4: f64) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let width = 31;
+    let mut lines = vec![Line::from("")];
+    match tuner.pitch {
+        Some(hz) => {
+            let (note, cents) = pitch::nearest_note(hz, a4);
+            let name = format!(
+                "{}{}",
+                NOTE_NAMES[note.rem_euclid(12) as usize],
+                note.div_euclid(12) - 1
+            );
+            let color = if cents.abs() <= 5.0 {
+                Color::Green
+            } else if cents.abs() <= 15.0 {
+                Color::Yellow
+            } else {
+                Color::Red
+            };
+            lines.push(Line::from(vec![
+                Span::raw("    "),
+                Span::styled(
+                    format!("{:<5}", name),
+                    Style::default().fg(color).add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(format!("{:>8.1} Hz  ", hz), dim),
+                Span::styled(
+                    format!("{:+.0} {}", cents, tr("tuner.cents")),
+                    Style::default().fg(color),
+                ),
+            ]));
+            let at = ((cents / RANGE_CENTS + 1.0) / 2.0 * (width - 1) as f64).round() as usize;
+            let needle: Vec<Span> = (0..width)
+                .map(|i| {
+                    if i == at.min(width - 1) {
+                        Span::styled("┃", Style::default().fg(color))
+                    } else if i == width / 2 {
+                        Span::styled("┼", dim)
+                    } else {
+                        Span::styled("─", dim)
+                    }
+                })
+                .collect();
+            lines.push(Line::from(""));
+            lines.push(Line::from(
+                [
+                    vec![Span::styled("    b ", dim)],
+                    needle,
+                    vec![Span::styled(" #", dim)],
+                ]
+                .concat(),
+            ));
+        }
+        None => {
+            lines.push(Line::from(Span::styled(
+                format!("    {}", tr("tuner.listening")),
+                dim,
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(""));
+        }
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!("    A4 = {} Hz   T {}", a4, tr("tuner.close")),
+        dim,
+    )));
+
+    let area = centered_rect(44, lines.len() as u16 + 2, f.area());
+    let popup = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(dim)
+            .title(format!(" {} ", tr("tuner.title"))),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
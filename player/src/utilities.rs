@@ -0,0 +1,119 @@
+//! Utilities menu (key `u`): reference tones for level calibration,
+//! routing checks and room response. `:tone` plays any sine frequency.
+
+use crate::{
+    generator::{Routing, Signal},
+    i18n::tr,
+};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+pub const SIGNALS: [Signal; 4] = [
+    Signal::Sine { freq: 1000.0 },
+    Signal::Pink,
+    Signal::White,
+    Signal::Sweep,
+];
+pub const LEVELS: [f32; 6] = [-12.0, -14.0, -18.0, -20.0, -23.0, -24.0];
+
+pub struct UtilityMenu {
+    pub open: bool,
+    pub selected: usize,
+    pub level_idx: usize,
+    pub routing: Routing,
+}
+
+impl Default for UtilityMenu {
+    fn default() -> Self {
+        UtilityMenu {
+            open: false,
+            selected: 0,
+            level_idx: 3,
+            routing: Routing::Both,
+        }
+    }
+}
+
+impl UtilityMenu {
+    pub fn signal(&self) -> Signal {
+        SIGNALS[self.selected]
+    }
+
+    pub fn level(&self) -> f32 {
+        LEVELS[self.level_idx]
+    }
+
+    pub fn select(&mut self, delta: isize) {
+        self.selected =
+            (self.selected as isize + delta).rem_euclid(SIGNALS.len() as isize) as usize;
+    }
+
+    pub fn change_level(&mut self, delta: isize) {
+        self.level_idx =
+            (self.level_idx as isize + delta).clamp(0, LEVELS.len() as isize - 1) as usize;
+    }
+}
+
+pub fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+pub fn render(f: &mut Frame, menu: &UtilityMenu, active: Option<Signal>) {
+    let key = Style::default()
+        .fg(Color::White)
+        .add_modifier(Modifier::BOLD);
+    let dim = Style::default().fg(Color::DarkGray);
+
+    let mut lines = vec![Line::from("")];
+    for (i, signal) in SIGNALS.iter().enumerate() {
+        let cursor = if i == menu.selected { "  ▸ " } else { "    " };
+        let running = if active == Some(*signal) { "  ◉" } else { "" };
+        lines.push(Line::from(vec![
+            Span::styled(cursor, Style::default().fg(Color::Cyan)),
+            Span::styled(format!("{:<12}", signal.label()), key),
+            Span::styled(running, Style::default().fg(Color::Magenta)),
+        ]));
+    }
+    let unit = if menu.signal().rms() {
+        tr("utilities.rms")
+    } else {
+        tr("utilities.peak")
+    };
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled(format!("    {:<8}", tr("utilities.level")), dim),
+        Span::styled(format!("◂ {:.0} {} ▸", menu.level(), unit), key),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled(format!("    {:<8}", tr("utilities.output")), dim),
+        Span::styled(menu.routing.label(), key),
+        Span::styled("  (c)", dim),
+    ]));
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!("    {}", tr("utilities.help")),
+        dim,
+    )));
+
+    let area = centered_rect(44, lines.len() as u16 + 2, f.area());
+    let popup = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::DarkGray))
+            .title(format!(" {} ", tr("utilities.title"))),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
@@ -0,0 +1,104 @@
+//! Catches the audio output stalling for good while playing: the sink's
+//! position not moving for `stall_ms` (`"watchdog": {"stall_ms": 500}` in
+//! the player config). The playhead then holds where the sound stopped
+//! instead of running on over silence, a STALLED badge shows, and the
+//! player reopens the output device and plays on from there, trying again
+//! every `RETRY` until the sink moves.
+//!
+//! [`crate::drift`] counts the short stalls that clear up by themselves;
+//! this is for the ones that don't.
+
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+/// Between attempts to reopen an output that stays stalled.
+const RETRY: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    /// A sink frozen this long while playing has stalled.
+    pub stall_ms: u64,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        WatchdogConfig { stall_ms: 500 }
+    }
+}
+
+/// What the watchdog saw on a tick.
+#[derive(PartialEq)]
+pub enum Event {
+    /// The output just stalled.
+    Stalled,
+    /// Still stalled, and due another attempt at reopening it.
+    Retry,
+    /// Moving again after a stall.
+    Resumed,
+}
+
+pub struct Watchdog {
+    stall: Duration,
+    last_played: f64,
+    /// When the sink last moved, or playback (re)started.
+    moved: Instant,
+    /// Stalled and not moving again yet.
+    pub stalled: bool,
+    /// When reopening the output was last tried.
+    tried: Instant,
+    /// Stalls recovered from since the player started.
+    pub recoveries: u32,
+}
+
+impl Watchdog {
+    pub fn new(config: &WatchdogConfig) -> Self {
+        Watchdog {
+            stall: Duration::from_millis(config.stall_ms.max(1)),
+            last_played: 0.0,
+            moved: Instant::now(),
+            stalled: false,
+            tried: Instant::now(),
+            recoveries: 0,
+        }
+    }
+
+    /// Playback (re)started on a new source, which begins at 0. A stall
+    /// stands until that moves.
+    pub fn reset(&mut self) {
+        self.last_played = 0.0;
+        self.moved = Instant::now();
+    }
+
+    /// Playback stopped, so nothing is stalled any more.
+    pub fn clear(&mut self) {
+        self.reset();
+        self.stalled = false;
+    }
+
+    /// Note the sink's position, seconds played since the last reset.
+    pub fn watch(&mut self, played: f64) -> Option<Event> {
+        let now = Instant::now();
+        if played != self.last_played {
+            self.last_played = played;
+            self.moved = now;
+            if !std::mem::take(&mut self.stalled) {
+                return None;
+            }
+            self.recoveries += 1;
+            return Some(Event::Resumed);
+        }
+        if now.duration_since(self.moved) < self.stall {
+            return None;
+        }
+        if !self.stalled {
+            self.stalled = true;
+            self.tried = now;
+            return Some(Event::Stalled);
+        }
+        (now.duration_since(self.tried) >= RETRY).then(|| {
+            self.tried = now;
+            Event::Retry
+        })
+    }
+}
@@ -0,0 +1,419 @@
+//! Reading WAV files as normalized samples, and writing them. [`Stream`]
+//! decodes a file straight from disk, and [`ReadAhead`] plays it (or any
+//! source) from a ring filled on a thread of its own, so the audio thread
+//! never waits on the disk.
+
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use rodio::Source;
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// File buffer under a [`Stream`].
+const STREAM_BUFFER: usize = 64 * 1024;
+/// Frames a [`Stream`] decodes at a time.
+pub const PREFETCH_FRAMES: usize = 4096;
+/// How far a [`ReadAhead`] reads ahead of where it plays.
+pub const READ_AHEAD_SECS: f64 = 1.0;
+/// How often a [`ReadAhead`]'s reader tops the ring up.
+const REFILL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Interleaved samples scaled to -1..1, whatever the file's bit depth.
+pub fn read(path: &Path) -> Result<(WavSpec, Vec<f64>), String> {
+    let reader = WavReader::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let spec = reader.spec();
+    Ok((spec, normalized(reader).collect()))
+}
+
+/// Like [`read`], handing the samples to `block` `frames` at a time as they
+/// are decoded instead of collecting them. Stops early when `block` returns
+/// false.
+pub fn read_blocks<R: Read + 'static>(
+    reader: WavReader<R>,
+    frames: usize,
+    mut block: impl FnMut(&[f64]) -> bool,
+) {
+    let size = frames.max(1) * reader.spec().channels.max(1) as usize;
+    let mut samples = normalized(reader);
+    let mut buf = Vec::with_capacity(size);
+    loop {
+        buf.clear();
+        buf.extend(samples.by_ref().take(size));
+        if buf.is_empty() || !block(&buf) {
+            return;
+        }
+    }
+}
+
+/// What an integer sample of `spec` reads at full scale, so dividing by it
+/// gives -1..1: 2^15 for 16-bit, 2^23 for 24-bit. `None` for float files,
+/// which are in that range already.
+pub fn full_scale(spec: WavSpec) -> Option<f64> {
+    (spec.sample_format == SampleFormat::Int)
+        .then(|| (1_i64 << (spec.bits_per_sample.clamp(8, 32) - 1)) as f64)
+}
+
+/// The samples of `reader` scaled to -1..1, decoded as they're taken.
+pub fn normalized<R: Read + 'static>(reader: WavReader<R>) -> Box<dyn Iterator<Item = f64>> {
+    match full_scale(reader.spec()) {
+        None => Box::new(
+            reader
+                .into_samples::<f32>()
+                .filter_map(|s| s.ok())
+                .map(|s| s as f64),
+        ),
+        // Integer samples come back unscaled: 24-bit peaks at 2^23
+        Some(full_scale) => Box::new(
+            reader
+                .into_samples::<i32>()
+                .filter_map(|s| s.ok())
+                .map(move |s| s as f64 / full_scale),
+        ),
+    }
+}
+
+/// Writes mono `samples` (-1..1) as a 32-bit float WAV file.
+pub fn write_mono(path: &Path, sample_rate: u32, samples: &[f64]) -> Result<(), String> {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    let error = |e: hound::Error| format!("{}: {}", path.display(), e);
+    let mut writer = WavWriter::create(path, spec).map_err(error)?;
+    for &s in samples {
+        writer.write_sample(s as f32).map_err(error)?;
+    }
+    writer.finalize().map_err(error)
+}
+
+/// Copies `start_secs`..`end_secs` of `source` to `dest` in the source's own
+/// format, returning the frames written.
+pub fn trim(source: &Path, dest: &Path, start_secs: f64, end_secs: f64) -> Result<u64, String> {
+    let mut reader = WavReader::open(source).map_err(|e| format!("{}: {}", source.display(), e))?;
+    let spec = reader.spec();
+    let frames = reader.duration() as u64;
+    let start = ((start_secs * spec.sample_rate as f64) as u64).min(frames);
+    let end = ((end_secs * spec.sample_rate as f64) as u64).clamp(start, frames);
+    reader
+        .seek(start as u32)
+        .map_err(|e| format!("{}: {}", source.display(), e))?;
+    let count = ((end - start) * spec.channels as u64) as usize;
+
+    let error = |e: hound::Error| format!("{}: {}", dest.display(), e);
+    let mut writer = WavWriter::create(dest, spec).map_err(error)?;
+    match spec.sample_format {
+        SampleFormat::Float => {
+            for s in reader.samples::<f32>().take(count) {
+                writer.write_sample(s.map_err(error)?).map_err(error)?;
+            }
+        }
+        SampleFormat::Int => {
+            for s in reader.samples::<i32>().take(count) {
+                writer.write_sample(s.map_err(error)?).map_err(error)?;
+            }
+        }
+    }
+    writer.finalize().map_err(error)?;
+    Ok(end - start)
+}
+
+/// Writes interleaved `samples` (-1..1) in `spec`'s format. Integer formats
+/// clip anything outside full scale.
+pub fn write(path: &Path, spec: WavSpec, samples: &[f64]) -> Result<(), String> {
+    let error = |e: hound::Error| format!("{}: {}", path.display(), e);
+    let mut writer = WavWriter::create(path, spec).map_err(error)?;
+    match full_scale(spec) {
+        None => {
+            for &s in samples {
+                writer.write_sample(s as f32).map_err(error)?;
+            }
+        }
+        Some(full_scale) => {
+            for &s in samples {
+                let s = (s * full_scale)
+                    .round()
+                    .clamp(-full_scale, full_scale - 1.0);
+                writer.write_sample(s as i32).map_err(error)?;
+            }
+        }
+    }
+    writer.finalize().map_err(error)
+}
+
+/// A file decoded as it's read, through a buffered reader a block of
+/// samples at a time, scaled to -1..1. Only the block is held, however
+/// long the file, and a seek moves the reader instead of decoding up to
+/// the new place. Reading it waits on the disk; play it through a
+/// [`ReadAhead`].
+pub struct Stream {
+    reader: WavReader<BufReader<File>>,
+    /// Integer full scale; float files need no scaling.
+    full_scale: Option<f32>,
+    channels: u16,
+    sample_rate: u32,
+    /// Samples in the file.
+    len: usize,
+    /// Samples read ahead, the first of them at `first`.
+    ahead: Vec<f32>,
+    first: usize,
+    /// Index of the sample the reader is at.
+    read_to: usize,
+    /// Index of the next sample played.
+    pos: usize,
+}
+
+impl Stream {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let error = |e: String| format!("{}: {}", path.display(), e);
+        let file = File::open(path).map_err(|e| error(e.to_string()))?;
+        let reader = WavReader::new(BufReader::with_capacity(STREAM_BUFFER, file))
+            .map_err(|e| error(e.to_string()))?;
+        let spec = reader.spec();
+        Ok(Stream {
+            full_scale: full_scale(spec).map(|f| f as f32),
+            channels: spec.channels.max(1),
+            sample_rate: spec.sample_rate,
+            len: reader.len() as usize,
+            ahead: Vec::with_capacity(PREFETCH_FRAMES * spec.channels.max(1) as usize),
+            first: 0,
+            read_to: 0,
+            pos: 0,
+            reader,
+        })
+    }
+
+    /// Play on from `secs` in, on a whole frame.
+    pub fn seek(&mut self, secs: f64) {
+        let frame = (secs.max(0.0) * self.sample_rate as f64).round() as usize;
+        self.pos = (frame * self.channels as usize).min(self.len);
+    }
+
+    /// Sample `i`, reading the block it's in when it isn't the one held.
+    /// `None` past the end, or where the file can't be read.
+    pub fn get(&mut self, i: usize) -> Option<f32> {
+        if i >= self.len {
+            return None;
+        }
+        if !(self.first..self.first + self.ahead.len()).contains(&i) {
+            self.prefetch(i)?;
+        }
+        self.ahead.get(i - self.first).copied()
+    }
+
+    /// Read the block starting on the frame sample `i` is in.
+    fn prefetch(&mut self, i: usize) -> Option<()> {
+        let channels = self.channels as usize;
+        let start = i / channels * channels;
+        // Reading on from the last block needs no seek
+        if start != self.read_to {
+            self.reader.seek((start / channels) as u32).ok()?;
+            self.read_to = start;
+        }
+        let count = (PREFETCH_FRAMES * channels).min(self.len - start);
+        self.ahead.clear();
+        self.first = start;
+        // Unknown until the block is in, so a failed read seeks next time
+        self.read_to = usize::MAX;
+        match self.full_scale {
+            None => {
+                for s in self.reader.samples::<f32>().take(count) {
+                    self.ahead.push(s.ok()?);
+                }
+            }
+            Some(full_scale) => {
+                for s in self.reader.samples::<i32>().take(count) {
+                    self.ahead.push(s.ok()? as f32 / full_scale);
+                }
+            }
+        }
+        self.read_to = start + self.ahead.len();
+        Some(())
+    }
+}
+
+impl Iterator for Stream {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.get(self.pos)?;
+        self.pos += 1;
+        Some(sample)
+    }
+}
+
+impl Source for Stream {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        let frames = self.len / self.channels as usize;
+        Some(Duration::from_secs_f64(
+            frames as f64 / self.sample_rate.max(1) as f64,
+        ))
+    }
+}
+
+/// Samples on their way from a [`ReadAhead`]'s reader to where it plays.
+struct Ring {
+    /// `f32` bits; slot `i % len` holds sample `i`.
+    data: Box<[AtomicU32]>,
+    /// Samples written so far, in whole frames until the last.
+    written: AtomicUsize,
+    /// Samples played so far; the reader doesn't write over any after.
+    read: AtomicUsize,
+    /// Set once the source has run out and everything is written.
+    done: AtomicBool,
+}
+
+impl Ring {
+    /// Write as many whole frames of `source` as there's room for. False
+    /// once the source has run out.
+    fn fill(&self, source: &mut impl Iterator<Item = f32>, channels: usize) -> bool {
+        let len = self.data.len();
+        let mut written = self.written.load(Ordering::Relaxed);
+        let free = len - (written - self.read.load(Ordering::Acquire));
+        for _ in 0..free / channels * channels {
+            let Some(sample) = source.next() else {
+                self.written.store(written, Ordering::Release);
+                self.done.store(true, Ordering::Release);
+                return false;
+            };
+            self.data[written % len].store(sample.to_bits(), Ordering::Relaxed);
+            written += 1;
+        }
+        self.written.store(written, Ordering::Release);
+        true
+    }
+}
+
+/// A source read on a thread of its own into a ring [`READ_AHEAD_SECS`]
+/// ahead of where it plays, so reading the disk, seeking in it and
+/// decoding all happen off the audio thread, which only takes what's
+/// there. The first of it is read before playback starts. If the reader
+/// falls behind, the gap plays as silence, a frame at a time so the
+/// channels stay in place.
+pub struct ReadAhead {
+    ring: Arc<Ring>,
+    channels: u16,
+    sample_rate: u32,
+    /// Samples played.
+    read: usize,
+    /// Samples left of a frame of silence.
+    gap: usize,
+    /// Wait for the reader instead of playing silence.
+    wait: bool,
+}
+
+impl ReadAhead {
+    pub fn new<S: Source<Item = f32> + Send + 'static>(mut source: S) -> Self {
+        let channels = source.channels().max(1);
+        let sample_rate = source.sample_rate();
+        let frames = ((READ_AHEAD_SECS * sample_rate as f64) as usize).max(1);
+        let ring = Arc::new(Ring {
+            data: (0..frames * channels as usize)
+                .map(|_| AtomicU32::new(0))
+                .collect(),
+            written: AtomicUsize::new(0),
+            read: AtomicUsize::new(0),
+            done: AtomicBool::new(false),
+        });
+        if ring.fill(&mut source, channels as usize) {
+            let reading = ring.clone();
+            let spawned = thread::Builder::new()
+                .name("read-ahead".into())
+                .spawn(move || {
+                    // Until the source runs out, or nobody is left to play it
+                    while Arc::strong_count(&reading) > 1
+                        && reading.fill(&mut source, channels as usize)
+                    {
+                        thread::sleep(REFILL_INTERVAL);
+                    }
+                });
+            if spawned.is_err() {
+                ring.done.store(true, Ordering::Release);
+            }
+        }
+        ReadAhead {
+            ring,
+            channels,
+            sample_rate,
+            read: 0,
+            gap: 0,
+            wait: false,
+        }
+    }
+
+    /// Wait for the reader rather than play silence, for an output that
+    /// renders faster than real time (the null output).
+    pub fn waiting(mut self) -> Self {
+        self.wait = true;
+        self
+    }
+}
+
+impl Iterator for ReadAhead {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.gap > 0 {
+            self.gap -= 1;
+            return Some(0.0);
+        }
+        loop {
+            // Done first: everything is written by the time it's set
+            let done = self.ring.done.load(Ordering::Acquire);
+            if self.read < self.ring.written.load(Ordering::Acquire) {
+                let slot = &self.ring.data[self.read % self.ring.data.len()];
+                let sample = f32::from_bits(slot.load(Ordering::Relaxed));
+                self.read += 1;
+                self.ring.read.store(self.read, Ordering::Release);
+                return Some(sample);
+            }
+            if done {
+                return None;
+            }
+            if !self.wait {
+                self.gap = self.channels as usize - 1;
+                return Some(0.0);
+            }
+            thread::yield_now();
+        }
+    }
+}
+
+impl Source for ReadAhead {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
@@ -0,0 +1,168 @@
+//! Level overview of the whole file with the playhead, drawn with braille
+//! dots (2x4 per cell) when the terminal can show them and half blocks
+//! (1x2 per cell) otherwise, under a ruler numbering the bars. Given the
+//! file's brightness, columns are coloured by it from dark red for bass to
+//! bright cyan for treble, so verses, drops and breakdowns stand apart by
+//! tone as well as level.
+
+use cornwall_dsp::{
+    levels::display_level,
+    waveform::{column_peaks, column_tones},
+};
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+
+/// Braille dot bits, indexed by `[column][row]` within a cell.
+pub const DOT_BITS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+/// Numbers a bar in this many, doubling until the labels fit.
+const RULER_STEPS: [u32; 11] = [1, 2, 4, 8, 16, 32, 64, 128, 256, 512, 1024];
+
+/// A line of `width` cells numbering the bars of a `duration`-second file
+/// at `bar_secs` each, as many as fit with a space between labels. Bars
+/// between labels get a dot where there's room. The bar under `progress`
+/// (0..1) is brighter.
+pub fn ruler(duration: f64, bar_secs: f64, width: usize, progress: f64) -> Line<'static> {
+    if duration <= 0.0 || bar_secs <= 0.0 || width == 0 {
+        return Line::from("");
+    }
+    let bars = (duration / bar_secs).ceil().max(1.0) as u32;
+    let bar_cells = bar_secs / duration * width as f64;
+    let widest = bars.to_string().len() as f64 + 1.0;
+    let step = RULER_STEPS
+        .iter()
+        .copied()
+        .find(|&step| step as f64 * bar_cells >= widest)
+        .unwrap_or(bars);
+    let current = (progress * duration / bar_secs) as u32 + 1;
+    let mut cells = vec![(' ', Color::DarkGray); width];
+    for bar in 1..=bars {
+        let at = ((bar - 1) as f64 * bar_cells) as usize;
+        if at >= width {
+            break;
+        }
+        let color = if bar == current {
+            Color::White
+        } else {
+            Color::DarkGray
+        };
+        if (bar - 1) % step == 0 {
+            let label = bar.to_string();
+            if at + label.len() <= width {
+                for (i, c) in label.chars().enumerate() {
+                    cells[at + i] = (c, color);
+                }
+            }
+        } else if bar_cells >= 2.0 && cells[at].0 == ' ' {
+            cells[at] = ('·', color);
+        }
+    }
+    let spans: Vec<Span> = cells
+        .into_iter()
+        .map(|(c, color)| Span::styled(c.to_string(), Style::default().fg(color)))
+        .collect();
+    Line::from(spans)
+}
+
+/// Per-chunk data the overview draws.
+pub struct Levels<'a> {
+    pub left: &'a [f64],
+    pub right: &'a [f64],
+    /// Brightness of each chunk in Hz, to colour by.
+    pub tones: Option<&'a [f64]>,
+}
+
+/// `rows` lines of `width` cells showing the level envelope mirrored about
+/// the centre line, brighter left of the playhead at `progress` (0..1).
+/// `markers` are positions as fractions of the file, highlighted in yellow.
+pub fn overview(
+    levels: Levels,
+    width: usize,
+    rows: usize,
+    progress: f64,
+    markers: &[f64],
+    braille: bool,
+) -> Vec<Line<'static>> {
+    let (dots_x, dots_y) = if braille { (2, 4) } else { (1, 2) };
+    let Levels { left, right, tones } = levels;
+    let peaks = column_peaks(left, right, width * dots_x);
+    let tones = tones.map(|tones| column_tones(left, right, tones, width));
+    let half = (rows * dots_y) as f64 / 2.0;
+    // Same scale as the track sparklines; the centre line stays lit through silence
+    let lit = |x: usize, y: usize| {
+        let reach = (display_level(peaks[x]) * half).max(0.5);
+        (y as f64 + 0.5 - half).abs() <= reach
+    };
+    let playhead = (progress * width as f64) as usize;
+    let marker_cells: Vec<usize> = markers
+        .iter()
+        .map(|m| (m * width as f64) as usize)
+        .collect();
+
+    (0..rows)
+        .map(|row| {
+            let spans = (0..width)
+                .map(|cell| {
+                    let glyph = if braille {
+                        let mut bits = 0;
+                        for (dx, column) in DOT_BITS.iter().enumerate() {
+                            for (dy, bit) in column.iter().enumerate() {
+                                if lit(cell * 2 + dx, row * 4 + dy) {
+                                    bits |= bit;
+                                }
+                            }
+                        }
+                        char::from_u32(0x2800 + bits).unwrap_or(' ')
+                    } else {
+                        match (lit(cell, row * 2), lit(cell, row * 2 + 1)) {
+                            (true, true) => '█',
+                            (true, false) => '▀',
+                            (false, true) => '▄',
+                            (false, false) => ' ',
+                        }
+                    };
+                    let color = if cell == playhead {
+                        Color::White
+                    } else if marker_cells.contains(&cell) {
+                        Color::Yellow
+                    } else if let Some(tones) = &tones {
+                        tone_color(tones[cell], cell > playhead)
+                    } else if cell < playhead {
+                        Color::Cyan
+                    } else {
+                        Color::DarkGray
+                    };
+                    Span::styled(glyph.to_string(), Style::default().fg(color))
+                })
+                .collect::<Vec<_>>();
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// Brightness at either end of the colour scale, in Hz.
+const TONE_LOW: f64 = 150.0;
+const TONE_HIGH: f64 = 4000.0;
+
+/// Colours along the scale from `TONE_LOW` to `TONE_HIGH`, evenly spaced on
+/// a log scale.
+const TONE_STOPS: [(u8, u8, u8); 4] = [(120, 0, 0), (220, 80, 0), (210, 210, 90), (80, 255, 255)];
+
+/// The colour of `hz` of brightness, at half intensity when `dim`. Silence
+/// counts as bass.
+pub fn tone_color(hz: f64, dim: bool) -> Color {
+    let at = if hz > 0.0 {
+        ((hz / TONE_LOW).ln() / (TONE_HIGH / TONE_LOW).ln()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let scaled = at * (TONE_STOPS.len() - 1) as f64;
+    let i = (scaled as usize).min(TONE_STOPS.len() - 2);
+    let frac = scaled - i as f64;
+    let (a, b) = (TONE_STOPS[i], TONE_STOPS[i + 1]);
+    let scale = if dim { 0.45 } else { 1.0 };
+    let mix = |a: u8, b: u8| ((a as f64 + (b as f64 - a as f64) * frac) * scale).round() as u8;
+    Color::Rgb(mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+}
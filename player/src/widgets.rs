@@ -0,0 +1,718 @@
+//! The player's transport, level meters, loudness gauge and track list as
+//! ratatui widgets, for embedding a mini player pane in another TUI:
+//!
+//! ```ignore
+//! use cornwall_player::widgets::{Meter, Transport};
+//!
+//! frame.render_widget(Transport::new(status.position_secs, duration).playing(true), top);
+//! frame.render_widget(Meter::new(status.level_l, status.level_r), bottom);
+//! ```
+//!
+//! Widgets take plain values rather than the player itself, so a host can
+//! feed them from `state/.player.json` (`cornwall-player --status`). Labels
+//! default to English; the player passes in its translations.
+
+use crate::text;
+use cornwall_dsp::{
+    levels::{dbfs, display_level},
+    waveform::column_peaks,
+};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Widget},
+};
+use std::path::Path;
+
+/// Words drawn by the widgets.
+#[derive(Clone, Copy)]
+pub struct Labels<'a> {
+    pub playing: &'a str,
+    pub stopped: &'a str,
+    pub bar: &'a str,
+    pub bpm: &'a str,
+    pub solo: &'a str,
+    pub muted: &'a str,
+    pub empty: &'a str,
+    pub volume: &'a str,
+    pub pan: &'a str,
+}
+
+impl Default for Labels<'_> {
+    fn default() -> Self {
+        Labels {
+            playing: "PLAYING",
+            stopped: "STOPPED",
+            bar: "BAR",
+            bpm: "BPM",
+            solo: "S",
+            muted: "M",
+            empty: "(empty)",
+            volume: "vol",
+            pan: "pan",
+        }
+    }
+}
+
+/// `mm:ss.t`
+pub fn format_position(secs: f64) -> String {
+    format!(
+        "{:02}:{:02}.{:01}",
+        (secs as u32) / 60,
+        (secs as u32) % 60,
+        ((secs * 10.0) as u32) % 10
+    )
+}
+
+/// The ▶/■ badge.
+pub fn play_state(playing: bool, labels: &Labels) -> Span<'static> {
+    if playing {
+        Span::styled(
+            format!("  ▶ {} ", labels.playing),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        Span::styled(
+            format!("  ■ {} ", labels.stopped),
+            Style::default()
+                .fg(Color::White)
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+    }
+}
+
+/// Bottom of the level meter scale in dBFS; the top is 0.
+const METER_FLOOR_DB: f64 = -48.0;
+
+/// Levels marked across the meters, in dBFS.
+const METER_GRID_DB: [f64; 5] = [-24.0, -18.0, -12.0, -6.0, -3.0];
+
+/// Where `db` sits across a meter, 0 to 1.
+fn meter_fraction(db: f64) -> f64 {
+    ((db - METER_FLOOR_DB) / -METER_FLOOR_DB).clamp(0.0, 1.0)
+}
+
+/// Cell of a `width`-cell meter that `db` falls in.
+fn meter_cell(db: f64, width: u16) -> u16 {
+    (meter_fraction(db) * width as f64) as u16
+}
+
+/// Green up to -18 dBFS, yellow to -6 and red above.
+fn meter_color(db: f64) -> Color {
+    if db < -18.0 {
+        Color::Green
+    } else if db < -6.0 {
+        Color::Yellow
+    } else {
+        Color::Red
+    }
+}
+
+/// One meter row of `width` cells, green through yellow to red, for an RMS
+/// `level` on a dB scale from -48 to 0 dBFS, with faint gridlines at -24,
+/// -18, -12, -6 and -3.
+pub fn meter_bar(level: f64, width: u16, braille: bool) -> Vec<Span<'static>> {
+    // A braille cell holds two dot columns, doubling the resolution
+    let steps = if braille { 2 } else { 1 };
+    let filled = (meter_fraction(dbfs(level)) * (width * steps) as f64) as u16;
+    let grid: Vec<u16> = METER_GRID_DB
+        .iter()
+        .map(|&db| meter_cell(db, width))
+        .collect();
+    let mut spans = Vec::new();
+
+    for i in 0..width {
+        let lit = filled.saturating_sub(i * steps).min(steps);
+        if lit > 0 {
+            let db = METER_FLOOR_DB * (1.0 - i as f64 / width as f64);
+            let glyph = match (braille, lit) {
+                (false, _) => "█",
+                (true, 2) => "⣿",
+                (true, _) => "⡇",
+            };
+            spans.push(Span::styled(glyph, Style::default().fg(meter_color(db))));
+        } else if grid.contains(&i) {
+            spans.push(Span::styled("│", Style::default().fg(Color::DarkGray)));
+        } else {
+            let empty = if braille { "⠤" } else { "░" };
+            spans.push(Span::styled(empty, Style::default().fg(Color::DarkGray)));
+        }
+    }
+    spans
+}
+
+/// `level` in dBFS for a meter readout, six cells wide.
+fn meter_readout(level: f64) -> String {
+    let db = dbfs(level);
+    if db > -100.0 {
+        format!(" {db:>5.1}")
+    } else {
+        "  -inf".to_string()
+    }
+}
+
+/// `levels` squeezed into `width` cells of rising blocks, loudest chunk
+/// per cell.
+pub fn sparkline(levels: &[f64], width: usize) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    column_peaks(levels, levels, width)
+        .into_iter()
+        .map(|peak| BLOCKS[(display_level(peak) * 7.0).round().clamp(0.0, 7.0) as usize])
+        .collect()
+}
+
+fn render_lines(lines: Vec<Line>, block: Option<Block>, area: Rect, buf: &mut Buffer) {
+    let mut paragraph = Paragraph::new(lines);
+    if let Some(block) = block {
+        paragraph = paragraph.block(block);
+    }
+    paragraph.render(area, buf);
+}
+
+/// Play state, bar and beat, position, tempo and a progress line. Four rows
+/// plus the block.
+pub struct Transport<'a> {
+    position: f64,
+    duration: f64,
+    playing: bool,
+    bar: Option<(u32, u32)>,
+    tempo: Option<(f64, &'a str)>,
+    indicator: Option<Span<'a>>,
+    badges: Vec<Span<'a>>,
+    labels: Labels<'a>,
+    block: Option<Block<'a>>,
+}
+
+impl<'a> Transport<'a> {
+    pub fn new(position: f64, duration: f64) -> Self {
+        Transport {
+            position,
+            duration,
+            playing: false,
+            bar: None,
+            tempo: None,
+            indicator: None,
+            badges: Vec::new(),
+            labels: Labels::default(),
+            block: None,
+        }
+    }
+
+    #[must_use]
+    pub fn playing(mut self, playing: bool) -> Self {
+        self.playing = playing;
+        self
+    }
+
+    #[must_use]
+    pub fn bar(mut self, bar: u32, beat: u32) -> Self {
+        self.bar = Some((bar, beat));
+        self
+    }
+
+    /// Tempo and time signature (`"6/8"`).
+    #[must_use]
+    pub fn tempo(mut self, bpm: f64, time_sig: &'a str) -> Self {
+        self.tempo = Some((bpm, time_sig));
+        self
+    }
+
+    /// Replace the ▶/■ badge.
+    #[must_use]
+    pub fn indicator(mut self, indicator: Span<'a>) -> Self {
+        self.indicator = Some(indicator);
+        self
+    }
+
+    /// Extra badges after the play state.
+    #[must_use]
+    pub fn badges(mut self, badges: Vec<Span<'a>>) -> Self {
+        self.badges = badges;
+        self
+    }
+
+    #[must_use]
+    pub fn labels(mut self, labels: Labels<'a>) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    #[must_use]
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl Widget for Transport<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let inner = self.block.as_ref().map_or(area, |b| b.inner(area));
+        let indicator = self
+            .indicator
+            .unwrap_or_else(|| play_state(self.playing, &self.labels));
+
+        let mut line1 = vec![indicator, Span::raw(" ")];
+        line1.extend(self.badges);
+        line1.push(Span::raw("  "));
+        if let Some((bar, beat)) = self.bar {
+            line1.push(Span::styled(
+                format!("  {} {:>3} . {}   ", self.labels.bar, bar, beat),
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        }
+        line1.push(Span::styled(
+            format_position(self.position),
+            Style::default().fg(Color::White),
+        ));
+        if let Some((bpm, time_sig)) = self.tempo {
+            line1.push(Span::styled(
+                format!("  {} {}  {}  ", bpm, self.labels.bpm, time_sig),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+
+        let progress = if self.duration > 0.0 {
+            (self.position / self.duration).min(1.0)
+        } else {
+            0.0
+        };
+        let width = inner.width.saturating_sub(4) as usize;
+        let filled = (progress * width as f64) as usize;
+        let mut progress_line = vec![Span::raw("  ")];
+        for i in 0..width {
+            if i < filled {
+                progress_line.push(Span::styled("━", Style::default().fg(Color::Cyan)));
+            } else if i == filled && self.playing {
+                progress_line.push(Span::styled("╸", Style::default().fg(Color::White)));
+            } else {
+                progress_line.push(Span::styled("─", Style::default().fg(Color::DarkGray)));
+            }
+        }
+
+        let lines = vec![
+            Line::from(""),
+            Line::from(line1),
+            Line::from(""),
+            Line::from(progress_line),
+        ];
+        render_lines(lines, self.block, area, buf);
+    }
+}
+
+/// Left and right level meters with their levels in dBFS beside them and
+/// the gridlines labelled between them. Four rows plus the block.
+pub struct Meter<'a> {
+    left: f64,
+    right: f64,
+    braille: bool,
+    block: Option<Block<'a>>,
+}
+
+impl<'a> Meter<'a> {
+    /// RMS levels, 0 to 1.
+    pub fn new(left: f64, right: f64) -> Self {
+        Meter {
+            left,
+            right,
+            braille: false,
+            block: None,
+        }
+    }
+
+    #[must_use]
+    pub fn braille(mut self, braille: bool) -> Self {
+        self.braille = braille;
+        self
+    }
+
+    #[must_use]
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl Widget for Meter<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let inner = self.block.as_ref().map_or(area, |b| b.inner(area));
+        let width = inner.width.saturating_sub(12);
+        let row = |label: &'static str, level: f64| {
+            let mut spans = vec![Span::styled(label, Style::default().fg(Color::DarkGray))];
+            spans.extend(meter_bar(level, width, self.braille));
+            spans.push(Span::styled(
+                meter_readout(level),
+                Style::default().fg(meter_color(dbfs(level))),
+            ));
+            Line::from(spans)
+        };
+        let mut marks = vec![' '; width as usize];
+        let mut free = 0;
+        for db in METER_GRID_DB {
+            let label = format!("{db}");
+            let at = meter_cell(db, width) as usize;
+            // -6 and -3 crowd each other on a narrow meter
+            if at < free || at + label.len() > marks.len() {
+                continue;
+            }
+            for (i, c) in label.chars().enumerate() {
+                marks[at + i] = c;
+            }
+            free = at + label.len() + 1;
+        }
+        let scale = Line::from(vec![
+            Span::raw("    "),
+            Span::styled(
+                marks.into_iter().collect::<String>(),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]);
+        let lines = vec![
+            Line::from(""),
+            row("  L ", self.left),
+            scale,
+            row("  R ", self.right),
+        ];
+        render_lines(lines, self.block, area, buf);
+    }
+}
+
+/// Left and right VU meters: needles over a scale from -20 to +3 VU spaced
+/// like an analogue meter face, red past 0. Four rows plus the block.
+pub struct VuMeter<'a> {
+    left: f64,
+    right: f64,
+    braille: bool,
+    block: Option<Block<'a>>,
+}
+
+impl<'a> VuMeter<'a> {
+    /// Levels in VU, 0 at the reference level.
+    pub fn new(left: f64, right: f64) -> Self {
+        VuMeter {
+            left,
+            right,
+            braille: false,
+            block: None,
+        }
+    }
+
+    #[must_use]
+    pub fn braille(mut self, braille: bool) -> Self {
+        self.braille = braille;
+        self
+    }
+
+    #[must_use]
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+}
+
+/// Where `vu` sits across the scale, 0 to 1. The face is linear in
+/// voltage, so the bottom of the scale is bunched up.
+fn vu_fraction(vu: f64) -> f64 {
+    let volts = |vu: f64| 10f64.powf((vu - 3.0) / 20.0);
+    (volts(vu.clamp(-20.0, 3.0)) - volts(-20.0)) / (1.0 - volts(-20.0))
+}
+
+impl Widget for VuMeter<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let inner = self.block.as_ref().map_or(area, |b| b.inner(area));
+        let width = inner.width.saturating_sub(8) as usize;
+        let zero = (vu_fraction(0.0) * width as f64) as usize;
+        let needle = |label: &'static str, vu: f64| {
+            // Half cells with braille, one column of dots each
+            let halves = (vu_fraction(vu) * (width * 2).saturating_sub(1) as f64).round() as usize;
+            let mut spans = vec![Span::styled(label, Style::default().fg(Color::DarkGray))];
+            for i in 0..width {
+                let scale = if i >= zero {
+                    Color::Red
+                } else {
+                    Color::DarkGray
+                };
+                let span = if i == halves / 2 {
+                    let glyph = match (self.braille, halves % 2) {
+                        (false, _) => "┃",
+                        (true, 0) => "⡇",
+                        (true, _) => "⢸",
+                    };
+                    let color = if vu > 0.0 { Color::Red } else { Color::White };
+                    Span::styled(glyph, Style::default().fg(color))
+                } else {
+                    Span::styled("─", Style::default().fg(scale))
+                };
+                spans.push(span);
+            }
+            Line::from(spans)
+        };
+        let mut marks = vec![' '; width];
+        let mut free = 0;
+        for (vu, label) in [
+            (-20.0, "-20"),
+            (-10.0, "-10"),
+            (-7.0, "-7"),
+            (-5.0, "-5"),
+            (-3.0, "-3"),
+            (-1.0, "-1"),
+            (0.0, "0"),
+            (1.0, "+1"),
+            (3.0, "+3"),
+        ] {
+            let at =
+                ((vu_fraction(vu) * width as f64) as usize).min(width.saturating_sub(label.len()));
+            // Crowded labels at the bottom of the scale give way
+            if at < free || at + label.len() > width {
+                continue;
+            }
+            for (i, c) in label.chars().enumerate() {
+                marks[at + i] = c;
+            }
+            free = at + label.len() + 1;
+        }
+        let scale = Line::from(vec![
+            Span::raw("    "),
+            Span::styled(
+                marks.into_iter().collect::<String>(),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]);
+        let lines = vec![
+            Line::from(""),
+            needle("  L ", self.left),
+            scale,
+            needle("  R ", self.right),
+        ];
+        render_lines(lines, self.block, area, buf);
+    }
+}
+
+/// Momentary, short-term and integrated loudness in LUFS against a target,
+/// green within 1 LU of it, red over and yellow under. Bars span 24 LU
+/// below the target to 6 above, with the target marked. Three rows plus
+/// the block.
+pub struct LoudnessGauge<'a> {
+    momentary: f64,
+    short_term: f64,
+    integrated: f64,
+    target: f64,
+    braille: bool,
+    block: Option<Block<'a>>,
+}
+
+impl<'a> LoudnessGauge<'a> {
+    /// Values in LUFS; negative infinity shows as unmeasured.
+    pub fn new(momentary: f64, short_term: f64, integrated: f64, target: f64) -> Self {
+        LoudnessGauge {
+            momentary,
+            short_term,
+            integrated,
+            target,
+            braille: false,
+            block: None,
+        }
+    }
+
+    #[must_use]
+    pub fn braille(mut self, braille: bool) -> Self {
+        self.braille = braille;
+        self
+    }
+
+    #[must_use]
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+}
+
+/// Green on target, red over, yellow under, grey unmeasured.
+pub fn loudness_color(lufs: f64, target: f64) -> Color {
+    if !lufs.is_finite() {
+        Color::DarkGray
+    } else if lufs > target + 1.0 {
+        Color::Red
+    } else if lufs < target - 1.0 {
+        Color::Yellow
+    } else {
+        Color::Green
+    }
+}
+
+impl Widget for LoudnessGauge<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let inner = self.block.as_ref().map_or(area, |b| b.inner(area));
+        let width = inner.width.saturating_sub(16);
+        let steps = if self.braille { 2 } else { 1 };
+        let (floor, span) = (self.target - 24.0, 30.0);
+        let mark = (24.0 / span * width as f64) as u16;
+        let row = |label: &'static str, lufs: f64| {
+            let color = loudness_color(lufs, self.target);
+            let filled = if lufs.is_finite() {
+                (((lufs - floor) / span).clamp(0.0, 1.0) * (width * steps) as f64) as u16
+            } else {
+                0
+            };
+            let mut spans = vec![Span::styled(label, Style::default().fg(Color::DarkGray))];
+            for i in 0..width {
+                let lit = filled.saturating_sub(i * steps).min(steps);
+                let (glyph, fg) = match (self.braille, lit) {
+                    (_, 0) if i == mark => ("┃", Color::White),
+                    (false, 0) => ("░", Color::DarkGray),
+                    (true, 0) => ("⠤", Color::DarkGray),
+                    (false, _) => ("█", color),
+                    (true, 2) => ("⣿", color),
+                    (true, _) => ("⡇", color),
+                };
+                let fg = if i == mark { Color::White } else { fg };
+                spans.push(Span::styled(glyph, Style::default().fg(fg)));
+            }
+            let value = if lufs.is_finite() {
+                format!(" {:>6.1} LUFS", lufs)
+            } else {
+                format!(" {:>6} LUFS", "--")
+            };
+            spans.push(Span::styled(value, Style::default().fg(color)));
+            Line::from(spans)
+        };
+        let lines = vec![
+            row("  M ", self.momentary),
+            row("  S ", self.short_term),
+            row("  I ", self.integrated),
+        ];
+        render_lines(lines, self.block, area, buf);
+    }
+}
+
+/// A row of the track list.
+pub struct TrackRow<'a> {
+    pub id: u32,
+    pub name: &'a str,
+    pub mute: bool,
+    pub solo: bool,
+    pub volume: f64,
+    pub pan: f64,
+    /// Path of the track's audio; only the file name is shown.
+    pub source: Option<&'a str>,
+    /// RMS envelope of the source, 0 to 1, drawn as a mini waveform.
+    pub levels: Option<&'a [f64]>,
+}
+
+/// Tracks with their mute/solo state, volume, pan, source file and a mini
+/// waveform filling the rest of the row.
+pub struct TrackList<'a> {
+    rows: Vec<TrackRow<'a>>,
+    selected: Option<usize>,
+    labels: Labels<'a>,
+    block: Option<Block<'a>>,
+}
+
+impl<'a> TrackList<'a> {
+    pub fn new(rows: Vec<TrackRow<'a>>) -> Self {
+        TrackList {
+            rows,
+            selected: None,
+            labels: Labels::default(),
+            block: None,
+        }
+    }
+
+    /// Mark the row at `index` with ▸.
+    #[must_use]
+    pub fn selected(mut self, index: Option<usize>) -> Self {
+        self.selected = index;
+        self
+    }
+
+    #[must_use]
+    pub fn labels(mut self, labels: Labels<'a>) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    #[must_use]
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl Widget for TrackList<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let labels = self.labels;
+        let width = self.block.as_ref().map_or(area, |b| b.inner(area)).width as usize;
+        let mut lines = vec![Line::from("")];
+        for (i, t) in self.rows.iter().enumerate() {
+            let mute_solo = match (t.mute, t.solo) {
+                (_, true) => Span::styled(
+                    format!(" {} ", labels.solo),
+                    Style::default().fg(Color::Black).bg(Color::Yellow),
+                ),
+                (true, _) => Span::styled(
+                    format!(" {} ", labels.muted),
+                    Style::default().fg(Color::Black).bg(Color::Red),
+                ),
+                _ => Span::styled("   ", Style::default()),
+            };
+            let source_name = t
+                .source
+                .map(|s| {
+                    Path::new(s)
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .unwrap_or_else(|| labels.empty.to_string());
+
+            let mut line = Line::from(vec![
+                Span::styled(
+                    format!(
+                        " {}{:>2} ",
+                        if self.selected == Some(i) { "▸" } else { " " },
+                        t.id
+                    ),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                mute_solo,
+                Span::raw(" "),
+                Span::styled(text::fit(t.name, 16), Style::default().fg(Color::White)),
+                Span::styled(
+                    format!("  {} {:<4}", labels.volume, format!("{:.1}", t.volume)),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(
+                    format!("  {} {:<5}", labels.pan, format!("{:.1}", t.pan)),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(
+                    format!("  {}", source_name),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]);
+            let room = width.saturating_sub(line.width() + 3);
+            if let Some(levels) = t.levels.filter(|_| room >= 8) {
+                let color = if self.selected == Some(i) {
+                    Color::Cyan
+                } else {
+                    Color::DarkGray
+                };
+                line.spans.push(Span::raw("  "));
+                line.spans.push(Span::styled(
+                    sparkline(levels, room),
+                    Style::default().fg(color),
+                ));
+            }
+            lines.push(line);
+        }
+        render_lines(lines, self.block, area, buf);
+    }
+}
@@ -0,0 +1,184 @@
+//! Workspaces: state directories registered with `cornwall-player
+//! workspace add`, so the switcher (`Ctrl+o`) can move the player to
+//! another project without restarting it. The list is shared by every
+//! project, in `$XDG_CONFIG_HOME/cornwall/workspaces.json` (or
+//! `~/.config/cornwall/`).
+
+use crate::{i18n::tr, utilities::centered_rect};
+use cornwall_player::{json, project::ProjectState};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Workspace {
+    pub name: String,
+    pub state_dir: PathBuf,
+}
+
+fn registry() -> Result<PathBuf, String> {
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok_or("neither XDG_CONFIG_HOME nor HOME is set")?;
+    Ok(config.join("cornwall").join("workspaces.json"))
+}
+
+pub fn load() -> Result<Vec<Workspace>, String> {
+    json::load(&registry()?)
+}
+
+fn save(workspaces: &[Workspace]) -> Result<(), String> {
+    let path = registry()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(workspaces).map_err(|e| e.to_string())?;
+    fs::write(&path, json + "\n").map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Register `state_dir` as `name`, by default the project's name. Adding a
+/// directory again renames it.
+pub fn add(state_dir: &Path, name: Option<String>) -> Result<Workspace, String> {
+    if !state_dir.join("project.json").exists() {
+        return Err(format!("{}: no project.json", state_dir.display()));
+    }
+    let state_dir = state_dir
+        .canonicalize()
+        .map_err(|e| format!("{}: {}", state_dir.display(), e))?;
+    let name = match name {
+        Some(name) => name,
+        None => ProjectState::load(&state_dir)?.name,
+    };
+    if name.is_empty() {
+        return Err("the project has no name; pass --name".into());
+    }
+    let mut workspaces = load()?;
+    if workspaces
+        .iter()
+        .any(|w| w.name == name && w.state_dir != state_dir)
+    {
+        return Err(format!("a workspace called {} is already registered", name));
+    }
+    workspaces.retain(|w| w.state_dir != state_dir);
+    let workspace = Workspace { name, state_dir };
+    workspaces.push(workspace.clone());
+    save(&workspaces)?;
+    Ok(workspace)
+}
+
+pub fn remove(name: &str) -> Result<(), String> {
+    let mut workspaces = load()?;
+    let count = workspaces.len();
+    workspaces.retain(|w| w.name != name);
+    if workspaces.len() == count {
+        return Err(format!("no workspace called {}", name));
+    }
+    save(&workspaces)
+}
+
+/// The switcher overlay, listing the registered workspaces.
+#[derive(Default)]
+pub struct Switcher {
+    pub open: bool,
+    pub selected: usize,
+    pub workspaces: Vec<Workspace>,
+}
+
+impl Switcher {
+    /// Reread the registry and open on the project in `current`.
+    pub fn open(&mut self, current: &Path) -> Result<(), String> {
+        self.workspaces = load()?;
+        self.selected = self
+            .workspaces
+            .iter()
+            .position(|w| is_current(w, current))
+            .unwrap_or(0);
+        self.open = true;
+        Ok(())
+    }
+
+    pub fn select(&mut self, delta: isize) {
+        let count = self.workspaces.len().max(1) as isize;
+        self.selected = (self.selected as isize + delta).rem_euclid(count) as usize;
+    }
+
+    pub fn chosen(&self) -> Option<&Workspace> {
+        self.workspaces.get(self.selected)
+    }
+}
+
+pub fn is_current(workspace: &Workspace, current: &Path) -> bool {
+    current
+        .canonicalize()
+        .is_ok_and(|dir| dir == workspace.state_dir)
+}
+
+pub fn render(f: &mut Frame, switcher: &Switcher, current: &Path) {
+    let key = Style::default()
+        .fg(Color::White)
+        .add_modifier(Modifier::BOLD);
+    let dim = Style::default().fg(Color::DarkGray);
+
+    let mut lines = vec![Line::from("")];
+    for (i, workspace) in switcher.workspaces.iter().enumerate() {
+        let cursor = if i == switcher.selected {
+            "  ▸ "
+        } else {
+            "    "
+        };
+        let marker = if is_current(workspace, current) {
+            "  ◉"
+        } else if !workspace.state_dir.exists() {
+            "  ✕"
+        } else {
+            ""
+        };
+        lines.push(Line::from(vec![
+            Span::styled(cursor, Style::default().fg(Color::Cyan)),
+            Span::styled(format!("{:<20}", workspace.name), key),
+            Span::styled(marker, Style::default().fg(Color::Magenta)),
+        ]));
+        lines.push(Line::from(Span::styled(
+            format!("    {}", workspace.state_dir.display()),
+            dim,
+        )));
+    }
+    if switcher.workspaces.is_empty() {
+        lines.push(Line::from(Span::styled(
+            format!("    {}", tr("switcher.empty")),
+            dim,
+        )));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!("    {}", tr("switcher.help")),
+        dim,
+    )));
+
+    let width = switcher
+        .workspaces
+        .iter()
+        .map(|w| w.state_dir.display().to_string().chars().count() + 6)
+        .max()
+        .unwrap_or(0)
+        .clamp(48, 80) as u16;
+    let area = centered_rect(width, lines.len() as u16 + 2, f.area());
+    let popup = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(dim)
+            .title(format!(" {} ", tr("switcher.title"))),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
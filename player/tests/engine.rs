@@ -0,0 +1,163 @@
+//! The playback engine end to end on the null output: transport, playhead,
+//! bar and beat, meters and the audio itself.
+
+mod harness;
+mod support;
+
+use harness::{assert_audio, Harness};
+use support::{assert_silent, ramp, tone};
+
+/// 8 kHz keeps the mixes small, and whole seconds of it skip exactly.
+const RATE: u32 = 8000;
+
+fn frames(secs: f64) -> usize {
+    (secs * RATE as f64) as usize
+}
+
+#[test]
+fn stopped_renders_silence_and_holds_the_playhead() {
+    let mut h = Harness::new("stopped", 120.0, 2, RATE, &ramp(frames(4.0)));
+    assert_silent(&h.render(1.0));
+    let status = h.status();
+    assert!(!status.playing);
+    assert_eq!(status.position_secs, 0.0);
+}
+
+#[test]
+fn play_renders_the_mix_and_moves_the_playhead_as_far() {
+    let mut h = Harness::new("play", 120.0, 2, RATE, &ramp(frames(4.0)));
+    h.engine.play().unwrap();
+    let rendered = h.render(1.5);
+    assert_audio(&rendered, h.mix_at(0.0, 1.5));
+    let status = h.status();
+    assert!(status.playing);
+    assert_eq!(status.position_secs, 1.5);
+
+    let rendered = h.render(0.25);
+    assert_audio(&rendered, h.mix_at(1.5, 0.25));
+    assert_eq!(h.status().position_secs, 1.75);
+}
+
+#[test]
+fn seek_while_playing_goes_on_from_there() {
+    let mut h = Harness::new("seek-playing", 120.0, 2, RATE, &ramp(frames(4.0)));
+    h.engine.play().unwrap();
+    h.render(0.5);
+    h.engine.seek(2.0).unwrap();
+    let rendered = h.render(0.5);
+    assert_audio(&rendered, h.mix_at(2.0, 0.5));
+    assert_eq!(h.status().position_secs, 2.5);
+}
+
+#[test]
+fn seek_while_stopped_starts_there() {
+    let mut h = Harness::new("seek-stopped", 120.0, 2, RATE, &ramp(frames(4.0)));
+    h.engine.seek(1.0).unwrap();
+    assert_silent(&h.render(0.5));
+    assert_eq!(h.status().position_secs, 1.0);
+    h.engine.play().unwrap();
+    let rendered = h.render(0.5);
+    assert_audio(&rendered, h.mix_at(1.0, 0.5));
+}
+
+#[test]
+fn pause_holds_the_playhead_and_resume_carries_on() {
+    let mut h = Harness::new("pause", 120.0, 2, RATE, &ramp(frames(4.0)));
+    h.engine.play().unwrap();
+    h.render(1.0);
+    h.engine.pause();
+    assert_silent(&h.render(0.5));
+    let status = h.status();
+    assert!(!status.playing);
+    assert!(status.paused);
+    assert_eq!(status.position_secs, 1.0);
+
+    h.engine.resume().unwrap();
+    let rendered = h.render(0.5);
+    assert_audio(&rendered, h.mix_at(1.0, 0.5));
+    let status = h.status();
+    assert!(status.playing);
+    assert!(!status.paused);
+    assert_eq!(status.position_secs, 1.5);
+}
+
+#[test]
+fn seek_while_paused_stays_paused_there() {
+    let mut h = Harness::new("seek-paused", 120.0, 2, RATE, &ramp(frames(4.0)));
+    h.engine.play().unwrap();
+    h.render(0.5);
+    h.engine.pause();
+    h.engine.seek(2.0).unwrap();
+    assert_silent(&h.render(0.5));
+    let status = h.status();
+    assert!(status.paused);
+    assert_eq!(status.position_secs, 2.0);
+    // Play resumes too
+    h.engine.play().unwrap();
+    let rendered = h.render(0.5);
+    assert_audio(&rendered, h.mix_at(2.0, 0.5));
+}
+
+#[test]
+fn bar_and_beat_follow_the_rendered_audio() {
+    // 120 BPM in 4/4: half a second a beat, two seconds a bar
+    let mut h = Harness::new("bars", 120.0, 2, RATE, &ramp(frames(8.0)));
+    h.engine.play().unwrap();
+    let status = h.status();
+    assert_eq!((status.bar, status.beat), (1, 1));
+    h.render(2.75);
+    let status = h.status();
+    assert_eq!((status.bar, status.beat), (2, 2));
+    assert_eq!(status.bpm, 120.0);
+}
+
+#[test]
+fn meters_read_the_rendered_levels() {
+    let mut h = Harness::new(
+        "meters",
+        120.0,
+        2,
+        RATE,
+        &tone(440.0, 0.5, frames(2.0), RATE),
+    );
+    h.engine.play().unwrap();
+    h.render(0.5);
+    let (left, right) = h.engine.levels();
+    // A sine's RMS is its amplitude over √2
+    assert!(
+        (left - 0.5 / 2f64.sqrt()).abs() < 0.01,
+        "left level {}",
+        left
+    );
+    assert_eq!(right, 0.0);
+
+    h.engine.stop();
+    h.render(0.5);
+    assert_eq!(h.engine.levels(), (0.0, 0.0));
+}
+
+#[test]
+fn reaching_the_end_stops_and_rewinds() {
+    let mut h = Harness::new("end", 120.0, 2, RATE, &ramp(frames(1.0)));
+    h.engine.play().unwrap();
+    let rendered = h.render(1.5);
+    let (played, after) = rendered.split_at(h.mix.len());
+    assert_audio(played, &h.mix);
+    assert_silent(after);
+    let status = h.status();
+    assert!(!status.playing);
+    assert_eq!(status.position_secs, 0.0);
+}
+
+#[test]
+fn stop_rewinds_and_silences() {
+    let mut h = Harness::new("stop", 120.0, 2, RATE, &ramp(frames(4.0)));
+    h.engine.play().unwrap();
+    h.render(1.0);
+    h.engine.stop();
+    assert_silent(&h.render(0.5));
+    assert_eq!(h.status().position_secs, 0.0);
+    h.engine.play().unwrap();
+    let rendered = h.render(0.5);
+    assert_audio(&rendered, h.mix_at(0.0, 0.5));
+}
@@ -0,0 +1,73 @@
+//! End-to-end harness for the playback engine: a throwaway project whose
+//! mix is generated 16-bit audio, opened on the null output. The test
+//! drives the transport, and time only passes as it renders audio, so the
+//! playhead, bar and beat, meters and samples it checks are exact. The
+//! player's own `App` has a harness like it in `src/tests.rs`, on the same
+//! fixtures.
+
+use crate::support::{scaled, write_wav, TempProject};
+use cornwall_player::engine::{Engine, Status};
+
+/// Rendered samples may differ from the mix by this much, the step of a
+/// 16-bit sample.
+const TOLERANCE: f32 = 1.0 / 32768.0;
+
+pub struct Harness {
+    pub engine: Engine,
+    /// The mix as the engine plays it, interleaved.
+    pub mix: Vec<f32>,
+    pub channels: usize,
+    pub rate: u32,
+    _project: TempProject,
+}
+
+impl Harness {
+    /// A 4/4 project at `bpm` whose mix is `samples`, interleaved, in a
+    /// directory of its own named after `test`.
+    pub fn new(test: &str, bpm: f64, channels: u16, rate: u32, samples: &[i16]) -> Self {
+        let project = TempProject::new(test, bpm, rate);
+        write_wav(&project.mix(), channels, rate, samples);
+
+        Harness {
+            engine: Engine::open_null(&project.state_dir()).unwrap(),
+            mix: scaled(samples),
+            channels: channels as usize,
+            rate,
+            _project: project,
+        }
+    }
+
+    fn frames(&self, secs: f64) -> usize {
+        (secs * self.rate as f64).round() as usize
+    }
+
+    /// The next `secs` of output.
+    pub fn render(&mut self, secs: f64) -> Vec<f32> {
+        let frames = self.frames(secs);
+        self.engine.render(frames).unwrap()
+    }
+
+    pub fn status(&mut self) -> Status {
+        self.engine.status()
+    }
+
+    /// `secs` of the mix from `at` seconds in, as rendering it should give.
+    pub fn mix_at(&self, at: f64, secs: f64) -> &[f32] {
+        let start = self.frames(at) * self.channels;
+        let end = (start + self.frames(secs) * self.channels).min(self.mix.len());
+        &self.mix[start..end]
+    }
+}
+
+pub fn assert_audio(rendered: &[f32], expected: &[f32]) {
+    assert_eq!(rendered.len(), expected.len(), "sample count");
+    for (i, (r, e)) in rendered.iter().zip(expected).enumerate() {
+        assert!(
+            (r - e).abs() <= TOLERANCE,
+            "sample {}: rendered {}, expected {}",
+            i,
+            r,
+            e
+        );
+    }
+}
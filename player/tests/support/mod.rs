@@ -0,0 +1,106 @@
+//! Fixtures shared by the integration tests and the player's own tests in
+//! `src/tests.rs`, which take this file in with `#[path]`: a throwaway
+//! project directory, generated 16-bit audio and the checks on what was
+//! rendered from it. Each test crate uses only some of it.
+#![allow(dead_code)]
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Frames before [`ramp`] repeats.
+pub const RAMP_FRAMES: usize = 16384;
+
+/// A project directory in the temporary directory, named after the test
+/// and removed when dropped: `state/project.json` for a 4/4 project and an
+/// empty `projects/test/` for its audio.
+pub struct TempProject {
+    root: PathBuf,
+}
+
+impl TempProject {
+    pub fn new(test: &str, bpm: f64, rate: u32) -> Self {
+        let root = env::temp_dir().join(format!("cornwall-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let project = TempProject { root };
+        fs::create_dir_all(project.state_dir()).unwrap();
+        fs::create_dir_all(project.mix().parent().unwrap()).unwrap();
+        fs::write(
+            project.state_dir().join("project.json"),
+            format!(
+                r#"{{"name": "test", "bpm": {}, "sample_rate": {}, "time_sig": "4/4"}}"#,
+                bpm, rate
+            ),
+        )
+        .unwrap();
+        project
+    }
+
+    pub fn state_dir(&self) -> PathBuf {
+        self.root.join("state")
+    }
+
+    /// Where the project's mix goes.
+    pub fn mix(&self) -> PathBuf {
+        self.root.join("projects").join("test").join("mix.wav")
+    }
+}
+
+impl Drop for TempProject {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Write interleaved 16-bit `samples` as a WAV file.
+pub fn write_wav(path: &Path, channels: u16, rate: u32, samples: &[i16]) {
+    let spec = hound::WavSpec {
+        channels,
+        sample_rate: rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).unwrap();
+    for &sample in samples {
+        writer.write_sample(sample).unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+/// `frames` of stereo counting up, different in every frame and channel
+/// until it repeats after `RAMP_FRAMES`, so a misplaced playhead shows and
+/// a left sample heard tells which frame it came from ([`ramp_frame`]).
+pub fn ramp(frames: usize) -> Vec<i16> {
+    (0..frames)
+        .flat_map(|f| {
+            let step = (f % RAMP_FRAMES) as i16 * 2 - 16384;
+            [step, step + 1]
+        })
+        .collect()
+}
+
+/// The frame of a [`ramp`] that a left sample, scaled to -1..1, came from.
+pub fn ramp_frame(left: f32) -> usize {
+    ((left as f64 * 32768.0 + 16384.0) / 2.0).round() as usize
+}
+
+/// `frames` of a stereo sine at `freq` Hz and `amplitude` (0..1), on the
+/// left only.
+pub fn tone(freq: f64, amplitude: f64, frames: usize, rate: u32) -> Vec<i16> {
+    (0..frames)
+        .flat_map(|f| {
+            let phase = 2.0 * std::f64::consts::PI * freq * f as f64 / rate as f64;
+            [(phase.sin() * amplitude * 32767.0).round() as i16, 0]
+        })
+        .collect()
+}
+
+/// 16-bit samples as a player reads them, -1..1.
+pub fn scaled(samples: &[i16]) -> Vec<f32> {
+    samples.iter().map(|&s| s as f32 / 32768.0).collect()
+}
+
+pub fn assert_silent(rendered: &[f32]) {
+    assert!(rendered.iter().all(|&s| s == 0.0), "expected silence");
+}
@@ -0,0 +1,288 @@
+//! Reading WAV files at each bit depth the player takes, scaled to -1..1,
+//! whole and streamed from disk.
+
+mod support;
+
+use cornwall_player::wav;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use rodio::Source;
+use std::{env, fs, path::PathBuf, thread, time::Duration};
+use support::{ramp, scaled, write_wav};
+
+const RATE: u32 = 8000;
+
+const FORMATS: [(u16, SampleFormat); 4] = [
+    (16, SampleFormat::Int),
+    (24, SampleFormat::Int),
+    (32, SampleFormat::Int),
+    (32, SampleFormat::Float),
+];
+
+fn spec(bits: u16, sample_format: SampleFormat) -> WavSpec {
+    WavSpec {
+        channels: 2,
+        sample_rate: RATE,
+        bits_per_sample: bits,
+        sample_format,
+    }
+}
+
+/// A WAV file in the temporary directory, removed when dropped.
+struct TempWav(PathBuf);
+
+impl TempWav {
+    fn path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("cornwall-wav-{}-{}.wav", name, std::process::id()))
+    }
+
+    /// One frame at full scale up and one down, in `spec`.
+    fn full_scale(spec: WavSpec) -> Self {
+        let path = Self::path(&format!(
+            "{}-{:?}",
+            spec.bits_per_sample, spec.sample_format
+        ));
+        let mut writer = WavWriter::create(&path, spec).unwrap();
+        match spec.sample_format {
+            SampleFormat::Float => {
+                for s in [1.0f32, 1.0, -1.0, -1.0] {
+                    writer.write_sample(s).unwrap();
+                }
+            }
+            SampleFormat::Int => {
+                let max = 1_i64 << (spec.bits_per_sample - 1);
+                for s in [max - 1, max - 1, -max, -max] {
+                    writer.write_sample(s as i32).unwrap();
+                }
+            }
+        }
+        writer.finalize().unwrap();
+        TempWav(path)
+    }
+
+    /// `frames` of 16-bit stereo, every sample different, as `ramp` gives.
+    fn ramp(name: &str, frames: usize) -> Self {
+        let path = Self::path(name);
+        write_wav(&path, 2, RATE, &ramp(frames));
+        TempWav(path)
+    }
+}
+
+impl Drop for TempWav {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn assert_full_scale(samples: &[f64], format: &str) {
+    assert_eq!(samples.len(), 4, "{}: sample count", format);
+    for &s in &samples[..2] {
+        assert!(
+            (s - 1.0).abs() < 1e-4,
+            "{}: read {} for full scale up",
+            format,
+            s
+        );
+    }
+    for &s in &samples[2..] {
+        assert_eq!(s, -1.0, "{}: full scale down", format);
+    }
+}
+
+#[test]
+fn full_scale_reads_as_one_at_every_depth() {
+    for (bits, format) in FORMATS {
+        let file = TempWav::full_scale(spec(bits, format));
+        let (read_spec, samples) = wav::read(&file.0).unwrap();
+        assert_eq!(read_spec, spec(bits, format));
+        assert_full_scale(&samples, &format!("{}-bit {:?}", bits, format));
+    }
+}
+
+#[test]
+fn full_scale_is_the_integer_peak() {
+    assert_eq!(wav::full_scale(spec(16, SampleFormat::Int)), Some(32768.0));
+    assert_eq!(
+        wav::full_scale(spec(24, SampleFormat::Int)),
+        Some(8388608.0)
+    );
+    assert_eq!(
+        wav::full_scale(spec(32, SampleFormat::Int)),
+        Some(2147483648.0)
+    );
+    assert_eq!(wav::full_scale(spec(32, SampleFormat::Float)), None);
+}
+
+#[test]
+fn write_round_trips_at_every_depth() {
+    for (bits, format) in FORMATS {
+        let spec = spec(bits, format);
+        let path = env::temp_dir().join(format!(
+            "cornwall-wav-round-{}-{:?}-{}.wav",
+            bits,
+            format,
+            std::process::id()
+        ));
+        let written = [0.5, -0.25, 1.0, -1.0];
+        wav::write(&path, spec, &written).unwrap();
+        let (_, read) = wav::read(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        let step = wav::full_scale(spec).map_or(1e-7, |f| 1.0 / f);
+        for (w, r) in written.iter().zip(&read) {
+            // Full scale up clips to the largest integer below it
+            assert!(
+                (w - r).abs() <= step,
+                "{}-bit {:?}: wrote {}, read {}",
+                bits,
+                format,
+                w,
+                r
+            );
+        }
+    }
+}
+
+#[test]
+fn stream_scales_full_scale_to_one_at_every_depth() {
+    for (bits, format) in FORMATS {
+        let file = TempWav::full_scale(spec(bits, format));
+        let stream = wav::Stream::open(&file.0).unwrap();
+        let samples: Vec<f64> = stream.map(|s| s as f64).collect();
+        assert_full_scale(&samples, &format!("streamed {}-bit {:?}", bits, format));
+    }
+}
+
+#[test]
+fn stream_plays_across_prefetch_blocks() {
+    // Three blocks and a bit, so the reads run on over two boundaries
+    let frames = wav::PREFETCH_FRAMES * 3 + 100;
+    let file = TempWav::ramp("blocks", frames);
+    let streamed: Vec<f32> = wav::Stream::open(&file.0).unwrap().collect();
+    assert_eq!(streamed, scaled(&ramp(frames)));
+}
+
+#[test]
+fn stream_reads_back_and_forth_over_a_block_boundary() {
+    let frames = wav::PREFETCH_FRAMES * 2 + 10;
+    let file = TempWav::ramp("boundary", frames);
+    let expected = scaled(&ramp(frames));
+    let mut stream = wav::Stream::open(&file.0).unwrap();
+    let boundary = wav::PREFETCH_FRAMES * 2;
+    // Forward over the boundary, back into the block before it, then far
+    // ahead and back to the start
+    for i in [
+        boundary - 2,
+        boundary - 1,
+        boundary,
+        boundary + 1,
+        boundary - 1,
+        3,
+        expected.len() - 1,
+        0,
+    ] {
+        assert_eq!(stream.get(i), Some(expected[i]), "sample {}", i);
+    }
+}
+
+#[test]
+fn stream_seek_near_the_end_plays_what_is_left() {
+    let frames = wav::PREFETCH_FRAMES + 500;
+    let file = TempWav::ramp("near-end", frames);
+    let expected = scaled(&ramp(frames));
+    let mut stream = wav::Stream::open(&file.0).unwrap();
+    stream.seek((frames - 3) as f64 / RATE as f64);
+    let rest: Vec<f32> = stream.by_ref().collect();
+    assert_eq!(rest, expected[expected.len() - 6..]);
+    assert_eq!(stream.next(), None);
+}
+
+#[test]
+fn stream_seek_past_the_end_plays_nothing() {
+    let frames = 1000;
+    let file = TempWav::ramp("past-end", frames);
+    let mut stream = wav::Stream::open(&file.0).unwrap();
+    stream.seek(frames as f64 / RATE as f64 + 1.0);
+    assert_eq!(stream.next(), None);
+    assert_eq!(stream.get(frames * 2), None);
+    // A seek back in plays again
+    stream.seek(0.0);
+    assert_eq!(stream.next(), Some(scaled(&ramp(1))[0]));
+}
+
+#[test]
+fn read_ahead_plays_a_stream_longer_than_its_ring() {
+    let frames = (wav::READ_AHEAD_SECS * RATE as f64) as usize * 3 + 5;
+    let file = TempWav::ramp("read-ahead", frames);
+    // Drained as fast as it goes, faster than the reader fills it
+    let played: Vec<f32> = wav::ReadAhead::new(wav::Stream::open(&file.0).unwrap())
+        .waiting()
+        .collect();
+    assert_eq!(played, scaled(&ramp(frames)));
+}
+
+/// Stereo samples 1, 2, 3 and so on up to `len`, at a rate that makes a
+/// [`wav::ReadAhead`]'s ring two frames long; each after the first
+/// `quick` takes `delay` to read.
+struct Slow {
+    read: usize,
+    len: usize,
+    quick: usize,
+    delay: Duration,
+}
+
+impl Slow {
+    fn new(len: usize, quick: usize) -> Self {
+        Slow {
+            read: 0,
+            len,
+            quick,
+            delay: Duration::from_millis(200),
+        }
+    }
+}
+
+impl Iterator for Slow {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.read == self.len {
+            return None;
+        }
+        if self.read >= self.quick {
+            thread::sleep(self.delay);
+        }
+        self.read += 1;
+        Some(self.read as f32)
+    }
+}
+
+impl Source for Slow {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn sample_rate(&self) -> u32 {
+        (2.0 / wav::READ_AHEAD_SECS) as u32
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[test]
+fn read_ahead_plays_whole_frames_of_silence_while_the_reader_is_behind() {
+    // Two frames are read before it plays; the third takes a while
+    let mut played = wav::ReadAhead::new(Slow::new(6, 4));
+    let first: Vec<f32> = played.by_ref().take(6).collect();
+    assert_eq!(first, [1.0, 2.0, 3.0, 4.0, 0.0, 0.0]);
+}
+
+#[test]
+fn waiting_read_ahead_waits_for_the_reader() {
+    let played: Vec<f32> = wav::ReadAhead::new(Slow::new(6, 4)).waiting().collect();
+    assert_eq!(played, [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+}
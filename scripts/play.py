@@ -7,6 +7,7 @@ Commands:
   track <id>            Play a single track through headphones
   mix                   Render and play the full mix
   render                Render the mix to a file (no playback)
+                        (--upload sends it to state/upload.json's destination)
   loop <id|mix>         Loop a track or the mix in background
   stop                  Stop background playback
   status                Check if something is playing
@@ -15,6 +16,7 @@ Commands:
 Examples:
   play.py track 1
   play.py mix
+  play.py render --upload
   play.py loop mix
   play.py stop
   play.py file ~/samples/banjo.wav
@@ -26,10 +28,11 @@ import signal
 import subprocess
 import sys
 import tempfile
+from datetime import datetime
 from pathlib import Path
 
 sys.path.insert(0, str(Path(__file__).resolve().parent.parent))
-from cornwall import state
+from cornwall import player_control, state, upload
 from cornwall.sox_effects import build_sox_effects
 
 
@@ -107,11 +110,27 @@ def cmd_mix(args):
             subprocess.run(["sox", "-m"] + tmp_files + [output], check=True)
 
     print(f"Rendered: {output}")
+    # A running player picks up the new mix where it is
+    reply = player_control.send("reload")
+    if reply and reply.get("ok"):
+        print("Player reloaded")
+    if args.upload:
+        _upload_mix(Path(output))
     if not args.no_play:
         print("Playing mix...")
         _run_sox(["play", output])
 
 
+def _upload_mix(path: Path):
+    # Timestamped so each bounce gets its own link
+    name = f"{state.get_project()['name']}-{datetime.now():%Y%m%d-%H%M}{path.suffix}"
+    print(f"Uploading {name}...")
+    link = upload.upload(path, name)
+    print(f"Link: {link}")
+    if upload.copy_to_clipboard(link):
+        print("Link copied to clipboard")
+
+
 def cmd_render(args):
     args.no_play = True
     cmd_mix(args)
@@ -213,9 +232,11 @@ def main():
     p = sub.add_parser("mix", help="Render and play the full mix")
     p.add_argument("--output", help="Output file path")
     p.add_argument("--no-play", action="store_true", help="Render only, don't play")
+    p.add_argument("--upload", action="store_true", help="Upload the mix and print a link")
 
     p = sub.add_parser("render", help="Render mix to file (no playback)")
     p.add_argument("--output", help="Output file path")
+    p.add_argument("--upload", action="store_true", help="Upload the mix and print a link")
 
     p = sub.add_parser("loop", help="Loop a track or mix in background")
     p.add_argument("target", help="Track ID or 'mix'")
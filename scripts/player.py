@@ -1,5 +1,5 @@
 #!/usr/bin/env python3
-"""Query the Cornwall player's state from Claude Code.
+"""Query and control the Cornwall player from Claude Code.
 
 Usage: player.py <command>
 
@@ -8,6 +8,11 @@ Commands:
   playing   Exit 0 if playing, 1 if stopped
   position  Print current position in seconds
   bar       Print current bar number
+  reload    Make the player reread project state and audio after edits
+  seek POS  Seek to a bar (33, 33.2) or time (1:32.5)
+  audition ID|off
+            Play one track's source in place of the mix, or go back
+  send LINE Run any player `:` command (e.g. "loop 33 41")
 
 The player TUI runs in a separate terminal. This script reads its state
 file to report what it's doing, so Claude Code can make decisions based
@@ -23,66 +28,132 @@ Examples:
   player.py status
   player.py playing && echo "Music is playing"
   player.py bar
+  player.py seek 17
+  player.py audition 3
 """
 
 import argparse
 import json
+import os
 import sys
+import time
 from pathlib import Path
 
 sys.path.insert(0, str(Path(__file__).resolve().parent.parent))
-from cornwall import state
+from cornwall import player_control, state
 
+# The player rewrites its status at least once a second while running
+STALE_AFTER = 5.0
+# `cornwall-player schema` prints the schema of this version
+STATUS_SCHEMA = 3
 
-def cmd_status(args):
+
+def upgrade_status(data):
+    """Bring a status written by an older player up to STATUS_SCHEMA."""
+    # Version 1 had no schema field and only said whether it was playing
+    if data.get("schema", 1) < 2:
+        data.setdefault("transport", "playing" if data.get("playing") else "stopped")
+        data.setdefault("loop_mode", "off")
+        data.setdefault("loop_bars", None)
+        data["schema"] = 2
+    # Version 3 only added "paused" to transport
+    if data["schema"] < 3:
+        data["schema"] = 3
+    return data
+
+
+def read_status():
+    """The player's status, or None if it isn't running.
+
+    A status file whose writer has exited (crash, kill -9) is ignored.
+    """
     status_file = state.STATE_DIR / ".player.json"
-    if status_file.exists():
-        data = json.loads(status_file.read_text())
+    try:
+        data = upgrade_status(json.loads(status_file.read_text()))
+    except (OSError, ValueError):
+        return None
+    if time.time() - data.get("updated", 0) > STALE_AFTER:
+        return None
+    pid = data.get("pid")
+    if pid:
+        try:
+            os.kill(pid, 0)
+        except ProcessLookupError:
+            return None
+        except PermissionError:
+            pass
+    return data
+
+
+def cmd_status(args):
+    data = read_status()
+    if data:
         if args.json:
             print(json.dumps(data, indent=2))
         else:
-            playing = "▶ PLAYING" if data.get("playing") else "■ STOPPED"
-            print(f"State:    {playing}")
+            transport = {
+                "playing": "▶ PLAYING",
+                "paused": "❚❚ PAUSED",
+                "stalled": "▶ STALLED (output not taking audio)",
+            }.get(data["transport"], "■ STOPPED")
+            print(f"State:    {transport}")
             print(f"Position: {data.get('position_secs', 0):.1f}s")
+            if data["loop_mode"] != "off":
+                bars = data.get("loop_bars")
+                span = f" bars {bars[0]}-{bars[1]}" if bars else ""
+                print(f"Loop:     {data['loop_mode']}{span}")
             print(f"Bar:      {data.get('bar', 0)}.{data.get('beat', 0)}")
             print(f"Tempo:    {data.get('bpm', 0)} BPM  {data.get('time_sig', '')}")
             print(f"Level:    L={data.get('level_l', 0):.3f}  R={data.get('level_r', 0):.3f}")
             print(f"File:     {data.get('file', '')}")
+            if data.get("drift_corrections"):
+                print(f"Drift:    {data.get('drift_ms', 0):.1f} ms worst, "
+                      f"{data['drift_corrections']} corrections")
     else:
         if args.json:
-            print('{"playing": false}')
+            print(json.dumps({"schema": STATUS_SCHEMA, "transport": "stopped",
+                              "playing": False}))
         else:
             print("Player not running")
 
 
 def cmd_playing(args):
-    status_file = state.STATE_DIR / ".player.json"
-    if status_file.exists():
-        data = json.loads(status_file.read_text())
-        sys.exit(0 if data.get("playing") else 1)
-    sys.exit(1)
+    data = read_status()
+    sys.exit(0 if data and data.get("playing") else 1)
 
 
 def cmd_position(args):
-    status_file = state.STATE_DIR / ".player.json"
-    if status_file.exists():
-        data = json.loads(status_file.read_text())
+    data = read_status()
+    if data:
         print(f"{data.get('position_secs', 0):.2f}")
     else:
         print("0.00")
 
 
 def cmd_bar(args):
-    status_file = state.STATE_DIR / ".player.json"
-    if status_file.exists():
-        data = json.loads(status_file.read_text())
+    data = read_status()
+    if data:
         print(f"{data.get('bar', 0)}.{data.get('beat', 0)}")
     else:
         print("0.0")
 
 
+def cmd_send(args):
+    line = args.line if args.command == "send" else " ".join([args.command] + args.args)
+    reply = player_control.send(line)
+    if reply is None:
+        print("Player not running", file=sys.stderr)
+        sys.exit(1)
+    if not reply.get("ok"):
+        print(f"Error: {reply.get('error')}", file=sys.stderr)
+        sys.exit(1)
+    status = reply.get("status", {})
+    print(f"OK  bar {status.get('bar', 0)}.{status.get('beat', 0)}  "
+          f"{status.get('position_secs', 0):.1f}s  {status.get('file', '')}")
+
+
 def main():
-    parser = argparse.ArgumentParser(description="Query the Cornwall player state")
+    parser = argparse.ArgumentParser(description="Query and control the Cornwall player")
     sub = parser.add_subparsers(dest="command")
 
     p = sub.add_parser("status", help="Show current player state")
@@ -92,14 +163,23 @@ def main():
     sub.add_parser("position", help="Print current position in seconds")
     sub.add_parser("bar", help="Print current bar number")
 
+    sub.add_parser("reload", help="Reread project state and audio").set_defaults(args=[])
+    p = sub.add_parser("seek", help="Seek to a bar or time")
+    p.add_argument("args", nargs=1, metavar="POS")
+    p = sub.add_parser("audition", help="Play one track in place of the mix")
+    p.add_argument("args", nargs=1, metavar="ID|off")
+    p = sub.add_parser("send", help="Run a player command")
+    p.add_argument("line")
+
     args = parser.parse_args()
     if not args.command:
         parser.print_help()
         sys.exit(0)
 
-    {"status": cmd_status, "playing": cmd_playing, "position": cmd_position, "bar": cmd_bar}[
-        args.command
-    ](args)
+    {
+        "status": cmd_status, "playing": cmd_playing, "position": cmd_position, "bar": cmd_bar,
+        "reload": cmd_send, "seek": cmd_send, "audition": cmd_send, "send": cmd_send,
+    }[args.command](args)
 
 
 if __name__ == "__main__":
@@ -8,6 +8,7 @@ Commands:
   info      Show current project info
   set       Change a project setting
   open      Open (switch to) an existing project
+  export    Write the project as a DAWproject file (Bitwig, Studio One)
 
 project.py create [options]
   --name <name>         Project name (required)
@@ -25,11 +26,18 @@ project.py set <key> <value>
 project.py open <name>
   Switch to an existing project by name.
 
+project.py export [options]
+  Write tracks (volume, pan, mute, solo, source clips) and markers to
+  projects/<name>/<name>.dawproject with the audio inside.
+  --output <path>   Write here instead
+  --link            Refer to the sources where they are instead of copying
+
 Examples:
   project.py create --name "folk-session" --bpm 110
   project.py info
   project.py set bpm 140
   project.py set time-sig 3/4
+  project.py export
 """
 
 import argparse
@@ -38,7 +46,7 @@ import sys
 from pathlib import Path
 
 sys.path.insert(0, str(Path(__file__).resolve().parent.parent))
-from cornwall import state
+from cornwall import dawproject, state
 
 
 def cmd_create(args):
@@ -102,6 +110,16 @@ def cmd_open(args):
     cmd_info(InfoArgs())
 
 
+def cmd_export(args):
+    project = state.get_project()
+    output = Path(args.output) if args.output else state.get_project_dir() / f"{project['name']}.dawproject"
+    result = dawproject.export(output, link=args.link)
+    print(f"Wrote {output}: {result['clips']} clips on {result['tracks']} tracks, {result['markers']} markers")
+    if result["empty"]:
+        print(f"Without audio (MIDI or no source): {', '.join(result['empty'])}")
+    print("Effects chains are not exported.")
+
+
 def main():
     parser = argparse.ArgumentParser(description="Manage Cornwall projects", add_help=True)
     sub = parser.add_subparsers(dest="command")
@@ -122,12 +140,17 @@ def main():
     p_open = sub.add_parser("open", help="Open an existing project")
     p_open.add_argument("name", help="Project name")
 
+    p_export = sub.add_parser("export", help="Write the project as a DAWproject file")
+    p_export.add_argument("--output", help="Output path (default: projects/<name>/<name>.dawproject)")
+    p_export.add_argument("--link", action="store_true", help="Refer to sources instead of copying them in")
+
     args = parser.parse_args()
     if not args.command:
         parser.print_help()
         sys.exit(0)
 
-    {"create": cmd_create, "info": cmd_info, "set": cmd_set, "open": cmd_open}[args.command](args)
+    cmds = {"create": cmd_create, "info": cmd_info, "set": cmd_set, "open": cmd_open, "export": cmd_export}
+    cmds[args.command](args)
 
 
 if __name__ == "__main__":
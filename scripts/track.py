@@ -16,6 +16,7 @@ Commands:
   rename    Rename a track
   info      Show detailed track info
   import    Import an audio file to a track
+  verify    Check track sources against their recorded hashes
 
 Examples:
   track.py add --name "mandolin" --type audio
@@ -24,6 +25,7 @@ Examples:
   track.py volume 2 0.8
   track.py pan 1 -0.3
   track.py import 1 ~/samples/mandolin-riff.wav
+  track.py verify
 """
 
 import argparse
@@ -150,6 +152,27 @@ def cmd_import(args):
     print(f"Imported '{src.name}' to track {args.id} '{track['name']}'")
 
 
+def cmd_verify(args):
+    state.require_project()
+    if args.update:
+        state.update_hashes()
+        print("Recorded the current hash of every track source")
+        return
+    problems = state.verify_sources()
+    if args.json:
+        print(json.dumps([
+            {"id": t["id"], "name": t["name"], "source": t["source"], "problem": problem}
+            for t, problem in problems
+        ], indent=2))
+    elif not problems:
+        print("All track sources match their recorded hashes")
+    else:
+        for t, problem in problems:
+            print(f"Track {t['id']} '{t['name']}': {problem}: {t['source']}")
+    if problems:
+        sys.exit(1)
+
+
 def main():
     parser = argparse.ArgumentParser(description="Manage tracks in the current Cornwall project")
     sub = parser.add_subparsers(dest="command")
@@ -196,6 +219,10 @@ def main():
     p.add_argument("id", type=int, help="Track ID")
     p.add_argument("file", help="Path to audio file")
 
+    p = sub.add_parser("verify", help="Check track sources against their recorded hashes")
+    p.add_argument("--update", action="store_true", help="Record the sources' current hashes instead")
+    p.add_argument("--json", action="store_true", help="Output as raw JSON")
+
     args = parser.parse_args()
     if not args.command:
         parser.print_help()
@@ -205,7 +232,7 @@ def main():
         "add": cmd_add, "list": cmd_list, "remove": cmd_remove,
         "solo": cmd_solo, "unsolo": cmd_unsolo, "mute": cmd_mute, "unmute": cmd_unmute,
         "volume": cmd_volume, "pan": cmd_pan, "rename": cmd_rename,
-        "info": cmd_info, "import": cmd_import,
+        "info": cmd_info, "import": cmd_import, "verify": cmd_verify,
     }
     cmds[args.command](args)
 